@@ -0,0 +1,266 @@
+// Color adjustments, like brightness and contrast, applied to the resized image so that they
+// only ever touch as many pixels as are printed.
+use crate::Config;
+use image::{Rgb, RgbaImage};
+
+// Weights of the channels in the luma of a pixel, as used by the image crate's grayscale
+const LUMA: [f32; 3] = [0.2126, 0.7152, 0.0722];
+
+// The classic sepia tone
+const SEPIA: [[f32; 3]; 3] = [
+    [0.393, 0.769, 0.189],
+    [0.349, 0.686, 0.168],
+    [0.272, 0.534, 0.131],
+];
+
+/// Rendering with only two colors, set through [Config::threshold]: pixels darker than the
+/// level get the dark color and the others the light one, e.g. for previews of documents or
+/// QR codes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(default))]
+pub struct Threshold {
+    /// Level separating the dark pixels from the light ones. Defaults to
+    /// [ThresholdLevel::Otsu].
+    pub level: ThresholdLevel,
+    /// Color of the dark pixels. Defaults to black.
+    #[cfg_attr(feature = "serde", serde(with = "crate::serde_remote::rgb"))]
+    pub dark: Rgb<u8>,
+    /// Color of the light pixels. Defaults to white.
+    #[cfg_attr(feature = "serde", serde(with = "crate::serde_remote::rgb"))]
+    pub light: Rgb<u8>,
+}
+
+impl Default for Threshold {
+    fn default() -> Self {
+        Self {
+            level: ThresholdLevel::Otsu,
+            dark: Rgb([0, 0, 0]),
+            light: Rgb([255, 255, 255]),
+        }
+    }
+}
+
+/// Level of a [Threshold].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum ThresholdLevel {
+    /// Pixels whose luma is below the value are dark.
+    Fixed(u8),
+    /// Chosen from the image with Otsu's method, which separates its pixels into the two
+    /// groups whose luma varies the least, e.g. ink and paper.
+    Otsu,
+}
+
+// Check whether the config changes the colors of the image
+pub fn is_needed(config: &Config) -> bool {
+    config.invert
+        || config.sepia
+        || config.color_matrix.is_some()
+        || config.brightness != 0.0
+        || config.contrast != 1.0
+        || config.saturation != 1.0
+        || config.gamma != 1.0
+}
+
+// Apply the adjustments from the config to the image, in place
+pub fn apply(img: &mut RgbaImage, config: &Config) {
+    if !is_needed(config) {
+        return;
+    }
+    let table = tone_table(config);
+    let saturation = config.saturation;
+    for pixel in img.pixels_mut() {
+        if config.invert {
+            for c in &mut pixel.0[..3] {
+                *c = 255 - *c;
+            }
+        }
+        if config.sepia {
+            transform_colors(&mut pixel.0, &SEPIA);
+        }
+        if let Some(matrix) = &config.color_matrix {
+            transform_colors(&mut pixel.0, matrix);
+        }
+        if saturation != 1.0 {
+            let [r, g, b, _] = pixel.0;
+            let luma = LUMA[0] * r as f32 + LUMA[1] * g as f32 + LUMA[2] * b as f32;
+            for c in &mut pixel.0[..3] {
+                let value = luma + (*c as f32 - luma) * saturation;
+                *c = value.round().clamp(0.0, 255.0) as u8;
+            }
+        }
+        for c in &mut pixel.0[..3] {
+            *c = table[*c as usize];
+        }
+    }
+}
+
+// Replace every pixel by the dark or the light color of the threshold, keeping the alpha
+pub fn threshold(img: &mut RgbaImage, threshold: &Threshold) {
+    let level = match threshold.level {
+        ThresholdLevel::Fixed(level) => level,
+        ThresholdLevel::Otsu => otsu_level(img),
+    };
+    for pixel in img.pixels_mut() {
+        let color = if luma(&pixel.0) < level {
+            threshold.dark
+        } else {
+            threshold.light
+        };
+        pixel.0[..3].copy_from_slice(&color.0);
+    }
+}
+
+fn luma([r, g, b, _]: &[u8; 4]) -> u8 {
+    let luma = LUMA[0] * *r as f32 + LUMA[1] * *g as f32 + LUMA[2] * *b as f32;
+    luma.round() as u8
+}
+
+// The lowest luma of the light pixels which maximizes the variance between the dark and
+// the light ones, following Otsu's method
+fn otsu_level(img: &RgbaImage) -> u8 {
+    let mut histogram = [0u64; 256];
+    for pixel in img.pixels() {
+        histogram[luma(&pixel.0) as usize] += 1;
+    }
+    let total: u64 = histogram.iter().sum();
+    let sum: f64 = (0..256).map(|i| i as f64 * histogram[i] as f64).sum();
+
+    let (mut dark_count, mut dark_sum) = (0u64, 0.0);
+    let (mut best_level, mut best_variance) = (128, 0.0);
+    for (i, &count) in histogram.iter().enumerate() {
+        dark_count += count;
+        dark_sum += i as f64 * count as f64;
+        let light_count = total - dark_count;
+        if dark_count == 0 || light_count == 0 {
+            continue;
+        }
+        let dark_mean = dark_sum / dark_count as f64;
+        let light_mean = (sum - dark_sum) / light_count as f64;
+        let variance = dark_count as f64 * light_count as f64 * (dark_mean - light_mean).powi(2);
+        if variance > best_variance {
+            best_variance = variance;
+            best_level = i as u8 + 1;
+        }
+    }
+    best_level
+}
+
+// Replace the color channels by their combinations in the rows of the matrix
+fn transform_colors(pixel: &mut [u8; 4], matrix: &[[f32; 3]; 3]) {
+    let [r, g, b, _] = pixel.map(|c| c as f32);
+    for (c, row) in pixel.iter_mut().zip(matrix) {
+        let value = row[0] * r + row[1] * g + row[2] * b;
+        *c = value.round().clamp(0.0, 255.0) as u8;
+    }
+}
+
+// The value every channel value is mapped to by the brightness, contrast and gamma
+fn tone_table(config: &Config) -> [u8; 256] {
+    let mut table = [0; 256];
+    for (i, entry) in table.iter_mut().enumerate() {
+        let value = i as f32 / 255.0 + config.brightness;
+        let value = (value - 0.5) * config.contrast + 0.5;
+        let value = value.clamp(0.0, 1.0).powf(1.0 / config.gamma);
+        *entry = (value * 255.0).round() as u8;
+    }
+    table
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use image::Rgba;
+
+    #[test]
+    fn test_no_adjustment() {
+        let config = Config::default();
+        assert!(!is_needed(&config));
+        let table = tone_table(&config);
+        assert!(table.iter().enumerate().all(|(i, &v)| v as usize == i));
+    }
+
+    #[test]
+    fn test_adjustments() {
+        let mut img = RgbaImage::from_pixel(1, 1, Rgba([64, 128, 192, 100]));
+        let config = Config {
+            contrast: 2.0,
+            ..Default::default()
+        };
+        apply(&mut img, &config);
+        // Away from mid gray, the alpha is kept
+        assert_eq!(img.get_pixel(0, 0), &Rgba([1, 129, 255, 100]));
+
+        let mut img = RgbaImage::from_pixel(1, 1, Rgba([64, 128, 192, 255]));
+        let config = Config {
+            saturation: 0.0,
+            brightness: 0.1,
+            ..Default::default()
+        };
+        apply(&mut img, &config);
+        let [r, g, b, _] = img.get_pixel(0, 0).0;
+        assert!(r == g && g == b);
+        assert_eq!(r, 145);
+
+        let config = Config {
+            gamma: 2.0,
+            ..Default::default()
+        };
+        assert_eq!(tone_table(&config)[64], 128);
+    }
+
+    #[test]
+    fn test_threshold() {
+        // Gray text on a lighter background
+        let mut img = RgbaImage::from_fn(4, 1, |x, _| match x {
+            0 => Rgba([60, 60, 60, 255]),
+            1 => Rgba([80, 80, 80, 255]),
+            _ => Rgba([170, 170, 170, 128]),
+        });
+        assert_eq!(otsu_level(&img), 81);
+        let threshold = Threshold {
+            dark: Rgb([0, 0, 128]),
+            ..Default::default()
+        };
+        crate::adjust::threshold(&mut img, &threshold);
+        assert_eq!(img.get_pixel(1, 0), &Rgba([0, 0, 128, 255]));
+        assert_eq!(img.get_pixel(2, 0), &Rgba([255, 255, 255, 128]));
+
+        let threshold = Threshold {
+            level: ThresholdLevel::Fixed(255),
+            ..Default::default()
+        };
+        // Everything but white is dark
+        crate::adjust::threshold(&mut img, &threshold);
+        assert_eq!(img.get_pixel(1, 0), &Rgba([0, 0, 0, 255]));
+        assert_eq!(img.get_pixel(2, 0), &Rgba([255, 255, 255, 128]));
+    }
+
+    #[test]
+    fn test_color_filters() {
+        let mut img = RgbaImage::from_pixel(1, 1, Rgba([200, 100, 0, 50]));
+        let config = Config {
+            invert: true,
+            ..Default::default()
+        };
+        apply(&mut img, &config);
+        assert_eq!(img.get_pixel(0, 0), &Rgba([55, 155, 255, 50]));
+
+        // Swap red and blue
+        let config = Config {
+            color_matrix: Some([[0.0, 0.0, 1.0], [0.0, 1.0, 0.0], [1.0, 0.0, 0.0]]),
+            ..Default::default()
+        };
+        apply(&mut img, &config);
+        assert_eq!(img.get_pixel(0, 0), &Rgba([255, 155, 55, 50]));
+
+        let mut img = RgbaImage::from_pixel(1, 1, Rgba([100, 100, 100, 255]));
+        let config = Config {
+            sepia: true,
+            ..Default::default()
+        };
+        apply(&mut img, &config);
+        assert_eq!(img.get_pixel(0, 0), &Rgba([135, 120, 94, 255]));
+    }
+}
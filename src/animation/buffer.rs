@@ -0,0 +1,131 @@
+use super::Frame;
+use crate::error::ViuResult;
+
+use std::collections::VecDeque;
+
+// Memory kept for decoded frames, so that short animations loop and seek without decoding
+// their frames again
+pub const DEFAULT_BUDGET: usize = 256 * 1024 * 1024;
+
+// Reads the source of the frames again from the start
+type Reopen<I> = Box<dyn FnMut() -> ViuResult<I>>;
+
+// What the buffer has at an index of the animation
+pub enum Lookup<'a> {
+    Frame(&'a Frame),
+    // The animation has fewer frames
+    End,
+    // The frame was dropped from the buffer, and the source cannot be read again
+    Dropped,
+}
+
+// The frames of a playback, decoded when they are needed. Decoded frames are kept while they
+// fit in the budget. Beyond it, the oldest ones are dropped, and going back to one of them
+// reads the source again from the start, if it can be reopened.
+pub struct FrameBuffer<I> {
+    frames: I,
+    reopen: Option<Reopen<I>>,
+    budget: usize,
+    cached: VecDeque<Frame>,
+    // Index of the first cached frame
+    first: usize,
+    // Size of the cached frames in bytes
+    bytes: usize,
+}
+
+impl<I: Iterator<Item = ViuResult<Frame>>> FrameBuffer<I> {
+    pub fn new(frames: I, budget: usize) -> Self {
+        Self {
+            frames,
+            reopen: None,
+            budget,
+            cached: VecDeque::new(),
+            first: 0,
+            bytes: 0,
+        }
+    }
+
+    // Read the frames from `reopen` when the playback goes back to a dropped frame
+    pub fn with_reopen(mut self, reopen: impl FnMut() -> ViuResult<I> + 'static) -> Self {
+        self.reopen = Some(Box::new(reopen));
+        self
+    }
+
+    // Get the frame at the given index, decoding the frames up to it
+    pub fn get(&mut self, index: usize) -> ViuResult<Lookup<'_>> {
+        if index < self.first {
+            let Some(reopen) = &mut self.reopen else {
+                return Ok(Lookup::Dropped);
+            };
+            self.frames = reopen()?;
+            self.cached.clear();
+            self.first = 0;
+            self.bytes = 0;
+        }
+
+        while self.first + self.cached.len() <= index {
+            let frame = match self.frames.next() {
+                Some(frame) => frame?,
+                None => return Ok(Lookup::End),
+            };
+            self.bytes += frame_size(&frame);
+            self.cached.push_back(frame);
+            // Drop the oldest frames, but always keep the one which was just decoded
+            while self.bytes > self.budget && self.cached.len() > 1 {
+                if let Some(dropped) = self.cached.pop_front() {
+                    self.bytes -= frame_size(&dropped);
+                    self.first += 1;
+                }
+            }
+        }
+        Ok(Lookup::Frame(&self.cached[index - self.first]))
+    }
+}
+
+fn frame_size(frame: &Frame) -> usize {
+    frame.image.as_bytes().len()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use image::{DynamicImage, GenericImageView, Rgba, RgbaImage};
+    use std::time::Duration;
+
+    // Frames of 3x2 pixels, taking 24 bytes each, with the index as their red value
+    fn frames() -> impl Iterator<Item = ViuResult<Frame>> {
+        (0..5).map(|i| {
+            Ok(Frame {
+                image: DynamicImage::ImageRgba8(RgbaImage::from_pixel(3, 2, Rgba([i, 0, 0, 255]))),
+                delay: Duration::ZERO,
+            })
+        })
+    }
+
+    fn red(lookup: Lookup) -> Option<u8> {
+        match lookup {
+            Lookup::Frame(frame) => Some(frame.image.get_pixel(0, 0)[0]),
+            _ => None,
+        }
+    }
+
+    #[test]
+    fn test_keeps_frames_within_budget() {
+        let mut buffer = FrameBuffer::new(frames(), 48);
+        assert_eq!(red(buffer.get(0).unwrap()), Some(0));
+        assert_eq!(red(buffer.get(3).unwrap()), Some(3));
+        assert_eq!(red(buffer.get(2).unwrap()), Some(2));
+        assert!(matches!(buffer.get(1).unwrap(), Lookup::Dropped));
+        assert!(matches!(buffer.get(5).unwrap(), Lookup::End));
+        assert_eq!(buffer.cached.len(), 2);
+    }
+
+    #[test]
+    fn test_reopens_source() {
+        let mut buffer = FrameBuffer::new(frames(), 0).with_reopen(|| Ok(frames()));
+        assert_eq!(red(buffer.get(4).unwrap()), Some(4));
+        assert_eq!(buffer.cached.len(), 1);
+        assert_eq!(red(buffer.get(1).unwrap()), Some(1));
+        assert_eq!(red(buffer.get(0).unwrap()), Some(0));
+    }
+}
@@ -0,0 +1,132 @@
+use crate::error::ViuResult;
+
+use crossterm::cursor::{Hide, RestorePosition, SavePosition, Show};
+use crossterm::execute;
+use crossterm::style::ResetColor;
+use std::io::Write;
+
+// Sequence restoring the colors and the cursor, written from the signal handler
+#[cfg(all(unix, feature = "signal-handler"))]
+const RESTORE: &[u8] = b"\x1b[0m\x1b[?25h";
+
+// Hides the cursor while an animation plays, and restores the terminal when it ends. This also
+// happens when the playback returns early with an error or panics, since the guard is dropped.
+// With the signal-handler feature, it also happens when the process is interrupted.
+pub struct TerminalGuard {
+    restore_position: bool,
+    finished: bool,
+    #[cfg(all(unix, feature = "signal-handler"))]
+    signals: Vec<signal_hook::SigId>,
+}
+
+impl TerminalGuard {
+    // Hide the cursor, saving its position first if it should be restored
+    pub fn new(stdout: &mut impl Write, restore_position: bool) -> ViuResult<Self> {
+        let guard = Self {
+            restore_position,
+            finished: false,
+            #[cfg(all(unix, feature = "signal-handler"))]
+            signals: register_signals(),
+        };
+        if restore_position {
+            execute!(stdout, SavePosition)?;
+        }
+        execute!(stdout, Hide)?;
+        Ok(guard)
+    }
+
+    // Restore the terminal at the end of the playback, returning the errors which Drop ignores
+    pub fn finish(mut self, stdout: &mut impl Write) -> ViuResult {
+        self.finished = true;
+        self.restore(stdout)
+    }
+
+    fn restore(&mut self, stdout: &mut impl Write) -> ViuResult {
+        #[cfg(all(unix, feature = "signal-handler"))]
+        for id in self.signals.drain(..) {
+            signal_hook::low_level::unregister(id);
+        }
+        execute!(stdout, ResetColor, Show)?;
+        if self.restore_position {
+            execute!(stdout, RestorePosition)?;
+        }
+        Ok(())
+    }
+}
+
+impl Drop for TerminalGuard {
+    fn drop(&mut self) {
+        if !self.finished {
+            let _ = self.restore(&mut std::io::stdout());
+        }
+    }
+}
+
+#[cfg(all(unix, feature = "signal-handler"))]
+lazy_static::lazy_static! {
+    // Whether SIGINT and SIGTERM ended the process before the guard registered its handlers
+    // the first time. Afterwards, the handler installed by signal-hook stays in place.
+    static ref ENDS_PROCESS: [bool; 2] = [
+        has_default_action(libc::SIGINT),
+        has_default_action(libc::SIGTERM),
+    ];
+}
+
+// Restore the terminal when the process is interrupted or terminated. When the application
+// did not install its own handler, the signal then ends the process as it would have without
+// this one, while an application which handles it keeps running and can clean up itself.
+// Only async-signal-safe functions can be called here, so the sequence is written to the
+// file descriptor directly.
+#[cfg(all(unix, feature = "signal-handler"))]
+fn register_signals() -> Vec<signal_hook::SigId> {
+    use signal_hook::consts::{SIGINT, SIGTERM};
+    use signal_hook::low_level::{emulate_default_handler, register};
+
+    [SIGINT, SIGTERM]
+        .into_iter()
+        .zip(*ENDS_PROCESS)
+        .filter_map(|(signal, ends_process)| {
+            let action = move || {
+                unsafe { libc::write(libc::STDOUT_FILENO, RESTORE.as_ptr().cast(), RESTORE.len()) };
+                if ends_process {
+                    let _ = emulate_default_handler(signal);
+                }
+            };
+            unsafe { register(signal, action) }.ok()
+        })
+        .collect()
+}
+
+// Whether the signal has its default action, which ends the process for SIGINT and SIGTERM
+#[cfg(all(unix, feature = "signal-handler"))]
+fn has_default_action(signal: libc::c_int) -> bool {
+    let mut action = std::mem::MaybeUninit::<libc::sigaction>::zeroed();
+    let queried = unsafe { libc::sigaction(signal, std::ptr::null(), action.as_mut_ptr()) };
+    queried == 0 && unsafe { action.assume_init() }.sa_sigaction == libc::SIG_DFL
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_finish_restores_terminal() {
+        let mut out = Vec::new();
+        let guard = TerminalGuard::new(&mut out, true).unwrap();
+        assert_eq!(out, b"\x1b7\x1b[?25l");
+        out.clear();
+        guard.finish(&mut out).unwrap();
+        assert_eq!(out, b"\x1b[0m\x1b[?25h\x1b8");
+    }
+
+    #[cfg(all(unix, feature = "signal-handler"))]
+    #[test]
+    fn test_keeps_application_handler() {
+        // An application handling the signal is not ended by the guard's handler
+        let handled = signal_hook::consts::SIGUSR2;
+        assert!(has_default_action(handled));
+        let flag = std::sync::Arc::new(std::sync::atomic::AtomicBool::new(false));
+        signal_hook::flag::register(handled, flag).unwrap();
+        assert!(!has_default_action(handled));
+    }
+}
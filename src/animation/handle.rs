@@ -0,0 +1,165 @@
+use std::sync::{Arc, Condvar, Mutex, MutexGuard};
+use std::time::{Duration, Instant};
+
+#[derive(Default)]
+struct State {
+    paused: bool,
+    stopped: bool,
+    seek: Option<usize>,
+}
+
+/// Controls the playback of an animation, see [crate::print_frames_with_handle].
+///
+/// The handle can be cloned and sent to other threads, and all clones control the same playback.
+/// ## Example
+/// ```no_run
+/// use viuer::{print_frames_with_handle, Config, FrameIterator, PlaybackHandle};
+///
+/// let handle = PlaybackHandle::new();
+/// let control = handle.clone();
+/// std::thread::spawn(move || {
+///     std::thread::sleep(std::time::Duration::from_secs(2));
+///     control.stop();
+/// });
+///
+/// let frames = FrameIterator::from_file("animation.gif").expect("Could not decode file.");
+/// print_frames_with_handle(frames, &Config::default(), &handle).expect("Playback failed.");
+/// ```
+#[derive(Clone, Default)]
+pub struct PlaybackHandle {
+    inner: Arc<(Mutex<State>, Condvar)>,
+}
+
+impl PlaybackHandle {
+    /// Create a handle for a new playback.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Pause the playback. The current frame stays on screen.
+    pub fn pause(&self) {
+        self.update(|state| state.paused = true);
+    }
+
+    /// Resume a paused playback.
+    pub fn resume(&self) {
+        self.update(|state| state.paused = false);
+    }
+
+    /// Stop the playback. It cannot be resumed afterwards.
+    pub fn stop(&self) {
+        self.update(|state| state.stopped = true);
+    }
+
+    /// Continue the playback from the given frame, counting from 0.
+    pub fn seek(&self, frame: usize) {
+        self.update(|state| state.seek = Some(frame));
+    }
+
+    /// Check whether the playback is paused.
+    pub fn is_paused(&self) -> bool {
+        self.lock().paused
+    }
+
+    /// Check whether the playback was stopped.
+    pub fn is_stopped(&self) -> bool {
+        self.lock().stopped
+    }
+
+    fn lock(&self) -> MutexGuard<'_, State> {
+        // The state stays consistent even if a thread panicked while holding the lock
+        self.inner.0.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    fn update(&self, f: impl FnOnce(&mut State)) {
+        f(&mut self.lock());
+        self.inner.1.notify_all();
+    }
+
+    // Block while the playback is paused. Returns false if it was stopped.
+    pub(crate) fn wait_while_paused(&self) -> bool {
+        let mut state = self.lock();
+        while state.paused && !state.stopped {
+            state = self.inner.1.wait(state).unwrap_or_else(|e| e.into_inner());
+        }
+        !state.stopped
+    }
+
+    // Sleep for the given duration, waking up early if the playback is paused, stopped
+    // or seeked in the meantime.
+    pub(crate) fn sleep(&self, duration: Duration) {
+        let deadline = Instant::now() + duration;
+        let mut state = self.lock();
+        while !state.paused && !state.stopped && state.seek.is_none() {
+            let now = Instant::now();
+            if now >= deadline {
+                break;
+            }
+            state = self
+                .inner
+                .1
+                .wait_timeout(state, deadline - now)
+                .unwrap_or_else(|e| e.into_inner())
+                .0;
+        }
+    }
+
+    // Take the frame requested through seek(), if any
+    pub(crate) fn take_seek(&self) -> Option<usize> {
+        self.lock().seek.take()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_handle_state() {
+        let handle = PlaybackHandle::new();
+        let clone = handle.clone();
+        assert!(!handle.is_paused());
+
+        clone.pause();
+        assert!(handle.is_paused());
+        clone.resume();
+        assert!(!handle.is_paused());
+
+        clone.seek(3);
+        assert_eq!(handle.take_seek(), Some(3));
+        assert_eq!(handle.take_seek(), None);
+
+        clone.stop();
+        assert!(handle.is_stopped());
+        assert!(!handle.wait_while_paused());
+    }
+
+    #[test]
+    fn test_sleep_interrupted() {
+        let handle = PlaybackHandle::new();
+        let clone = handle.clone();
+        let thread = std::thread::spawn(move || {
+            std::thread::sleep(Duration::from_millis(20));
+            clone.stop();
+        });
+
+        let start = Instant::now();
+        handle.sleep(Duration::from_secs(10));
+        assert!(start.elapsed() < Duration::from_secs(5));
+        thread.join().unwrap();
+    }
+
+    #[test]
+    fn test_resume_wakes_up() {
+        let handle = PlaybackHandle::new();
+        handle.pause();
+        let clone = handle.clone();
+        let thread = std::thread::spawn(move || {
+            std::thread::sleep(Duration::from_millis(20));
+            clone.resume();
+        });
+
+        assert!(handle.wait_while_paused());
+        thread.join().unwrap();
+    }
+}
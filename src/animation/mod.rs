@@ -0,0 +1,597 @@
+use crate::error::{ViuError, ViuResult};
+use crate::utils::{self, write_at_once_with};
+use crate::Config;
+
+#[cfg(feature = "tokio")]
+use crate::asynchronous::{forward, ChannelWriter};
+use image::codecs::{gif::GifDecoder, png::PngDecoder, webp::WebPDecoder};
+use image::{AnimationDecoder, DynamicImage, ImageFormat};
+use std::fs::File;
+use std::io::{BufRead, BufReader, Seek, Write};
+use std::path::Path;
+use std::time::{Duration, Instant};
+
+mod buffer;
+use buffer::{FrameBuffer, Lookup, DEFAULT_BUDGET};
+
+mod guard;
+use guard::TerminalGuard;
+
+mod handle;
+pub use handle::PlaybackHandle;
+
+mod renderer;
+use renderer::FrameRenderer;
+
+#[cfg(feature = "video")]
+mod video;
+#[cfg(feature = "video")]
+pub use video::{print_video, VideoFrames};
+
+/// Options for playing animations, set through [Config::animation].
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(default))]
+pub struct AnimationConfig {
+    /// How many times to play the animation. None loops forever. Defaults to `Some(1)`.
+    pub loop_count: Option<u32>,
+    /// Play the frames forwards and then backwards in every loop. Defaults to false.
+    pub bounce: bool,
+    /// Index of the first frame to play, counting from 0. Defaults to 0.
+    pub start_frame: usize,
+    /// Index of the frame where playback stops, exclusive. Defaults to None,
+    /// which plays until the last frame.
+    pub end_frame: Option<usize>,
+    /// Playback speed multiplier, applied to frame delays. Must be positive. Defaults to 1.0.
+    pub speed: f32,
+    /// Skip frames when printing falls behind the animation's timing, e.g. on slow
+    /// connections, so that it keeps playing in real time. Defaults to true.
+    pub skip_frames: bool,
+}
+
+impl Default for AnimationConfig {
+    fn default() -> Self {
+        Self {
+            loop_count: Some(1),
+            bounce: false,
+            start_frame: 0,
+            end_frame: None,
+            speed: 1.0,
+            skip_frames: true,
+        }
+    }
+}
+
+/// A single frame of an animation.
+pub struct Frame {
+    /// The image to display.
+    pub image: DynamicImage,
+    /// How long the frame should stay on screen.
+    pub delay: Duration,
+}
+
+impl From<image::Frame> for Frame {
+    fn from(frame: image::Frame) -> Self {
+        Self {
+            delay: frame.delay().into(),
+            image: DynamicImage::ImageRgba8(frame.into_buffer()),
+        }
+    }
+}
+
+/// Iterator over the frames of an animation, independent of the format it was decoded from.
+///
+/// Animated GIF, PNG (APNG) and WebP images are supported. Any other image, including
+/// still PNG and WebP files, is returned as a single frame.
+pub struct FrameIterator<'a> {
+    frames: Box<dyn Iterator<Item = ViuResult<Frame>> + 'a>,
+}
+
+impl FrameIterator<'static> {
+    /// Open a file and iterate over its frames.
+    pub fn from_file<P: AsRef<Path>>(filename: P) -> ViuResult<Self> {
+        let reader = BufReader::new(File::open(filename)?);
+        FrameIterator::from_reader(reader)
+    }
+}
+
+impl<'a> FrameIterator<'a> {
+    /// Guess the format of the data in the reader and iterate over its frames.
+    pub fn from_reader<R: BufRead + Seek + 'a>(reader: R) -> ViuResult<Self> {
+        let reader = image::io::Reader::new(reader).with_guessed_format()?;
+
+        let frames = match reader.format() {
+            Some(ImageFormat::Gif) => GifDecoder::new(reader.into_inner())?.into_frames(),
+            Some(ImageFormat::Png) => {
+                let decoder = PngDecoder::new(reader.into_inner())?;
+                if !decoder.is_apng() {
+                    return Ok(Self::from_image(DynamicImage::from_decoder(decoder)?));
+                }
+                decoder.apng().into_frames()
+            }
+            Some(ImageFormat::WebP) => {
+                let decoder = WebPDecoder::new(reader.into_inner())?;
+                if !decoder.has_animation() {
+                    return Ok(Self::from_image(DynamicImage::from_decoder(decoder)?));
+                }
+                decoder.into_frames()
+            }
+            _ => return Ok(Self::from_image(reader.decode()?)),
+        };
+
+        Ok(Self {
+            frames: Box::new(frames.map(|frame| frame.map(Frame::from).map_err(ViuError::from))),
+        })
+    }
+
+    /// A still image, as an animation of a single frame without delay.
+    pub fn from_image(img: DynamicImage) -> Self {
+        let frame = Frame {
+            image: img,
+            delay: Duration::ZERO,
+        };
+        Self {
+            frames: Box::new(std::iter::once(Ok(frame))),
+        }
+    }
+}
+
+impl Iterator for FrameIterator<'_> {
+    type Item = ViuResult<Frame>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.frames.next()
+    }
+}
+
+/// Print frames one after another at the same position, waiting for each frame's delay
+/// before drawing the next one. The printer is chosen once, before the first frame.
+/// Looping, the frame range and the speed are set through [Config::animation].
+///
+/// With the Kitty printer, the frames are sent through the animation protocol and replace
+/// the image on screen, instead of being printed as new images. With blocks, only the cells
+/// which changed since the previous frame are drawn.
+///
+/// When the output is closed, e.g. by piping it into `head`, the playback stops and
+/// returns successfully.
+///
+/// The cursor is hidden during the playback, and shown again when it ends, returns an error
+/// or panics. With the `signal-handler` feature, this also happens when the process is
+/// interrupted with Ctrl-C or terminated, before it exits as it would otherwise.
+///
+/// ## Example
+/// ```no_run
+/// use viuer::{print_frames, Config, FrameIterator};
+///
+/// let frames = FrameIterator::from_file("animation.gif").expect("Could not decode file.");
+/// print_frames(frames, &Config::default()).expect("Animation printing failed.");
+/// ```
+pub fn print_frames<I>(frames: I, config: &Config) -> ViuResult
+where
+    I: IntoIterator<Item = ViuResult<Frame>>,
+{
+    print_frames_with_handle(frames, config, &PlaybackHandle::new())
+}
+
+/// Like [print_frames], but the playback can be paused, resumed, stopped or moved to another
+/// frame through the given handle, e.g. from another thread.
+///
+/// Decoded frames are kept in memory up to 256 MiB, so that looping and seeking back do not
+/// decode them again. Beyond that, the oldest frames are dropped, and the playback ends when
+/// it loops or seeks back to one of them.
+pub fn print_frames_with_handle<I>(frames: I, config: &Config, handle: &PlaybackHandle) -> ViuResult
+where
+    I: IntoIterator<Item = ViuResult<Frame>>,
+{
+    let frames = FrameBuffer::new(frames.into_iter(), DEFAULT_BUDGET);
+    play(frames, config, handle)
+}
+
+// Play the frames from the buffer, see print_frames_with_handle
+fn play<I>(frames: FrameBuffer<I>, config: &Config, handle: &PlaybackHandle) -> ViuResult
+where
+    I: Iterator<Item = ViuResult<Frame>>,
+{
+    if !utils::should_print(config.when) {
+        return Ok(());
+    }
+    utils::with_stdout(config, |mut stdout, config| {
+        play_to(&mut stdout, frames, config, handle)
+    })
+}
+
+// Play the frames from the buffer, writing them to `stdout`
+fn play_to<I>(
+    stdout: &mut impl Write,
+    mut frames: FrameBuffer<I>,
+    config: &Config,
+    handle: &PlaybackHandle,
+) -> ViuResult
+where
+    I: Iterator<Item = ViuResult<Frame>>,
+{
+    let guard = TerminalGuard::new(stdout, config.restore_cursor)?;
+
+    let options = &config.animation;
+    let mut renderer = FrameRenderer::new(config);
+    let mut playhead = Playhead::new(options);
+    // The output of a frame, reused for all of them
+    let mut output = Vec::new();
+    // The time at which the current frame should appear on screen
+    let mut due = Instant::now();
+
+    loop {
+        let wait_start = Instant::now();
+        if !handle.wait_while_paused() {
+            break;
+        }
+        // Time spent paused does not count towards the playback
+        due += wait_start.elapsed();
+
+        if let Some(target) = handle.take_seek() {
+            playhead.seek(target);
+            due = Instant::now();
+        }
+
+        let index = playhead.index;
+        let frame = match next_frame(&mut frames, &playhead)? {
+            Lookup::Frame(frame) => frame,
+            Lookup::End => {
+                if !playhead.end_reached() {
+                    break;
+                }
+                continue;
+            }
+            // The frame cannot be decoded again, so the playback cannot go back to it
+            Lookup::Dropped => break,
+        };
+        let delay = if options.speed > 0.0 {
+            frame.delay.div_f32(options.speed)
+        } else {
+            frame.delay
+        };
+
+        if options.skip_frames && is_late(Instant::now(), due, delay) {
+            due += delay;
+        } else {
+            let written = write_at_once_with(stdout, &mut output, |mut stdout| {
+                renderer.draw(&mut stdout, index, &frame.image)
+            })
+            .and_then(|_| Ok(stdout.flush()?));
+            match written {
+                // Nothing reads the frames anymore, which ends the playback like stop does
+                Err(ViuError::Closed) => return Ok(()),
+                result => result?,
+            };
+            due += delay;
+            handle.sleep(due.saturating_duration_since(Instant::now()));
+        }
+
+        if !playhead.advance() {
+            break;
+        }
+    }
+
+    guard.finish(stdout)
+}
+
+/// Same as [print_frames], but writes the frames to an async writer. The frames are decoded,
+/// rendered and timed on a blocking thread, and the output is written from the calling task.
+///
+/// Only available with the `tokio` feature.
+#[cfg(feature = "tokio")]
+pub async fn print_frames_async<I, W>(frames: I, config: &Config, writer: &mut W) -> ViuResult
+where
+    I: IntoIterator<Item = ViuResult<Frame>>,
+    I::IntoIter: Send + 'static,
+    W: tokio::io::AsyncWrite + Unpin,
+{
+    print_frames_with_handle_async(frames, config, &PlaybackHandle::new(), writer).await
+}
+
+/// Same as [print_frames_with_handle], but writes the frames to an async writer, like
+/// [print_frames_async].
+///
+/// Only available with the `tokio` feature.
+#[cfg(feature = "tokio")]
+pub async fn print_frames_with_handle_async<I, W>(
+    frames: I,
+    config: &Config,
+    handle: &PlaybackHandle,
+    writer: &mut W,
+) -> ViuResult
+where
+    I: IntoIterator<Item = ViuResult<Frame>>,
+    I::IntoIter: Send + 'static,
+    W: tokio::io::AsyncWrite + Unpin,
+{
+    let frames = frames.into_iter();
+    play_async(
+        move || Ok(FrameBuffer::new(frames, DEFAULT_BUDGET)),
+        config,
+        handle,
+        writer,
+    )
+    .await
+}
+
+/// Same as [print_animation_from_file], but writes the frames to an async writer, like
+/// [print_frames_async]. The file is read on the blocking thread as well.
+///
+/// Only available with the `tokio` feature.
+#[cfg(feature = "tokio")]
+pub async fn print_animation_from_file_async<P, W>(
+    filename: P,
+    config: &Config,
+    writer: &mut W,
+) -> ViuResult
+where
+    P: AsRef<Path>,
+    W: tokio::io::AsyncWrite + Unpin,
+{
+    let filename = filename.as_ref().to_path_buf();
+    let open = move || {
+        let frames = FrameIterator::from_file(&filename)?;
+        Ok(FrameBuffer::new(frames, DEFAULT_BUDGET)
+            .with_reopen(move || FrameIterator::from_file(&filename)))
+    };
+    play_async(open, config, &PlaybackHandle::new(), writer).await
+}
+
+// Play the frames from the buffer made by `open` on a blocking thread, writing the output
+// from the calling task
+#[cfg(feature = "tokio")]
+async fn play_async<F, I, W>(
+    open: F,
+    config: &Config,
+    handle: &PlaybackHandle,
+    writer: &mut W,
+) -> ViuResult
+where
+    F: FnOnce() -> ViuResult<FrameBuffer<I>> + Send + 'static,
+    I: Iterator<Item = ViuResult<Frame>>,
+    W: tokio::io::AsyncWrite + Unpin,
+{
+    let (config, handle) = (config.clone(), handle.clone());
+    let (sender, receiver) = tokio::sync::mpsc::channel(1);
+    let playback = tokio::task::spawn_blocking(move || {
+        play_to(&mut ChannelWriter::new(sender), open()?, &config, &handle)
+    });
+
+    // When writing fails, the channel is closed, which stops the playback as well
+    let written = forward(receiver, writer).await;
+    let played = playback
+        .await
+        .map_err(|e| ViuError::Io(std::io::Error::other(e)))?;
+    match written {
+        // Nothing reads the frames anymore, see print_frames_with_handle
+        Err(ViuError::Closed) => Ok(()),
+        Err(e) => Err(e),
+        Ok(()) => played,
+    }
+}
+
+// Position of the playback, following the loop and bounce options
+struct Playhead<'a> {
+    options: &'a AnimationConfig,
+    index: usize,
+    forward: bool,
+    loops: u32,
+}
+
+impl<'a> Playhead<'a> {
+    fn new(options: &'a AnimationConfig) -> Self {
+        Self {
+            options,
+            index: options.start_frame,
+            forward: true,
+            loops: 0,
+        }
+    }
+
+    fn seek(&mut self, index: usize) {
+        self.index = index;
+        self.forward = true;
+    }
+
+    // Called when there is no frame at the current index, i.e. the end was reached while
+    // playing forwards. Returns false if the playback is over.
+    fn end_reached(&mut self) -> bool {
+        let start = self.options.start_frame;
+        if self.index <= start {
+            return false;
+        }
+        // Play backwards without repeating the last and first frames
+        if self.options.bounce && self.index >= start + 3 {
+            self.forward = false;
+            self.index -= 2;
+            return true;
+        }
+        self.next_loop()
+    }
+
+    // Move past the frame which was just displayed. Returns false if the playback is over.
+    fn advance(&mut self) -> bool {
+        if self.forward {
+            self.index += 1;
+        } else if self.index > self.options.start_frame + 1 {
+            self.index -= 1;
+        } else {
+            return self.next_loop();
+        }
+        true
+    }
+
+    fn next_loop(&mut self) -> bool {
+        self.loops += 1;
+        self.forward = true;
+        self.index = self.options.start_frame;
+        self.options.loop_count.is_none_or(|n| self.loops < n)
+    }
+}
+
+// Check whether the time to display a frame has already passed. Frames without a delay
+// are never considered late, so that still images are always displayed.
+fn is_late(now: Instant, due: Instant, delay: Duration) -> bool {
+    !delay.is_zero() && now >= due + delay
+}
+
+// The frame at the playhead, unless it is past the end of the frame range
+fn next_frame<'a, I>(frames: &'a mut FrameBuffer<I>, playhead: &Playhead) -> ViuResult<Lookup<'a>>
+where
+    I: Iterator<Item = ViuResult<Frame>>,
+{
+    let index = playhead.index;
+    if playhead.options.end_frame.is_some_and(|end| index >= end) {
+        return Ok(Lookup::End);
+    }
+    frames.get(index)
+}
+
+/// Decode an animated GIF, APNG or WebP file and play it according to [Config::animation].
+/// Still images are printed as usual.
+///
+/// Long animations whose frames do not fit in memory are decoded again from the file when
+/// they loop.
+pub fn print_animation_from_file<P: AsRef<Path>>(filename: P, config: &Config) -> ViuResult {
+    let filename = filename.as_ref().to_path_buf();
+    let frames = FrameBuffer::new(FrameIterator::from_file(&filename)?, DEFAULT_BUDGET)
+        .with_reopen(move || FrameIterator::from_file(&filename));
+    play(frames, config, &PlaybackHandle::new())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use image::codecs::gif::GifEncoder;
+    use image::{Delay, GenericImageView, ImageOutputFormat, Rgba, RgbaImage};
+    use std::io::Cursor;
+
+    #[test]
+    fn test_gif_frames() {
+        let mut buf = Vec::new();
+        {
+            let mut encoder = GifEncoder::new(&mut buf);
+            for (color, ms) in [(10, 100), (200, 50)] {
+                let img = RgbaImage::from_pixel(3, 2, Rgba([color, 0, 0, 255]));
+                let delay = Delay::from_numer_denom_ms(ms, 1);
+                encoder
+                    .encode_frame(image::Frame::from_parts(img, 0, 0, delay))
+                    .unwrap();
+            }
+        }
+
+        let frames: Vec<Frame> = FrameIterator::from_reader(Cursor::new(buf))
+            .unwrap()
+            .collect::<ViuResult<_>>()
+            .unwrap();
+        assert_eq!(frames.len(), 2);
+        assert_eq!(frames[0].delay, Duration::from_millis(100));
+        assert_eq!(frames[1].delay, Duration::from_millis(50));
+        assert_eq!(frames[1].image.dimensions(), (3, 2));
+        assert_eq!(frames[1].image.get_pixel(0, 0), Rgba([200, 0, 0, 255]));
+    }
+
+    // Simulate a playback of `count` frames and return the displayed indices
+    fn played_frames(options: &AnimationConfig, count: usize) -> Vec<usize> {
+        let mut playhead = Playhead::new(options);
+        let mut played = Vec::new();
+        while played.len() < 100 {
+            let end = options.end_frame.unwrap_or(count).min(count);
+            if playhead.index >= end {
+                if !playhead.end_reached() {
+                    break;
+                }
+                continue;
+            }
+            played.push(playhead.index);
+            if !playhead.advance() {
+                break;
+            }
+        }
+        played
+    }
+
+    #[test]
+    fn test_playback_order() {
+        let mut options = AnimationConfig::default();
+        assert_eq!(played_frames(&options, 3), [0, 1, 2]);
+
+        options.loop_count = Some(2);
+        assert_eq!(played_frames(&options, 3), [0, 1, 2, 0, 1, 2]);
+
+        options.bounce = true;
+        assert_eq!(
+            played_frames(&options, 4),
+            [0, 1, 2, 3, 2, 1, 0, 1, 2, 3, 2, 1]
+        );
+        assert_eq!(played_frames(&options, 2), [0, 1, 0, 1]);
+
+        options.start_frame = 1;
+        options.end_frame = Some(4);
+        options.loop_count = Some(1);
+        assert_eq!(played_frames(&options, 10), [1, 2, 3, 2]);
+
+        options.loop_count = None;
+        assert_eq!(played_frames(&options, 10).len(), 100);
+        assert_eq!(played_frames(&options, 1), []);
+    }
+
+    #[test]
+    fn test_is_late() {
+        let due = Instant::now();
+        let delay = Duration::from_millis(50);
+        assert!(!is_late(due, due, delay));
+        assert!(!is_late(due + Duration::from_millis(49), due, delay));
+        assert!(is_late(due + Duration::from_millis(50), due, delay));
+        assert!(!is_late(due + Duration::from_secs(1), due, Duration::ZERO));
+    }
+
+    #[cfg(feature = "tokio")]
+    #[test]
+    fn test_print_frames_async() {
+        let frames = (0..3).map(|i| {
+            Ok(Frame {
+                image: DynamicImage::ImageRgba8(RgbaImage::from_pixel(2, 2, Rgba([i, 0, 0, 255]))),
+                delay: Duration::from_millis(1),
+            })
+        });
+        let config = Config {
+            printer: Some(crate::PrinterType::Block),
+            absolute_offset: false,
+            width: Some(2),
+            animation: AnimationConfig {
+                skip_frames: false,
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        let mut out = Vec::new();
+        tokio::runtime::Builder::new_current_thread()
+            .build()
+            .unwrap()
+            .block_on(print_frames_async(frames, &config, &mut out))
+            .unwrap();
+        let out = String::from_utf8_lossy(&out);
+        // The cursor is hidden during the playback and shown at the end
+        assert!(out.starts_with("\x1b[?25l"));
+        assert!(out.ends_with("\x1b[?25h"));
+        assert!(out.contains("\u{2584}"));
+    }
+
+    #[test]
+    fn test_still_image_frames() {
+        let mut buf = Vec::new();
+        DynamicImage::ImageRgba8(RgbaImage::new(4, 4))
+            .write_to(&mut Cursor::new(&mut buf), ImageOutputFormat::Png)
+            .unwrap();
+
+        let frames: Vec<Frame> = FrameIterator::from_reader(Cursor::new(buf))
+            .unwrap()
+            .collect::<ViuResult<_>>()
+            .unwrap();
+        assert_eq!(frames.len(), 1);
+        assert_eq!(frames[0].delay, Duration::ZERO);
+        assert_eq!(frames[0].image.dimensions(), (4, 4));
+    }
+}
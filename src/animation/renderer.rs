@@ -0,0 +1,107 @@
+use crate::error::ViuResult;
+use crate::printer::backend::CrosstermBackend;
+use crate::printer::{cell, kitty, post_process, render_cells_into};
+use crate::utils::cells;
+use crate::{choose_printer, transform, Config, Printer, PrinterType};
+
+use crossterm::cursor::MoveToPreviousLine;
+use crossterm::execute;
+use image::{DynamicImage, GenericImageView};
+use std::io::Write;
+
+// Draws the frames of an animation over each other, keeping track of what is on screen.
+// The printer is chosen once, when the renderer is created.
+pub struct FrameRenderer {
+    printer: PrinterType,
+    config: Config,
+    kitty: kitty::KittyAnimation,
+    previous_height: Option<u32>,
+    previous_cells: Option<Vec<Vec<cell::Cell>>>,
+    // The cells of the frame being drawn. Swapped with previous_cells afterwards, so that
+    // the rows are allocated only once.
+    cells: Vec<Vec<cell::Cell>>,
+}
+
+impl FrameRenderer {
+    pub fn new(config: &Config) -> Self {
+        Self {
+            printer: choose_printer(config),
+            config: Config {
+                restore_cursor: false,
+                ..config.clone()
+            },
+            kitty: kitty::KittyAnimation::new(),
+            previous_height: None,
+            previous_cells: None,
+            cells: Vec::new(),
+        }
+    }
+
+    // Draw the frame at the index of the animation over the previous one. Returns its
+    // dimensions in terminal cells.
+    pub fn draw(
+        &mut self,
+        stdout: &mut impl Write,
+        index: usize,
+        img: &DynamicImage,
+    ) -> ViuResult<(u32, u32)> {
+        if self.previous_height.is_none() {
+            // Align with the first frame only, so that the animation does not move around
+            let dimensions = transform::dimensions(img.dimensions(), &self.config);
+            self.config = self.printer.align(dimensions, &self.config);
+        }
+
+        let size = match self.printer {
+            PrinterType::Kitty => {
+                // Kitty replaces the image on screen, the cursor does not need to move
+                let config = &self.config;
+                let medium = kitty::medium(config)?;
+                self.kitty.draw(stdout, index, config, medium, || {
+                    post_process(transform::apply(img, config), config).into_owned()
+                })?
+            }
+            PrinterType::Block => {
+                let img = transform::apply(img, &self.config);
+                render_cells_into(&img, &self.config, &mut self.cells);
+                let mut stream = CrosstermBackend::new(&mut *stdout);
+                let size = match &self.previous_cells {
+                    Some(previous) if same_size(previous, &self.cells) => {
+                        cell::write_cells_diff(&mut stream, &self.cells, previous, &self.config)?
+                    }
+                    _ => {
+                        self.move_to_previous(&mut stream)?;
+                        cell::write_cells(&mut stream, &self.cells, &self.config)?
+                    }
+                };
+                match &mut self.previous_cells {
+                    Some(previous) => std::mem::swap(previous, &mut self.cells),
+                    None => self.previous_cells = Some(std::mem::take(&mut self.cells)),
+                }
+                size
+            }
+            _ => {
+                self.move_to_previous(stdout)?;
+                self.printer.print(stdout, img, &self.config)?
+            }
+        };
+        self.previous_height = Some(size.1);
+        Ok(size)
+    }
+
+    // With a relative offset, go back to the first line of the previous frame so that the
+    // next one is drawn over it. The offset was already applied when printing the first frame.
+    fn move_to_previous(&mut self, stdout: &mut impl Write) -> ViuResult {
+        if let Some(height) = self.previous_height {
+            if !self.config.absolute_offset {
+                let height = cells(height)?;
+                execute!(stdout, MoveToPreviousLine(height))?;
+                self.config.y = 0;
+            }
+        }
+        Ok(())
+    }
+}
+
+fn same_size(a: &[Vec<cell::Cell>], b: &[Vec<cell::Cell>]) -> bool {
+    a.len() == b.len() && a.iter().zip(b).all(|(a, b)| a.len() == b.len())
+}
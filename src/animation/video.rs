@@ -0,0 +1,149 @@
+use super::buffer::FrameBuffer;
+use super::{Frame, PlaybackHandle};
+use crate::error::{ViuError, ViuResult};
+
+use image::{DynamicImage, RgbaImage};
+use std::ffi::OsStr;
+use std::io::{Error, ErrorKind, Read};
+use std::process::{Child, ChildStdout, Command, Stdio};
+use std::time::Duration;
+
+/// Iterator over the frames of a video, decoded by an `ffmpeg` process.
+///
+/// Requires the `ffmpeg` and `ffprobe` binaries to be available in `PATH`. Anything they can
+/// read is supported, including files, URLs and streams.
+/// ## Example
+/// ```no_run
+/// use viuer::{print_frames, Config, VideoFrames};
+///
+/// let frames = VideoFrames::open("video.mp4").expect("Could not start ffmpeg.");
+/// print_frames(frames, &Config::default()).expect("Video playback failed.");
+/// ```
+pub struct VideoFrames {
+    child: Child,
+    stdout: ChildStdout,
+    width: u32,
+    height: u32,
+    delay: Duration,
+}
+
+impl VideoFrames {
+    /// Start decoding the first video stream of the input.
+    pub fn open<S: AsRef<OsStr>>(input: S) -> ViuResult<Self> {
+        let probe = Command::new("ffprobe")
+            .args(["-v", "error", "-select_streams", "v:0"])
+            .args(["-show_entries", "stream=width,height,r_frame_rate"])
+            .args(["-of", "csv=p=0"])
+            .arg(input.as_ref())
+            .stderr(Stdio::null())
+            .output()?;
+        let (width, height, delay) = parse_probe_output(&String::from_utf8_lossy(&probe.stdout))?;
+
+        let mut child = Command::new("ffmpeg")
+            .args(["-loglevel", "error", "-i"])
+            .arg(input.as_ref())
+            .args(["-an", "-f", "rawvideo", "-pix_fmt", "rgba", "-"])
+            .stdin(Stdio::null())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::null())
+            .spawn()?;
+        let stdout = child
+            .stdout
+            .take()
+            .ok_or_else(|| Error::other("Could not read ffmpeg output"))?;
+
+        Ok(Self {
+            child,
+            stdout,
+            width,
+            height,
+            delay,
+        })
+    }
+}
+
+impl Iterator for VideoFrames {
+    type Item = ViuResult<Frame>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let mut buf = vec![0; (self.width * self.height * 4) as usize];
+        match self.stdout.read_exact(&mut buf) {
+            Ok(()) => {}
+            // The video ended
+            Err(e) if e.kind() == ErrorKind::UnexpectedEof => return None,
+            Err(e) => return Some(Err(e.into())),
+        }
+
+        let img = RgbaImage::from_raw(self.width, self.height, buf)?;
+        Some(Ok(Frame {
+            image: DynamicImage::ImageRgba8(img),
+            delay: self.delay,
+        }))
+    }
+}
+
+impl Drop for VideoFrames {
+    fn drop(&mut self) {
+        // Playback may be stopped before the end of the video
+        let _ = self.child.kill();
+        let _ = self.child.wait();
+    }
+}
+
+// Parse the "width,height,frame_rate" line written by ffprobe, where the frame rate is a
+// fraction such as 30000/1001. Returns the frame dimensions and the delay between frames.
+fn parse_probe_output(output: &str) -> ViuResult<(u32, u32, Duration)> {
+    let invalid = || ViuError::Io(Error::new(ErrorKind::InvalidData, "No video stream found"));
+
+    let mut fields = output.lines().next().ok_or_else(invalid)?.trim().split(',');
+    let mut next_number = || -> ViuResult<u32> {
+        fields
+            .next()
+            .and_then(|f| f.parse().ok())
+            .filter(|&n| n > 0)
+            .ok_or_else(invalid)
+    };
+    let (width, height) = (next_number()?, next_number()?);
+
+    let rate = fields.next().ok_or_else(invalid)?;
+    let (num, den) = rate.split_once('/').unwrap_or((rate, "1"));
+    let (num, den): (f64, f64) = match (num.parse(), den.parse()) {
+        (Ok(num), Ok(den)) if num > 0.0 && den > 0.0 => (num, den),
+        _ => return Err(invalid()),
+    };
+
+    Ok((width, height, Duration::from_secs_f64(den / num)))
+}
+
+/// Decode a video with `ffmpeg` and play it. See [VideoFrames] for the requirements.
+///
+/// The frames are streamed: only the one being printed is kept in memory, and `ffmpeg` is
+/// started again when the video loops.
+pub fn print_video<S: AsRef<OsStr>>(input: S, config: &crate::Config) -> ViuResult {
+    let input = input.as_ref().to_os_string();
+    let frames = FrameBuffer::new(VideoFrames::open(&input)?, 0)
+        .with_reopen(move || VideoFrames::open(&input));
+    super::play(frames, config, &PlaybackHandle::new())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_probe_output() {
+        let (w, h, delay) = parse_probe_output("1920,1080,25/1\n").unwrap();
+        assert_eq!((w, h), (1920, 1080));
+        assert_eq!(delay, Duration::from_millis(40));
+
+        let (_, _, delay) = parse_probe_output("640,480,30000/1001").unwrap();
+        assert_eq!(delay.as_micros(), 33366);
+    }
+
+    #[test]
+    fn test_parse_invalid_probe_output() {
+        assert!(parse_probe_output("").is_err());
+        assert!(parse_probe_output("640,0,25/1").is_err());
+        assert!(parse_probe_output("640,480,0/0").is_err());
+    }
+}
@@ -0,0 +1,125 @@
+// Printing from async code running on tokio. The output is rendered into a buffer, which is
+// then written in chunks, so that large payloads do not block the runtime while they drain.
+use crate::error::{ViuError, ViuResult};
+use crate::{print_from_file_to, print_to, Config, PrintedInfo};
+
+use image::DynamicImage;
+use std::io::{self, Write};
+use std::path::Path;
+use tokio::io::{AsyncWrite, AsyncWriteExt};
+use tokio::sync::mpsc::{Receiver, Sender};
+
+// Size of the chunks in which the rendered output is written
+const CHUNK_SIZE: usize = 16 * 1024;
+
+/// Same as [crate::print], but writes the output to an async writer, yielding to the
+/// runtime between chunks of output. The image itself is resized and encoded on the
+/// calling task, before anything is written.
+///
+/// Only available with the `tokio` feature.
+/// ## Example
+/// ```no_run
+/// # async fn run() -> viuer::ViuResult {
+/// use viuer::{print_async, Config};
+///
+/// let img = image::DynamicImage::ImageRgba8(image::RgbaImage::new(20, 10));
+/// print_async(&img, &Config::default(), &mut tokio::io::stdout()).await?;
+/// # Ok(())
+/// # }
+/// ```
+pub async fn print_async<W>(
+    img: &DynamicImage,
+    config: &Config,
+    writer: &mut W,
+) -> ViuResult<PrintedInfo>
+where
+    W: AsyncWrite + Unpin,
+{
+    let mut buf = Vec::new();
+    let info = print_to(img, config, &mut buf)?;
+    write_chunks(writer, &buf).await?;
+    Ok(info)
+}
+
+/// Same as [crate::print_from_file], but writes the output to an async writer. The file is
+/// read, decoded and rendered on a blocking thread, and the output written in chunks.
+///
+/// Only available with the `tokio` feature.
+pub async fn print_from_file_async<P, W>(
+    filename: P,
+    config: &Config,
+    writer: &mut W,
+) -> ViuResult<PrintedInfo>
+where
+    P: AsRef<Path>,
+    W: AsyncWrite + Unpin,
+{
+    let filename = filename.as_ref().to_path_buf();
+    let config = config.clone();
+    let (info, buf) = tokio::task::spawn_blocking(move || {
+        let mut buf = Vec::new();
+        print_from_file_to(filename, &config, &mut buf).map(|info| (info, buf))
+    })
+    .await
+    .map_err(|e| ViuError::Io(std::io::Error::other(e)))??;
+
+    write_chunks(writer, &buf).await?;
+    Ok(info)
+}
+
+// Write the whole buffer and flush it, letting other tasks run after every chunk
+pub(crate) async fn write_chunks<W>(writer: &mut W, buf: &[u8]) -> ViuResult
+where
+    W: AsyncWrite + Unpin,
+{
+    for chunk in buf.chunks(CHUNK_SIZE) {
+        writer.write_all(chunk).await?;
+        tokio::task::yield_now().await;
+    }
+    writer.flush().await?;
+    Ok(())
+}
+
+// Write the output received through the channel, until the sender is dropped. Returns when
+// writing fails, which closes the channel.
+pub(crate) async fn forward<W>(mut receiver: Receiver<Vec<u8>>, writer: &mut W) -> ViuResult
+where
+    W: AsyncWrite + Unpin,
+{
+    while let Some(output) = receiver.recv().await {
+        write_chunks(writer, &output).await?;
+    }
+    Ok(())
+}
+
+// Writer used on a blocking thread, which sends the output to an async task every time it is
+// flushed. Writing fails with a broken pipe once the channel is closed.
+pub(crate) struct ChannelWriter {
+    sender: Sender<Vec<u8>>,
+    buf: Vec<u8>,
+}
+
+impl ChannelWriter {
+    pub fn new(sender: Sender<Vec<u8>>) -> Self {
+        Self {
+            sender,
+            buf: Vec::new(),
+        }
+    }
+}
+
+impl Write for ChannelWriter {
+    fn write(&mut self, data: &[u8]) -> io::Result<usize> {
+        self.buf.extend_from_slice(data);
+        Ok(data.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        if self.buf.is_empty() {
+            return Ok(());
+        }
+        self.sender
+            .blocking_send(std::mem::take(&mut self.buf))
+            .map_err(|_| io::Error::from(io::ErrorKind::BrokenPipe))
+    }
+}
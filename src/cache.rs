@@ -0,0 +1,220 @@
+//! Opt-in cache of rendered output, for applications which print the same images over and
+//! over, like file browsers re-drawing their thumbnails while scrolling.
+//!
+//! ## Example
+//! ```no_run
+//! use viuer::{cache::RenderCache, Config};
+//!
+//! let img = image::open("img.png").expect("Could not decode file.");
+//! let conf = Config {
+//!     width: Some(20),
+//!     ..Default::default()
+//! };
+//! let mut cache = RenderCache::new(64);
+//! // The second call writes the output of the first one again, without rendering
+//! cache.print(&img, &conf).expect("Image printing failed.");
+//! cache.print(&img, &conf).expect("Image printing failed.");
+//! ```
+use crate::{choose_printer, print_to, utils, Config, PrintedInfo, PrinterType, ViuResult};
+
+use image::{DynamicImage, GenericImageView};
+use std::collections::hash_map::DefaultHasher;
+use std::collections::VecDeque;
+use std::hash::{Hash, Hasher};
+use std::io::Write;
+
+/// Least recently used cache of printed images. The output is kept for each combination of
+/// image content, target size, offset and printer, so the other settings of the [Config]
+/// passed to the cache should stay the same.
+///
+/// Output which cannot be written again is printed without caching: Kitty images shared
+/// through temporary files, which the terminal deletes after reading them.
+pub struct RenderCache {
+    capacity: usize,
+    // The most recently used entry is last
+    entries: VecDeque<(Key, Rendered)>,
+}
+
+#[derive(PartialEq, Eq)]
+struct Key {
+    image: u64,
+    size: (u32, u32),
+    offset: (u16, i16, bool),
+    printer: PrinterType,
+}
+
+struct Rendered {
+    payload: Vec<u8>,
+    width: u32,
+    height: u32,
+}
+
+impl RenderCache {
+    /// Create a cache which keeps the output of at most `capacity` images.
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            capacity,
+            entries: VecDeque::with_capacity(capacity),
+        }
+    }
+
+    /// Same as [crate::print], reusing the output of an earlier call for the same image,
+    /// size, offset and printer.
+    pub fn print(&mut self, img: &DynamicImage, config: &Config) -> ViuResult<PrintedInfo> {
+        if !utils::should_print(config.when) {
+            return Ok(PrintedInfo::nothing());
+        }
+        utils::with_stdout(config, |mut stdout, config| {
+            self.print_to(img, config, &mut stdout)
+        })
+    }
+
+    /// Same as [crate::print_to], reusing the output of an earlier call for the same image,
+    /// size, offset and printer.
+    pub fn print_to(
+        &mut self,
+        img: &DynamicImage,
+        config: &Config,
+        writer: &mut impl Write,
+    ) -> ViuResult<PrintedInfo> {
+        let printer = choose_printer(config);
+        if !is_replayable(printer) {
+            return print_to(img, config, writer);
+        }
+
+        let key = Key {
+            image: image_hash(img),
+            size: printer.fit(img.dimensions(), config),
+            offset: (config.x, config.y, config.absolute_offset),
+            printer,
+        };
+        let rendered = match self.take(&key) {
+            Some(rendered) => rendered,
+            None => {
+                let config = Config {
+                    printer: Some(printer),
+                    ..config.clone()
+                };
+                let mut payload = Vec::new();
+                let info = print_to(img, &config, &mut payload)?;
+                Rendered {
+                    payload,
+                    width: info.width,
+                    height: info.height,
+                }
+            }
+        };
+
+        writer.write_all(&rendered.payload)?;
+        writer.flush()?;
+        let info = PrintedInfo {
+            width: rendered.width,
+            height: rendered.height,
+            printer,
+            bytes_written: rendered.payload.len(),
+        };
+        self.insert(key, rendered);
+        Ok(info)
+    }
+
+    /// Number of images whose output is cached.
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    /// Whether no output is cached.
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// Drop all the cached output, e.g. after the terminal was resized.
+    pub fn clear(&mut self) {
+        self.entries.clear();
+    }
+
+    // Remove the entry for the key, if any. It is put back as the most recent one by insert.
+    fn take(&mut self, key: &Key) -> Option<Rendered> {
+        let index = self.entries.iter().position(|(k, _)| k == key)?;
+        self.entries.remove(index).map(|(_, rendered)| rendered)
+    }
+
+    // Add an entry, evicting the least recently used one when the cache is full
+    fn insert(&mut self, key: Key, rendered: Rendered) {
+        if self.capacity == 0 {
+            return;
+        }
+        if self.entries.len() == self.capacity {
+            self.entries.pop_front();
+        }
+        self.entries.push_back((key, rendered));
+    }
+}
+
+// Whether the output of the printer can be written again later
+fn is_replayable(printer: PrinterType) -> bool {
+    match printer {
+        PrinterType::Kitty => crate::get_kitty_support() != crate::KittySupport::Local,
+        _ => true,
+    }
+}
+
+fn image_hash(img: &DynamicImage) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    img.dimensions().hash(&mut hasher);
+    img.color().hash(&mut hasher);
+    img.as_bytes().hash(&mut hasher);
+    hasher.finish()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use image::{Rgba, RgbaImage};
+
+    fn block_config(width: u32) -> Config {
+        Config {
+            printer: Some(PrinterType::Block),
+            absolute_offset: false,
+            width: Some(width),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_cached_output() {
+        let img = DynamicImage::ImageRgba8(RgbaImage::from_pixel(4, 4, Rgba([1, 2, 3, 255])));
+        let mut cache = RenderCache::new(2);
+
+        let mut first = Vec::new();
+        let info = cache.print_to(&img, &block_config(4), &mut first).unwrap();
+        let mut second = Vec::new();
+        let cached = cache.print_to(&img, &block_config(4), &mut second).unwrap();
+        assert_eq!(first, second);
+        assert_eq!(info, cached);
+        assert_eq!(info.bytes_written, first.len());
+        assert_eq!(cache.len(), 1);
+
+        cache.print_to(&img, &block_config(2), &mut vec![]).unwrap();
+        assert_eq!(cache.len(), 2);
+    }
+
+    #[test]
+    fn test_least_recently_used_eviction() {
+        let images: Vec<_> = (0..3)
+            .map(|i| DynamicImage::ImageRgba8(RgbaImage::from_pixel(2, 2, Rgba([i, 0, 0, 255]))))
+            .collect();
+        let mut cache = RenderCache::new(2);
+        let config = block_config(2);
+
+        cache.print_to(&images[0], &config, &mut vec![]).unwrap();
+        cache.print_to(&images[1], &config, &mut vec![]).unwrap();
+        cache.print_to(&images[0], &config, &mut vec![]).unwrap();
+        cache.print_to(&images[2], &config, &mut vec![]).unwrap();
+
+        let hashes: Vec<_> = cache.entries.iter().map(|(key, _)| key.image).collect();
+        assert_eq!(hashes, [image_hash(&images[0]), image_hash(&images[2])]);
+
+        cache.clear();
+        assert!(cache.is_empty());
+    }
+}
@@ -1,10 +1,36 @@
+use crate::error::{ViuError, ViuResult};
 use crate::utils;
+use crate::{
+    AnimationConfig, BlockStyle, Border, CaptionPosition, Checkerboard, ColorDepth, Dither,
+    FilterFn, HAlign, KittyFormat, KittyMedium, Overlay, PrintWhen, PrinterType, ProtocolPolicy,
+    ResizePolicy, Rotation, SizeMode, Threshold, ToneMapping, VAlign, VisionFilter,
+};
+use image::{imageops::FilterType, io::Limits, Rgb, RgbaImage};
+use std::sync::Arc;
 
 /// Configuration struct to customize printing behaviour.
+///
+/// With the `serde` feature enabled, it can be serialized and deserialized. Missing fields
+/// take their default values, colors are written as `[r, g, b]` arrays.
+#[derive(Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(default))]
 pub struct Config {
     /// Enable true transparency instead of checkerboard background.
     /// Available only for the block printer. Defaults to false.
     pub transparent: bool,
+    /// Colors and size of the checkerboard shown behind transparent pixels.
+    /// See [Checkerboard] for the defaults.
+    pub checkerboard: Checkerboard,
+    /// Blend transparent pixels over this color instead of showing the checkerboard or
+    /// the terminal's background. Applies to every printer. Defaults to None.
+    #[cfg_attr(feature = "serde", serde(with = "crate::serde_remote::option_rgb"))]
+    pub background: Option<Rgb<u8>>,
+    /// With [Config::transparent], blend the image over the terminal's actual background
+    /// color, so that semi-transparent edges match it. The color is queried once, with
+    /// [crate::terminal_background_color]. Ignored when [Config::background] is set.
+    /// Defaults to false.
+    pub detect_background: bool,
     /// Make the x and y offset be relative to the top left terminal corner.
     /// If false, the y offset is relative to the cursor's position.
     /// Defaults to true.
@@ -20,32 +46,741 @@ pub struct Config {
     pub width: Option<u32>,
     /// Optional image height. Defaults to None.
     pub height: Option<u32>,
-    /// Use truecolor if the terminal supports it. Defaults to true.
+    /// Horizontal alignment of an image narrower than `width`, or than the rest of the
+    /// terminal when `width` is not set. Defaults to [HAlign::Left].
+    pub halign: HAlign,
+    /// Vertical alignment of an image shorter than `height`, or than the rest of the
+    /// terminal when `height` is not set. Defaults to [VAlign::Top].
+    pub valign: VAlign,
+    /// How the image is sized to `width` and `height`. Defaults to [SizeMode::Contain],
+    /// which preserves the aspect ratio. Use [SizeMode::Stretch] to match both exactly.
+    pub size_mode: SizeMode,
+    /// How the image is sized, in place of `width`, `height` and `size_mode`, which are
+    /// ignored when it is set. Defaults to None.
+    pub resize_policy: Option<ResizePolicy>,
+    /// Height of a terminal cell divided by its width, used to keep the image's proportions.
+    /// Defaults to None, which detects it from the window size in pixels reported by the
+    /// terminal and falls back to 2.
+    pub cell_aspect_ratio: Option<f32>,
+    /// Filter used when resizing the image for the block and Sixel printers.
+    /// [FilterType::Nearest] keeps pixel art sharp. Defaults to None, which uses
+    /// [FilterType::Nearest] for upscaled images and [FilterType::Triangle] otherwise.
+    #[cfg_attr(feature = "serde", serde(with = "crate::serde_remote::option_filter"))]
+    pub filter: Option<FilterType>,
+    /// Enlarge images smaller than `width` or `height`, or than the terminal when they are
+    /// not set, while preserving the aspect ratio. Defaults to false.
+    pub upscale: bool,
+    /// Resize the image in linear light instead of sRGB, which keeps fine detail from turning
+    /// darker when the image is scaled down. Defaults to true.
+    pub linear_resize: bool,
+    /// Draw a frame around the image. The image is shrunk to keep the frame within `width`
+    /// and `height`. Defaults to None.
+    pub border: Option<Border>,
+    /// Fill the space left around the image within `width` and `height` with this color, so
+    /// that exactly `width` x `height` cells are printed, e.g. for tiles of the same size.
+    /// The image is placed within that space by `halign` and `valign`. When `width` or
+    /// `height` is not set, the image's own size is used for it. Defaults to None.
+    #[cfg_attr(feature = "serde", serde(with = "crate::serde_remote::option_rgb"))]
+    pub letterbox: Option<Rgb<u8>>,
+    /// Text printed centered on a line below or above the image, within the same width.
+    /// Text that does not fit is truncated. Defaults to None.
+    pub caption: Option<String>,
+    /// Whether the caption is printed below or above the image.
+    /// Defaults to [CaptionPosition::Below].
+    pub caption_position: CaptionPosition,
+    /// Colors available when printing with blocks. Defaults to the depth detected from
+    /// the `COLORTERM` and `TERM` environment variables.
+    pub color_depth: ColorDepth,
+    /// Use truecolor if the terminal supports it. When false, the colors are limited to
+    /// [ColorDepth::Ansi256], whatever `color_depth` is. Defaults to true.
+    #[deprecated(note = "use color_depth instead")]
     pub truecolor: bool,
+    /// Dithering used when the colors have to be reduced to fit `color_depth`, or the
+    /// Sixel palette. Defaults to [Dither::None].
+    pub dither: Dither,
     /// Use Kitty protocol if the terminal supports it. Defaults to true.
     pub use_kitty: bool,
+    /// How images are sent with the Kitty protocol. Defaults to [KittyFormat::Auto].
+    pub kitty_format: KittyFormat,
+    /// Where images are put for the terminal to read them with the Kitty protocol, when it
+    /// runs locally, including the frames of animations and [crate::ImageHandle]s. Defaults
+    /// to [KittyMedium::Auto].
+    pub kitty_medium: KittyMedium,
     /// Use iTerm protocol if the terminal supports it. Defaults to true.
     pub use_iterm: bool,
     /// Use Sixel protocol if the terminal supports it. Defaults to true.
     #[cfg(feature = "sixel")]
     pub use_sixel: bool,
+    /// How the terminal's support for the graphics protocols is detected. Defaults to
+    /// [ProtocolPolicy::Auto], which only trusts the environment variables when the program
+    /// does not run over SSH or inside tmux.
+    pub protocol_policy: ProtocolPolicy,
+    /// How images are printed when stdout is not a terminal, e.g. when the output is piped
+    /// into `less`, or when the `NO_COLOR` environment variable is set.
+    /// Defaults to [PrintWhen::Always], which prints them as usual. [PrintWhen::Auto] prints
+    /// them without colors in both cases.
+    pub when: PrintWhen,
+    /// Force a specific printer, skipping detection and the `use_*` options.
+    /// Defaults to None, meaning Kitty, iTerm and Sixel are tried in that order
+    /// before falling back to half blocks.
+    ///
+    /// When it is None, users can override detection with the `VIUER_BACKEND` environment
+    /// variable, set to `blocks`, `kitty`, `iterm` or `sixel`, or disable the graphics
+    /// protocols with `VIUER_NO_GRAPHICS=1`. Unknown values are ignored. `VIUER_BACKEND=ascii`
+    /// prints to stdout without colors, like [PrintWhen::Auto] does when colors are off,
+    /// even with a printer set here.
+    pub printer: Option<PrinterType>,
+    /// Print only a region of the image, given as `(x, y, width, height)` in pixels.
+    /// The region is clamped to the image's bounds. Defaults to None.
+    pub crop: Option<(u32, u32, u32, u32)>,
+    /// Rotate the image clockwise, after cropping it. Defaults to None.
+    pub rotate: Option<Rotation>,
+    /// Mirror the image horizontally, after rotating it. Defaults to false.
+    pub flip_h: bool,
+    /// Mirror the image vertically, after rotating it. Defaults to false.
+    pub flip_v: bool,
+    /// Convert the image to grayscale before printing. Defaults to false.
+    pub grayscale: bool,
+    /// Invert the colors, e.g. to view film negatives. Like the other color adjustments, it
+    /// is applied to the resized image, so it costs little for large images.
+    /// Defaults to false.
+    pub invert: bool,
+    /// Give the image the brown tone of old photographs. Defaults to false.
+    pub sepia: bool,
+    /// Matrix replacing the red, green and blue channels, in its rows, by combinations of
+    /// them, applied after [Config::invert] and [Config::sepia]. Defaults to None.
+    pub color_matrix: Option<[[f32; 3]; 3]>,
+    /// Added to the color channels, as a fraction of their range, from -1 for black to 1 for
+    /// white. Defaults to 0.
+    pub brightness: f32,
+    /// Factor by which the color channels are moved away from mid gray, e.g. 1.5 to make
+    /// an image more legible in the terminal. Defaults to 1.
+    pub contrast: f32,
+    /// Factor by which the colors are moved away from gray, from 0 for grayscale.
+    /// Defaults to 1.
+    pub saturation: f32,
+    /// Gamma correction, brightening the midtones when above 1 and darkening them when
+    /// below 1. Defaults to 1.
+    pub gamma: f32,
+    /// Simulate a color vision deficiency, or correct the colors for it, e.g. to review the
+    /// accessibility of charts. Applied after the other adjustments. Defaults to None.
+    pub vision_filter: Option<VisionFilter>,
+    /// Print the image with only two colors, after the other color adjustments. Pairs well
+    /// with [BlockStyle::Braille]. Defaults to None.
+    pub threshold: Option<Threshold>,
+    /// Text and images drawn over the image, in their order, after the other
+    /// transformations and before it is resized. Not serialized. Defaults to none.
+    #[cfg_attr(feature = "serde", serde(skip))]
+    pub overlays: Vec<Overlay>,
+    /// Function run over the image after it was resized and adjusted, right before it is printed,
+    /// e.g. to invert it or key out a color, without copying the image once more. Images
+    /// sent with the Kitty or iTerm protocols are not resized, so it runs over the image as
+    /// it is sent. Not serialized. Defaults to None.
+    #[cfg_attr(feature = "serde", serde(skip))]
+    pub filter_fn: Option<FilterFn>,
+    /// How the light of high dynamic range images is fitted into the 8 bits per channel
+    /// printed to the terminal. Defaults to [ToneMapping::Reinhard].
+    pub tone_mapping: ToneMapping,
+    /// Factor applied to the light of high dynamic range images before tone mapping.
+    /// Defaults to None, which exposes the image so that its average is mid gray, or leaves
+    /// it as it is with [ToneMapping::Clamp]. When set, 16-bit images are exposed and tone
+    /// mapped too, e.g. linear astronomy data. Otherwise, their values are only narrowed to
+    /// 8 bits.
+    pub exposure: Option<f32>,
+    /// Characters used when printing with blocks. Defaults to [BlockStyle::HalfBlock].
+    pub block_style: BlockStyle,
+    /// Limits on the size of the images decoded from files, memory or readers, so that huge
+    /// or corrupt files are rejected with [ViuError::Image] instead of exhausting the memory.
+    /// Defaults to the default [Limits] of the image crate, which only cap the allocations
+    /// at 512 MiB.
+    #[cfg_attr(feature = "serde", serde(with = "crate::serde_remote::limits"))]
+    pub limits: Limits,
+    /// Page of multi-page images decoded from files, memory or readers, counted from 0: a
+    /// page of a TIFF file, or an icon of an ICO file. [crate::image_pages] lists the pages.
+    /// Defaults to None, which decodes the first page of a TIFF file and the largest icon
+    /// of an ICO file.
+    pub page: Option<u32>,
+    /// Options for playing animations. See [AnimationConfig] for the defaults.
+    pub animation: AnimationConfig,
 }
 
+#[allow(deprecated)]
 impl std::default::Default for Config {
     fn default() -> Self {
         Self {
             transparent: false,
+            checkerboard: Checkerboard::default(),
+            background: None,
+            detect_background: false,
             absolute_offset: true,
             x: 0,
             y: 0,
             restore_cursor: false,
             width: None,
             height: None,
-            truecolor: utils::truecolor_available(),
+            halign: HAlign::Left,
+            valign: VAlign::Top,
+            size_mode: SizeMode::Contain,
+            resize_policy: None,
+            cell_aspect_ratio: None,
+            filter: None,
+            upscale: false,
+            linear_resize: true,
+            border: None,
+            letterbox: None,
+            caption: None,
+            caption_position: CaptionPosition::Below,
+            color_depth: utils::color_depth(),
+            truecolor: true,
+            dither: Dither::None,
             use_kitty: true,
+            kitty_format: KittyFormat::Auto,
+            kitty_medium: KittyMedium::Auto,
             use_iterm: true,
             #[cfg(feature = "sixel")]
             use_sixel: true,
+            protocol_policy: ProtocolPolicy::Auto,
+            when: PrintWhen::Always,
+            printer: None,
+            crop: None,
+            rotate: None,
+            flip_h: false,
+            flip_v: false,
+            grayscale: false,
+            invert: false,
+            sepia: false,
+            color_matrix: None,
+            brightness: 0.0,
+            contrast: 1.0,
+            saturation: 1.0,
+            gamma: 1.0,
+            vision_filter: None,
+            threshold: None,
+            overlays: Vec::new(),
+            filter_fn: None,
+            tone_mapping: ToneMapping::Reinhard,
+            exposure: None,
+            block_style: BlockStyle::HalfBlock,
+            limits: Limits::default(),
+            page: None,
+            animation: AnimationConfig::default(),
+        }
+    }
+}
+
+impl Config {
+    /// Start building a config from the defaults, as an alternative to the struct update
+    /// syntax. The options are validated when calling [ConfigBuilder::build].
+    ///
+    /// ## Example
+    /// ```no_run
+    /// use viuer::{Config, PrinterType};
+    ///
+    /// let config = Config::builder()
+    ///     .width(40)
+    ///     .transparent(true)
+    ///     .printer(PrinterType::Kitty)
+    ///     .build()
+    ///     .expect("Invalid configuration.");
+    /// ```
+    pub fn builder() -> ConfigBuilder {
+        ConfigBuilder {
+            config: Config::default(),
+        }
+    }
+
+    // The width and height bounds which the image is sized to, from the resize policy
+    // when it is set
+    pub(crate) fn bounds(&self) -> (Option<u32>, Option<u32>) {
+        match self.resize_policy {
+            Some(policy) => policy.bounds(),
+            None => (self.width, self.height),
+        }
+    }
+
+    // The size mode which the image is sized with, from the resize policy when it is set
+    pub(crate) fn sizing(&self) -> SizeMode {
+        self.resize_policy
+            .map_or(self.size_mode, |policy| policy.size_mode())
+    }
+
+    // The colors which the image is printed with, from the deprecated truecolor option when
+    // it is turned off
+    #[allow(deprecated)]
+    pub(crate) fn depth(&self) -> ColorDepth {
+        match self.color_depth {
+            ColorDepth::TrueColor if !self.truecolor => ColorDepth::Ansi256,
+            depth => depth,
+        }
+    }
+
+    // Check for options which can never be printed
+    fn validate(&self) -> ViuResult {
+        let invalid = |message: &str| Err(ViuError::InvalidConfiguration(message.to_owned()));
+
+        if self.width == Some(0) || self.height == Some(0) {
+            return invalid("width and height must be greater than 0");
+        }
+        match self.resize_policy {
+            Some(ResizePolicy::FitWidth(0) | ResizePolicy::FitHeight(0)) => {
+                return invalid("the resize policy's bounds must be greater than 0");
+            }
+            Some(ResizePolicy::Exact(w, h)) if w == 0 || h == 0 => {
+                return invalid("the resize policy's bounds must be greater than 0");
+            }
+            Some(ResizePolicy::Scale(scale)) if !(scale.is_finite() && scale > 0.0) => {
+                return invalid("the resize scale must be a positive number");
+            }
+            _ => {}
         }
+        if self.absolute_offset && self.y < 0 {
+            return invalid("absolute_offset is true but y offset is negative");
+        }
+        if let Some(ratio) = self.cell_aspect_ratio {
+            if !(ratio.is_finite() && ratio > 0.0) {
+                return invalid("cell_aspect_ratio must be a positive number");
+            }
+        }
+        if let Some((_, _, width, height)) = self.crop {
+            if width == 0 || height == 0 {
+                return invalid("the crop region must not be empty");
+            }
+        }
+        if self.checkerboard.size == 0 {
+            return invalid("the checkerboard size must be greater than 0");
+        }
+        if let Some(matrix) = &self.color_matrix {
+            if !matrix.iter().flatten().all(|value| value.is_finite()) {
+                return invalid("the color matrix must only hold finite numbers");
+            }
+        }
+        if !(self.brightness.is_finite() && (-1.0..=1.0).contains(&self.brightness)) {
+            return invalid("brightness must be between -1 and 1");
+        }
+        if !(self.contrast.is_finite() && self.contrast >= 0.0) {
+            return invalid("contrast must not be negative");
+        }
+        if !(self.saturation.is_finite() && self.saturation >= 0.0) {
+            return invalid("saturation must not be negative");
+        }
+        if !(self.gamma.is_finite() && self.gamma > 0.0) {
+            return invalid("gamma must be a positive number");
+        }
+        if let Some(exposure) = self.exposure {
+            if !(exposure.is_finite() && exposure > 0.0) {
+                return invalid("exposure must be a positive number");
+            }
+        }
+        if !(self.animation.speed.is_finite() && self.animation.speed > 0.0) {
+            return invalid("the animation speed must be a positive number");
+        }
+        Ok(())
+    }
+}
+
+/// Chainable builder for [Config], created with [Config::builder].
+/// Options which are not set keep their default value.
+#[derive(Clone)]
+pub struct ConfigBuilder {
+    config: Config,
+}
+
+impl ConfigBuilder {
+    /// Set [Config::transparent].
+    pub fn transparent(mut self, transparent: bool) -> Self {
+        self.config.transparent = transparent;
+        self
+    }
+
+    /// Set [Config::checkerboard].
+    pub fn checkerboard(mut self, checkerboard: Checkerboard) -> Self {
+        self.config.checkerboard = checkerboard;
+        self
+    }
+
+    /// Set [Config::background].
+    pub fn background(mut self, background: Rgb<u8>) -> Self {
+        self.config.background = Some(background);
+        self
+    }
+
+    /// Set [Config::detect_background].
+    pub fn detect_background(mut self, detect_background: bool) -> Self {
+        self.config.detect_background = detect_background;
+        self
+    }
+
+    /// Set [Config::absolute_offset].
+    pub fn absolute_offset(mut self, absolute_offset: bool) -> Self {
+        self.config.absolute_offset = absolute_offset;
+        self
+    }
+
+    /// Set [Config::x].
+    pub fn x(mut self, x: u16) -> Self {
+        self.config.x = x;
+        self
+    }
+
+    /// Set [Config::y].
+    pub fn y(mut self, y: i16) -> Self {
+        self.config.y = y;
+        self
+    }
+
+    /// Set [Config::restore_cursor].
+    pub fn restore_cursor(mut self, restore_cursor: bool) -> Self {
+        self.config.restore_cursor = restore_cursor;
+        self
+    }
+
+    /// Set [Config::width].
+    pub fn width(mut self, width: u32) -> Self {
+        self.config.width = Some(width);
+        self
+    }
+
+    /// Set [Config::height].
+    pub fn height(mut self, height: u32) -> Self {
+        self.config.height = Some(height);
+        self
+    }
+
+    /// Set [Config::halign].
+    pub fn halign(mut self, halign: HAlign) -> Self {
+        self.config.halign = halign;
+        self
+    }
+
+    /// Set [Config::valign].
+    pub fn valign(mut self, valign: VAlign) -> Self {
+        self.config.valign = valign;
+        self
+    }
+
+    /// Set [Config::size_mode].
+    pub fn size_mode(mut self, size_mode: SizeMode) -> Self {
+        self.config.size_mode = size_mode;
+        self
+    }
+
+    /// Set [Config::resize_policy].
+    pub fn resize_policy(mut self, resize_policy: ResizePolicy) -> Self {
+        self.config.resize_policy = Some(resize_policy);
+        self
+    }
+
+    /// Set [Config::cell_aspect_ratio].
+    pub fn cell_aspect_ratio(mut self, cell_aspect_ratio: f32) -> Self {
+        self.config.cell_aspect_ratio = Some(cell_aspect_ratio);
+        self
+    }
+
+    /// Set [Config::filter].
+    pub fn filter(mut self, filter: FilterType) -> Self {
+        self.config.filter = Some(filter);
+        self
+    }
+
+    /// Set [Config::upscale].
+    pub fn upscale(mut self, upscale: bool) -> Self {
+        self.config.upscale = upscale;
+        self
+    }
+
+    /// Set [Config::linear_resize].
+    pub fn linear_resize(mut self, linear_resize: bool) -> Self {
+        self.config.linear_resize = linear_resize;
+        self
+    }
+
+    /// Set [Config::border].
+    pub fn border(mut self, border: Border) -> Self {
+        self.config.border = Some(border);
+        self
+    }
+
+    /// Set [Config::letterbox].
+    pub fn letterbox(mut self, letterbox: Rgb<u8>) -> Self {
+        self.config.letterbox = Some(letterbox);
+        self
+    }
+
+    /// Set [Config::caption].
+    pub fn caption(mut self, caption: impl Into<String>) -> Self {
+        self.config.caption = Some(caption.into());
+        self
+    }
+
+    /// Set [Config::caption_position].
+    pub fn caption_position(mut self, caption_position: CaptionPosition) -> Self {
+        self.config.caption_position = caption_position;
+        self
+    }
+
+    /// Set [Config::color_depth].
+    pub fn color_depth(mut self, color_depth: ColorDepth) -> Self {
+        self.config.color_depth = color_depth;
+        self
+    }
+
+    /// Set [Config::dither].
+    pub fn dither(mut self, dither: Dither) -> Self {
+        self.config.dither = dither;
+        self
+    }
+
+    /// Set [Config::use_kitty].
+    pub fn use_kitty(mut self, use_kitty: bool) -> Self {
+        self.config.use_kitty = use_kitty;
+        self
+    }
+
+    /// Set [Config::kitty_format].
+    pub fn kitty_format(mut self, kitty_format: KittyFormat) -> Self {
+        self.config.kitty_format = kitty_format;
+        self
+    }
+
+    /// Set [Config::kitty_medium].
+    pub fn kitty_medium(mut self, kitty_medium: KittyMedium) -> Self {
+        self.config.kitty_medium = kitty_medium;
+        self
+    }
+
+    /// Set [Config::use_iterm].
+    pub fn use_iterm(mut self, use_iterm: bool) -> Self {
+        self.config.use_iterm = use_iterm;
+        self
+    }
+
+    #[cfg(feature = "sixel")]
+    /// Set [Config::use_sixel].
+    pub fn use_sixel(mut self, use_sixel: bool) -> Self {
+        self.config.use_sixel = use_sixel;
+        self
+    }
+
+    /// Set [Config::protocol_policy].
+    pub fn protocol_policy(mut self, protocol_policy: ProtocolPolicy) -> Self {
+        self.config.protocol_policy = protocol_policy;
+        self
+    }
+
+    /// Set [Config::when].
+    pub fn when(mut self, when: PrintWhen) -> Self {
+        self.config.when = when;
+        self
+    }
+
+    /// Set [Config::printer].
+    pub fn printer(mut self, printer: PrinterType) -> Self {
+        self.config.printer = Some(printer);
+        self
+    }
+
+    /// Set [Config::crop].
+    pub fn crop(mut self, crop: (u32, u32, u32, u32)) -> Self {
+        self.config.crop = Some(crop);
+        self
+    }
+
+    /// Set [Config::rotate].
+    pub fn rotate(mut self, rotate: Rotation) -> Self {
+        self.config.rotate = Some(rotate);
+        self
+    }
+
+    /// Set [Config::flip_h].
+    pub fn flip_h(mut self, flip_h: bool) -> Self {
+        self.config.flip_h = flip_h;
+        self
+    }
+
+    /// Set [Config::flip_v].
+    pub fn flip_v(mut self, flip_v: bool) -> Self {
+        self.config.flip_v = flip_v;
+        self
+    }
+
+    /// Set [Config::grayscale].
+    pub fn grayscale(mut self, grayscale: bool) -> Self {
+        self.config.grayscale = grayscale;
+        self
+    }
+
+    /// Set [Config::invert].
+    pub fn invert(mut self, invert: bool) -> Self {
+        self.config.invert = invert;
+        self
+    }
+
+    /// Set [Config::sepia].
+    pub fn sepia(mut self, sepia: bool) -> Self {
+        self.config.sepia = sepia;
+        self
+    }
+
+    /// Set [Config::color_matrix].
+    pub fn color_matrix(mut self, color_matrix: [[f32; 3]; 3]) -> Self {
+        self.config.color_matrix = Some(color_matrix);
+        self
+    }
+
+    /// Set [Config::brightness].
+    pub fn brightness(mut self, brightness: f32) -> Self {
+        self.config.brightness = brightness;
+        self
+    }
+
+    /// Set [Config::contrast].
+    pub fn contrast(mut self, contrast: f32) -> Self {
+        self.config.contrast = contrast;
+        self
+    }
+
+    /// Set [Config::saturation].
+    pub fn saturation(mut self, saturation: f32) -> Self {
+        self.config.saturation = saturation;
+        self
+    }
+
+    /// Set [Config::gamma].
+    pub fn gamma(mut self, gamma: f32) -> Self {
+        self.config.gamma = gamma;
+        self
+    }
+
+    /// Set [Config::vision_filter].
+    pub fn vision_filter(mut self, vision_filter: VisionFilter) -> Self {
+        self.config.vision_filter = Some(vision_filter);
+        self
+    }
+
+    /// Set [Config::threshold].
+    pub fn threshold(mut self, threshold: Threshold) -> Self {
+        self.config.threshold = Some(threshold);
+        self
+    }
+
+    /// Add an overlay to [Config::overlays].
+    pub fn overlay(mut self, overlay: Overlay) -> Self {
+        self.config.overlays.push(overlay);
+        self
+    }
+
+    /// Set [Config::filter_fn].
+    pub fn filter_fn(mut self, filter_fn: impl Fn(&mut RgbaImage) + Send + Sync + 'static) -> Self {
+        self.config.filter_fn = Some(Arc::new(filter_fn));
+        self
+    }
+
+    /// Set [Config::tone_mapping].
+    pub fn tone_mapping(mut self, tone_mapping: ToneMapping) -> Self {
+        self.config.tone_mapping = tone_mapping;
+        self
+    }
+
+    /// Set [Config::exposure].
+    pub fn exposure(mut self, exposure: f32) -> Self {
+        self.config.exposure = Some(exposure);
+        self
+    }
+
+    /// Set [Config::block_style].
+    pub fn block_style(mut self, block_style: BlockStyle) -> Self {
+        self.config.block_style = block_style;
+        self
+    }
+
+    /// Set [Config::limits].
+    pub fn limits(mut self, limits: Limits) -> Self {
+        self.config.limits = limits;
+        self
+    }
+
+    /// Set [Config::page].
+    pub fn page(mut self, page: u32) -> Self {
+        self.config.page = Some(page);
+        self
+    }
+
+    /// Set [Config::animation].
+    pub fn animation(mut self, animation: AnimationConfig) -> Self {
+        self.config.animation = animation;
+        self
+    }
+
+    /// Validate the options and return the config. Fails with
+    /// [ViuError::InvalidConfiguration] if e.g. a zero width or height was given.
+    pub fn build(self) -> ViuResult<Config> {
+        self.config.validate()?;
+        Ok(self.config)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_builder() {
+        let config = Config::builder()
+            .width(40)
+            .transparent(true)
+            .caption("cat.png")
+            .printer(PrinterType::Kitty)
+            .build()
+            .unwrap();
+        assert_eq!(config.width, Some(40));
+        assert!(config.transparent);
+        assert_eq!(config.caption.as_deref(), Some("cat.png"));
+        assert_eq!(config.printer, Some(PrinterType::Kitty));
+        assert_eq!(config.height, None);
+    }
+
+    #[test]
+    #[allow(deprecated)]
+    fn test_truecolor() {
+        let config = Config {
+            color_depth: ColorDepth::TrueColor,
+            truecolor: false,
+            ..Default::default()
+        };
+        assert_eq!(config.depth(), ColorDepth::Ansi256);
+        let config = Config {
+            color_depth: ColorDepth::Ansi16,
+            truecolor: false,
+            ..Default::default()
+        };
+        assert_eq!(config.depth(), ColorDepth::Ansi16);
+        let config = Config {
+            color_depth: ColorDepth::TrueColor,
+            ..Default::default()
+        };
+        assert_eq!(config.depth(), ColorDepth::TrueColor);
+    }
+
+    #[test]
+    fn test_builder_validation() {
+        assert!(matches!(
+            Config::builder().width(0).build(),
+            Err(ViuError::InvalidConfiguration(_))
+        ));
+        assert!(Config::builder().y(-2).build().is_err());
+        assert!(Config::builder()
+            .absolute_offset(false)
+            .y(-2)
+            .build()
+            .is_ok());
+        assert!(Config::builder().cell_aspect_ratio(0.0).build().is_err());
+        assert!(Config::builder()
+            .resize_policy(ResizePolicy::Exact(10, 0))
+            .build()
+            .is_err());
+        assert!(Config::builder()
+            .resize_policy(ResizePolicy::Scale(f32::NAN))
+            .build()
+            .is_err());
     }
 }
@@ -0,0 +1,498 @@
+// Elements drawn around the printed image, independently of the printer: letterboxes, borders
+// and captions.
+use crate::error::ViuResult;
+use crate::printer::backend::{Backend, CrosstermBackend};
+use crate::printer::{align_cells, cell::rgb_to_color, needs_alignment};
+use crate::utils::cells;
+use crate::{Config, ResizePolicy};
+
+use crossterm::cursor::{MoveRight, MoveToNextLine, MoveToPreviousLine};
+use crossterm::queue;
+use crossterm::style::Colors;
+use image::Rgb;
+use std::io::Write;
+
+/// A frame drawn around the image, set through [Config::border].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(default))]
+pub struct Border {
+    /// Characters used to draw the frame. Defaults to [BorderStyle::Single].
+    pub style: BorderStyle,
+    /// Color of the frame. Defaults to None, which uses the terminal's foreground color.
+    #[cfg_attr(feature = "serde", serde(with = "crate::serde_remote::option_rgb"))]
+    pub color: Option<Rgb<u8>>,
+}
+
+impl Default for Border {
+    fn default() -> Self {
+        Self {
+            style: BorderStyle::Single,
+            color: None,
+        }
+    }
+}
+
+/// Where the caption is printed, set through [Config::caption_position].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum CaptionPosition {
+    /// On the line above the image.
+    Above,
+    /// On the line below the image.
+    Below,
+}
+
+/// The box drawing characters used for a [Border].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum BorderStyle {
+    /// ┌─┐
+    Single,
+    /// ╔═╗
+    Double,
+    /// ╭─╮
+    Rounded,
+    /// ┏━┓
+    Heavy,
+}
+
+impl BorderStyle {
+    // Top left, top right, bottom left, bottom right, horizontal and vertical characters
+    fn chars(self) -> [char; 6] {
+        match self {
+            BorderStyle::Single => ['┌', '┐', '└', '┘', '─', '│'],
+            BorderStyle::Double => ['╔', '╗', '╚', '╝', '═', '║'],
+            BorderStyle::Rounded => ['╭', '╮', '╰', '╯', '─', '│'],
+            BorderStyle::Heavy => ['┏', '┓', '┗', '┛', '━', '┃'],
+        }
+    }
+}
+
+// Print the image through the given function, with the decorations from the config around it.
+// The size of the printed image in terminal cells, for the config it is printed with, is only
+// requested when the decorations have to be aligned together with it. Returns the size of
+// everything that was printed, in terminal cells.
+pub fn print<W, F, S>(
+    stdout: &mut W,
+    config: &Config,
+    img_size: S,
+    print_image: F,
+) -> ViuResult<(u32, u32)>
+where
+    W: Write,
+    F: FnOnce(&mut W, &Config) -> ViuResult<(u32, u32)>,
+    S: FnOnce(&Config) -> ViuResult<(u32, u32)>,
+{
+    let caption = caption(config);
+    if config.border.is_none() && caption.is_none() && config.letterbox.is_none() {
+        return print_image(stdout, config);
+    }
+
+    let inner = inner_config(config);
+    // Align here, so that the decorations are drawn around the image wherever it ends up
+    let mut image_config = inner.clone();
+    if needs_alignment(&inner) || inner.letterbox.is_some() {
+        image_config = align(&inner, img_size(&inner)?);
+    }
+    let (mut width, mut height) = print_image(stdout, &image_config)?;
+    let mut x = image_config.x;
+
+    if let Some(fill) = config.letterbox {
+        let (box_width, box_height) = letterbox_size(&inner, (width, height));
+        let pad = (
+            (image_config.x - inner.x) as u32,
+            (image_config.y - inner.y) as u32,
+        );
+        draw_letterbox(
+            stdout,
+            fill,
+            &inner,
+            pad,
+            (width, height),
+            (box_width, box_height),
+        )?;
+        (width, height) = (box_width, box_height);
+        x = inner.x;
+    }
+    let x = x - config.border.is_some() as u16;
+
+    if let Some(border) = &config.border {
+        draw_border(stdout, border, x, config, width, height)?;
+        width += 2;
+        height += 2;
+    }
+
+    if let Some(caption) = caption {
+        draw_caption(stdout, caption, x, config.caption_position, width, height)?;
+        height += 1;
+    }
+
+    Ok((width, height))
+}
+
+// Same as print, returning the size of everything that would be printed without printing it.
+// `img_size` is the size of the image in terminal cells, for the config it is printed with.
+pub fn size(config: &Config, img_size: impl FnOnce(&Config) -> (u32, u32)) -> (u32, u32) {
+    let inner = inner_config(config);
+    let (width, height) = letterbox_size(&inner, img_size(&inner));
+    let border_size = if config.border.is_some() { 2 } else { 0 };
+    let caption_size = caption(config).is_some() as u32;
+    (width + border_size, height + border_size + caption_size)
+}
+
+fn caption(config: &Config) -> Option<&str> {
+    config
+        .caption
+        .as_deref()
+        .filter(|caption| !caption.is_empty())
+}
+
+// The config the image is printed with. It makes room for the decorations within the requested
+// size, leaves lines for the top border and a caption above, and moves the image to the right
+// of the left border.
+pub fn inner_config(config: &Config) -> Config {
+    let caption = caption(config);
+    if config.border.is_none() && caption.is_none() {
+        return config.clone();
+    }
+
+    let (border_size, border_offset) = if config.border.is_some() {
+        (2, 1)
+    } else {
+        (0, 0)
+    };
+    let caption_size = caption.is_some() as u32;
+    let caption_offset =
+        (caption.is_some() && config.caption_position == CaptionPosition::Above) as i16;
+    Config {
+        width: config.width.map(|w| w.saturating_sub(border_size).max(1)),
+        height: config
+            .height
+            .map(|h| h.saturating_sub(border_size + caption_size).max(1)),
+        resize_policy: config
+            .resize_policy
+            .map(|policy| policy.shrink(border_size, border_size + caption_size)),
+        x: config.x.saturating_add(border_offset),
+        y: config
+            .y
+            .saturating_add(border_offset as i16 + caption_offset),
+        caption: None,
+        ..config.clone()
+    }
+}
+
+// The config an image of the given size in terminal cells is printed with: moved to its place
+// within the letterbox, or within the available space when it is aligned.
+pub fn align(config: &Config, img_size: (u32, u32)) -> Config {
+    if config.letterbox.is_some() {
+        let (width, height) = letterbox_size(config, img_size);
+        let aligned = align_cells(
+            img_size,
+            &Config {
+                resize_policy: Some(ResizePolicy::Exact(width, height)),
+                ..config.clone()
+            },
+        );
+        return Config {
+            resize_policy: config.resize_policy,
+            ..aligned
+        };
+    }
+    if !needs_alignment(config) {
+        return config.clone();
+    }
+    align_cells(img_size, config)
+}
+
+// Size of what is printed for an image of the given size in terminal cells: the bounds when it
+// is letterboxed, the image otherwise
+fn letterbox_size(config: &Config, (width, height): (u32, u32)) -> (u32, u32) {
+    if config.letterbox.is_none() {
+        return (width, height);
+    }
+    let (bound_w, bound_h) = config.bounds();
+    (
+        bound_w.unwrap_or(width).max(width),
+        bound_h.unwrap_or(height).max(height),
+    )
+}
+
+// Fill the letterbox around an image of the given size, which was printed `pad` cells from
+// its top left corner. Printers leave the cursor on the line below the image, and it is left
+// on the line below the letterbox.
+fn draw_letterbox(
+    stdout: &mut impl Write,
+    fill: Rgb<u8>,
+    config: &Config,
+    (pad_x, pad_y): (u32, u32),
+    (width, height): (u32, u32),
+    (box_width, box_height): (u32, u32),
+) -> ViuResult {
+    let mut stdout = CrosstermBackend::new(stdout);
+    let color = Colors {
+        foreground: None,
+        background: Some(rgb_to_color((fill[0], fill[1], fill[2]), config.depth())),
+    };
+    let spaces = |n: u32| " ".repeat(n as usize);
+
+    if height + pad_y > 0 {
+        stdout.move_to_previous_line(cells(height + pad_y)?)?;
+    }
+    for row in 0..box_height {
+        move_right(&mut stdout, config.x)?;
+        stdout.set_colors(color)?;
+        if (pad_y..pad_y + height).contains(&row) {
+            write!(stdout, "{}", spaces(pad_x))?;
+            stdout.reset_colors()?;
+            move_right(&mut stdout, cells(width)?)?;
+            stdout.set_colors(color)?;
+            write!(stdout, "{}", spaces(box_width - pad_x - width))?;
+        } else {
+            write!(stdout, "{}", spaces(box_width))?;
+        }
+        stdout.reset_colors()?;
+        // writeln! is used instead of MoveDown to scroll when the box reaches the bottom
+        writeln!(stdout)?;
+    }
+    stdout.flush()?;
+    Ok(())
+}
+
+// Draw the border around an image of the given size, starting at column x. Printers leave
+// the cursor on the line below the image, which is where the bottom border goes.
+fn draw_border(
+    stdout: &mut impl Write,
+    border: &Border,
+    x: u16,
+    config: &Config,
+    width: u32,
+    height: u32,
+) -> ViuResult {
+    let [top_left, top_right, bottom_left, bottom_right, horizontal, vertical] =
+        border.style.chars();
+    let line = horizontal.to_string().repeat(width as usize);
+
+    let mut stdout = CrosstermBackend::new(stdout);
+    let color = Colors {
+        foreground: border
+            .color
+            .map(|c| rgb_to_color((c[0], c[1], c[2]), config.depth())),
+        background: None,
+    };
+
+    stdout.move_to_previous_line(cells(height + 1)?)?;
+    move_right(&mut stdout, x)?;
+    stdout.set_colors(color)?;
+    write!(stdout, "{}{}{}", top_left, line, top_right)?;
+
+    for _ in 0..height {
+        stdout.move_to_next_line(1)?;
+        move_right(&mut stdout, x)?;
+        write!(stdout, "{}", vertical)?;
+        stdout.move_right(cells(width)?)?;
+        write!(stdout, "{}", vertical)?;
+    }
+
+    stdout.move_to_next_line(1)?;
+    move_right(&mut stdout, x)?;
+    write!(stdout, "{}{}{}", bottom_left, line, bottom_right)?;
+    stdout.reset_colors()?;
+    writeln!(stdout)?;
+    stdout.flush()?;
+    Ok(())
+}
+
+// Write the caption centered on the line below or above what was printed, truncating it to
+// the given width. The cursor is left on the line below everything.
+fn draw_caption(
+    stdout: &mut impl Write,
+    caption: &str,
+    x: u16,
+    position: CaptionPosition,
+    width: u32,
+    height: u32,
+) -> ViuResult {
+    let lines = cells(height + 1)?;
+    let text = truncate(caption, width as usize);
+    let padding = (width as usize - text.chars().count()) / 2;
+
+    if position == CaptionPosition::Above {
+        queue!(stdout, MoveToPreviousLine(lines))?;
+    }
+    move_right(stdout, x.saturating_add(cells(padding as u32)?))?;
+    write!(stdout, "{}", text)?;
+    if position == CaptionPosition::Above {
+        queue!(stdout, MoveToNextLine(lines))?;
+    } else {
+        writeln!(stdout)?;
+    }
+    stdout.flush()?;
+    Ok(())
+}
+
+// Shorten the text to at most the given number of characters, ending it with an ellipsis
+// if anything had to be removed
+fn truncate(text: &str, width: usize) -> String {
+    if text.chars().count() <= width {
+        return text.to_owned();
+    }
+    let mut truncated: String = text.chars().take(width.saturating_sub(1)).collect();
+    if width > 0 {
+        truncated.push('…');
+    }
+    truncated
+}
+
+// Some terminals interpret 0 as 1, see MoveRight documentation
+fn move_right(stdout: &mut impl Write, columns: u16) -> ViuResult {
+    if columns > 0 {
+        queue!(stdout, MoveRight(columns))?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::HAlign;
+
+    fn no_size(_: &Config) -> ViuResult<(u32, u32)> {
+        panic!("the size is not needed without alignment")
+    }
+
+    #[test]
+    fn test_no_border() {
+        let mut buf = Vec::new();
+        let size = print(&mut buf, &Config::default(), no_size, |_, _| Ok((3, 2))).unwrap();
+        assert_eq!(size, (3, 2));
+        assert!(buf.is_empty());
+    }
+
+    #[test]
+    fn test_border() {
+        let config = Config {
+            x: 2,
+            width: Some(10),
+            border: Some(Border {
+                style: BorderStyle::Rounded,
+                color: None,
+            }),
+            ..Default::default()
+        };
+        let mut buf = Vec::new();
+        let size = print(&mut buf, &config, no_size, |_, inner| {
+            assert_eq!((inner.x, inner.y, inner.width), (3, 1, Some(8)));
+            Ok((3, 2))
+        })
+        .unwrap();
+
+        assert_eq!(size, (5, 4));
+        assert_eq!(
+            std::str::from_utf8(&buf).unwrap(),
+            "\x1b[3F\x1b[2C\x1b[0m╭───╮\x1b[1E\x1b[2C│\x1b[3C│\x1b[1E\x1b[2C│\x1b[3C│\
+             \x1b[1E\x1b[2C╰───╯\x1b[0m\n"
+        );
+    }
+
+    #[test]
+    fn test_caption() {
+        let config = Config {
+            x: 1,
+            height: Some(5),
+            caption: Some("cat.png".to_owned()),
+            ..Default::default()
+        };
+        let mut buf = Vec::new();
+        let size = print(&mut buf, &config, no_size, |_, inner| {
+            assert_eq!((inner.y, inner.height), (0, Some(4)));
+            Ok((11, 4))
+        })
+        .unwrap();
+
+        assert_eq!(size, (11, 5));
+        assert_eq!(std::str::from_utf8(&buf).unwrap(), "\x1b[3Ccat.png\n");
+    }
+
+    #[test]
+    fn test_caption_above_border() {
+        let config = Config {
+            caption: Some("a long caption".to_owned()),
+            caption_position: CaptionPosition::Above,
+            border: Some(Border::default()),
+            ..Default::default()
+        };
+        let mut buf = Vec::new();
+        let size = print(&mut buf, &config, no_size, |_, inner| {
+            assert_eq!((inner.x, inner.y), (1, 2));
+            Ok((3, 1))
+        })
+        .unwrap();
+
+        assert_eq!(size, (5, 4));
+        assert!(std::str::from_utf8(&buf)
+            .unwrap()
+            .ends_with("\x1b[4Fa lo…\x1b[4E"));
+    }
+
+    #[test]
+    fn test_aligned_border() {
+        let config = Config {
+            width: Some(12),
+            halign: HAlign::Right,
+            border: Some(Border::default()),
+            ..Default::default()
+        };
+        let mut buf = Vec::new();
+        // 4 columns wide, within the 10 left by the border
+        let size = print(
+            &mut buf,
+            &config,
+            |_| Ok((4, 1)),
+            |_, inner| {
+                assert_eq!((inner.x, inner.halign), (7, HAlign::Left));
+                Ok((4, 1))
+            },
+        )
+        .unwrap();
+
+        assert_eq!(size, (6, 3));
+        assert!(std::str::from_utf8(&buf)
+            .unwrap()
+            .starts_with("\x1b[2F\x1b[6C\x1b[0m┌────┐"));
+    }
+
+    #[test]
+    fn test_letterbox() {
+        let config = Config {
+            x: 1,
+            width: Some(6),
+            height: Some(3),
+            halign: HAlign::Center,
+            letterbox: Some(Rgb([0, 0, 0])),
+            color_depth: crate::ColorDepth::TrueColor,
+            ..Default::default()
+        };
+        let mut buf = Vec::new();
+        // 2x3, centered within the 6x3 box
+        let size = print(
+            &mut buf,
+            &config,
+            |_| Ok((2, 3)),
+            |_, inner| {
+                assert_eq!((inner.x, inner.halign), (3, HAlign::Left));
+                Ok((2, 3))
+            },
+        )
+        .unwrap();
+
+        assert_eq!(size, (6, 3));
+        assert_eq!(size, super::size(&config, |_| (2, 3)));
+        let row =
+            "\x1b[1C\x1b[0m\x1b[48;2;0;0;0m  \x1b[0m\x1b[2C\x1b[0m\x1b[48;2;0;0;0m  \x1b[0m\n";
+        assert_eq!(
+            std::str::from_utf8(&buf).unwrap(),
+            format!("\x1b[3F{}", row.repeat(3))
+        );
+    }
+}
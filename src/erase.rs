@@ -0,0 +1,121 @@
+// Removal of images which were printed before.
+use crate::error::ViuResult;
+use crate::printer::align_cells;
+use crate::utils::{cells, write_graphics};
+use crate::{Config, PrintedInfo, PrinterType};
+
+use crossterm::cursor::{
+    MoveTo, MoveToColumn, MoveToNextLine, MoveToPreviousLine, RestorePosition, SavePosition,
+};
+use crossterm::{execute, queue};
+use std::io::Write;
+
+/// Remove an image printed with the given config, which returned the given info.
+/// The cells it covered, decorations included, are overwritten with spaces. Images
+/// printed with Kitty are also deleted, as they are drawn above the text.
+///
+/// With a relative offset, the cursor has to be where printing left it: on the line below
+/// the image, or at the starting position with [Config::restore_cursor]. The cursor ends
+/// up on the line below the erased area, unless [Config::restore_cursor] is set.
+/// ## Example
+/// ```no_run
+/// use viuer::{clear, print_from_file, Config};
+///
+/// let conf = Config::default();
+/// let info = print_from_file("img.jpg", &conf).expect("Image printing failed.");
+/// // ...
+/// clear(&info, &conf).expect("Could not clear the image.");
+/// ```
+pub fn clear(info: &PrintedInfo, config: &Config) -> ViuResult {
+    clear_to(&mut std::io::stdout(), info, config)
+}
+
+fn clear_to(stdout: &mut impl Write, info: &PrintedInfo, config: &Config) -> ViuResult {
+    if info.width == 0 || info.height == 0 {
+        return Ok(());
+    }
+    if config.restore_cursor {
+        execute!(stdout, SavePosition)?;
+    }
+
+    // Go to the first line of what was printed
+    let aligned = align_cells((info.width, info.height), config);
+    if aligned.absolute_offset {
+        queue!(stdout, MoveTo(0, aligned.y.max(0) as u16))?;
+    } else if !config.restore_cursor {
+        let height = cells(info.height)?;
+        queue!(stdout, MoveToPreviousLine(height))?;
+    } else if aligned.y > 0 {
+        queue!(stdout, MoveToNextLine(aligned.y as u16))?;
+    } else if aligned.y < 0 {
+        queue!(stdout, MoveToPreviousLine(-aligned.y as u16))?;
+    }
+
+    let spaces = " ".repeat(info.width as usize);
+    for row in 0..info.height {
+        if row > 0 {
+            queue!(stdout, MoveToNextLine(1))?;
+        }
+        if info.printer == PrinterType::Kitty {
+            // Delete the placements intersecting the cell under the cursor, which is in the
+            // middle column so that it is not on a border
+            queue!(
+                stdout,
+                MoveToColumn(aligned.x.saturating_add((info.width / 2) as u16))
+            )?;
+            write_graphics(stdout, format_args!("\x1b_Ga=d,d=C,q=2\x1b\\"))?;
+        }
+        queue!(stdout, MoveToColumn(aligned.x))?;
+        write!(stdout, "{}", spaces)?;
+    }
+    writeln!(stdout)?;
+    stdout.flush()?;
+
+    if config.restore_cursor {
+        execute!(stdout, RestorePosition)?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn info(printer: PrinterType) -> PrintedInfo {
+        PrintedInfo {
+            width: 3,
+            height: 2,
+            printer,
+            bytes_written: 0,
+        }
+    }
+
+    #[test]
+    fn test_clear_absolute() {
+        let config = Config {
+            x: 4,
+            y: 1,
+            ..Default::default()
+        };
+        let mut buf = Vec::new();
+        clear_to(&mut buf, &info(PrinterType::Block), &config).unwrap();
+        assert_eq!(
+            std::str::from_utf8(&buf).unwrap(),
+            "\x1b[2;1H\x1b[5G   \x1b[1E\x1b[5G   \n"
+        );
+    }
+
+    #[test]
+    fn test_clear_relative_kitty() {
+        crate::utils::clear_terminal_env();
+        let config = Config {
+            absolute_offset: false,
+            ..Default::default()
+        };
+        let mut buf = Vec::new();
+        clear_to(&mut buf, &info(PrinterType::Kitty), &config).unwrap();
+        assert!(std::str::from_utf8(&buf)
+            .unwrap()
+            .starts_with("\x1b[2F\x1b[2G\x1b_Ga=d,d=C,q=2\x1b\\\x1b[1G   "));
+    }
+}
@@ -1,31 +1,115 @@
 /// Custom result type for error-prone operations
 pub type ViuResult<T = ()> = std::result::Result<T, ViuError>;
 
+/// Graphics protocols which can be unsupported, see [ViuError::UnsupportedProtocol].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ProtocolKind {
+    /// Kitty graphics protocol.
+    Kitty,
+    /// iTerm inline images protocol.
+    ITerm,
+    /// Sixel graphics.
+    Sixel,
+}
+
+impl std::fmt::Display for ProtocolKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ProtocolKind::Kitty => write!(f, "Kitty graphics protocol"),
+            ProtocolKind::ITerm => write!(f, "iTerm inline images protocol"),
+            ProtocolKind::Sixel => write!(f, "Sixel graphics"),
+        }
+    }
+}
+
 /// Custom error enum for `viu`ing operations
+///
+/// New variants can be added in minor releases, so matches on it need a wildcard arm.
 #[derive(Debug)]
+#[non_exhaustive]
 pub enum ViuError {
     /// Error while doing transformations with the [`image`] crate
     Image(image::ImageError),
     /// Error while doing IO operations
     Io(std::io::Error),
+    /// The output was closed before the image was written, e.g. when stdout is piped into
+    /// a program which exits early, like `head`
+    Closed,
     /// Invalid configuration provided
     InvalidConfiguration(String),
     /// Error while creating temp files
     Tempfile(tempfile::PersistError),
     /// Errenous response received from Kitty
     KittyResponse(Vec<console::Key>),
-    /// Kitty protocol not supported
+    /// Kitty protocol not supported. No longer returned, the library returns
+    /// `UnsupportedProtocol(ProtocolKind::Kitty)` instead.
+    #[deprecated(note = "match UnsupportedProtocol(ProtocolKind::Kitty) instead")]
     KittyNotSupported,
+    /// The graphics protocol is not supported by the terminal
+    UnsupportedProtocol(ProtocolKind),
+    /// The terminal did not reply to a query in time, so whether it supports a feature
+    /// is unknown
+    QueryTimeout,
+    /// The output needs `needed` columns, but only `available` are left in the terminal,
+    /// or in the width from the config
+    TerminalTooSmall {
+        /// Columns needed by the output
+        needed: u32,
+        /// Columns available for it
+        available: u32,
+    },
+    /// A size or position is too large to be expressed in terminal cells
+    SizeOverflow,
+    /// The external decoder could not decode the image, with what it printed to stderr
+    DecoderFailed {
+        /// The decoder which was run
+        decoder: String,
+        /// Its error output
+        message: String,
+    },
+    /// None of the external decoders for the format of the image is installed, with the
+    /// ones which were tried
+    DecoderNotFound(Vec<String>),
     /// Error while printing with sixel
     #[cfg(feature = "sixel")]
     SixelError(sixel_rs::status::Error),
+    /// Error while downloading an image
+    #[cfg(feature = "network")]
+    Http(Box<ureq::Error>),
+    /// The downloaded image is larger than the allowed size, in bytes
+    #[cfg(feature = "network")]
+    TooLarge(u64),
+    /// Error while watching a file for changes
+    #[cfg(feature = "watch")]
+    Watch(notify::Error),
+    /// Error while rendering a PDF page, including a missing pdfium library
+    #[cfg(feature = "pdf")]
+    Pdf(pdfium_render::prelude::PdfiumError),
 }
 
-impl std::error::Error for ViuError {}
+impl std::error::Error for ViuError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            ViuError::Image(e) => Some(e),
+            ViuError::Io(e) => Some(e),
+            ViuError::Tempfile(e) => Some(e),
+            #[cfg(feature = "network")]
+            ViuError::Http(e) => Some(e.as_ref()),
+            #[cfg(feature = "watch")]
+            ViuError::Watch(e) => Some(e),
+            #[cfg(feature = "pdf")]
+            ViuError::Pdf(e) => Some(e),
+            _ => None,
+        }
+    }
+}
 
 impl From<std::io::Error> for ViuError {
     fn from(err: std::io::Error) -> Self {
-        ViuError::Io(err)
+        match err.kind() {
+            std::io::ErrorKind::BrokenPipe => ViuError::Closed,
+            _ => ViuError::Io(err),
+        }
     }
 }
 impl From<image::ImageError> for ViuError {
@@ -47,17 +131,62 @@ impl From<sixel_rs::status::Error> for ViuError {
     }
 }
 
+#[cfg(feature = "network")]
+impl From<ureq::Error> for ViuError {
+    fn from(e: ureq::Error) -> Self {
+        ViuError::Http(Box::new(e))
+    }
+}
+
+#[cfg(feature = "watch")]
+impl From<notify::Error> for ViuError {
+    fn from(e: notify::Error) -> Self {
+        ViuError::Watch(e)
+    }
+}
+
+#[cfg(feature = "pdf")]
+impl From<pdfium_render::prelude::PdfiumError> for ViuError {
+    fn from(e: pdfium_render::prelude::PdfiumError) -> Self {
+        ViuError::Pdf(e)
+    }
+}
+
 impl std::fmt::Display for ViuError {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
             ViuError::Image(e) => write!(f, "Image error: {}", e),
             ViuError::Io(e) => write!(f, "IO error: {}", e),
+            ViuError::Closed => write!(f, "Output closed"),
             ViuError::InvalidConfiguration(s) => write!(f, "Invalid Configuration: {}", s),
             ViuError::Tempfile(e) => write!(f, "Tempfile error: {}", e),
             ViuError::KittyResponse(keys) => write!(f, "Kitty response: {:?}", keys),
+            #[allow(deprecated)]
             ViuError::KittyNotSupported => write!(f, "Kitty graphics protocol not supported"),
+            ViuError::UnsupportedProtocol(kind) => write!(f, "{} not supported", kind),
+            ViuError::QueryTimeout => write!(f, "The terminal did not reply in time"),
+            ViuError::TerminalTooSmall { needed, available } => write!(
+                f,
+                "Terminal too small: {} columns needed, {} available",
+                needed, available
+            ),
+            ViuError::SizeOverflow => write!(f, "Size too large for the terminal"),
+            ViuError::DecoderFailed { decoder, message } => {
+                write!(f, "{} could not decode the image: {}", decoder, message)
+            }
+            ViuError::DecoderNotFound(decoders) => {
+                write!(f, "No decoder installed, tried {}", decoders.join(", "))
+            }
             #[cfg(feature = "sixel")]
             ViuError::SixelError(e) => write!(f, "Sixel error: {:?}", e),
+            #[cfg(feature = "network")]
+            ViuError::Http(e) => write!(f, "HTTP error: {}", e),
+            #[cfg(feature = "network")]
+            ViuError::TooLarge(limit) => write!(f, "Image is larger than {} bytes", limit),
+            #[cfg(feature = "watch")]
+            ViuError::Watch(e) => write!(f, "Watch error: {}", e),
+            #[cfg(feature = "pdf")]
+            ViuError::Pdf(e) => write!(f, "PDF error: {}", e),
         }
     }
 }
@@ -0,0 +1,215 @@
+// Rendering of the block output to other formats than terminal escape sequences.
+use crate::printer::cell::{Cell, Rgb};
+use crate::printer::dither::quantize;
+use crate::printer::render_cells;
+use crate::{transform, BlockStyle, Config};
+
+use image::DynamicImage;
+use std::fmt::Write;
+
+// Size in the SVG of a pixel of the block rendering, i.e. of half a cell
+const SVG_PIXEL_SIZE: u32 = 8;
+
+/// Render the image with blocks, like [crate::render_to_string], as HTML: a `<pre>` element
+/// holding the characters, with a `<span>` setting the colors of each run of cells which
+/// share them. The colors are limited to [Config::color_depth], so the output looks like it
+/// does in a terminal using the xterm palette. Transparent cells show the page's background.
+///
+/// The offsets, borders and captions are ignored.
+/// ## Example
+/// ```no_run
+/// use viuer::{render_to_html, Config};
+///
+/// let img = image::open("img.png").expect("Could not decode file.");
+/// let conf = Config {
+///     width: Some(40),
+///     ..Default::default()
+/// };
+/// std::fs::write("preview.html", render_to_html(&img, &conf)).expect("Could not write file.");
+/// ```
+pub fn render_to_html(img: &DynamicImage, config: &Config) -> String {
+    let rows = render_cells(&transform::apply(img, config), config);
+
+    let mut html = String::from("<pre style=\"font-family: monospace; line-height: 1;\">");
+    for (i, row) in rows.iter().enumerate() {
+        if i > 0 {
+            html.push('\n');
+        }
+        let mut cells = row.iter().peekable();
+        while let Some(cell) = cells.next() {
+            let colors = cell_colors(cell, config);
+            let mut text = text(cell);
+            while let Some(next) = cells.next_if(|next| cell_colors(next, config) == colors) {
+                text.push(next.ch);
+            }
+            push_span(&mut html, colors, &text);
+        }
+    }
+    html.push_str("</pre>");
+    html
+}
+
+/// Render the image with half blocks as an SVG image, drawing each half of a cell as a square
+/// of its color. The colors are limited to [Config::color_depth], like in
+/// [render_to_html], and transparent pixels are left out.
+///
+/// [Config::block_style], the offsets, borders and captions are ignored.
+/// ## Example
+/// ```no_run
+/// use viuer::{render_to_svg, Config};
+///
+/// let img = image::open("img.png").expect("Could not decode file.");
+/// let conf = Config {
+///     width: Some(40),
+///     ..Default::default()
+/// };
+/// std::fs::write("preview.svg", render_to_svg(&img, &conf)).expect("Could not write file.");
+/// ```
+pub fn render_to_svg(img: &DynamicImage, config: &Config) -> String {
+    let config = &Config {
+        block_style: BlockStyle::HalfBlock,
+        ..config.clone()
+    };
+    let rows = render_cells(&transform::apply(img, config), config);
+    let width = rows.first().map_or(0, |row| row.len()) as u32 * SVG_PIXEL_SIZE;
+    let height = rows.len() as u32 * 2 * SVG_PIXEL_SIZE;
+
+    let mut svg = format!(
+        "<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"{0}\" height=\"{1}\" \
+         viewBox=\"0 0 {0} {1}\" shape-rendering=\"crispEdges\">",
+        width, height
+    );
+    for (i, row) in rows.iter().enumerate() {
+        let halves: Vec<_> = row.iter().map(|cell| halves(cell, config)).collect();
+        let top = halves.iter().map(|half| half.0);
+        let bottom = halves.iter().map(|half| half.1);
+        push_rects(&mut svg, 2 * i as u32, top);
+        push_rects(&mut svg, 2 * i as u32 + 1, bottom);
+    }
+    svg.push_str("</svg>");
+    svg
+}
+
+// Colors of the top and bottom halves of a half block cell
+fn halves(cell: &Cell, config: &Config) -> (Option<Rgb>, Option<Rgb>) {
+    let (fg, bg) = cell_colors(cell, config);
+    match cell.ch {
+        '▄' => (bg, fg),
+        '▀' => (fg, bg),
+        _ => (bg, bg),
+    }
+}
+
+// Draw a line of pixels, with a rectangle for each run of the same color
+fn push_rects(svg: &mut String, y: u32, colors: impl Iterator<Item = Option<Rgb>>) {
+    let mut colors = colors.enumerate().peekable();
+    while let Some((x, color)) = colors.next() {
+        let mut width = 1;
+        while colors.next_if(|(_, next)| *next == color).is_some() {
+            width += 1;
+        }
+        if let Some(rgb) = color {
+            let _ = write!(
+                svg,
+                "<rect x=\"{}\" y=\"{}\" width=\"{}\" height=\"{}\" fill=\"{}\"/>",
+                x as u32 * SVG_PIXEL_SIZE,
+                y * SVG_PIXEL_SIZE,
+                width * SVG_PIXEL_SIZE,
+                SVG_PIXEL_SIZE,
+                css_color(rgb)
+            );
+        }
+    }
+}
+
+// The foreground and background colors of a cell, as they are printed
+fn cell_colors(cell: &Cell, config: &Config) -> (Option<Rgb>, Option<Rgb>) {
+    let quantize = |rgb| quantize(rgb, config.depth());
+    (cell.fg.map(quantize), cell.bg.map(quantize))
+}
+
+// The character of a cell. Cells without colors are skipped by the printers, and left blank.
+fn text(cell: &Cell) -> String {
+    if cell.fg.is_none() && cell.bg.is_none() {
+        " ".to_owned()
+    } else {
+        cell.ch.to_string()
+    }
+}
+
+fn push_span(html: &mut String, colors: (Option<Rgb>, Option<Rgb>), text: &str) {
+    let mut style = String::new();
+    if let Some(fg) = colors.0 {
+        let _ = write!(style, "color:{};", css_color(fg));
+    }
+    if let Some(bg) = colors.1 {
+        let _ = write!(style, "background-color:{};", css_color(bg));
+    }
+
+    if style.is_empty() {
+        html.push_str(text);
+    } else {
+        let _ = write!(html, "<span style=\"{}\">{}</span>", style, text);
+    }
+}
+
+fn css_color(rgb: Rgb) -> String {
+    format!("#{:02x}{:02x}{:02x}", rgb.0, rgb.1, rgb.2)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ColorDepth;
+    use image::{Rgba, RgbaImage};
+
+    #[test]
+    fn test_render_to_html() {
+        let mut img = RgbaImage::new(3, 2);
+        for x in 0..2 {
+            img.put_pixel(x, 0, Rgba([255, 0, 0, 255]));
+            img.put_pixel(x, 1, Rgba([0, 0, 255, 255]));
+        }
+        let config = Config {
+            transparent: true,
+            width: Some(3),
+            height: Some(1),
+            color_depth: ColorDepth::TrueColor,
+            ..Default::default()
+        };
+        assert_eq!(
+            render_to_html(&DynamicImage::ImageRgba8(img), &config),
+            "<pre style=\"font-family: monospace; line-height: 1;\">\
+             <span style=\"color:#0000ff;background-color:#ff0000;\">▄▄</span> </pre>"
+        );
+    }
+
+    #[test]
+    fn test_render_to_svg() {
+        let mut img = RgbaImage::new(3, 2);
+        for x in 0..2 {
+            img.put_pixel(x, 0, Rgba([255, 0, 0, 255]));
+        }
+        img.put_pixel(2, 1, Rgba([0, 0, 255, 255]));
+        let config = Config {
+            transparent: true,
+            width: Some(3),
+            height: Some(1),
+            color_depth: ColorDepth::TrueColor,
+            block_style: BlockStyle::Braille,
+            ..Default::default()
+        };
+        assert_eq!(
+            render_to_svg(&DynamicImage::ImageRgba8(img), &config),
+            "<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"24\" height=\"16\" \
+             viewBox=\"0 0 24 16\" shape-rendering=\"crispEdges\">\
+             <rect x=\"0\" y=\"0\" width=\"16\" height=\"8\" fill=\"#ff0000\"/>\
+             <rect x=\"16\" y=\"8\" width=\"8\" height=\"8\" fill=\"#0000ff\"/></svg>"
+        );
+    }
+
+    #[test]
+    fn test_css_color() {
+        assert_eq!(css_color((255, 8, 128)), "#ff0880");
+    }
+}
@@ -0,0 +1,177 @@
+//! Thumbnails of the images in a directory, printed as a grid with their file names, one
+//! page at a time. Meant for previews in file managers.
+//!
+//! ## Example
+//! ```no_run
+//! use viuer::gallery::{print_dir, GalleryConfig};
+//!
+//! let page = print_dir("photos", &GalleryConfig::default()).expect("Gallery printing failed.");
+//! if page.page + 1 < page.pages {
+//!     let next = GalleryConfig {
+//!         page: page.page + 1,
+//!         ..Default::default()
+//!     };
+//!     print_dir("photos", &next).expect("Gallery printing failed.");
+//! }
+//! ```
+use crate::error::ViuResult;
+use crate::layout::cell_size;
+use crate::printer::open_image;
+use crate::{print_grid, terminal_size, Config, GridConfig};
+
+use image::{DynamicImage, ImageFormat};
+use std::path::{Path, PathBuf};
+
+/// Options for printing the images of a directory with [print_dir].
+#[derive(Clone, Default)]
+pub struct GalleryConfig {
+    /// Grid the thumbnails are printed in. Its captions are replaced by the file names.
+    /// Defaults to [GridConfig::default].
+    pub grid: GridConfig,
+    /// Page to print, counting from 0. Pages past the last one print the last one.
+    /// Defaults to 0.
+    pub page: usize,
+    /// Number of rows of thumbnails on a page. Defaults to None, which fits as many as the
+    /// terminal's height allows below the offset, with a line left for the prompt.
+    pub rows_per_page: Option<u32>,
+}
+
+/// The page printed by [print_dir].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct GalleryPage {
+    /// Index of the page which was printed, counting from 0.
+    pub page: usize,
+    /// Number of pages the directory's images fill.
+    pub pages: usize,
+    /// The image files on the page, in the order they were printed.
+    pub files: Vec<PathBuf>,
+    /// Width of the printed grid in terminal cells.
+    pub width: u32,
+    /// Height of the printed grid in terminal cells.
+    pub height: u32,
+}
+
+/// Print one page of thumbnails of the images in the directory, sorted by file name. Files
+/// are recognized as images by their extension. Images which cannot be decoded leave an empty
+/// place in the grid.
+pub fn print_dir<P: AsRef<Path>>(path: P, gallery: &GalleryConfig) -> ViuResult<GalleryPage> {
+    let files = image_files(path)?;
+    let per_page = gallery.grid.columns.max(1) as usize * rows_per_page(gallery)? as usize;
+    let pages = files.len().div_ceil(per_page);
+    let page = gallery.page.min(pages.saturating_sub(1));
+    let files: Vec<_> = files
+        .into_iter()
+        .skip(page * per_page)
+        .take(per_page)
+        .collect();
+
+    // Decoded for the size of the grid's cells, which is faster for large JPEGs
+    let (width, height) = cell_size(&gallery.grid)?;
+    let thumbnail_config = Config {
+        width: Some(width),
+        height: Some(height),
+        ..gallery.grid.config.clone()
+    };
+    let images: Vec<_> = files
+        .iter()
+        .map(|file| {
+            open_image(file, &thumbnail_config)
+                .unwrap_or_else(|_| DynamicImage::ImageRgba8(image::RgbaImage::new(1, 1)))
+        })
+        .collect();
+    let grid = GridConfig {
+        captions: files
+            .iter()
+            .map(|file| {
+                file.file_name()
+                    .unwrap_or_default()
+                    .to_string_lossy()
+                    .into()
+            })
+            .collect(),
+        ..gallery.grid.clone()
+    };
+    let (width, height) = print_grid(&images, &grid)?;
+
+    Ok(GalleryPage {
+        page,
+        pages,
+        files,
+        width,
+        height,
+    })
+}
+
+/// The image files in the directory, sorted by file name. Files are recognized as images by
+/// their extension.
+pub fn image_files<P: AsRef<Path>>(path: P) -> ViuResult<Vec<PathBuf>> {
+    let mut files = Vec::new();
+    for entry in std::fs::read_dir(path)? {
+        let path = entry?.path();
+        if path.is_file() && ImageFormat::from_path(&path).is_ok() {
+            files.push(path);
+        }
+    }
+    files.sort();
+    Ok(files)
+}
+
+// Number of rows of thumbnails which fit on a page
+fn rows_per_page(gallery: &GalleryConfig) -> ViuResult<u32> {
+    if let Some(rows) = gallery.rows_per_page {
+        return Ok(rows.max(1));
+    }
+    let config = &gallery.grid.config;
+    let (_, row_height) = cell_size(&gallery.grid)?;
+    let offset = if config.absolute_offset {
+        config.y.max(0) as u32
+    } else {
+        0
+    };
+    let available = (terminal_size().1 as u32)
+        .saturating_sub(offset)
+        .saturating_sub(1);
+    // The rows are separated by spacing, but the last one is not followed by it
+    let spacing = gallery.grid.spacing;
+    Ok(((available + spacing) / (row_height + spacing)).max(1))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_rows_per_page() {
+        // Rows of 5 lines separated by one in the 80x24 test terminal
+        let gallery = GalleryConfig {
+            grid: GridConfig {
+                row_height: Some(5),
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        assert_eq!(rows_per_page(&gallery).unwrap(), 4);
+
+        let gallery = GalleryConfig {
+            rows_per_page: Some(0),
+            ..gallery
+        };
+        assert_eq!(rows_per_page(&gallery).unwrap(), 1);
+    }
+
+    #[test]
+    fn test_image_files() {
+        let dir = tempfile::tempdir().unwrap();
+        for name in ["b.png", "a.JPG", "notes.txt"] {
+            std::fs::write(dir.path().join(name), b"").unwrap();
+        }
+        std::fs::create_dir(dir.path().join("c.png")).unwrap();
+
+        let names: Vec<_> = image_files(dir.path())
+            .unwrap()
+            .into_iter()
+            .map(|path| path.file_name().unwrap().to_owned())
+            .collect();
+        assert_eq!(names, ["a.JPG", "b.png"]);
+    }
+}
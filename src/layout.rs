@@ -0,0 +1,588 @@
+// Arrangement of several images on screen.
+use crate::error::{ViuError, ViuResult};
+use crate::printer::adjust_offset;
+use crate::utils::{cells, colored, should_print, uncolored, with_stdout, write_at_once};
+use crate::{choose_printer, decoration, print_to, terminal_size, transform, Config, ResizePolicy};
+
+use crossterm::cursor::{MoveToPreviousLine, RestorePosition, SavePosition};
+use crossterm::{execute, queue};
+use image::{DynamicImage, GenericImageView, Rgb, Rgba};
+use std::io::Write;
+
+/// Options for printing several images as a grid with [print_grid].
+#[derive(Clone)]
+pub struct GridConfig {
+    /// Number of images in every row. Defaults to 4.
+    pub columns: u32,
+    /// Width of the whole grid in terminal cells. Defaults to None, which uses the space left
+    /// in the terminal after the horizontal offset.
+    pub width: Option<u32>,
+    /// Height of every row in terminal cells, captions included. Defaults to None, which
+    /// uses half of the width available for each image.
+    pub row_height: Option<u32>,
+    /// Number of empty columns between the images, and of empty lines between the rows.
+    /// Defaults to 1.
+    pub spacing: u32,
+    /// Captions of the images, in the same order. Images past the end of the list do not
+    /// get one. Defaults to no captions.
+    pub captions: Vec<String>,
+    /// Options used to print every image. The offsets position the whole grid, while the
+    /// width, height and caption are set for each image by the grid.
+    /// Defaults to [Config::default].
+    pub config: Config,
+}
+
+impl Default for GridConfig {
+    fn default() -> Self {
+        Self {
+            columns: 4,
+            width: None,
+            row_height: None,
+            spacing: 1,
+            captions: Vec::new(),
+            config: Config::default(),
+        }
+    }
+}
+
+/// The size in terminal cells which printing an image of the given size in pixels with the
+/// config would take, borders and captions included, without decoding or printing anything.
+/// Applications can use it to reserve room for an image before it is loaded.
+///
+/// The printer is chosen as for printing, which may query the terminal. Images printed from
+/// files should be given their dimensions after the EXIF orientation, if it is applied.
+/// ## Example
+/// ```no_run
+/// use viuer::{layout_for, Config};
+///
+/// let (width, height) = image::image_dimensions("img.jpg").expect("Could not read file.");
+/// let conf = Config {
+///     width: Some(40),
+///     ..Default::default()
+/// };
+/// let (columns, rows) = layout_for((width, height), &conf);
+/// ```
+pub fn layout_for(img_dims: (u32, u32), config: &Config) -> (u32, u32) {
+    let printer = choose_printer(config);
+    decoration::size(config, |config| {
+        printer.fit(transform::dimensions(img_dims, config), config)
+    })
+}
+
+/// Print the images in rows and columns, each one fit into an equally sized cell of the grid.
+///
+/// Returns the dimensions of the whole grid in terminal cells.
+/// ## Example
+/// ```no_run
+/// use viuer::{print_grid, GridConfig};
+///
+/// let images: Vec<_> = ["a.jpg", "b.jpg", "c.jpg"]
+///     .iter()
+///     .map(|name| image::open(name).expect("Could not decode file."))
+///     .collect();
+/// let grid = GridConfig {
+///     columns: 3,
+///     captions: vec!["a".into(), "b".into(), "c".into()],
+///     ..Default::default()
+/// };
+/// print_grid(&images, &grid).expect("Grid printing failed.");
+/// ```
+pub fn print_grid(images: &[DynamicImage], grid: &GridConfig) -> ViuResult<(u32, u32)> {
+    if !should_print(grid.config.when) {
+        return Ok((0, 0));
+    }
+    with_stdout(&grid.config, |mut stdout, config| {
+        let grid = &GridConfig {
+            config: config.clone(),
+            ..grid.clone()
+        };
+        write_at_once(&mut stdout, |mut stdout| {
+            print_grid_to(&mut stdout, images, grid)
+        })
+    })
+}
+
+fn print_grid_to(
+    stdout: &mut impl Write,
+    images: &[DynamicImage],
+    grid: &GridConfig,
+) -> ViuResult<(u32, u32)> {
+    let config = &grid.config;
+    if config.restore_cursor {
+        execute!(stdout, SavePosition)?;
+    }
+
+    let (cell_width, row_height) = cell_size(grid)?;
+    let columns = grid.columns.max(1) as usize;
+
+    // Go to the top left corner of the grid. The images are printed relative to it.
+    adjust_offset(
+        stdout,
+        &Config {
+            x: 0,
+            ..config.clone()
+        },
+    )?;
+
+    let (mut width, mut height) = (0, 0);
+    for (row, images) in images.chunks(columns).enumerate() {
+        if row > 0 {
+            for _ in 0..grid.spacing {
+                writeln!(stdout)?;
+            }
+            height += grid.spacing;
+        }
+
+        let mut printed_height = 0;
+        for (column, img) in images.iter().enumerate() {
+            let x = column as u32 * (cell_width + grid.spacing);
+            let image_config = Config {
+                absolute_offset: false,
+                restore_cursor: false,
+                x: config.x.saturating_add(x as u16),
+                y: 0,
+                width: Some(cell_width),
+                height: Some(row_height),
+                // The grid's cells bound the images
+                resize_policy: None,
+                caption: grid.captions.get(row * columns + column).cloned(),
+                ..config.clone()
+            };
+            let info = print_to(img, &image_config, stdout)?;
+
+            // Back to the first line of the row, for the next image
+            if info.height > 0 {
+                let height = cells(info.height)?;
+                queue!(stdout, MoveToPreviousLine(height))?;
+            }
+            width = width.max(x + info.width);
+            printed_height = printed_height.max(info.height);
+        }
+
+        // writeln! is used instead of MoveDown to scroll when the grid reaches the bottom
+        for _ in 0..printed_height {
+            writeln!(stdout)?;
+        }
+        height += printed_height;
+    }
+    stdout.flush()?;
+
+    if config.restore_cursor {
+        execute!(stdout, RestorePosition)?;
+    }
+
+    Ok((width, height))
+}
+
+/// Print two images next to each other, for example to compare the output of a program with
+/// the expected one. The images share the width and height from the config, separated by
+/// an empty column.
+///
+/// With a highlight color, the pixels of the second image which differ from the first one are
+/// replaced by that color, and so are the pixels outside of the first image's bounds.
+///
+/// Returns the dimensions of the printed images in terminal cells.
+/// ## Example
+/// ```no_run
+/// use image::Rgb;
+/// use viuer::{print_diff, Config};
+///
+/// let expected = image::open("expected.png").expect("Could not decode file.");
+/// let actual = image::open("actual.png").expect("Could not decode file.");
+/// print_diff(&expected, &actual, &Config::default(), Some(Rgb([255, 0, 0])))
+///     .expect("Image printing failed.");
+/// ```
+pub fn print_diff(
+    a: &DynamicImage,
+    b: &DynamicImage,
+    config: &Config,
+    highlight: Option<Rgb<u8>>,
+) -> ViuResult<(u32, u32)> {
+    let b = match highlight {
+        Some(color) => highlight_differences(a, b, color),
+        None => b.clone(),
+    };
+    let (width, row_height) = config.bounds();
+    let grid = GridConfig {
+        columns: 2,
+        width,
+        row_height,
+        config: config.clone(),
+        ..Default::default()
+    };
+    print_grid(&[a.clone(), b], &grid)
+}
+
+/// Print the images next to each other, scaled to the same height and separated by `spacing`
+/// empty columns. The height is the smallest one the images get from their own config, so
+/// that every image stays within its bounds, and smaller images are enlarged to it.
+///
+/// Each image is printed with its own config, decorations included, apart from the offset:
+/// the row starts at the offset of the first config, and the images are placed after each
+/// other from there.
+///
+/// Returns the dimensions of the whole row in terminal cells.
+/// ## Example
+/// ```no_run
+/// use viuer::{print_row, Config};
+///
+/// let before = image::open("before.png").expect("Could not decode file.");
+/// let after = image::open("after.png").expect("Could not decode file.");
+/// let conf = Config {
+///     height: Some(20),
+///     ..Default::default()
+/// };
+/// print_row(&[(before, conf.clone()), (after, conf)], 2).expect("Image printing failed.");
+/// ```
+pub fn print_row(images: &[(DynamicImage, Config)], spacing: u32) -> ViuResult<(u32, u32)> {
+    print_stack(images, spacing, Direction::Row)
+}
+
+/// Print the images below each other, scaled to the same width and separated by `spacing`
+/// empty lines. The width is chosen like the height in [print_row].
+///
+/// Returns the dimensions of the whole column in terminal cells.
+pub fn print_column(images: &[(DynamicImage, Config)], spacing: u32) -> ViuResult<(u32, u32)> {
+    print_stack(images, spacing, Direction::Column)
+}
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Direction {
+    Row,
+    Column,
+}
+
+fn print_stack(
+    images: &[(DynamicImage, Config)],
+    spacing: u32,
+    direction: Direction,
+) -> ViuResult<(u32, u32)> {
+    let first = match images.first() {
+        Some((_, config)) if should_print(config.when) => config,
+        _ => return Ok((0, 0)),
+    };
+    let configs = stack_configs(images, direction, colored(first.when));
+    with_stdout(first, |mut stdout, _| {
+        write_at_once(&mut stdout, |mut stdout| {
+            print_stack_to(&mut stdout, images, &configs, first, spacing, direction)
+        })
+    })
+}
+
+fn print_stack_to(
+    stdout: &mut impl Write,
+    images: &[(DynamicImage, Config)],
+    configs: &[Config],
+    first: &Config,
+    spacing: u32,
+    direction: Direction,
+) -> ViuResult<(u32, u32)> {
+    if first.restore_cursor {
+        execute!(stdout, SavePosition)?;
+    }
+    // Go to the top left corner of the stack. The images are printed relative to it.
+    adjust_offset(
+        stdout,
+        &Config {
+            x: 0,
+            ..first.clone()
+        },
+    )?;
+
+    let (mut width, mut height) = (0, 0);
+    for (i, ((img, _), config)) in images.iter().zip(configs).enumerate() {
+        let gap = if i > 0 { spacing } else { 0 };
+        let config = Config {
+            absolute_offset: false,
+            restore_cursor: false,
+            ..config.clone()
+        };
+        match direction {
+            Direction::Row => {
+                let x = width + gap;
+                let config = Config {
+                    x: first.x.saturating_add(cells(x)?),
+                    y: 0,
+                    ..config
+                };
+                let info = print_to(img, &config, stdout)?;
+                // Back to the first line, for the next image
+                if info.height > 0 {
+                    let lines = cells(info.height)?;
+                    queue!(stdout, MoveToPreviousLine(lines))?;
+                }
+                width = x + info.width;
+                height = height.max(info.height);
+            }
+            Direction::Column => {
+                for _ in 0..gap {
+                    writeln!(stdout)?;
+                }
+                let config = Config {
+                    x: first.x,
+                    y: 0,
+                    ..config
+                };
+                let info = print_to(img, &config, stdout)?;
+                width = width.max(info.width);
+                height += gap + info.height;
+            }
+        }
+    }
+
+    // writeln! is used instead of MoveDown to scroll when the row reaches the bottom
+    if direction == Direction::Row {
+        for _ in 0..height {
+            writeln!(stdout)?;
+        }
+    }
+    stdout.flush()?;
+
+    if first.restore_cursor {
+        execute!(stdout, RestorePosition)?;
+    }
+    Ok((width, height))
+}
+
+// The configs which give the images the same height in a row, or the same width in a
+// column, the smallest one they would get on their own. The decorations are left out of it.
+fn stack_configs(
+    images: &[(DynamicImage, Config)],
+    direction: Direction,
+    colored: bool,
+) -> Vec<Config> {
+    // Without colors, all the images are printed with the blocks of uncolored()
+    let configs: Vec<_> = images
+        .iter()
+        .map(|(_, config)| {
+            if colored {
+                config.clone()
+            } else {
+                uncolored(config)
+            }
+        })
+        .collect();
+    let sizes: Vec<_> = images
+        .iter()
+        .zip(&configs)
+        .map(|((img, _), config)| {
+            let printer = choose_printer(config);
+            let img_size = |config: &Config| {
+                printer.fit(transform::dimensions(img.dimensions(), config), config)
+            };
+            let decorated = decoration::size(config, img_size);
+            let inner = img_size(&decoration::inner_config(config));
+            (
+                inner,
+                (
+                    decorated.0 - inner.0.min(decorated.0),
+                    decorated.1 - inner.1.min(decorated.1),
+                ),
+            )
+        })
+        .collect();
+    let shared = sizes
+        .iter()
+        .map(|((w, h), _)| match direction {
+            Direction::Row => *h,
+            Direction::Column => *w,
+        })
+        .min()
+        .unwrap_or(1)
+        .max(1);
+
+    configs
+        .into_iter()
+        .zip(sizes)
+        .map(|(config, (_, (extra_w, extra_h)))| Config {
+            resize_policy: Some(match direction {
+                Direction::Row => ResizePolicy::FitHeight(shared + extra_h),
+                Direction::Column => ResizePolicy::FitWidth(shared + extra_w),
+            }),
+            upscale: true,
+            ..config
+        })
+        .collect()
+}
+
+// Copy of b with every pixel which differs from the one at the same position in a replaced
+// by the given color
+fn highlight_differences(a: &DynamicImage, b: &DynamicImage, color: Rgb<u8>) -> DynamicImage {
+    let mut highlighted = b.to_rgba8();
+    for (x, y, pixel) in highlighted.enumerate_pixels_mut() {
+        if !a.in_bounds(x, y) || a.get_pixel(x, y) != *pixel {
+            *pixel = Rgba([color[0], color[1], color[2], 255]);
+        }
+    }
+    DynamicImage::ImageRgba8(highlighted)
+}
+
+// Width of each image and height of each row, in terminal cells. Fails if the images would
+// not get a column each.
+pub(crate) fn cell_size(grid: &GridConfig) -> ViuResult<(u32, u32)> {
+    let columns = grid.columns.max(1);
+    let width = grid
+        .width
+        .unwrap_or_else(|| (terminal_size().0 as u32).saturating_sub(grid.config.x as u32));
+    let spacing = grid.spacing.saturating_mul(columns - 1);
+    let needed = columns.saturating_add(spacing);
+    if width < needed {
+        return Err(ViuError::TerminalTooSmall {
+            needed,
+            available: width,
+        });
+    }
+    let cell_width = (width - spacing) / columns;
+    let row_height = grid.row_height.unwrap_or(cell_width / 2).max(1);
+    Ok((cell_width, row_height))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::PrinterType;
+    use image::RgbaImage;
+
+    #[test]
+    fn test_cell_size() {
+        let grid = GridConfig {
+            columns: 3,
+            width: Some(32),
+            ..Default::default()
+        };
+        assert_eq!(cell_size(&grid).unwrap(), (10, 5));
+
+        let grid = GridConfig {
+            columns: 0,
+            row_height: Some(3),
+            ..Default::default()
+        };
+        assert_eq!(cell_size(&grid).unwrap(), (80, 3));
+
+        let grid = GridConfig {
+            columns: 4,
+            width: Some(6),
+            ..Default::default()
+        };
+        assert!(matches!(
+            cell_size(&grid),
+            Err(ViuError::TerminalTooSmall {
+                needed: 7,
+                available: 6
+            })
+        ));
+    }
+
+    #[test]
+    fn test_highlight_differences() {
+        let a = DynamicImage::ImageRgba8(RgbaImage::new(2, 1));
+        let mut b = RgbaImage::new(3, 1);
+        b.put_pixel(1, 0, Rgba([1, 2, 3, 255]));
+        let red = Rgba([255, 0, 0, 255]);
+
+        let highlighted = highlight_differences(&a, &b.into(), Rgb([255, 0, 0])).to_rgba8();
+        assert_eq!(highlighted.get_pixel(0, 0), &Rgba([0, 0, 0, 0]));
+        assert_eq!(highlighted.get_pixel(1, 0), &red);
+        assert_eq!(highlighted.get_pixel(2, 0), &red);
+    }
+
+    #[test]
+    fn test_layout_for() {
+        let img = DynamicImage::ImageRgba8(RgbaImage::new(30, 17));
+        let configs = [
+            Config::default(),
+            Config {
+                width: Some(12),
+                border: Some(Default::default()),
+                caption: Some("img".to_owned()),
+                ..Default::default()
+            },
+            Config {
+                width: Some(10),
+                height: Some(10),
+                crop: Some((5, 0, 100, 100)),
+                rotate: Some(crate::Rotation::Rotate90),
+                ..Default::default()
+            },
+        ];
+        for config in configs {
+            let config = Config {
+                absolute_offset: false,
+                printer: Some(PrinterType::Block),
+                ..config
+            };
+            let info = print_to(&img, &config, &mut Vec::new()).unwrap();
+            assert_eq!(layout_for((30, 17), &config), (info.width, info.height));
+        }
+    }
+
+    #[test]
+    fn test_print_grid() {
+        let images = vec![DynamicImage::ImageRgba8(RgbaImage::new(8, 8)); 3];
+        let grid = GridConfig {
+            columns: 2,
+            width: Some(9),
+            captions: vec!["ab".to_owned()],
+            config: Config {
+                absolute_offset: false,
+                printer: Some(PrinterType::Block),
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        let mut buf = Vec::new();
+        // Cells of 4x2, the first image shrunk to 2x1 to leave a line for the caption
+        let size = print_grid_to(&mut buf, &images, &grid).unwrap();
+        assert_eq!(size, (9, 5));
+
+        let output = std::str::from_utf8(&buf).unwrap();
+        assert!(output.contains("ab"));
+        // The second image starts after the first one and the spacing
+        assert!(output.contains("\x1b[2F\x1b[5C"));
+    }
+
+    #[test]
+    fn test_print_row() {
+        let images = vec![
+            (
+                DynamicImage::ImageRgba8(RgbaImage::new(8, 8)),
+                Config {
+                    absolute_offset: false,
+                    printer: Some(PrinterType::Block),
+                    height: Some(3),
+                    ..Default::default()
+                },
+            ),
+            (
+                DynamicImage::ImageRgba8(RgbaImage::new(4, 8)),
+                Config {
+                    absolute_offset: false,
+                    printer: Some(PrinterType::Block),
+                    height: Some(10),
+                    ..Default::default()
+                },
+            ),
+        ];
+        // Both images get the height of the smaller one: the second one would be printed at
+        // its own size of 4x4, not enlarged to its bounds
+        let configs = stack_configs(&images, Direction::Row, true);
+        let mut buf = Vec::new();
+        let size =
+            print_stack_to(&mut buf, &images, &configs, &images[0].1, 2, Direction::Row).unwrap();
+        assert_eq!(size, (6 + 2 + 3, 3));
+
+        let mut buf = Vec::new();
+        let configs = stack_configs(&images, Direction::Column, true);
+        let size = print_stack_to(
+            &mut buf,
+            &images,
+            &configs,
+            &images[0].1,
+            1,
+            Direction::Column,
+        )
+        .unwrap();
+        // Both images get the width of the second one, the first one shrunk to it
+        assert_eq!(size, (4, 2 + 1 + 4));
+    }
+}
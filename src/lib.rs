@@ -26,35 +26,101 @@
 //! print_from_file("img.jpg", &conf).expect("Image printing failed.");
 //! ```
 
+use std::io::{BufReader, Read, Seek, Write};
 use std::path::Path;
 
 use crossterm::{
     cursor::{RestorePosition, SavePosition},
     execute,
 };
-use image::DynamicImage;
-use printer::{Printer, PrinterType};
+use image::{DynamicImage, GenericImageView, Rgba, RgbaImage};
 
+use crate::utils::{write_at_once, CountingWriter};
+
+mod adjust;
+mod animation;
+#[cfg(feature = "tokio")]
+mod asynchronous;
+pub mod cache;
 mod config;
+mod decoration;
+mod erase;
 mod error;
+mod export;
+pub mod gallery;
+mod layout;
+#[cfg(feature = "network")]
+mod network;
+mod overlay;
+#[cfg(feature = "pdf")]
+mod pdf;
 mod printer;
+#[cfg(feature = "serde")]
+mod serde_remote;
+mod slideshow;
+mod terminal;
+mod tonemap;
+mod transform;
+mod update;
 mod utils;
+pub mod viewer;
+mod viewport;
+mod vision;
+mod window;
 
-pub use config::Config;
-pub use error::{ViuError, ViuResult};
-pub use printer::{get_kitty_support, is_iterm_supported, resize, KittySupport};
-pub use utils::terminal_size;
+pub use adjust::{Threshold, ThresholdLevel};
+pub use animation::{
+    print_animation_from_file, print_frames, print_frames_with_handle, AnimationConfig, Frame,
+    FrameIterator, PlaybackHandle,
+};
+pub use config::{Config, ConfigBuilder};
+pub use decoration::{Border, BorderStyle, CaptionPosition};
+pub use erase::clear;
+pub use error::{ProtocolKind, ViuError, ViuResult};
+pub use export::{render_to_html, render_to_svg};
+pub use layout::{layout_for, print_column, print_diff, print_grid, print_row, GridConfig};
+pub use overlay::{Corner, Overlay, OverlayContent};
+pub use printer::{
+    get_kitty_support, image_pages, is_iterm_supported, resize, resize_with_policy, BlockStyle,
+    Checkerboard, ColorDepth, Dither, FilterFn, HAlign, ImageHandle, KittyFormat, KittyMedium,
+    KittySupport, PixelFormat, PrintWhen, PrintedInfo, Printer, PrinterType, ResizePolicy,
+    SizeMode, VAlign,
+};
+pub use slideshow::{print_slideshow, SlideshowConfig};
+pub use terminal::{
+    capabilities, capabilities_with_timeout, terminal_background_color, Capabilities,
+    ProtocolPolicy,
+};
+pub use tonemap::ToneMapping;
+pub use transform::Rotation;
+pub use update::reprint_region;
+pub use utils::{terminal_size, terminal_size_pixels};
+pub use viewport::{print_viewport, Rect};
+pub use vision::{ColorBlindness, VisionFilter};
+pub use window::{on_resize, watch, Watch};
 
 #[cfg(feature = "sixel")]
 pub use printer::is_sixel_supported;
 
-/// Default printing method. Uses either iTerm or Kitty graphics protocol, if supported,
-/// and half blocks otherwise.
+#[cfg(feature = "video")]
+pub use animation::{print_video, VideoFrames};
+
+#[cfg(feature = "tokio")]
+pub use animation::{
+    print_animation_from_file_async, print_frames_async, print_frames_with_handle_async,
+};
+#[cfg(feature = "tokio")]
+pub use asynchronous::{print_async, print_from_file_async};
+
+/// Default printing method. Uses either Kitty, iTerm or Sixel graphics protocol, if supported,
+/// and half blocks otherwise. A specific printer can be forced through [Config::printer].
 ///
-/// Check the [Config] struct for all customization options.
+/// Returns the dimensions of the printed image in terminal cells, together with the
+/// printer that was used. Check the [Config] struct for all customization options.
 /// ## Example
 /// The snippet below reads all of stdin, decodes it with the [`image`] crate
 /// and prints it to the terminal. The image will also be resized to fit in the terminal.
+/// [print_from_memory] does the same for an undecoded buffer.
 ///
 /// ```no_run
 /// use std::io::{stdin, Read};
@@ -71,19 +137,214 @@ pub use printer::is_sixel_supported;
 /// let img = image::load_from_memory(&buf).expect("Data from stdin could not be decoded.");
 /// print(&img, &Config::default()).expect("Image printing failed.");
 /// ```
-pub fn print(img: &DynamicImage, config: &Config) -> ViuResult<(u32, u32)> {
-    let mut stdout = std::io::stdout();
-    if config.restore_cursor {
-        execute!(&mut stdout, SavePosition)?;
+pub fn print(img: &DynamicImage, config: &Config) -> ViuResult<PrintedInfo> {
+    if !utils::should_print(config.when) {
+        return Ok(PrintedInfo::nothing());
     }
+    utils::with_stdout(config, |mut stdout, config| {
+        print_to(img, config, &mut stdout)
+    })
+}
 
-    let (w, h) = choose_printer(config).print(&mut stdout, img, config)?;
+/// Same as [print], but writes the output to the given writer instead of stdout.
+/// [Config::when] is ignored, the image is always written.
+///
+/// Support for the graphics protocols is still detected through stdout, so set
+/// [Config::printer] when the writer is not connected to the same terminal.
+/// ## Example
+/// ```no_run
+/// use viuer::{Config, print_to};
+///
+/// let img = image::DynamicImage::ImageRgba8(image::RgbaImage::new(20, 10));
+/// print_to(&img, &Config::default(), &mut std::io::stderr()).expect("Image printing failed.");
+/// ```
+pub fn print_to(
+    img: &DynamicImage,
+    config: &Config,
+    writer: &mut impl Write,
+) -> ViuResult<PrintedInfo> {
+    let mut writer = CountingWriter::new(writer);
+    let printer = choose_printer(config);
+    let (width, height) = write_at_once(&mut writer, |mut writer| {
+        if config.restore_cursor {
+            execute!(&mut writer, SavePosition)?;
+        }
+
+        let img_size = |config: &Config| {
+            Ok(printer.fit(transform::dimensions(img.dimensions(), config), config))
+        };
+        let size = decoration::print(&mut writer, config, img_size, |writer, config| {
+            printer.print(writer, img, config)
+        })?;
+
+        if config.restore_cursor {
+            execute!(&mut writer, RestorePosition)?;
+        };
+        Ok(size)
+    })?;
 
-    if config.restore_cursor {
-        execute!(&mut stdout, RestorePosition)?;
+    Ok(PrintedInfo {
+        width,
+        height,
+        printer,
+        bytes_written: writer.count,
+    })
+}
+
+/// Same as [print], for any image view with RGBA pixels, e.g. a part of an image selected
+/// with [GenericImageView::view] or an image type of the application.
+///
+/// The printers work on a [DynamicImage]. When the image is printed with blocks at a fraction
+/// of its size, the view is scaled down while its pixels are read, and only the smaller image
+/// is allocated. Otherwise, e.g. for the graphics protocols, which send every pixel, or when
+/// the config transforms the image, the pixels of the view, and only those, are copied
+/// into an image first.
+/// ## Example
+/// ```no_run
+/// use image::GenericImageView;
+/// use viuer::{Config, print_view};
+///
+/// let img = image::RgbaImage::new(200, 100);
+/// // Print the left half of the image
+/// print_view(&*img.view(0, 0, 100, 100), &Config::default()).expect("Image printing failed.");
+/// ```
+pub fn print_view<V>(img: &V, config: &Config) -> ViuResult<PrintedInfo>
+where
+    V: GenericImageView<Pixel = Rgba<u8>>,
+{
+    if !utils::should_print(config.when) {
+        return Ok(PrintedInfo::nothing());
+    }
+    utils::with_stdout(config, |mut stdout, config| {
+        print_view_to(img, config, &mut stdout)
+    })
+}
+
+/// Same as [print_view], but writes the output to the given writer, like [print_to].
+pub fn print_view_to<V>(img: &V, config: &Config, writer: &mut impl Write) -> ViuResult<PrintedInfo>
+where
+    V: GenericImageView<Pixel = Rgba<u8>>,
+{
+    let img = printer::shrink_view(img, choose_printer(config), config).unwrap_or_else(|| {
+        RgbaImage::from_fn(img.width(), img.height(), |x, y| img.get_pixel(x, y))
+    });
+    print_to(&DynamicImage::ImageRgba8(img), config, writer)
+}
+
+/// Same as [print], for a buffer of pixels in the given format, e.g. a frame from a camera
+/// or read back from a GPU. The buffer must hold exactly `width * height` pixels.
+///
+/// The buffer is borrowed. When the frame is printed with blocks at a fraction of its size,
+/// it is scaled down while it is read, like with [print_view]. Otherwise it is copied into an
+/// image once, without converting the pixels, since the graphics protocols send every pixel.
+/// ## Example
+/// ```no_run
+/// use viuer::{Config, PixelFormat, print_raw};
+///
+/// // A red 4x2 image
+/// let buf = [255, 0, 0].repeat(8);
+/// print_raw(&buf, 4, 2, PixelFormat::Rgb8, &Config::default()).expect("Image printing failed.");
+/// ```
+pub fn print_raw(
+    buf: &[u8],
+    width: u32,
+    height: u32,
+    format: PixelFormat,
+    config: &Config,
+) -> ViuResult<PrintedInfo> {
+    if !utils::should_print(config.when) {
+        return Ok(PrintedInfo::nothing());
+    }
+    utils::with_stdout(config, |mut stdout, config| {
+        let printer = choose_printer(config);
+        let img = printer::image_from_raw(buf, width, height, format, printer, config)?;
+        print_to(&img, config, &mut stdout)
+    })
+}
+
+/// Render the image with blocks and return the output instead of printing it, for example
+/// to cache it or to place it in a larger terminal interface. [Config::printer] is ignored,
+/// while [Config::block_style] selects the characters that are used.
+///
+/// With [Config::absolute_offset] set, the output starts by moving the cursor to the offset,
+/// so it is usually unset when the image is composed with other output.
+/// ## Example
+/// ```no_run
+/// use viuer::{Config, render_to_string};
+///
+/// let img = image::DynamicImage::ImageRgba8(image::RgbaImage::new(20, 10));
+/// let conf = Config {
+///     absolute_offset: false,
+///     ..Default::default()
+/// };
+/// let rendered = render_to_string(&img, &conf).expect("Image rendering failed.");
+/// print!("{}", rendered);
+/// ```
+pub fn render_to_string(img: &DynamicImage, config: &Config) -> ViuResult<String> {
+    let config = Config {
+        printer: Some(PrinterType::Block),
+        ..config.clone()
     };
+    let mut buf = Vec::new();
+    print_to(img, &config, &mut buf)?;
+    Ok(String::from_utf8_lossy(&buf).into_owned())
+}
 
-    Ok((w, h))
+/// Print an image with the given printer instead of the one chosen by detection.
+/// The image is transformed and the offsets aligned according to the config before they
+/// are passed to the printer.
+///
+/// Returns the dimensions of the printed image in terminal cells.
+/// ## Example
+/// ```no_run
+/// use std::io::Write;
+/// use viuer::{Config, Printer, ViuResult, print_with};
+///
+/// struct SizePrinter;
+///
+/// impl Printer for SizePrinter {
+///     fn print(
+///         &self,
+///         stdout: &mut impl Write,
+///         img: &image::DynamicImage,
+///         _config: &Config,
+///     ) -> ViuResult<(u32, u32)> {
+///         writeln!(stdout, "{}x{} image", img.width(), img.height())?;
+///         Ok((img.width(), 1))
+///     }
+/// }
+///
+/// let img = image::DynamicImage::ImageRgba8(image::RgbaImage::new(20, 10));
+/// print_with(&SizePrinter, &img, &Config::default()).expect("Image printing failed.");
+/// ```
+pub fn print_with(
+    printer: &impl Printer,
+    img: &DynamicImage,
+    config: &Config,
+) -> ViuResult<(u32, u32)> {
+    if !utils::should_print(config.when) {
+        return Ok((0, 0));
+    }
+    utils::with_stdout(config, |mut stdout, config| {
+        write_at_once(&mut stdout, |mut stdout| {
+            if config.restore_cursor {
+                execute!(&mut stdout, SavePosition)?;
+            }
+
+            let img = transform::apply(img, config);
+            let img_size =
+                |config: &Config| Ok(printer::fit_to_config(img.width(), img.height(), config));
+            let size = decoration::print(&mut stdout, config, img_size, |stdout, config| {
+                let config = &printer::align(img.dimensions(), config);
+                printer.print(stdout, &img, config)
+            })?;
+
+            if config.restore_cursor {
+                execute!(&mut stdout, RestorePosition)?;
+            };
+            Ok(size)
+        })
+    })
 }
 
 /// Helper method that reads a file, tries to decode it and prints it.
@@ -100,33 +361,370 @@ pub fn print(img: &DynamicImage, config: &Config) -> ViuResult<(u32, u32)> {
 /// // Also, the terminal's background color will be used instead of checkerboard pattern.
 /// print_from_file("img.jpg", &conf).expect("Image printing failed.");
 /// ```
-pub fn print_from_file<P: AsRef<Path>>(filename: P, config: &Config) -> ViuResult<(u32, u32)> {
-    let mut stdout = std::io::stdout();
-    if config.restore_cursor {
-        execute!(&mut stdout, SavePosition)?;
+pub fn print_from_file<P: AsRef<Path>>(filename: P, config: &Config) -> ViuResult<PrintedInfo> {
+    if !utils::should_print(config.when) {
+        return Ok(PrintedInfo::nothing());
     }
+    utils::with_stdout(config, |mut stdout, config| {
+        print_from_file_to(filename, config, &mut stdout)
+    })
+}
 
-    let (w, h) = choose_printer(config).print_from_file(&mut stdout, filename, config)?;
+// Same as print_from_file, writing to the given writer
+fn print_from_file_to<P: AsRef<Path>>(
+    filename: P,
+    config: &Config,
+    writer: &mut impl Write,
+) -> ViuResult<PrintedInfo> {
+    let mut writer = CountingWriter::new(writer);
+    let printer = choose_printer(config);
+    let (width, height) = write_at_once(&mut writer, |mut writer| {
+        if config.restore_cursor {
+            execute!(&mut writer, SavePosition)?;
+        }
 
-    if config.restore_cursor {
-        execute!(&mut stdout, RestorePosition)?;
-    };
+        let img_size =
+            |config: &Config| Ok(printer.fit(printer::file_dimensions(&filename, config)?, config));
+        let size = decoration::print(&mut writer, config, img_size, |writer, config| {
+            printer.print_from_file(writer, &filename, config)
+        })?;
 
-    Ok((w, h))
+        if config.restore_cursor {
+            execute!(&mut writer, RestorePosition)?;
+        };
+        Ok(size)
+    })?;
+
+    Ok(PrintedInfo {
+        width,
+        height,
+        printer,
+        bytes_written: writer.count,
+    })
 }
 
-// Choose the appropriate printer to use based on user config and availability
+/// Helper method that reads an image from any seekable source, tries to decode it and prints it.
+/// The format is guessed from the content, so the source does not need a file name.
+///
+/// ## Example
+/// ```no_run
+/// use std::fs::File;
+/// use viuer::{Config, print_from_reader};
+///
+/// // Any reader works, the extension of the file is not used
+/// let file = File::open("img.data").expect("Could not open the file.");
+/// print_from_reader(file, &Config::default()).expect("Image printing failed.");
+/// ```
+pub fn print_from_reader<R: Read + Seek>(reader: R, config: &Config) -> ViuResult<PrintedInfo> {
+    let img = printer::decode_from_reader(BufReader::new(reader), config)?;
+    print(&img, config)
+}
+
+/// Helper method that decodes an image from a byte slice and prints it.
+///
+/// ## Example
+/// The snippet below reads all of stdin and prints it to the terminal.
+///
+/// ```no_run
+/// use std::io::{stdin, Read};
+/// use viuer::{Config, print_from_memory};
+///
+/// let mut buf: Vec<u8> = Vec::new();
+/// let _ = stdin()
+///     .lock()
+///     .read_to_end(&mut buf)
+///     .expect("Could not read until EOF.");
+///
+/// print_from_memory(&buf, &Config::default()).expect("Image printing failed.");
+/// ```
+pub fn print_from_memory(buf: &[u8], config: &Config) -> ViuResult<PrintedInfo> {
+    print(&printer::decode_from_memory(buf, config)?, config)
+}
+
+/// Helper method that downloads an image, tries to decode it and prints it.
+/// Downloads larger than 64 MiB are aborted with [ViuError::TooLarge].
+///
+/// Only available with the `network` feature.
+/// ## Example
+/// ```no_run
+/// use viuer::{Config, print_from_url};
+/// print_from_url("https://example.com/img.png", &Config::default())
+///     .expect("Image printing failed.");
+/// ```
+#[cfg(feature = "network")]
+pub fn print_from_url(url: &str, config: &Config) -> ViuResult<PrintedInfo> {
+    let buf = network::download(url, network::MAX_DOWNLOAD_SIZE)?;
+    print_from_memory(&buf, config)
+}
+
+/// Helper method that renders a page of a PDF document, counted from 0, and prints it. The
+/// page is rendered only as large as it is printed, unless the config needs its full size,
+/// e.g. for cropping.
+///
+/// Only available with the `pdf` feature, and requires the pdfium library to be installed
+/// where the system's dynamic linker finds it.
+/// ## Example
+/// ```no_run
+/// use viuer::{Config, print_from_pdf};
+/// print_from_pdf("paper.pdf", 0, &Config::default()).expect("Image printing failed.");
+/// ```
+#[cfg(feature = "pdf")]
+pub fn print_from_pdf<P: AsRef<Path>>(
+    filename: P,
+    page: u32,
+    config: &Config,
+) -> ViuResult<PrintedInfo> {
+    if !utils::should_print(config.when) {
+        return Ok(PrintedInfo::nothing());
+    }
+    print(&pdf::render_page(filename, page, config)?, config)
+}
+
+/// Print an image file, and print it again whenever the file changes on disk or the
+/// terminal is resized, until [Watch::stop] is called or the [Watch] is dropped. Like with
+/// [watch], the image printed before is removed first. A file which cannot be decoded, e.g.
+/// while it is still being written, is left out until it changes again.
+///
+/// The directory of the file is watched, so that a file replaced by renaming another one
+/// over it is noticed too. Only available with the `watch` feature.
+/// ## Example
+/// ```no_run
+/// use viuer::{Config, print_from_file_watch};
+///
+/// let conf = Config {
+///     restore_cursor: true,
+///     ..Default::default()
+/// };
+/// let watch = print_from_file_watch("plot.png", &conf).expect("Image printing failed.");
+/// // ...
+/// watch.stop().expect("Image printing failed.");
+/// ```
+#[cfg(feature = "watch")]
+pub fn print_from_file_watch<P: AsRef<Path>>(filename: P, config: &Config) -> ViuResult<Watch> {
+    use notify::{RecursiveMode, Watcher};
+
+    let path = std::fs::canonicalize(filename)?;
+    let file = path.clone();
+    let mut watch = Watch::start(config.clone(), move |config| print_from_file(&file, config))?;
+
+    let events = watch.events.clone();
+    let file = path.clone();
+    let mut watcher = notify::recommended_watcher(move |event: notify::Result<notify::Event>| {
+        if let Ok(event) = event {
+            if (event.kind.is_create() || event.kind.is_modify()) && event.paths.contains(&file) {
+                let _ = events.send(true);
+            }
+        }
+    })?;
+    // A canonical path to a file always has a parent
+    watcher.watch(path.parent().unwrap_or(&path), RecursiveMode::NonRecursive)?;
+    watch.file_watcher = Some(watcher);
+    Ok(watch)
+}
+
+// Choose the appropriate printer to use based on user config and availability.
+// Graphics protocols are tried in order Kitty -> iTerm -> Sixel, falling back to blocks.
 fn choose_printer(config: &Config) -> PrinterType {
+    if let Some(printer) = config.printer {
+        return printer;
+    }
+
+    if let Some(printer) = utils::printer_from_env() {
+        return printer;
+    }
+
+    // GNU screen does not forward the graphics escape sequences reliably, and would print
+    // them as text, like the legacy Windows console
+    if utils::in_screen() || !utils::virtual_terminal_enabled() {
+        return PrinterType::Block;
+    }
+
+    if config.protocol_policy.requires_query() {
+        return choose_verified_printer(config, terminal::verified_protocols());
+    }
+
+    if config.use_kitty && get_kitty_support() != KittySupport::None {
+        return PrinterType::Kitty;
+    }
+
+    if config.use_iterm && is_iterm_supported() {
+        return PrinterType::iTerm;
+    }
+
     #[cfg(feature = "sixel")]
     if config.use_sixel && is_sixel_supported() {
         return PrinterType::Sixel;
     }
 
-    if config.use_iterm && is_iterm_supported() {
-        PrinterType::iTerm
-    } else if config.use_kitty && get_kitty_support() != KittySupport::None {
-        PrinterType::Kitty
-    } else {
-        PrinterType::Block
+    PrinterType::Block
+}
+
+// Choose among the protocols which the terminal confirmed in its replies
+fn choose_verified_printer(config: &Config, verified: terminal::VerifiedProtocols) -> PrinterType {
+    if config.use_kitty && verified.kitty {
+        return PrinterType::Kitty;
+    }
+    if config.use_iterm && verified.iterm {
+        return PrinterType::iTerm;
+    }
+    #[cfg(feature = "sixel")]
+    if config.use_sixel && verified.sixel {
+        return PrinterType::Sixel;
+    }
+    PrinterType::Block
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_choose_forced_printer() {
+        let config = Config {
+            printer: Some(PrinterType::iTerm),
+            ..Default::default()
+        };
+        assert_eq!(choose_printer(&config), PrinterType::iTerm);
+    }
+
+    #[test]
+    fn test_choose_printer_fallback() {
+        utils::clear_terminal_env();
+        let config = Config {
+            use_kitty: false,
+            use_iterm: false,
+            #[cfg(feature = "sixel")]
+            use_sixel: false,
+            ..Default::default()
+        };
+        assert_eq!(choose_printer(&config), PrinterType::Block);
+    }
+
+    #[test]
+    fn test_choose_verified_printer() {
+        let config = Config {
+            protocol_policy: ProtocolPolicy::Query,
+            ..Default::default()
+        };
+        // Nothing is confirmed when the terminal can not be queried
+        let none = terminal::VerifiedProtocols::default();
+        assert_eq!(choose_verified_printer(&config, none), PrinterType::Block);
+
+        let iterm = terminal::VerifiedProtocols {
+            iterm: true,
+            ..none
+        };
+        assert_eq!(choose_verified_printer(&config, iterm), PrinterType::iTerm);
+
+        let both = terminal::VerifiedProtocols {
+            kitty: true,
+            ..iterm
+        };
+        assert_eq!(choose_verified_printer(&config, both), PrinterType::Kitty);
+        let config = Config {
+            use_kitty: false,
+            ..config
+        };
+        assert_eq!(choose_verified_printer(&config, both), PrinterType::iTerm);
+    }
+
+    #[test]
+    fn test_print_to_writer() {
+        let img = DynamicImage::ImageRgba8(image::RgbaImage::new(4, 4));
+        let config = Config {
+            width: Some(4),
+            height: Some(2),
+            printer: Some(PrinterType::Block),
+            ..Default::default()
+        };
+        let mut buf = Vec::new();
+        let info = print_to(&img, &config, &mut buf).unwrap();
+
+        assert_eq!((info.width, info.height), (4, 2));
+        assert_eq!(info.bytes_written, buf.len());
+        assert_eq!(buf.iter().filter(|&&b| b == b'\n').count(), 2);
+    }
+
+    #[test]
+    fn test_print_uncolored() {
+        let img =
+            DynamicImage::ImageRgba8(image::RgbaImage::from_pixel(4, 4, Rgba([9, 9, 9, 255])));
+        let config = utils::uncolored(&Config {
+            width: Some(4),
+            height: Some(2),
+            absolute_offset: false,
+            printer: Some(PrinterType::Kitty),
+            ..Default::default()
+        });
+        let mut out = Vec::new();
+        let info = print_to(&img, &config, &mut utils::Uncolored::new(&mut out)).unwrap();
+
+        assert_eq!(info.printer, PrinterType::Block);
+        out.retain(|&b| b != b'\r');
+        assert_eq!(String::from_utf8(out).unwrap(), "░░░░\n░░░░\n");
+    }
+
+    #[test]
+    fn test_print_view() {
+        let mut img = image::RgbaImage::new(8, 4);
+        img.put_pixel(4, 0, Rgba([255, 0, 0, 255]));
+        let config = Config {
+            width: Some(4),
+            height: Some(2),
+            printer: Some(PrinterType::Block),
+            ..Default::default()
+        };
+        let right = DynamicImage::ImageRgba8(img.view(4, 0, 4, 4).to_image());
+
+        let mut view_buf = Vec::new();
+        let info = print_view_to(&*img.view(4, 0, 4, 4), &config, &mut view_buf).unwrap();
+        let mut buf = Vec::new();
+        print_to(&right, &config, &mut buf).unwrap();
+        assert_eq!((info.width, info.height), (4, 2));
+        assert_eq!(view_buf, buf);
+
+        // A large view is scaled down while it is read, and printed the same
+        let img = image::RgbaImage::from_pixel(400, 200, Rgba([10, 20, 30, 255]));
+        view_buf.clear();
+        let info = print_view_to(&*img.view(0, 0, 200, 200), &config, &mut view_buf).unwrap();
+        buf.clear();
+        let left = DynamicImage::ImageRgba8(img.view(0, 0, 200, 200).to_image());
+        print_to(&left, &config, &mut buf).unwrap();
+        assert_eq!((info.width, info.height), (4, 2));
+        assert_eq!(view_buf, buf);
+    }
+
+    #[test]
+    fn test_print_to_closed_output() {
+        struct ClosedPipe;
+        impl Write for ClosedPipe {
+            fn write(&mut self, _: &[u8]) -> std::io::Result<usize> {
+                Err(std::io::ErrorKind::BrokenPipe.into())
+            }
+            fn flush(&mut self) -> std::io::Result<()> {
+                Ok(())
+            }
+        }
+
+        let img = DynamicImage::ImageRgba8(image::RgbaImage::new(4, 4));
+        let config = Config {
+            printer: Some(PrinterType::Block),
+            ..Default::default()
+        };
+        let result = print_to(&img, &config, &mut ClosedPipe);
+        assert!(matches!(result, Err(ViuError::Closed)));
+    }
+
+    #[test]
+    fn test_render_to_string() {
+        let img = DynamicImage::ImageRgba8(image::RgbaImage::new(2, 2));
+        let config = Config {
+            absolute_offset: false,
+            width: Some(2),
+            printer: Some(PrinterType::Kitty),
+            ..Default::default()
+        };
+        let rendered = render_to_string(&img, &config).unwrap();
+        assert_eq!(rendered.matches('\u{2584}').count(), 2);
     }
 }
@@ -0,0 +1,31 @@
+// Downloading of images for print_from_url
+use crate::error::{ViuError, ViuResult};
+
+use std::io::Read;
+
+// Largest image that is downloaded, in bytes
+pub const MAX_DOWNLOAD_SIZE: u64 = 64 * 1024 * 1024;
+
+// Fetch the body of the response for the given url, failing once it is larger than the limit.
+// The Content-Length header is checked first, so that big files are not downloaded at all.
+pub fn download(url: &str, limit: u64) -> ViuResult<Vec<u8>> {
+    let response = ureq::get(url).call()?;
+
+    let length = response
+        .header("Content-Length")
+        .and_then(|len| len.parse::<u64>().ok());
+    if length.is_some_and(|len| len > limit) {
+        return Err(ViuError::TooLarge(limit));
+    }
+
+    // Read one byte past the limit, to know when the body does not fit
+    let mut buf = Vec::with_capacity(length.unwrap_or(0) as usize);
+    response
+        .into_reader()
+        .take(limit + 1)
+        .read_to_end(&mut buf)?;
+    if buf.len() as u64 > limit {
+        return Err(ViuError::TooLarge(limit));
+    }
+    Ok(buf)
+}
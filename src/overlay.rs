@@ -0,0 +1,269 @@
+// Text and images drawn over the image before it is printed.
+use image::{imageops, DynamicImage, Rgba, RgbaImage};
+use std::sync::Arc;
+
+// Size of a glyph of the built-in font in its pixels, and the space after a glyph or line
+const GLYPH_WIDTH: u32 = 5;
+const GLYPH_HEIGHT: u32 = 7;
+const GLYPH_SPACING: u32 = 1;
+
+/// Something drawn over the image before it is printed, e.g. a timestamp, set through
+/// [crate::Config::overlays].
+///
+/// ## Example
+/// ```no_run
+/// use image::Rgba;
+/// use viuer::{Config, Corner, Overlay};
+///
+/// let stamp = Overlay {
+///     corner: Corner::BottomRight,
+///     ..Overlay::text("12:00:00", Rgba([255, 255, 0, 255]))
+/// };
+/// let config = Config {
+///     overlays: vec![stamp],
+///     ..Default::default()
+/// };
+/// ```
+#[derive(Clone)]
+pub struct Overlay {
+    /// What is drawn.
+    pub content: OverlayContent,
+    /// Corner of the image the overlay is placed in. Defaults to [Corner::TopLeft].
+    pub corner: Corner,
+    /// Distance in pixels of the image between the overlay and the edges next to the
+    /// corner, as `(x, y)`. Defaults to `(1, 1)`.
+    pub margin: (u32, u32),
+}
+
+/// What an [Overlay] draws.
+#[derive(Clone)]
+pub enum OverlayContent {
+    /// Text in a built-in 5x7 pixel font, which has the digits, the letters, shown in
+    /// upper case, and common punctuation. Other characters are shown as a box. Lines are
+    /// separated by `\n`.
+    Text {
+        /// The text to draw.
+        text: String,
+        /// Color of the text, blended over the image with its alpha.
+        color: Rgba<u8>,
+        /// Size of a pixel of the font in pixels of the image. None makes it a hundredth of
+        /// the image's height, so that the text keeps its size compared to the image.
+        scale: Option<u32>,
+    },
+    /// An image, blended over with its alpha channel. It is shared, so that configs can be
+    /// cloned without copying it.
+    Image(Arc<DynamicImage>),
+}
+
+/// Corner of the image an [Overlay] is placed in.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Corner {
+    /// The top left corner.
+    TopLeft,
+    /// The top right corner.
+    TopRight,
+    /// The bottom left corner.
+    BottomLeft,
+    /// The bottom right corner.
+    BottomRight,
+}
+
+impl Overlay {
+    /// Text in the given color, in the top left corner, with the font scaled to the image.
+    pub fn text(text: impl Into<String>, color: Rgba<u8>) -> Self {
+        Self::new(OverlayContent::Text {
+            text: text.into(),
+            color,
+            scale: None,
+        })
+    }
+
+    /// An image, in the top left corner.
+    pub fn image(img: DynamicImage) -> Self {
+        Self::new(OverlayContent::Image(Arc::new(img)))
+    }
+
+    fn new(content: OverlayContent) -> Self {
+        Self {
+            content,
+            corner: Corner::TopLeft,
+            margin: (1, 1),
+        }
+    }
+
+    // The overlay as it is drawn over an image of the given height
+    fn render(&self, img_height: u32) -> RgbaImage {
+        match &self.content {
+            OverlayContent::Text { text, color, scale } => {
+                let scale = scale.unwrap_or(img_height / 100).max(1);
+                render_text(text, *color, scale)
+            }
+            OverlayContent::Image(img) => img.to_rgba8(),
+        }
+    }
+}
+
+// Draw the overlays over the image, in their order
+pub fn apply(img: &DynamicImage, overlays: &[Overlay]) -> DynamicImage {
+    let mut frame = img.to_rgba8();
+    for overlay in overlays {
+        let top = overlay.render(frame.height());
+        let (x, y) = position(frame.dimensions(), top.dimensions(), overlay);
+        imageops::overlay(&mut frame, &top, x, y);
+    }
+    DynamicImage::ImageRgba8(frame)
+}
+
+// Top left corner of an overlay of the given size over the image, which may be outside of it
+fn position((width, height): (u32, u32), (w, h): (u32, u32), overlay: &Overlay) -> (i64, i64) {
+    let (margin_x, margin_y) = (overlay.margin.0 as i64, overlay.margin.1 as i64);
+    let left = margin_x;
+    let right = width as i64 - w as i64 - margin_x;
+    let top = margin_y;
+    let bottom = height as i64 - h as i64 - margin_y;
+    match overlay.corner {
+        Corner::TopLeft => (left, top),
+        Corner::TopRight => (right, top),
+        Corner::BottomLeft => (left, bottom),
+        Corner::BottomRight => (right, bottom),
+    }
+}
+
+// The text drawn with the built-in font over a transparent background
+fn render_text(text: &str, color: Rgba<u8>, scale: u32) -> RgbaImage {
+    let lines: Vec<_> = text.lines().collect();
+    let columns = lines
+        .iter()
+        .map(|line| line.chars().count() as u32)
+        .max()
+        .unwrap_or(0);
+    let width = (columns * (GLYPH_WIDTH + GLYPH_SPACING)).saturating_sub(GLYPH_SPACING);
+    let height =
+        (lines.len() as u32 * (GLYPH_HEIGHT + GLYPH_SPACING)).saturating_sub(GLYPH_SPACING);
+
+    let mut img = RgbaImage::new(width * scale, height * scale);
+    for (row, line) in lines.iter().enumerate() {
+        let top = row as u32 * (GLYPH_HEIGHT + GLYPH_SPACING);
+        for (column, c) in line.chars().enumerate() {
+            let left = column as u32 * (GLYPH_WIDTH + GLYPH_SPACING);
+            for (y, bits) in glyph(c).iter().enumerate() {
+                for x in 0..GLYPH_WIDTH {
+                    if bits & (1 << (GLYPH_WIDTH - 1 - x)) == 0 {
+                        continue;
+                    }
+                    let (px, py) = ((left + x) * scale, (top + y as u32) * scale);
+                    for dy in 0..scale {
+                        for dx in 0..scale {
+                            img.put_pixel(px + dx, py + dy, color);
+                        }
+                    }
+                }
+            }
+        }
+    }
+    img
+}
+
+// Rows of the character in the built-in font, the leftmost pixel in the highest of five bits
+#[rustfmt::skip]
+fn glyph(c: char) -> [u8; GLYPH_HEIGHT as usize] {
+    match c.to_ascii_uppercase() {
+        ' ' => [0b00000, 0b00000, 0b00000, 0b00000, 0b00000, 0b00000, 0b00000],
+        '0' => [0b01110, 0b10001, 0b10011, 0b10101, 0b11001, 0b10001, 0b01110],
+        '1' => [0b00100, 0b01100, 0b00100, 0b00100, 0b00100, 0b00100, 0b01110],
+        '2' => [0b01110, 0b10001, 0b00001, 0b00010, 0b00100, 0b01000, 0b11111],
+        '3' => [0b11111, 0b00010, 0b00100, 0b00010, 0b00001, 0b10001, 0b01110],
+        '4' => [0b00010, 0b00110, 0b01010, 0b10010, 0b11111, 0b00010, 0b00010],
+        '5' => [0b11111, 0b10000, 0b11110, 0b00001, 0b00001, 0b10001, 0b01110],
+        '6' => [0b00110, 0b01000, 0b10000, 0b11110, 0b10001, 0b10001, 0b01110],
+        '7' => [0b11111, 0b00001, 0b00010, 0b00100, 0b01000, 0b01000, 0b01000],
+        '8' => [0b01110, 0b10001, 0b10001, 0b01110, 0b10001, 0b10001, 0b01110],
+        '9' => [0b01110, 0b10001, 0b10001, 0b01111, 0b00001, 0b00010, 0b01100],
+        'A' => [0b01110, 0b10001, 0b10001, 0b11111, 0b10001, 0b10001, 0b10001],
+        'B' => [0b11110, 0b10001, 0b10001, 0b11110, 0b10001, 0b10001, 0b11110],
+        'C' => [0b01110, 0b10001, 0b10000, 0b10000, 0b10000, 0b10001, 0b01110],
+        'D' => [0b11100, 0b10010, 0b10001, 0b10001, 0b10001, 0b10010, 0b11100],
+        'E' => [0b11111, 0b10000, 0b10000, 0b11110, 0b10000, 0b10000, 0b11111],
+        'F' => [0b11111, 0b10000, 0b10000, 0b11110, 0b10000, 0b10000, 0b10000],
+        'G' => [0b01110, 0b10001, 0b10000, 0b10111, 0b10001, 0b10001, 0b01111],
+        'H' => [0b10001, 0b10001, 0b10001, 0b11111, 0b10001, 0b10001, 0b10001],
+        'I' => [0b01110, 0b00100, 0b00100, 0b00100, 0b00100, 0b00100, 0b01110],
+        'J' => [0b00111, 0b00010, 0b00010, 0b00010, 0b00010, 0b10010, 0b01100],
+        'K' => [0b10001, 0b10010, 0b10100, 0b11000, 0b10100, 0b10010, 0b10001],
+        'L' => [0b10000, 0b10000, 0b10000, 0b10000, 0b10000, 0b10000, 0b11111],
+        'M' => [0b10001, 0b11011, 0b10101, 0b10101, 0b10001, 0b10001, 0b10001],
+        'N' => [0b10001, 0b10001, 0b11001, 0b10101, 0b10011, 0b10001, 0b10001],
+        'O' => [0b01110, 0b10001, 0b10001, 0b10001, 0b10001, 0b10001, 0b01110],
+        'P' => [0b11110, 0b10001, 0b10001, 0b11110, 0b10000, 0b10000, 0b10000],
+        'Q' => [0b01110, 0b10001, 0b10001, 0b10001, 0b10101, 0b10010, 0b01101],
+        'R' => [0b11110, 0b10001, 0b10001, 0b11110, 0b10100, 0b10010, 0b10001],
+        'S' => [0b01111, 0b10000, 0b10000, 0b01110, 0b00001, 0b00001, 0b11110],
+        'T' => [0b11111, 0b00100, 0b00100, 0b00100, 0b00100, 0b00100, 0b00100],
+        'U' => [0b10001, 0b10001, 0b10001, 0b10001, 0b10001, 0b10001, 0b01110],
+        'V' => [0b10001, 0b10001, 0b10001, 0b10001, 0b10001, 0b01010, 0b00100],
+        'W' => [0b10001, 0b10001, 0b10001, 0b10101, 0b10101, 0b10101, 0b01010],
+        'X' => [0b10001, 0b10001, 0b01010, 0b00100, 0b01010, 0b10001, 0b10001],
+        'Y' => [0b10001, 0b10001, 0b10001, 0b01010, 0b00100, 0b00100, 0b00100],
+        'Z' => [0b11111, 0b00001, 0b00010, 0b00100, 0b01000, 0b10000, 0b11111],
+        '!' => [0b00100, 0b00100, 0b00100, 0b00100, 0b00100, 0b00000, 0b00100],
+        '"' => [0b01010, 0b01010, 0b00000, 0b00000, 0b00000, 0b00000, 0b00000],
+        '#' => [0b01010, 0b01010, 0b11111, 0b01010, 0b11111, 0b01010, 0b01010],
+        '%' => [0b11000, 0b11001, 0b00010, 0b00100, 0b01000, 0b10011, 0b00011],
+        '\'' => [0b00100, 0b00100, 0b00000, 0b00000, 0b00000, 0b00000, 0b00000],
+        '(' => [0b00010, 0b00100, 0b01000, 0b01000, 0b01000, 0b00100, 0b00010],
+        ')' => [0b01000, 0b00100, 0b00010, 0b00010, 0b00010, 0b00100, 0b01000],
+        '*' => [0b00000, 0b00100, 0b10101, 0b01110, 0b10101, 0b00100, 0b00000],
+        '+' => [0b00000, 0b00100, 0b00100, 0b11111, 0b00100, 0b00100, 0b00000],
+        ',' => [0b00000, 0b00000, 0b00000, 0b00000, 0b01100, 0b00100, 0b01000],
+        '-' => [0b00000, 0b00000, 0b00000, 0b11111, 0b00000, 0b00000, 0b00000],
+        '.' => [0b00000, 0b00000, 0b00000, 0b00000, 0b00000, 0b01100, 0b01100],
+        '/' => [0b00000, 0b00001, 0b00010, 0b00100, 0b01000, 0b10000, 0b00000],
+        ':' => [0b00000, 0b01100, 0b01100, 0b00000, 0b01100, 0b01100, 0b00000],
+        ';' => [0b00000, 0b01100, 0b01100, 0b00000, 0b01100, 0b00100, 0b01000],
+        '<' => [0b00010, 0b00100, 0b01000, 0b10000, 0b01000, 0b00100, 0b00010],
+        '=' => [0b00000, 0b00000, 0b11111, 0b00000, 0b11111, 0b00000, 0b00000],
+        '>' => [0b01000, 0b00100, 0b00010, 0b00001, 0b00010, 0b00100, 0b01000],
+        '?' => [0b01110, 0b10001, 0b00001, 0b00010, 0b00100, 0b00000, 0b00100],
+        '@' => [0b01110, 0b10001, 0b00001, 0b01101, 0b10101, 0b10101, 0b01110],
+        '[' => [0b01110, 0b01000, 0b01000, 0b01000, 0b01000, 0b01000, 0b01110],
+        ']' => [0b01110, 0b00010, 0b00010, 0b00010, 0b00010, 0b00010, 0b01110],
+        '_' => [0b00000, 0b00000, 0b00000, 0b00000, 0b00000, 0b00000, 0b11111],
+        '|' => [0b00100, 0b00100, 0b00100, 0b00100, 0b00100, 0b00100, 0b00100],
+        _ => [0b11111, 0b10001, 0b10001, 0b10001, 0b10001, 0b10001, 0b11111],
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_render_text() {
+        let white = Rgba([255, 255, 255, 255]);
+        let img = render_text("1.\n-", white, 2);
+        // Two glyphs and the space between them, on two lines
+        assert_eq!(img.dimensions(), (22, 30));
+        // The top of the 1, the dot and the dash
+        assert_eq!(*img.get_pixel(4, 0), white);
+        assert_eq!(*img.get_pixel(5, 1), white);
+        assert_eq!(*img.get_pixel(0, 0), Rgba([0, 0, 0, 0]));
+        assert_eq!(*img.get_pixel(15, 11), white);
+        assert_eq!(*img.get_pixel(0, 22), white);
+    }
+
+    #[test]
+    fn test_apply() {
+        let img = DynamicImage::ImageRgba8(RgbaImage::from_pixel(10, 10, Rgba([0, 0, 0, 255])));
+        let red = Rgba([255, 0, 0, 255]);
+        let overlay = Overlay {
+            corner: Corner::BottomRight,
+            margin: (2, 0),
+            ..Overlay::image(RgbaImage::from_pixel(3, 2, red).into())
+        };
+        let frame = apply(&img, &[overlay]).to_rgba8();
+        assert_eq!(*frame.get_pixel(7, 8), red);
+        assert_eq!(*frame.get_pixel(5, 9), red);
+        assert_eq!(*frame.get_pixel(8, 9), Rgba([0, 0, 0, 255]));
+        assert_eq!(*frame.get_pixel(5, 7), Rgba([0, 0, 0, 255]));
+    }
+}
@@ -0,0 +1,40 @@
+// Rendering of PDF pages for print_from_pdf, done by the pdfium library
+use crate::error::ViuResult;
+use crate::printer;
+use crate::Config;
+
+use image::DynamicImage;
+use pdfium_render::prelude::{PdfRenderConfig, Pdfium, PdfiumError};
+use std::path::Path;
+
+// Resolution of pages rendered at their full size, the same as pdftoppm's default
+const FULL_SIZE_DPI: f32 = 150.0;
+
+// Render the page of the PDF, counted from 0, only as large as it is printed when the config
+// allows it
+pub fn render_page<P: AsRef<Path>>(
+    filename: P,
+    page: u32,
+    config: &Config,
+) -> ViuResult<DynamicImage> {
+    let pdfium = Pdfium::new(Pdfium::bind_to_system_library()?);
+    let document = pdfium.load_pdf_from_file(filename.as_ref(), None)?;
+    let index = u16::try_from(page).map_err(|_| PdfiumError::PageIndexOutOfBounds)?;
+    let page = document.pages().get(index)?;
+
+    let render = if printer::full_size_needed(config) {
+        // The page is too large for the limits from the config when rendered at this resolution
+        let pixels = |points: f32| (points * FULL_SIZE_DPI / 72.0).ceil() as u32;
+        config
+            .limits
+            .check_dimensions(pixels(page.width().value), pixels(page.height().value))?;
+        // PDF points are 1/72 of an inch
+        PdfRenderConfig::new().scale_page_by_factor(FULL_SIZE_DPI / 72.0)
+    } else {
+        // Fit the longer side of the page to the size it is printed at
+        let size = printer::scaled_decoding_size(config) as i32;
+        PdfRenderConfig::new().scale_page_to_display_size(size, size)
+    };
+    let image = page.render_with_config(&render)?.as_image();
+    Ok(image)
+}
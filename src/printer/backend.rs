@@ -0,0 +1,228 @@
+// Output of the printers which draw with characters: text, cursor movements and colors.
+use crossterm::cursor::{MoveLeft, MoveRight, MoveTo, MoveToNextLine, MoveToPreviousLine};
+use crossterm::queue;
+use crossterm::style::{Colors, ResetColor, SetColors};
+use std::io::{self, Write};
+
+/// Where the block printers write to. Cursor movements and colors go through these methods
+/// instead of escape sequences assembled by hand, so that they can be recorded, e.g. in tests.
+pub trait Backend: Write {
+    fn move_to(&mut self, column: u16, row: u16) -> io::Result<()>;
+    fn move_right(&mut self, columns: u16) -> io::Result<()>;
+    fn move_left(&mut self, columns: u16) -> io::Result<()>;
+    fn move_to_next_line(&mut self, lines: u16) -> io::Result<()>;
+    fn move_to_previous_line(&mut self, lines: u16) -> io::Result<()>;
+    /// Replace the current colors. A missing color is left to the terminal's default.
+    fn set_colors(&mut self, colors: Colors) -> io::Result<()>;
+    fn reset_colors(&mut self) -> io::Result<()>;
+}
+
+/// [Backend] writing through crossterm. On Windows consoles which do not process escape
+/// sequences, crossterm uses the console API instead.
+///
+/// The colors are only written when they change, so that runs of cells with the same colors
+/// share a single escape sequence.
+pub struct CrosstermBackend<W: Write> {
+    inner: W,
+    // The colors set last, None if they were reset or not set yet
+    colors: Option<Colors>,
+}
+
+impl<W: Write> CrosstermBackend<W> {
+    pub fn new(inner: W) -> Self {
+        Self {
+            inner,
+            colors: None,
+        }
+    }
+
+    #[cfg(test)]
+    pub fn get_ref(&self) -> &W {
+        &self.inner
+    }
+}
+
+impl<W: Write> Write for CrosstermBackend<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.inner.write(buf)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.inner.flush()
+    }
+}
+
+impl<W: Write> Backend for CrosstermBackend<W> {
+    fn move_to(&mut self, column: u16, row: u16) -> io::Result<()> {
+        queue!(self.inner, MoveTo(column, row))
+    }
+
+    fn move_right(&mut self, columns: u16) -> io::Result<()> {
+        queue!(self.inner, MoveRight(columns))
+    }
+
+    fn move_left(&mut self, columns: u16) -> io::Result<()> {
+        queue!(self.inner, MoveLeft(columns))
+    }
+
+    fn move_to_next_line(&mut self, lines: u16) -> io::Result<()> {
+        queue!(self.inner, MoveToNextLine(lines))
+    }
+
+    fn move_to_previous_line(&mut self, lines: u16) -> io::Result<()> {
+        queue!(self.inner, MoveToPreviousLine(lines))
+    }
+
+    fn set_colors(&mut self, colors: Colors) -> io::Result<()> {
+        if self.colors == Some(colors) {
+            return Ok(());
+        }
+        self.colors = Some(colors);
+        // Reset first, so that a missing color does not keep the previous one
+        queue!(self.inner, ResetColor, SetColors(colors))
+    }
+
+    fn reset_colors(&mut self) -> io::Result<()> {
+        self.colors = None;
+        queue!(self.inner, ResetColor)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::printer::cell::{write_cells, Cell};
+    use crate::{ColorDepth, Config};
+    use crossterm::style::Color;
+
+    // Backend which records the calls instead of writing escape sequences
+    #[derive(Default)]
+    struct Recorder {
+        calls: Vec<String>,
+    }
+
+    impl Write for Recorder {
+        fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+            self.calls
+                .push(format!("write {:?}", String::from_utf8_lossy(buf)));
+            Ok(buf.len())
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    impl Backend for Recorder {
+        fn move_to(&mut self, column: u16, row: u16) -> io::Result<()> {
+            self.calls.push(format!("move_to {} {}", column, row));
+            Ok(())
+        }
+
+        fn move_right(&mut self, columns: u16) -> io::Result<()> {
+            self.calls.push(format!("move_right {}", columns));
+            Ok(())
+        }
+
+        fn move_left(&mut self, columns: u16) -> io::Result<()> {
+            self.calls.push(format!("move_left {}", columns));
+            Ok(())
+        }
+
+        fn move_to_next_line(&mut self, lines: u16) -> io::Result<()> {
+            self.calls.push(format!("move_to_next_line {}", lines));
+            Ok(())
+        }
+
+        fn move_to_previous_line(&mut self, lines: u16) -> io::Result<()> {
+            self.calls.push(format!("move_to_previous_line {}", lines));
+            Ok(())
+        }
+
+        fn set_colors(&mut self, colors: Colors) -> io::Result<()> {
+            self.calls.push(format!(
+                "set_colors {:?} {:?}",
+                colors.foreground, colors.background
+            ));
+            Ok(())
+        }
+
+        fn reset_colors(&mut self) -> io::Result<()> {
+            self.calls.push("reset_colors".to_owned());
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn test_recorded_cells() {
+        let rows = vec![vec![
+            Cell {
+                ch: '▀',
+                fg: Some((1, 2, 3)),
+                bg: None,
+            },
+            Cell {
+                ch: ' ',
+                fg: None,
+                bg: None,
+            },
+        ]];
+        let config = Config {
+            absolute_offset: false,
+            x: 2,
+            color_depth: ColorDepth::TrueColor,
+            ..Default::default()
+        };
+        let mut recorder = Recorder::default();
+        write_cells(&mut recorder, &rows, &config).unwrap();
+
+        let fg = Color::Rgb { r: 1, g: 2, b: 3 };
+        assert_eq!(
+            recorder.calls,
+            [
+                "move_right 2".to_owned(),
+                format!("set_colors {:?} None", Some(fg)),
+                "write \"▀\"".to_owned(),
+                "move_right 1".to_owned(),
+                "reset_colors".to_owned(),
+                "write \"\\n\"".to_owned(),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_crossterm_backend() {
+        let mut backend = CrosstermBackend::new(vec![]);
+        backend.move_right(3).unwrap();
+        backend
+            .set_colors(Colors {
+                foreground: Some(Color::AnsiValue(247)),
+                background: None,
+            })
+            .unwrap();
+        backend.reset_colors().unwrap();
+        assert_eq!(
+            std::str::from_utf8(backend.get_ref()).unwrap(),
+            "\x1b[3C\x1b[0m\x1b[38;5;247m\x1b[0m"
+        );
+    }
+
+    #[test]
+    fn test_crossterm_backend_repeated_colors() {
+        let colors = Colors {
+            foreground: Some(Color::AnsiValue(1)),
+            background: Some(Color::AnsiValue(2)),
+        };
+        let mut backend = CrosstermBackend::new(vec![]);
+        for _ in 0..3 {
+            backend.set_colors(colors).unwrap();
+            write!(backend, "▄").unwrap();
+        }
+        backend.reset_colors().unwrap();
+        backend.set_colors(colors).unwrap();
+        assert_eq!(
+            std::str::from_utf8(backend.get_ref()).unwrap(),
+            "\x1b[0m\x1b[38;5;1m\x1b[48;5;2m▄▄▄\x1b[0m\x1b[0m\x1b[38;5;1m\x1b[48;5;2m"
+        );
+    }
+}
@@ -0,0 +1,83 @@
+use crate::error::ViuResult;
+use crate::printer::backend::{Backend, CrosstermBackend};
+use crate::printer::cell::{fill_cells, pixel_rgb, write_cells, Cell};
+use crate::printer::dither::dither;
+use crate::printer::{fit_to_config, resize_to, Printer};
+use crate::Config;
+
+use image::DynamicImage;
+use std::io::{BufWriter, Write};
+
+/// Prints images using spaces with a background color, with each cell holding one pixel.
+/// Unlike the block characters, this cannot show seams or misaligned glyphs, whatever the
+/// font, at the cost of half the vertical resolution of [crate::BlockStyle::HalfBlock].
+pub struct BackgroundPrinter;
+
+impl Printer for BackgroundPrinter {
+    fn print(
+        &self,
+        stdout: &mut impl Write,
+        img: &DynamicImage,
+        config: &Config,
+    ) -> ViuResult<(u32, u32)> {
+        let mut stream = CrosstermBackend::new(BufWriter::new(stdout));
+        print_to_backend(&mut stream, img, config)
+    }
+}
+
+fn print_to_backend(
+    stdout: &mut impl Backend,
+    img: &DynamicImage,
+    config: &Config,
+) -> ViuResult<(u32, u32)> {
+    let mut cells = Vec::new();
+    render_into(img, config, &mut cells);
+    write_cells(stdout, &cells, config)
+}
+
+// Resize the image to fit the config and convert it to rows of cells, reusing the
+// allocations of `cells`
+pub fn render_into(img: &DynamicImage, config: &Config, cells: &mut Vec<Vec<Cell>>) {
+    let (w, h) = fit_to_config(img.width(), img.height(), config);
+    let mut img = resize_to(img, w, h, config).into_rgba8();
+    dither(&mut img, config);
+
+    fill_cells(cells, w, h, |row, col| Cell {
+        ch: ' ',
+        fg: None,
+        bg: pixel_rgb(col, row, img.get_pixel(col, row), config),
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ColorDepth;
+
+    #[test]
+    fn test_background_printer() {
+        let img = DynamicImage::ImageRgba8(image::RgbaImage::from_fn(2, 1, |x, _| {
+            if x == 0 {
+                image::Rgba([0, 0, 0, 0])
+            } else {
+                image::Rgba([10, 20, 30, 255])
+            }
+        }));
+        let config = Config {
+            absolute_offset: false,
+            transparent: true,
+            color_depth: ColorDepth::TrueColor,
+            width: Some(2),
+            height: Some(1),
+            size_mode: crate::SizeMode::Stretch,
+            ..Default::default()
+        };
+        let mut buf = CrosstermBackend::new(vec![]);
+
+        assert_eq!(print_to_backend(&mut buf, &img, &config).unwrap(), (2, 1));
+        assert_eq!(
+            std::str::from_utf8(buf.get_ref()).unwrap(),
+            "\x1b[1C\x1b[0m\x1b[48;2;10;20;30m \x1b[0m\n"
+        );
+    }
+}
@@ -1,199 +1,244 @@
 use crate::error::ViuResult;
+use crate::printer::backend::{Backend, CrosstermBackend};
+use crate::printer::cell::{checkerboard_rgb, fill_cells, pixel_rgb, rgb_to_color, Cell};
+use crate::printer::dither::dither;
 use crate::printer::{adjust_offset, Printer};
-use crate::Config;
+use crate::{ColorDepth, Config};
 
-use ansi_colours::ansi256_from_rgb;
-use image::{DynamicImage, GenericImageView, Rgba};
-use std::io::Write;
-use termcolor::{BufferedStandardStream, Color, ColorChoice, ColorSpec, WriteColor};
-
-use crossterm::cursor::MoveRight;
-use crossterm::execute;
+use crossterm::style::{Color, Colors};
+use image::{DynamicImage, GenericImageView, Rgba, RgbaImage};
+use std::io::{BufWriter, Write};
 
 const UPPER_HALF_BLOCK: &str = "\u{2580}";
 const LOWER_HALF_BLOCK: &str = "\u{2584}";
-
-const CHECKERBOARD_BACKGROUND_LIGHT: (u8, u8, u8) = (153, 153, 153);
-const CHECKERBOARD_BACKGROUND_DARK: (u8, u8, u8) = (102, 102, 102);
+const NO_COLORS: Colors = Colors {
+    foreground: None,
+    background: None,
+};
 
 pub struct BlockPrinter;
 
 impl Printer for BlockPrinter {
     fn print(
         &self,
-        // TODO: The provided object is not used because termcolor needs an implementation of the WriteColor trait
-        _stdout: &mut impl Write,
+        stdout: &mut impl Write,
         img: &DynamicImage,
         config: &Config,
     ) -> ViuResult<(u32, u32)> {
-        let mut stream = BufferedStandardStream::stdout(ColorChoice::Always);
-        print_to_writecolor(&mut stream, img, config)
+        let mut stream = CrosstermBackend::new(BufWriter::new(stdout));
+        print_to_backend(&mut stream, img, config)
     }
 }
 
-fn print_to_writecolor(
-    stdout: &mut impl WriteColor,
+fn print_to_backend(
+    stdout: &mut impl Backend,
     img: &DynamicImage,
     config: &Config,
 ) -> ViuResult<(u32, u32)> {
     // adjust with x=0 and handle horizontal offset entirely below
-    adjust_offset(stdout, &Config { x: 0, ..*config })?;
+    adjust_offset(
+        stdout,
+        &Config {
+            x: 0,
+            ..config.clone()
+        },
+    )?;
 
     // resize the image so that it fits in the constraints, if any
-    let img = super::resize(img, config.width, config.height);
+    let img = super::resize_for_config(img, config);
     let (width, height) = img.dimensions();
 
-    let mut row_color_buffer: Vec<ColorSpec> = vec![ColorSpec::new(); width as usize];
-    let img_buffer = img.to_rgba8(); //TODO: Can conversion be avoided?
+    let mut row_color_buffer: Vec<Colors> = vec![NO_COLORS; width as usize];
+    let mut img_buffer = img.to_rgba8(); //TODO: Can conversion be avoided?
+    dither(&mut img_buffer, config);
 
-    for (curr_row, img_row) in img_buffer.enumerate_rows() {
+    for (curr_row, row_colors) in (0..).zip(pixel_colors(&img_buffer, config)) {
         let is_even_row = curr_row % 2 == 0;
         let is_last_row = curr_row == height - 1;
 
         // move right if x offset is specified
         if config.x > 0 && (!is_even_row || is_last_row) {
-            execute!(stdout, MoveRight(config.x))?;
+            stdout.move_right(config.x)?;
         }
 
-        for pixel in img_row {
-            // choose the half block's color
-            let color = if is_pixel_transparent(pixel) {
-                if config.transparent {
-                    None
-                } else {
-                    Some(get_transparency_color(curr_row, pixel.0, config.truecolor))
-                }
-            } else {
-                Some(get_color_from_pixel(pixel, config.truecolor))
-            };
-
+        for (col, color) in row_colors.into_iter().enumerate() {
             // Even rows modify the background, odd rows the foreground
             // because lower half blocks are used by default
-            let colorspec = &mut row_color_buffer[pixel.0 as usize];
+            let colors = &mut row_color_buffer[col];
             if is_even_row {
-                colorspec.set_bg(color);
+                colors.background = color;
                 if is_last_row {
-                    write_colored_character(stdout, colorspec, true)?;
+                    write_colored_character(stdout, colors, true)?;
                 }
             } else {
-                colorspec.set_fg(color);
-                write_colored_character(stdout, colorspec, false)?;
+                colors.foreground = color;
+                write_colored_character(stdout, colors, false)?;
             }
         }
 
         if !is_even_row && !is_last_row {
-            stdout.reset()?;
+            stdout.reset_colors()?;
             writeln!(stdout, "\r")?;
         }
     }
 
-    stdout.reset()?;
+    stdout.reset_colors()?;
     writeln!(stdout)?;
     stdout.flush()?;
 
     Ok((width, height / 2 + height % 2))
 }
 
-fn write_colored_character(
-    stdout: &mut impl WriteColor,
-    c: &ColorSpec,
-    is_last_row: bool,
-) -> ViuResult {
+// The color of every pixel of the image, row by row. With the rayon feature, the rows are
+// computed in parallel.
+fn pixel_colors(img: &RgbaImage, config: &Config) -> Vec<Vec<Option<Color>>> {
+    let row_colors = |row: u32| -> Vec<Option<Color>> {
+        (0..img.width())
+            .map(|col| {
+                let pixel = (col, row, img.get_pixel(col, row));
+                // choose the half block's color
+                if is_pixel_transparent(pixel) {
+                    if config.transparent {
+                        None
+                    } else {
+                        Some(get_transparency_color(row, col, config))
+                    }
+                } else {
+                    Some(get_color_from_pixel(pixel, config.depth()))
+                }
+            })
+            .collect()
+    };
+
+    #[cfg(feature = "rayon")]
+    {
+        use rayon::prelude::*;
+        (0..img.height()).into_par_iter().map(row_colors).collect()
+    }
+    #[cfg(not(feature = "rayon"))]
+    {
+        (0..img.height()).map(row_colors).collect()
+    }
+}
+
+// Resize the image to fit the config and convert it to rows of cells, matching what
+// print_to_backend draws. The allocations of `cells` are reused.
+pub fn render_into(img: &DynamicImage, config: &Config, cells: &mut Vec<Vec<Cell>>) {
+    let mut img = super::resize_for_config(img, config).into_rgba8();
+    dither(&mut img, config);
+    let (width, height) = img.dimensions();
+
+    fill_cells(cells, width, height.div_ceil(2), |row, col| {
+        let row = 2 * row;
+        let top = pixel_rgb(col, row, img.get_pixel(col, row), config);
+        let bottom = if row + 1 < height {
+            pixel_rgb(col, row + 1, img.get_pixel(col, row + 1), config)
+        } else {
+            None
+        };
+        match (top, bottom) {
+            (Some(top), Some(bottom)) => Cell {
+                ch: '▄',
+                fg: Some(bottom),
+                bg: Some(top),
+            },
+            (None, Some(bottom)) => Cell {
+                ch: '▄',
+                fg: Some(bottom),
+                bg: None,
+            },
+            (top, None) => Cell {
+                ch: '▀',
+                fg: top,
+                bg: None,
+            },
+        }
+    });
+}
+
+fn write_colored_character(stdout: &mut impl Backend, c: &Colors, is_last_row: bool) -> ViuResult {
     let out_color;
     let out_char;
-    let mut new_color;
 
     // On the last row use upper blocks and leave the bottom half empty (transparent)
     if is_last_row {
-        new_color = ColorSpec::new();
-        if let Some(bg) = c.bg() {
-            new_color.set_fg(Some(*bg));
+        if let Some(bg) = c.background {
+            out_color = foreground(bg);
             out_char = UPPER_HALF_BLOCK;
         } else {
-            execute!(stdout, MoveRight(1))?;
+            stdout.move_right(1)?;
             return Ok(());
         }
-        out_color = &new_color;
     } else {
-        match (c.fg(), c.bg()) {
+        match (c.foreground, c.background) {
             (None, None) => {
                 // completely transparent
-                execute!(stdout, MoveRight(1))?;
+                stdout.move_right(1)?;
                 return Ok(());
             }
             (Some(bottom), None) => {
                 // only top transparent
-                new_color = ColorSpec::new();
-                new_color.set_fg(Some(*bottom));
-                out_color = &new_color;
+                out_color = foreground(bottom);
                 out_char = LOWER_HALF_BLOCK;
             }
             (None, Some(top)) => {
                 // only bottom transparent
-                new_color = ColorSpec::new();
-                new_color.set_fg(Some(*top));
-                out_color = &new_color;
+                out_color = foreground(top);
                 out_char = UPPER_HALF_BLOCK;
             }
             (Some(_top), Some(_bottom)) => {
                 // both parts have a color
-                out_color = c;
+                out_color = *c;
                 out_char = LOWER_HALF_BLOCK;
             }
         }
     }
-    stdout.set_color(out_color)?;
+    stdout.set_colors(out_color)?;
     write!(stdout, "{}", out_char)?;
 
     Ok(())
 }
 
+fn foreground(color: Color) -> Colors {
+    Colors {
+        foreground: Some(color),
+        background: None,
+    }
+}
+
 fn is_pixel_transparent(pixel: (u32, u32, &Rgba<u8>)) -> bool {
     pixel.2[3] == 0
 }
 
-fn get_transparency_color(row: u32, col: u32, truecolor: bool) -> Color {
-    //imitate the transparent chess board pattern
-    let rgb = if row % 2 == col % 2 {
-        CHECKERBOARD_BACKGROUND_DARK
-    } else {
-        CHECKERBOARD_BACKGROUND_LIGHT
-    };
-    if truecolor {
-        Color::Rgb(rgb.0, rgb.1, rgb.2)
-    } else {
-        Color::Ansi256(ansi256_from_rgb(rgb))
-    }
+fn get_transparency_color(row: u32, col: u32, config: &Config) -> Color {
+    rgb_to_color(
+        checkerboard_rgb(row, col, &config.checkerboard),
+        config.depth(),
+    )
 }
 
-fn get_color_from_pixel(pixel: (u32, u32, &Rgba<u8>), truecolor: bool) -> Color {
+fn get_color_from_pixel(pixel: (u32, u32, &Rgba<u8>), depth: ColorDepth) -> Color {
     let (_x, _y, data) = pixel;
-    let rgb = (data[0], data[1], data[2]);
-    if truecolor {
-        Color::Rgb(rgb.0, rgb.1, rgb.2)
-    } else {
-        Color::Ansi256(ansi256_from_rgb(rgb))
-    }
+    rgb_to_color((data[0], data[1], data[2]), depth)
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
-    use termcolor::{Ansi, Color};
 
     // Note: truecolor is not supported in CI. Hence, it should be disabled when writing the tests
 
     #[test]
     fn test_block_printer_e2e() {
         let img = DynamicImage::ImageRgba8(image::RgbaImage::new(5, 4));
-        let mut buf = Ansi::new(vec![]);
+        let mut buf = CrosstermBackend::new(vec![]);
 
         let config = Config {
-            truecolor: false,
+            color_depth: ColorDepth::Ansi256,
             ..Default::default()
         };
 
-        let (w, h) = print_to_writecolor(&mut buf, &img, &config).unwrap();
+        let (w, h) = print_to_backend(&mut buf, &img, &config).unwrap();
         assert_eq!((w, h), (5, 2));
 
         assert_eq!(
@@ -205,14 +250,14 @@ mod tests {
     #[test]
     fn test_block_printer_e2e_transparent() {
         let img = DynamicImage::ImageRgba8(image::RgbaImage::new(5, 4));
-        let mut buf = Ansi::new(vec![]);
+        let mut buf = CrosstermBackend::new(vec![]);
 
         let config = Config {
             transparent: true,
             ..Default::default()
         };
 
-        let (w, h) = print_to_writecolor(&mut buf, &img, &config).unwrap();
+        let (w, h) = print_to_backend(&mut buf, &img, &config).unwrap();
         assert_eq!((w, h), (5, 2));
 
         assert_eq!(
@@ -224,14 +269,14 @@ mod tests {
     #[test]
     fn test_block_printer_e2e_odd_height() {
         let img = DynamicImage::ImageRgba8(image::RgbaImage::new(4, 3));
-        let mut buf = Ansi::new(vec![]);
+        let mut buf = CrosstermBackend::new(vec![]);
 
         let config = Config {
-            truecolor: false,
+            color_depth: ColorDepth::Ansi256,
             absolute_offset: false,
             ..Default::default()
         };
-        let (w, h) = print_to_writecolor(&mut buf, &img, &config).unwrap();
+        let (w, h) = print_to_backend(&mut buf, &img, &config).unwrap();
         assert_eq!((w, h), (4, 2));
 
         assert_eq!(
@@ -240,12 +285,44 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_render_matches_printer() {
+        let mut img = image::RgbaImage::new(3, 5);
+        img.put_pixel(1, 2, Rgba([10, 20, 30, 255]));
+        img.put_pixel(2, 4, Rgba([40, 50, 60, 255]));
+        let img = DynamicImage::ImageRgba8(img);
+
+        for transparent in [false, true] {
+            let config = Config {
+                color_depth: ColorDepth::Ansi256,
+                absolute_offset: false,
+                transparent,
+                ..Default::default()
+            };
+
+            let mut printed = CrosstermBackend::new(vec![]);
+            print_to_backend(&mut printed, &img, &config).unwrap();
+            let mut rendered = CrosstermBackend::new(vec![]);
+            crate::printer::cell::write_cells(
+                &mut rendered,
+                &crate::printer::render_cells(&img, &config),
+                &config,
+            )
+            .unwrap();
+            assert_eq!(printed.get_ref(), rendered.get_ref());
+        }
+    }
+
     #[test]
     fn test_write_colored_char_only_fg() {
-        let mut buf = Ansi::new(vec![]);
-        let mut c = ColorSpec::new();
+        let mut buf = CrosstermBackend::new(vec![]);
+        let mut c = NO_COLORS;
 
-        c.set_fg(Some(Color::Rgb(10, 20, 30)));
+        c.foreground = Some(Color::Rgb {
+            r: 10,
+            g: 20,
+            b: 30,
+        });
 
         write_colored_character(&mut buf, &c, false).unwrap();
         assert_eq!(
@@ -256,10 +333,14 @@ mod tests {
 
     #[test]
     fn test_write_colored_char_only_bg() {
-        let mut buf = Ansi::new(vec![]);
-        let mut c = ColorSpec::new();
+        let mut buf = CrosstermBackend::new(vec![]);
+        let mut c = NO_COLORS;
 
-        c.set_bg(Some(Color::Rgb(50, 60, 70)));
+        c.background = Some(Color::Rgb {
+            r: 50,
+            g: 60,
+            b: 70,
+        });
 
         write_colored_character(&mut buf, &c, false).unwrap();
         assert_eq!(
@@ -270,11 +351,19 @@ mod tests {
 
     #[test]
     fn test_write_colored_char_fg_and_bg() {
-        let mut buf = Ansi::new(vec![]);
-        let mut c = ColorSpec::new();
-
-        c.set_fg(Some(Color::Rgb(10, 20, 30)));
-        c.set_bg(Some(Color::Rgb(15, 25, 35)));
+        let mut buf = CrosstermBackend::new(vec![]);
+        let mut c = NO_COLORS;
+
+        c.foreground = Some(Color::Rgb {
+            r: 10,
+            g: 20,
+            b: 30,
+        });
+        c.background = Some(Color::Rgb {
+            r: 15,
+            g: 25,
+            b: 35,
+        });
 
         write_colored_character(&mut buf, &c, false).unwrap();
         assert_eq!(
@@ -285,8 +374,8 @@ mod tests {
 
     #[test]
     fn test_write_colored_char_no_color() {
-        let mut buf = Ansi::new(vec![]);
-        let c = ColorSpec::new();
+        let mut buf = CrosstermBackend::new(vec![]);
+        let c = NO_COLORS;
 
         write_colored_character(&mut buf, &c, false).unwrap();
         // expect to print nothing, just move cursor to the right
@@ -295,10 +384,14 @@ mod tests {
 
     #[test]
     fn test_write_colored_char_last_row_bg() {
-        let mut buf = Ansi::new(vec![]);
-        let mut c = ColorSpec::new();
+        let mut buf = CrosstermBackend::new(vec![]);
+        let mut c = NO_COLORS;
 
-        c.set_bg(Some(Color::Rgb(10, 20, 30)));
+        c.background = Some(Color::Rgb {
+            r: 10,
+            g: 20,
+            b: 30,
+        });
 
         write_colored_character(&mut buf, &c, true).unwrap();
         assert_eq!(
@@ -309,17 +402,21 @@ mod tests {
 
     #[test]
     fn test_write_colored_char_last_row_no_bg() {
-        let mut buf = Ansi::new(vec![]);
-        let mut c = ColorSpec::new();
+        let mut buf = CrosstermBackend::new(vec![]);
+        let mut c = NO_COLORS;
 
         // test with no color
         write_colored_character(&mut buf, &c, true).unwrap();
         assert_eq!(std::str::from_utf8(buf.get_ref()).unwrap(), "\x1b[1C");
 
-        c.set_fg(Some(Color::Rgb(10, 20, 30)));
+        c.foreground = Some(Color::Rgb {
+            r: 10,
+            g: 20,
+            b: 30,
+        });
 
         // test with fg (unusual case)
-        let mut buf = Ansi::new(vec![]);
+        let mut buf = CrosstermBackend::new(vec![]);
         write_colored_character(&mut buf, &c, true).unwrap();
         assert_eq!(std::str::from_utf8(buf.get_ref()).unwrap(), "\x1b[1C");
     }
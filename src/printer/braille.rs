@@ -0,0 +1,133 @@
+use crate::error::ViuResult;
+use crate::printer::backend::{Backend, CrosstermBackend};
+use crate::printer::cell::{average, block_pixels, fill_cells, luminance, write_cells, Cell, Rgb};
+use crate::printer::dither::dither;
+use crate::printer::{fit_to_config, resize_to, Printer};
+use crate::Config;
+
+use image::DynamicImage;
+use std::io::{BufWriter, Write};
+
+pub(crate) const BRAILLE_BLANK: u32 = 0x2800;
+
+// Braille dot bits for each pixel of a 2x4 block, in row-major order
+pub(crate) const DOTS: [u32; 8] = [0x01, 0x08, 0x02, 0x10, 0x04, 0x20, 0x40, 0x80];
+
+/// Prints images using braille characters, with each cell holding 2x4 pixels.
+/// Pixels brighter than the image's average are drawn as dots, colored with
+/// the average color of the dots in the cell.
+pub struct BraillePrinter;
+
+impl Printer for BraillePrinter {
+    fn print(
+        &self,
+        stdout: &mut impl Write,
+        img: &DynamicImage,
+        config: &Config,
+    ) -> ViuResult<(u32, u32)> {
+        let mut stream = CrosstermBackend::new(BufWriter::new(stdout));
+        print_to_backend(&mut stream, img, config)
+    }
+}
+
+fn print_to_backend(
+    stdout: &mut impl Backend,
+    img: &DynamicImage,
+    config: &Config,
+) -> ViuResult<(u32, u32)> {
+    let mut cells = Vec::new();
+    render_into(img, config, &mut cells);
+    write_cells(stdout, &cells, config)
+}
+
+// Resize the image to fit the config and convert it to rows of cells, reusing the
+// allocations of `cells`
+pub fn render_into(img: &DynamicImage, config: &Config, cells: &mut Vec<Vec<Cell>>) {
+    let (w, h) = fit_to_config(img.width(), img.height(), config);
+    let mut img = resize_to(img, 2 * w, 4 * h, config).into_rgba8();
+    dither(&mut img, config);
+
+    // Transparent pixels are never drawn, regardless of config.transparent
+    let opaque: Vec<u32> = img
+        .pixels()
+        .filter(|p| p[3] != 0)
+        .map(|p| luminance((p[0], p[1], p[2])) as u32)
+        .collect();
+    let threshold = opaque.iter().sum::<u32>() / opaque.len().max(1) as u32;
+    let config = Config {
+        transparent: true,
+        ..config.clone()
+    };
+
+    fill_cells(cells, w, h, |row, col| {
+        let pixels = block_pixels(&img, 2 * col, 4 * row, 2, 4, &config);
+        braille_cell(&pixels, threshold as u8)
+    });
+}
+
+fn braille_cell(pixels: &[Option<Rgb>], threshold: u8) -> Cell {
+    let mut dots = 0;
+    let mut lit = Vec::new();
+    for (i, rgb) in pixels.iter().enumerate() {
+        if let Some(rgb) = rgb {
+            if luminance(*rgb) >= threshold {
+                dots |= DOTS[i];
+                lit.push(*rgb);
+            }
+        }
+    }
+
+    Cell {
+        ch: char::from_u32(BRAILLE_BLANK + dots).unwrap_or(' '),
+        fg: if lit.is_empty() {
+            None
+        } else {
+            Some(average(&lit))
+        },
+        bg: None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ColorDepth;
+
+    #[test]
+    fn test_braille_cell() {
+        let white = Some((255, 255, 255));
+        let black = Some((0, 0, 0));
+        let pixels = [white, black, black, white, None, None, white, white];
+        let cell = braille_cell(&pixels, 128);
+        assert_eq!(cell.ch, '⣑');
+        assert_eq!(cell.fg, Some((255, 255, 255)));
+        assert_eq!(cell.bg, None);
+
+        let cell = braille_cell(&[black; 8], 128);
+        assert_eq!(cell.ch, '⠀');
+        assert_eq!(cell.fg, None);
+    }
+
+    #[test]
+    fn test_braille_printer() {
+        let img = DynamicImage::ImageRgba8(image::RgbaImage::from_pixel(
+            2,
+            4,
+            image::Rgba([10, 20, 30, 255]),
+        ));
+        let config = Config {
+            absolute_offset: false,
+            color_depth: ColorDepth::TrueColor,
+            width: Some(1),
+            height: Some(1),
+            ..Default::default()
+        };
+        let mut buf = CrosstermBackend::new(vec![]);
+
+        assert_eq!(print_to_backend(&mut buf, &img, &config).unwrap(), (1, 1));
+        assert_eq!(
+            std::str::from_utf8(buf.get_ref()).unwrap(),
+            "\x1b[0m\x1b[38;2;10;20;30m⣿\x1b[0m\n"
+        );
+    }
+}
@@ -0,0 +1,535 @@
+// Helpers shared by the printers which draw several pixels per terminal cell,
+// using a character pattern with a foreground and a background color.
+use crate::error::ViuResult;
+use crate::printer::adjust_offset;
+use crate::printer::backend::Backend;
+use crate::{Checkerboard, ColorDepth, Config};
+
+use ansi_colours::ansi256_from_rgb;
+use crossterm::style::{Color, Colors};
+use image::{Rgba, RgbaImage};
+
+pub type Rgb = (u8, u8, u8);
+
+/// A single terminal cell: the character to print and its colors.
+/// A cell without any colors is skipped, leaving the terminal background visible.
+#[derive(Clone, PartialEq, Eq)]
+pub struct Cell {
+    pub ch: char,
+    pub fg: Option<Rgb>,
+    pub bg: Option<Rgb>,
+}
+
+// Fill `cells` with `height` rows of `width` cells, given by row and column, keeping the
+// allocated rows
+pub fn fill_cells(
+    cells: &mut Vec<Vec<Cell>>,
+    width: u32,
+    height: u32,
+    mut cell: impl FnMut(u32, u32) -> Cell,
+) {
+    cells.resize_with(height as usize, Vec::new);
+    for (row, cells) in (0..).zip(cells.iter_mut()) {
+        cells.clear();
+        cells.extend((0..width).map(|col| cell(row, col)));
+    }
+}
+
+// Imitate the transparent chess board pattern
+pub fn checkerboard_rgb(row: u32, col: u32, checkerboard: &Checkerboard) -> Rgb {
+    let size = checkerboard.size.max(1);
+    let color = if (row / size) % 2 == (col / size) % 2 {
+        checkerboard.dark
+    } else {
+        checkerboard.light
+    };
+    (color[0], color[1], color[2])
+}
+
+// Default xterm values of the basic 16 colors
+const ANSI16_PALETTE: [Rgb; 16] = [
+    (0, 0, 0),
+    (205, 0, 0),
+    (0, 205, 0),
+    (205, 205, 0),
+    (0, 0, 238),
+    (205, 0, 205),
+    (0, 205, 205),
+    (229, 229, 229),
+    (127, 127, 127),
+    (255, 0, 0),
+    (0, 255, 0),
+    (255, 255, 0),
+    (92, 92, 255),
+    (255, 0, 255),
+    (0, 255, 255),
+    (255, 255, 255),
+];
+
+pub fn rgb_to_color(rgb: Rgb, depth: ColorDepth) -> Color {
+    match depth {
+        ColorDepth::TrueColor => Color::Rgb {
+            r: rgb.0,
+            g: rgb.1,
+            b: rgb.2,
+        },
+        ColorDepth::Ansi256 => Color::AnsiValue(ansi256_from_rgb(rgb)),
+        ColorDepth::Ansi16 => ansi16_color(ansi16_from_rgb(rgb)),
+    }
+}
+
+// Find the closest of the basic 16 colors
+pub fn ansi16_from_rgb(rgb: Rgb) -> u8 {
+    (0..16)
+        .min_by_key(|&i| distance(rgb, ANSI16_PALETTE[i]))
+        .unwrap_or(0) as u8
+}
+
+// The closest of the basic 16 colors, as RGB
+pub fn ansi16_rgb(rgb: Rgb) -> Rgb {
+    ANSI16_PALETTE[ansi16_from_rgb(rgb) as usize]
+}
+
+// The named colors are mapped to the console's own palette on Windows consoles which do not
+// process escape sequences
+fn ansi16_color(index: u8) -> Color {
+    match index {
+        0 => Color::Black,
+        1 => Color::DarkRed,
+        2 => Color::DarkGreen,
+        3 => Color::DarkYellow,
+        4 => Color::DarkBlue,
+        5 => Color::DarkMagenta,
+        6 => Color::DarkCyan,
+        7 => Color::Grey,
+        8 => Color::DarkGrey,
+        9 => Color::Red,
+        10 => Color::Green,
+        11 => Color::Yellow,
+        12 => Color::Blue,
+        13 => Color::Magenta,
+        14 => Color::Cyan,
+        _ => Color::White,
+    }
+}
+
+// The color of a pixel, or None if it is transparent and should not be drawn
+pub fn pixel_rgb(x: u32, y: u32, pixel: &Rgba<u8>, config: &Config) -> Option<Rgb> {
+    if pixel[3] == 0 {
+        if config.transparent {
+            None
+        } else {
+            Some(checkerboard_rgb(y, x, &config.checkerboard))
+        }
+    } else {
+        Some((pixel[0], pixel[1], pixel[2]))
+    }
+}
+
+// Collect the colors of a `w`x`h` block of pixels starting at (x, y), row by row.
+// Pixels outside the image are treated as transparent.
+pub fn block_pixels(
+    img: &RgbaImage,
+    x: u32,
+    y: u32,
+    w: u32,
+    h: u32,
+    config: &Config,
+) -> Vec<Option<Rgb>> {
+    let mut pixels = Vec::with_capacity((w * h) as usize);
+    for dy in 0..h {
+        for dx in 0..w {
+            let (px, py) = (x + dx, y + dy);
+            if px < img.width() && py < img.height() {
+                pixels.push(pixel_rgb(px, py, img.get_pixel(px, py), config));
+            } else {
+                pixels.push(None);
+            }
+        }
+    }
+    pixels
+}
+
+/// Split a block of pixels into two color groups. Returns a bit mask of the pixels which
+/// belong to the foreground group (bit `i` for pixel `i`), and the averaged foreground and
+/// background colors. If any of the pixels are transparent, the background is left empty.
+pub fn split_colors(pixels: &[Option<Rgb>]) -> (u32, Option<Rgb>, Option<Rgb>) {
+    let present: Vec<(usize, Rgb)> = pixels
+        .iter()
+        .enumerate()
+        .filter_map(|(i, p)| p.map(|rgb| (i, rgb)))
+        .collect();
+
+    if present.is_empty() {
+        return (0, None, None);
+    }
+
+    if present.len() < pixels.len() {
+        let mask = present.iter().fold(0, |mask, (i, _)| mask | 1 << i);
+        let colors: Vec<Rgb> = present.iter().map(|(_, rgb)| *rgb).collect();
+        return (mask, Some(average(&colors)), None);
+    }
+
+    // Use the two most distant colors as the centers of the groups
+    let mut centers = (present[0].1, present[0].1);
+    let mut max_distance = 0;
+    for (i, (_, a)) in present.iter().enumerate() {
+        for (_, b) in &present[i + 1..] {
+            let d = distance(*a, *b);
+            if d > max_distance {
+                max_distance = d;
+                centers = (*a, *b);
+            }
+        }
+    }
+
+    let mut mask = 0;
+    let mut fg = Vec::new();
+    let mut bg = Vec::new();
+    for (i, rgb) in present {
+        if distance(rgb, centers.0) <= distance(rgb, centers.1) {
+            mask |= 1 << i;
+            fg.push(rgb);
+        } else {
+            bg.push(rgb);
+        }
+    }
+
+    let bg = if bg.is_empty() {
+        None
+    } else {
+        Some(average(&bg))
+    };
+    (mask, Some(average(&fg)), bg)
+}
+
+// Perceived brightness of a color (ITU-R BT.601)
+pub fn luminance(rgb: Rgb) -> u8 {
+    ((rgb.0 as u32 * 299 + rgb.1 as u32 * 587 + rgb.2 as u32 * 114) / 1000) as u8
+}
+
+pub fn average(colors: &[Rgb]) -> Rgb {
+    let n = colors.len().max(1) as u32;
+    let sum = colors.iter().fold((0, 0, 0), |acc, c| {
+        (acc.0 + c.0 as u32, acc.1 + c.1 as u32, acc.2 + c.2 as u32)
+    });
+    ((sum.0 / n) as u8, (sum.1 / n) as u8, (sum.2 / n) as u8)
+}
+
+fn distance(a: Rgb, b: Rgb) -> u32 {
+    let dr = a.0 as i32 - b.0 as i32;
+    let dg = a.1 as i32 - b.1 as i32;
+    let db = a.2 as i32 - b.2 as i32;
+    (dr * dr + dg * dg + db * db) as u32
+}
+
+/// Write rows of cells, starting from the offset in the config.
+/// Returns the dimensions of the printed image in terminal cells.
+pub fn write_cells(
+    stdout: &mut impl Backend,
+    rows: &[Vec<Cell>],
+    config: &Config,
+) -> ViuResult<(u32, u32)> {
+    // adjust with x=0 and handle horizontal offset entirely below
+    adjust_offset(
+        stdout,
+        &Config {
+            x: 0,
+            ..config.clone()
+        },
+    )?;
+
+    for (i, row) in rows.iter().enumerate() {
+        // move right if x offset is specified
+        if config.x > 0 {
+            stdout.move_right(config.x)?;
+        }
+
+        for cell in row {
+            write_cell(stdout, cell, config.depth())?;
+        }
+
+        stdout.reset_colors()?;
+        if i + 1 < rows.len() {
+            writeln!(stdout, "\r")?;
+        }
+    }
+
+    writeln!(stdout)?;
+    stdout.flush()?;
+
+    let width = rows.first().map_or(0, |row| row.len()) as u32;
+    Ok((width, rows.len() as u32))
+}
+
+/// Write only the cells which differ from `previous`, the cells of the image already on screen.
+/// Both must have the same dimensions, and the cursor must be on the line below the image,
+/// as left by [write_cells]. The cursor is left there again afterwards.
+pub fn write_cells_diff(
+    stdout: &mut impl Backend,
+    rows: &[Vec<Cell>],
+    previous: &[Vec<Cell>],
+    config: &Config,
+) -> ViuResult<(u32, u32)> {
+    let height = crate::utils::cells(rows.len() as u32)?;
+
+    // Go to the top left corner of the image
+    if config.absolute_offset {
+        stdout.move_to(config.x, config.y.max(0) as u16)?;
+    } else {
+        stdout.move_to_previous_line(height)?;
+        if config.x > 0 {
+            stdout.move_right(config.x)?;
+        }
+    }
+
+    // Track the cursor position relative to the top left corner
+    let (mut cursor_row, mut cursor_col) = (0, 0);
+    for (i, (row, previous_row)) in rows.iter().zip(previous).enumerate() {
+        for (j, (cell, previous_cell)) in row.iter().zip(previous_row).enumerate() {
+            if cell == previous_cell {
+                continue;
+            }
+
+            let (i, j) = (i as u16, j as u16);
+            if i > cursor_row {
+                stdout.move_to_next_line(i - cursor_row)?;
+                cursor_col = 0;
+                if config.x > 0 {
+                    stdout.move_right(config.x)?;
+                }
+            }
+            if j > cursor_col {
+                stdout.move_right(j - cursor_col)?;
+            } else if j < cursor_col {
+                stdout.move_left(cursor_col - j)?;
+            }
+
+            if cell.fg.is_none() && cell.bg.is_none() {
+                // The old content has to be erased
+                stdout.reset_colors()?;
+                write!(stdout, " ")?;
+            } else {
+                write_cell(stdout, cell, config.depth())?;
+            }
+            cursor_row = i;
+            cursor_col = j + 1;
+        }
+    }
+
+    stdout.reset_colors()?;
+    stdout.move_to_next_line(height - cursor_row)?;
+    stdout.flush()?;
+
+    let width = rows.first().map_or(0, |row| row.len()) as u32;
+    Ok((width, rows.len() as u32))
+}
+
+/// Write the cells within `region`, given as (column, row, width, height) from the top left
+/// corner of the image. The cursor must be on the first line of the image, which starts at
+/// column `x`. Where the cursor is left afterwards is unspecified.
+pub fn write_cells_region(
+    stdout: &mut impl Backend,
+    rows: &[Vec<Cell>],
+    (column, row, width, height): (u32, u32, u32, u32),
+    x: u16,
+    depth: ColorDepth,
+) -> ViuResult {
+    let left = crate::utils::cells(x as u32 + column)?;
+    let mut line = 0;
+    for (i, cells) in rows
+        .iter()
+        .enumerate()
+        .skip(row as usize)
+        .take(height as usize)
+    {
+        if i > line {
+            stdout.move_to_next_line(crate::utils::cells((i - line) as u32)?)?;
+            line = i;
+        } else {
+            write!(stdout, "\r")?;
+        }
+        if left > 0 {
+            stdout.move_right(left)?;
+        }
+
+        for cell in cells.iter().skip(column as usize).take(width as usize) {
+            if cell.fg.is_none() && cell.bg.is_none() {
+                // The old content has to be erased
+                stdout.reset_colors()?;
+                write!(stdout, " ")?;
+            } else {
+                write_cell(stdout, cell, depth)?;
+            }
+        }
+        stdout.reset_colors()?;
+    }
+    stdout.flush()?;
+    Ok(())
+}
+
+fn write_cell(stdout: &mut impl Backend, cell: &Cell, depth: ColorDepth) -> ViuResult {
+    if cell.fg.is_none() && cell.bg.is_none() {
+        stdout.move_right(1)?;
+        return Ok(());
+    }
+
+    stdout.set_colors(Colors {
+        foreground: cell.fg.map(|rgb| rgb_to_color(rgb, depth)),
+        background: cell.bg.map(|rgb| rgb_to_color(rgb, depth)),
+    })?;
+    write!(stdout, "{}", cell.ch)?;
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::printer::backend::CrosstermBackend;
+
+    #[test]
+    fn test_split_colors_two_groups() {
+        let pixels = [
+            Some((250, 250, 250)),
+            Some((0, 0, 10)),
+            Some((240, 250, 250)),
+            Some((10, 0, 0)),
+        ];
+        let (mask, fg, bg) = split_colors(&pixels);
+        assert_eq!(mask, 0b0101);
+        assert_eq!(fg, Some((245, 250, 250)));
+        assert_eq!(bg, Some((5, 0, 5)));
+    }
+
+    #[test]
+    fn test_split_colors_transparent() {
+        let pixels = [None, Some((10, 20, 30)), None, Some((30, 40, 50))];
+        assert_eq!(split_colors(&pixels), (0b1010, Some((20, 30, 40)), None));
+        assert_eq!(split_colors(&[None, None]), (0, None, None));
+    }
+
+    #[test]
+    fn test_checkerboard_rgb() {
+        let checkerboard = Checkerboard {
+            dark: image::Rgb([0, 0, 0]),
+            light: image::Rgb([255, 255, 255]),
+            size: 2,
+        };
+        assert_eq!(checkerboard_rgb(0, 0, &checkerboard), (0, 0, 0));
+        assert_eq!(checkerboard_rgb(1, 1, &checkerboard), (0, 0, 0));
+        assert_eq!(checkerboard_rgb(1, 2, &checkerboard), (255, 255, 255));
+        assert_eq!(checkerboard_rgb(2, 2, &checkerboard), (0, 0, 0));
+    }
+
+    #[test]
+    fn test_rgb_to_ansi16() {
+        assert_eq!(rgb_to_color((10, 0, 5), ColorDepth::Ansi16), Color::Black);
+        assert_eq!(
+            rgb_to_color((190, 20, 10), ColorDepth::Ansi16),
+            Color::DarkRed
+        );
+        assert_eq!(
+            rgb_to_color((250, 250, 240), ColorDepth::Ansi16),
+            Color::White
+        );
+    }
+
+    #[test]
+    fn test_write_cells_diff() {
+        let cell = |rgb| Cell {
+            ch: '█',
+            fg: Some(rgb),
+            bg: None,
+        };
+        let previous = vec![
+            vec![cell((1, 1, 1)), cell((1, 1, 1))],
+            vec![cell((1, 1, 1)), cell((1, 1, 1))],
+        ];
+        let rows = vec![
+            vec![cell((1, 1, 1)), cell((1, 1, 1))],
+            vec![cell((1, 1, 1)), cell((2, 2, 2))],
+        ];
+        let config = Config {
+            absolute_offset: false,
+            color_depth: ColorDepth::TrueColor,
+            ..Default::default()
+        };
+
+        let mut buf = CrosstermBackend::new(vec![]);
+        assert_eq!(
+            write_cells_diff(&mut buf, &rows, &previous, &config).unwrap(),
+            (2, 2)
+        );
+        assert_eq!(
+            std::str::from_utf8(buf.get_ref()).unwrap(),
+            "\x1b[2F\x1b[1E\x1b[1C\x1b[0m\x1b[38;2;2;2;2m█\x1b[0m\x1b[1E"
+        );
+
+        let mut buf = CrosstermBackend::new(vec![]);
+        write_cells_diff(&mut buf, &rows, &rows, &config).unwrap();
+        assert_eq!(
+            std::str::from_utf8(buf.get_ref()).unwrap(),
+            "\x1b[2F\x1b[0m\x1b[2E"
+        );
+    }
+
+    #[test]
+    fn test_write_cells() {
+        let rows = vec![
+            vec![
+                Cell {
+                    ch: '▘',
+                    fg: Some((1, 2, 3)),
+                    bg: Some((4, 5, 6)),
+                },
+                Cell {
+                    ch: ' ',
+                    fg: None,
+                    bg: None,
+                },
+            ],
+            vec![
+                Cell {
+                    ch: '█',
+                    fg: Some((7, 8, 9)),
+                    bg: None,
+                },
+                Cell {
+                    ch: ' ',
+                    fg: None,
+                    bg: None,
+                },
+            ],
+        ];
+        let config = Config {
+            absolute_offset: false,
+            color_depth: ColorDepth::TrueColor,
+            ..Default::default()
+        };
+        let mut buf = CrosstermBackend::new(vec![]);
+        assert_eq!(write_cells(&mut buf, &rows, &config).unwrap(), (2, 2));
+        assert_eq!(
+            std::str::from_utf8(buf.get_ref()).unwrap(),
+            "\x1b[0m\x1b[38;2;1;2;3m\x1b[48;2;4;5;6m▘\x1b[1C\x1b[0m\r\n\x1b[0m\x1b[38;2;7;8;9m█\x1b[1C\x1b[0m\n"
+        );
+    }
+
+    #[test]
+    fn test_fill_cells() {
+        let cell = |ch| Cell {
+            ch,
+            fg: None,
+            bg: None,
+        };
+        let mut cells = vec![vec![cell('a'); 3]; 3];
+        fill_cells(&mut cells, 2, 2, |row, col| {
+            cell(if row == col { 'x' } else { 'o' })
+        });
+        let chars: Vec<String> = cells
+            .iter()
+            .map(|row| row.iter().map(|c| c.ch).collect())
+            .collect();
+        assert_eq!(chars, ["xo", "ox"]);
+    }
+}
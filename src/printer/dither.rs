@@ -0,0 +1,179 @@
+// Dithering of the resized image to the palette of the configured color depth, so that
+// gradients do not turn into bands once the colors are quantized by the block printers.
+use crate::printer::cell::{ansi16_rgb, Rgb};
+use crate::{ColorDepth, Config, Dither};
+
+use ansi_colours::{ansi256_from_rgb, rgb_from_ansi256};
+use image::RgbaImage;
+
+// 4x4 Bayer matrix, used for ordered dithering
+const BAYER: [[u8; 4]; 4] = [[0, 8, 2, 10], [12, 4, 14, 6], [3, 11, 1, 9], [15, 7, 13, 5]];
+
+// Error diffusion kernels: offsets from the current pixel and the share of the error they get
+const FLOYD_STEINBERG: [(i32, i32, f32); 4] = [
+    (1, 0, 7.0 / 16.0),
+    (-1, 1, 3.0 / 16.0),
+    (0, 1, 5.0 / 16.0),
+    (1, 1, 1.0 / 16.0),
+];
+// Atkinson only spreads 3/4 of the error, which keeps more contrast
+const ATKINSON: [(i32, i32, f32); 6] = [
+    (1, 0, 1.0 / 8.0),
+    (2, 0, 1.0 / 8.0),
+    (-1, 1, 1.0 / 8.0),
+    (0, 1, 1.0 / 8.0),
+    (1, 1, 1.0 / 8.0),
+    (0, 2, 1.0 / 8.0),
+];
+
+// Dither the image in place with the algorithm from the config. Does nothing with true colors.
+// Transparent pixels are left untouched and do not take part in the error diffusion.
+pub fn dither(img: &mut RgbaImage, config: &Config) {
+    let depth = config.depth();
+    if depth == ColorDepth::TrueColor {
+        return;
+    }
+
+    match config.dither {
+        Dither::None => {}
+        Dither::Ordered => ordered(img, depth),
+        Dither::FloydSteinberg => diffuse(img, depth, &FLOYD_STEINBERG),
+        Dither::Atkinson => diffuse(img, depth, &ATKINSON),
+    }
+}
+
+// The closest color available with the given depth
+pub fn quantize(rgb: Rgb, depth: ColorDepth) -> Rgb {
+    match depth {
+        ColorDepth::TrueColor => rgb,
+        ColorDepth::Ansi256 => rgb_from_ansi256(ansi256_from_rgb(rgb)),
+        ColorDepth::Ansi16 => ansi16_rgb(rgb),
+    }
+}
+
+// Ordered dithering to the palette of the depth, also used for Sixel where libsixel has no
+// ordered dithering of its own
+pub fn ordered(img: &mut RgbaImage, depth: ColorDepth) {
+    // Roughly the distance between neighbouring palette colors
+    let spread = match depth {
+        ColorDepth::Ansi16 => 128.0,
+        _ => 48.0,
+    };
+
+    for (x, y, pixel) in img.enumerate_pixels_mut() {
+        if pixel[3] == 0 {
+            continue;
+        }
+        let threshold = BAYER[y as usize % 4][x as usize % 4] as f32 / 16.0 - 0.5;
+        let offset = threshold * spread;
+        let shifted = |c: u8| (c as f32 + offset).clamp(0.0, 255.0).round() as u8;
+        let (r, g, b) = quantize(
+            (shifted(pixel[0]), shifted(pixel[1]), shifted(pixel[2])),
+            depth,
+        );
+        pixel[0] = r;
+        pixel[1] = g;
+        pixel[2] = b;
+    }
+}
+
+fn diffuse(img: &mut RgbaImage, depth: ColorDepth, kernel: &[(i32, i32, f32)]) {
+    let (width, height) = img.dimensions();
+    let mut errors = vec![[0.0f32; 3]; (width * height) as usize];
+
+    for y in 0..height {
+        for x in 0..width {
+            let pixel = img.get_pixel_mut(x, y);
+            if pixel[3] == 0 {
+                continue;
+            }
+
+            let error = errors[(y * width + x) as usize];
+            let value = [0, 1, 2].map(|c| (pixel[c] as f32 + error[c]).clamp(0.0, 255.0));
+            let (r, g, b) = quantize(
+                (
+                    value[0].round() as u8,
+                    value[1].round() as u8,
+                    value[2].round() as u8,
+                ),
+                depth,
+            );
+            pixel[0] = r;
+            pixel[1] = g;
+            pixel[2] = b;
+
+            let error = [
+                value[0] - r as f32,
+                value[1] - g as f32,
+                value[2] - b as f32,
+            ];
+            for &(dx, dy, share) in kernel {
+                let (nx, ny) = (x as i32 + dx, y as i32 + dy);
+                if nx < 0 || nx >= width as i32 || ny >= height as i32 {
+                    continue;
+                }
+                let target = &mut errors[(ny as u32 * width + nx as u32) as usize];
+                for c in 0..3 {
+                    target[c] += error[c] * share;
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use image::Rgba;
+
+    fn gray_image() -> RgbaImage {
+        RgbaImage::from_pixel(8, 8, Rgba([100, 100, 100, 255]))
+    }
+
+    fn config(dither: Dither) -> Config {
+        Config {
+            color_depth: ColorDepth::Ansi16,
+            dither,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_no_dither() {
+        let mut img = gray_image();
+        dither(&mut img, &config(Dither::None));
+        assert_eq!(img, gray_image());
+
+        dither(
+            &mut img,
+            &Config {
+                color_depth: ColorDepth::TrueColor,
+                dither: Dither::FloydSteinberg,
+                ..Default::default()
+            },
+        );
+        assert_eq!(img, gray_image());
+    }
+
+    #[test]
+    fn test_dither_mixes_palette_colors() {
+        for algorithm in [Dither::Ordered, Dither::FloydSteinberg, Dither::Atkinson] {
+            let mut img = gray_image();
+            dither(&mut img, &config(algorithm));
+
+            let mut colors: Vec<Rgb> = img.pixels().map(|p| (p[0], p[1], p[2])).collect();
+            assert!(colors.iter().all(|&c| quantize(c, ColorDepth::Ansi16) == c));
+            colors.sort();
+            colors.dedup();
+            // A single gray between two palette entries needs both of them
+            assert!(colors.len() > 1, "{:?} used {:?}", algorithm, colors);
+        }
+    }
+
+    #[test]
+    fn test_dither_skips_transparent() {
+        let mut img = RgbaImage::new(2, 2);
+        dither(&mut img, &config(Dither::FloydSteinberg));
+        assert_eq!(img, RgbaImage::new(2, 2));
+    }
+}
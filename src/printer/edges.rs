@@ -0,0 +1,154 @@
+use crate::error::ViuResult;
+use crate::printer::backend::{Backend, CrosstermBackend};
+use crate::printer::braille::{BRAILLE_BLANK, DOTS};
+use crate::printer::cell::{average, fill_cells, luminance, pixel_rgb, write_cells, Cell};
+use crate::printer::{fit_to_config, resize_to, Printer};
+use crate::Config;
+
+use image::{DynamicImage, RgbaImage};
+use std::io::{BufWriter, Write};
+
+// Weakest gradient drawn as an edge, out of about 1440 for a sharp black and white edge, so
+// that noise in flat areas is left out
+const MIN_EDGE: f32 = 96.0;
+
+/// Prints the edges of images as line art, using braille characters with each cell holding
+/// 2x4 pixels. The edges are found with the Sobel operator and colored with the average
+/// color of the image under their dots.
+pub struct EdgePrinter;
+
+impl Printer for EdgePrinter {
+    fn print(
+        &self,
+        stdout: &mut impl Write,
+        img: &DynamicImage,
+        config: &Config,
+    ) -> ViuResult<(u32, u32)> {
+        let mut stream = CrosstermBackend::new(BufWriter::new(stdout));
+        print_to_backend(&mut stream, img, config)
+    }
+}
+
+fn print_to_backend(
+    stdout: &mut impl Backend,
+    img: &DynamicImage,
+    config: &Config,
+) -> ViuResult<(u32, u32)> {
+    let mut cells = Vec::new();
+    render_into(img, config, &mut cells);
+    write_cells(stdout, &cells, config)
+}
+
+// Resize the image to fit the config and convert its edges to rows of cells, reusing the
+// allocations of `cells`
+pub fn render_into(img: &DynamicImage, config: &Config, cells: &mut Vec<Vec<Cell>>) {
+    let (w, h) = fit_to_config(img.width(), img.height(), config);
+    let img = resize_to(img, 2 * w, 4 * h, config).into_rgba8();
+    let edges = edge_mask(&img);
+
+    fill_cells(cells, w, h, |row, col| {
+        let mut dots = 0;
+        let mut lit = Vec::new();
+        for (i, dot) in DOTS.iter().enumerate() {
+            let (x, y) = (2 * col + i as u32 % 2, 4 * row + i as u32 / 2);
+            if x >= img.width() || y >= img.height() || !edges[(y * img.width() + x) as usize] {
+                continue;
+            }
+            dots |= dot;
+            // Transparent pixels are drawn in the terminal's foreground color
+            let rgb = pixel_rgb(x, y, img.get_pixel(x, y), config).unwrap_or((255, 255, 255));
+            lit.push(rgb);
+        }
+        Cell {
+            ch: char::from_u32(BRAILLE_BLANK + dots).unwrap_or(' '),
+            fg: (!lit.is_empty()).then(|| average(&lit)),
+            bg: None,
+        }
+    });
+}
+
+// Whether every pixel, row by row, is on an edge: where the gradient of the luminance is both
+// strong and well above the image's average, which keeps textures from being drawn
+fn edge_mask(img: &RgbaImage) -> Vec<bool> {
+    let (width, height) = img.dimensions();
+    // Transparent pixels count as dark, so that the outline of shapes is drawn
+    let luma: Vec<f32> = img
+        .pixels()
+        .map(|p| luminance((p[0], p[1], p[2])) as f32 * p[3] as f32 / 255.0)
+        .collect();
+    let at = |x: i64, y: i64| {
+        let x = x.clamp(0, width as i64 - 1);
+        let y = y.clamp(0, height as i64 - 1);
+        luma[(y * width as i64 + x) as usize]
+    };
+
+    let magnitude: Vec<f32> = (0..height as i64)
+        .flat_map(|y| (0..width as i64).map(move |x| (x, y)))
+        .map(|(x, y)| {
+            let gx = at(x + 1, y - 1) + 2.0 * at(x + 1, y) + at(x + 1, y + 1)
+                - at(x - 1, y - 1)
+                - 2.0 * at(x - 1, y)
+                - at(x - 1, y + 1);
+            let gy = at(x - 1, y + 1) + 2.0 * at(x, y + 1) + at(x + 1, y + 1)
+                - at(x - 1, y - 1)
+                - 2.0 * at(x, y - 1)
+                - at(x + 1, y - 1);
+            (gx * gx + gy * gy).sqrt()
+        })
+        .collect();
+
+    let mean = magnitude.iter().sum::<f32>() / magnitude.len().max(1) as f32;
+    let threshold = (2.0 * mean).max(MIN_EDGE);
+    magnitude.into_iter().map(|m| m >= threshold).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ColorDepth;
+    use image::Rgba;
+
+    #[test]
+    fn test_edge_mask() {
+        // A white square in the middle of a black image
+        let img = RgbaImage::from_fn(8, 8, |x, y| {
+            if (2..6).contains(&x) && (2..6).contains(&y) {
+                Rgba([255, 255, 255, 255])
+            } else {
+                Rgba([0, 0, 0, 255])
+            }
+        });
+        let edges = edge_mask(&img);
+        // The pixels next to the boundary, on both sides, but not the middle or the corners
+        assert!(edges[3 * 8 + 1] && edges[3 * 8 + 2]);
+        assert!(!edges[3 * 8 + 3] && !edges[0]);
+
+        let flat = RgbaImage::from_pixel(4, 4, Rgba([90, 90, 90, 255]));
+        assert!(edge_mask(&flat).iter().all(|&edge| !edge));
+    }
+
+    #[test]
+    fn test_edge_printer() {
+        // White on the right half
+        let img = DynamicImage::ImageRgba8(RgbaImage::from_fn(4, 4, |x, _| {
+            if x >= 2 {
+                Rgba([255, 255, 255, 255])
+            } else {
+                Rgba([0, 0, 0, 255])
+            }
+        }));
+        let config = Config {
+            absolute_offset: false,
+            color_depth: ColorDepth::TrueColor,
+            width: Some(2),
+            height: Some(1),
+            ..Default::default()
+        };
+        let mut cells = Vec::new();
+        render_into(&img, &config, &mut cells);
+        // The right column of the first cell and the left one of the second
+        assert_eq!(cells[0][0].ch, '⢸');
+        assert_eq!(cells[0][1].ch, '⡇');
+        assert_eq!(cells[0][1].fg, Some((255, 255, 255)));
+    }
+}
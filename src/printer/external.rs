@@ -0,0 +1,209 @@
+// Decoding of the formats which the image crate cannot read, with the command line decoders of
+// their reference libraries. Each format is only recognized with its feature enabled.
+use crate::error::{ViuError, ViuResult};
+use crate::printer::decode_from_memory;
+use crate::Config;
+
+use image::DynamicImage;
+use std::io::{ErrorKind, Read};
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+
+// Start of a JPEG XL file in the ISOBMFF container, a bare codestream starts with FF 0A
+const JXL_CONTAINER: &[u8] = b"\0\0\0\x0cJXL \r\n\x87\n";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ExternalFormat {
+    Heic,
+    Avif,
+    JpegXl,
+}
+
+impl ExternalFormat {
+    // Decoders which are tried in turn, all taking the input and the output file as arguments.
+    // heif-dec was called heif-convert before libheif 1.17.
+    fn decoders(self) -> &'static [&'static str] {
+        match self {
+            ExternalFormat::Heic => &["heif-dec", "heif-convert"],
+            ExternalFormat::Avif => &["avifdec"],
+            ExternalFormat::JpegXl => &["djxl"],
+        }
+    }
+
+    fn is_enabled(self) -> bool {
+        match self {
+            ExternalFormat::Heic => cfg!(feature = "heic-cli"),
+            ExternalFormat::Avif => cfg!(feature = "avif-cli"),
+            ExternalFormat::JpegXl => cfg!(feature = "jxl-cli"),
+        }
+    }
+}
+
+// The external format of the file, from its first bytes
+pub fn detect<P: AsRef<Path>>(filename: P) -> ViuResult<Option<ExternalFormat>> {
+    if !cfg!(any(
+        feature = "heic-cli",
+        feature = "avif-cli",
+        feature = "jxl-cli"
+    )) {
+        return Ok(None);
+    }
+    let mut header = Vec::with_capacity(64);
+    std::fs::File::open(filename)?
+        .take(64)
+        .read_to_end(&mut header)?;
+    Ok(guess_format(&header).filter(|format| format.is_enabled()))
+}
+
+// Recognize JPEG XL by its signature, and HEIC and AVIF by the brands in the ftyp box which
+// starts their ISOBMFF container
+fn guess_format(header: &[u8]) -> Option<ExternalFormat> {
+    if header.starts_with(&[0xff, 0x0a]) || header.starts_with(JXL_CONTAINER) {
+        return Some(ExternalFormat::JpegXl);
+    }
+    if header.len() < 12 || &header[4..8] != b"ftyp" {
+        return None;
+    }
+    let size = u32::from_be_bytes([header[0], header[1], header[2], header[3]]) as usize;
+    // The major brand, then the compatible ones after the minor version, which a truncated
+    // header may not have
+    let compatible = header.get(16..size.min(header.len())).unwrap_or_default();
+    let brands = std::iter::once(&header[8..12]).chain(compatible.chunks_exact(4));
+
+    let mut format = None;
+    for brand in brands {
+        match brand {
+            b"avif" | b"avis" => return Some(ExternalFormat::Avif),
+            b"heic" | b"heix" | b"heim" | b"heis" | b"hevc" | b"hevx" | b"mif1" | b"msf1" => {
+                format = Some(ExternalFormat::Heic)
+            }
+            _ => {}
+        }
+    }
+    format
+}
+
+// Decode the file into a temporary PNG with the external decoder, and decode that within the
+// limits from the config
+pub fn decode<P: AsRef<Path>>(
+    filename: P,
+    format: ExternalFormat,
+    config: &Config,
+) -> ViuResult<DynamicImage> {
+    let output = tempfile::Builder::new()
+        .suffix(".png")
+        .tempfile()?
+        .into_temp_path();
+    run_decoder(format.decoders(), &input_arg(filename.as_ref()), &output)?;
+    decode_from_memory(&std::fs::read(&output)?, config)
+}
+
+// The path as an argument which the decoders cannot take for an option, like "-o.heic".
+// Joining keeps an absolute path as it is.
+fn input_arg(path: &Path) -> PathBuf {
+    Path::new(".").join(path)
+}
+
+// Run the first of the decoders which is installed
+fn run_decoder(decoders: &[&str], input: &Path, output: &Path) -> ViuResult {
+    for decoder in decoders {
+        let result = Command::new(decoder)
+            .arg(input)
+            .arg(output)
+            .stdin(Stdio::null())
+            .stdout(Stdio::null())
+            .stderr(Stdio::piped())
+            .output();
+        let result = match result {
+            Err(e) if e.kind() == ErrorKind::NotFound => continue,
+            result => result?,
+        };
+        if !result.status.success() {
+            return Err(ViuError::DecoderFailed {
+                decoder: decoder.to_string(),
+                message: String::from_utf8_lossy(&result.stderr).trim().to_owned(),
+            });
+        }
+        return Ok(());
+    }
+    Err(ViuError::DecoderNotFound(
+        decoders.iter().map(|decoder| decoder.to_string()).collect(),
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_guess_format() {
+        let ftyp = |brands: &[u8]| {
+            let mut header = ((8 + brands.len()) as u32).to_be_bytes().to_vec();
+            header.extend_from_slice(b"ftyp");
+            header.extend_from_slice(brands);
+            header
+        };
+        // An AVIF may list the generic HEIF brand first
+        let avif = ftyp(b"mif1\0\0\0\0mif1avifmiaf");
+        assert_eq!(guess_format(&avif), Some(ExternalFormat::Avif));
+        let heic = ftyp(b"heic\0\0\0\0mif1heic");
+        assert_eq!(guess_format(&heic), Some(ExternalFormat::Heic));
+        // An MP4 video
+        assert_eq!(guess_format(&ftyp(b"isom\0\0\x02\0isomiso2")), None);
+        // Cut off before the compatible brands
+        assert_eq!(guess_format(&heic[..14]), Some(ExternalFormat::Heic));
+        assert_eq!(guess_format(&ftyp(b"isom\0\0\x02\0")[..15]), None);
+
+        assert_eq!(
+            guess_format(&[0xff, 0x0a, 0xfa]),
+            Some(ExternalFormat::JpegXl)
+        );
+        assert_eq!(guess_format(JXL_CONTAINER), Some(ExternalFormat::JpegXl));
+        assert_eq!(guess_format(b"\x89PNG\r\n\x1a\n"), None);
+        assert_eq!(guess_format(b""), None);
+    }
+
+    #[test]
+    fn test_input_arg() {
+        assert_eq!(input_arg(Path::new("-o.heic")), Path::new("./-o.heic"));
+        assert_eq!(
+            input_arg(Path::new("/tmp/a.heic")),
+            Path::new("/tmp/a.heic")
+        );
+    }
+
+    #[test]
+    fn test_run_decoder() {
+        let output = Path::new("out.png");
+        match run_decoder(
+            &["viuer-missing-1", "viuer-missing-2"],
+            Path::new("in"),
+            output,
+        ) {
+            Err(ViuError::DecoderNotFound(tried)) => {
+                assert_eq!(tried, ["viuer-missing-1", "viuer-missing-2"])
+            }
+            result => panic!("unexpected {:?}", result),
+        }
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_run_decoder_failure() {
+        // sh runs the input as a script, which fails like a decoder would
+        let mut script = tempfile::NamedTempFile::new().unwrap();
+        std::io::Write::write_all(&mut script, b"echo 'bad header' >&2; exit 1").unwrap();
+        let result = run_decoder(
+            &["viuer-missing", "sh"],
+            script.path(),
+            Path::new("out.png"),
+        );
+        match result {
+            Err(ViuError::DecoderFailed { decoder, message }) => {
+                assert_eq!(decoder, "sh");
+                assert_eq!(message, "bad header");
+            }
+            result => panic!("unexpected {:?}", result),
+        }
+    }
+}
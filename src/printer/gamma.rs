@@ -0,0 +1,179 @@
+// Resizing in linear light. sRGB values are not proportional to the light they stand for,
+// so averaging them darkens edges and fine detail when an image is scaled down.
+use image::imageops::{self, FilterType};
+use image::{DynamicImage, GenericImageView, ImageBuffer, Pixel, Rgba, RgbaImage};
+use lazy_static::lazy_static;
+
+lazy_static! {
+    // The linear light of every sRGB value
+    static ref TO_LINEAR: [f32; 256] = {
+        let mut table = [0.0; 256];
+        for (value, linear) in table.iter_mut().enumerate() {
+            *linear = srgb_to_linear(value as f32 / 255.0);
+        }
+        table
+    };
+}
+
+// How much larger than the result the linear buffer is at least, in each direction
+const PRESCALE: u32 = 2;
+
+// Resize the image to exactly the given size in linear light. The colors are premultiplied
+// with their alpha, so that transparent pixels do not bleed into their neighbours.
+pub fn resize(img: &DynamicImage, width: u32, height: u32, filter: FilterType) -> DynamicImage {
+    let converted;
+    let rgba = match img.as_rgba8() {
+        Some(rgba) => rgba,
+        None => {
+            converted = img.to_rgba8();
+            &converted
+        }
+    };
+    // The linear buffer takes four floats per pixel, so a large image is averaged down to
+    // a few times the size of the result while it is converted, instead of being converted
+    // whole. Averaging the linear values keeps the light of the fine detail.
+    let block = (
+        (rgba.width() / width.saturating_mul(PRESCALE).max(1)).max(1),
+        (rgba.height() / height.saturating_mul(PRESCALE).max(1)).max(1),
+    );
+    let linear = to_linear(rgba, block);
+
+    let resized = imageops::resize(&linear, width, height, filter);
+    DynamicImage::ImageRgba8(to_srgb(&resized))
+}
+
+// Scale the image down by averaging square blocks of `block` pixels in linear light. Any
+// image view can be read, so that it does not have to be copied whole first.
+pub fn shrink<V, P>(img: &V, block: u32) -> RgbaImage
+where
+    V: GenericImageView<Pixel = P>,
+    P: Pixel<Subpixel = u8>,
+{
+    to_srgb(&to_linear(img, (block, block)))
+}
+
+// The sRGB image for premultiplied linear light
+fn to_srgb(linear: &ImageBuffer<Rgba<f32>, Vec<f32>>) -> RgbaImage {
+    let srgb = linear
+        .pixels()
+        .flat_map(|p| {
+            // Filters with negative lobes can overshoot
+            let alpha = p[3].clamp(0.0, 1.0);
+            if alpha == 0.0 {
+                return [0; 4];
+            }
+            [
+                linear_to_srgb(p[0] / alpha),
+                linear_to_srgb(p[1] / alpha),
+                linear_to_srgb(p[2] / alpha),
+                (alpha * 255.0).round() as u8,
+            ]
+        })
+        .collect();
+    RgbaImage::from_raw(linear.width(), linear.height(), srgb)
+        .expect("The buffer holds four values for every pixel")
+}
+
+// The premultiplied linear light of the image, averaged over blocks of the given size
+fn to_linear<V, P>(img: &V, (block_w, block_h): (u32, u32)) -> ImageBuffer<Rgba<f32>, Vec<f32>>
+where
+    V: GenericImageView<Pixel = P>,
+    P: Pixel<Subpixel = u8>,
+{
+    let (width, height) = img.dimensions();
+    let mut linear =
+        ImageBuffer::<Rgba<f32>, _>::new(width.div_ceil(block_w), height.div_ceil(block_h));
+    for (x, y, p) in img.pixels() {
+        let p = p.to_rgba();
+        let alpha = p[3] as f32 / 255.0;
+        let sum = linear.get_pixel_mut(x / block_w, y / block_h);
+        sum[0] += TO_LINEAR[p[0] as usize] * alpha;
+        sum[1] += TO_LINEAR[p[1] as usize] * alpha;
+        sum[2] += TO_LINEAR[p[2] as usize] * alpha;
+        sum[3] += alpha;
+    }
+    if (block_w, block_h) != (1, 1) {
+        for (x, y, sum) in linear.enumerate_pixels_mut() {
+            // The blocks on the right and bottom edges can be cut off
+            let w = block_w.min(width - x * block_w);
+            let h = block_h.min(height - y * block_h);
+            let count = (w * h) as f32;
+            sum.0.iter_mut().for_each(|value| *value /= count);
+        }
+    }
+    linear
+}
+
+pub fn srgb_to_linear(value: f32) -> f32 {
+    if value <= 0.04045 {
+        value / 12.92
+    } else {
+        ((value + 0.055) / 1.055).powf(2.4)
+    }
+}
+
+pub fn linear_to_srgb(linear: f32) -> u8 {
+    let linear = linear.clamp(0.0, 1.0);
+    let value = if linear <= 0.0031308 {
+        linear * 12.92
+    } else {
+        1.055 * linear.powf(1.0 / 2.4) - 0.055
+    };
+    (value * 255.0).round() as u8
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_round_trip() {
+        for value in 0..=255 {
+            assert_eq!(linear_to_srgb(TO_LINEAR[value as usize]), value);
+        }
+    }
+
+    #[test]
+    fn test_resize_in_linear_light() {
+        // Black and white stripes average to half of the light, which is brighter than the
+        // middle sRGB value
+        let img = RgbaImage::from_fn(4, 1, |x, _| {
+            let value = if x % 2 == 0 { 0 } else { 255 };
+            Rgba([value, value, value, 255])
+        });
+        let resized = resize(&DynamicImage::ImageRgba8(img), 1, 1, FilterType::Triangle);
+        assert_eq!(resized.get_pixel(0, 0), Rgba([188, 188, 188, 255]));
+    }
+
+    #[test]
+    fn test_resize_large_image() {
+        // The stripes are still averaged in linear light when the image is averaged down
+        // before the resizing
+        let img = RgbaImage::from_fn(64, 2, |x, _| {
+            let value = if x % 2 == 0 { 0 } else { 255 };
+            Rgba([value, value, value, 255])
+        });
+        let resized = resize(
+            &DynamicImage::ImageRgba8(img.clone()),
+            2,
+            1,
+            FilterType::Triangle,
+        );
+        assert_eq!(resized.get_pixel(0, 0), Rgba([188, 188, 188, 255]));
+        assert_eq!(to_linear(&img, (16, 1)).dimensions(), (4, 2));
+    }
+
+    #[test]
+    fn test_resize_premultiplied() {
+        // The color of a transparent pixel does not tint its neighbour
+        let img = RgbaImage::from_fn(2, 1, |x, _| {
+            if x == 0 {
+                Rgba([255, 0, 0, 0])
+            } else {
+                Rgba([0, 0, 255, 255])
+            }
+        });
+        let resized = resize(&DynamicImage::ImageRgba8(img), 1, 1, FilterType::Triangle);
+        assert_eq!(resized.get_pixel(0, 0), Rgba([0, 0, 255, 128]));
+    }
+}
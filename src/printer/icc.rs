@@ -0,0 +1,130 @@
+// Conversion of images with an embedded ICC profile to sRGB, the color space which terminals
+// assume. Wide gamut photos look oversaturated or washed out without it.
+use image::codecs::{jpeg::JpegDecoder, png::PngDecoder, webp::WebPDecoder};
+use image::{DynamicImage, ImageDecoder, ImageFormat};
+use qcms::{DataType, Intent, Profile, Transform};
+use std::io::{BufRead, Seek};
+
+// Convert the decoded image to sRGB with the profile embedded in the encoded data, if any.
+// Images without a profile, or with one that cannot be used, are returned as they are.
+pub fn convert_to_srgb<R: BufRead + Seek>(img: DynamicImage, encoded: R) -> DynamicImage {
+    match icc_profile(encoded).and_then(|profile| srgb_transform(&profile)) {
+        Some(transform) => {
+            let mut rgba = img.into_rgba8();
+            transform.apply(&mut rgba);
+            DynamicImage::ImageRgba8(rgba)
+        }
+        None => img,
+    }
+}
+
+// Read the embedded profile, for the formats which image can read it from
+fn icc_profile<R: BufRead + Seek>(encoded: R) -> Option<Vec<u8>> {
+    let reader = image::io::Reader::new(encoded).with_guessed_format().ok()?;
+    let format = reader.format()?;
+    let encoded = reader.into_inner();
+    match format {
+        ImageFormat::Jpeg => JpegDecoder::new(encoded).ok()?.icc_profile(),
+        ImageFormat::Png => PngDecoder::new(encoded).ok()?.icc_profile(),
+        ImageFormat::WebP => WebPDecoder::new(encoded).ok()?.icc_profile(),
+        _ => None,
+    }
+}
+
+fn srgb_transform(profile: &[u8]) -> Option<Transform> {
+    let input = Profile::new_from_slice(profile, false)?;
+    let mut output = Profile::new_sRGB();
+    output.precache_output_transform();
+    Transform::new(&input, &output, DataType::RGBA8, Intent::Perceptual)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use image::{ImageOutputFormat, Rgba, RgbaImage};
+    use std::io::Cursor;
+
+    // An RGB profile with the primaries of sRGB, red and blue swapped, and linear curves
+    fn swapped_profile() -> Vec<u8> {
+        let xyz = |[x, y, z]: [f64; 3]| {
+            let mut tag = b"XYZ \0\0\0\0".to_vec();
+            for v in [x, y, z] {
+                tag.extend_from_slice(&((v * 65536.0).round() as i32).to_be_bytes());
+            }
+            tag
+        };
+        let tags = [
+            (b"wtpt", xyz([0.9642, 1.0, 0.8249])),
+            (b"rXYZ", xyz([0.1431, 0.0606, 0.7141])),
+            (b"gXYZ", xyz([0.3851, 0.7169, 0.0971])),
+            (b"bXYZ", xyz([0.4361, 0.2225, 0.0139])),
+            // A curve without points is the identity
+            (b"rTRC", b"curv\0\0\0\0\0\0\0\0".to_vec()),
+            (b"gTRC", b"curv\0\0\0\0\0\0\0\0".to_vec()),
+            (b"bTRC", b"curv\0\0\0\0\0\0\0\0".to_vec()),
+        ];
+
+        let mut table = (tags.len() as u32).to_be_bytes().to_vec();
+        let mut data = Vec::new();
+        let start = 128 + 4 + 12 * tags.len();
+        for (signature, tag) in &tags {
+            table.extend_from_slice(*signature);
+            table.extend_from_slice(&((start + data.len()) as u32).to_be_bytes());
+            table.extend_from_slice(&(tag.len() as u32).to_be_bytes());
+            data.extend_from_slice(tag);
+        }
+
+        let mut header = vec![0; 128];
+        header[..4].copy_from_slice(&((start + data.len()) as u32).to_be_bytes());
+        header[8] = 2;
+        header[12..16].copy_from_slice(b"mntr");
+        header[16..20].copy_from_slice(b"RGB ");
+        header[20..24].copy_from_slice(b"XYZ ");
+        header[36..40].copy_from_slice(b"acsp");
+        [header, table, data].concat()
+    }
+
+    // Encode the image as a PNG with the profile in an iCCP chunk after the header
+    fn png_with_profile(img: &RgbaImage, profile: &[u8]) -> Vec<u8> {
+        let mut png = Vec::new();
+        DynamicImage::ImageRgba8(img.clone())
+            .write_to(&mut Cursor::new(&mut png), ImageOutputFormat::Png)
+            .unwrap();
+
+        let mut chunk = b"iCCPtest\0\0".to_vec();
+        let mut encoder = flate2::write::ZlibEncoder::new(Vec::new(), Default::default());
+        std::io::Write::write_all(&mut encoder, profile).unwrap();
+        chunk.extend_from_slice(&encoder.finish().unwrap());
+        let mut crc = flate2::Crc::new();
+        crc.update(&chunk);
+
+        // The signature, then the header chunk of 13 bytes with its length, type and crc
+        let end_of_header = 8 + 4 + 4 + 13 + 4;
+        let mut with_profile = png[..end_of_header].to_vec();
+        with_profile.extend_from_slice(&(chunk.len() as u32 - 4).to_be_bytes());
+        with_profile.extend_from_slice(&chunk);
+        with_profile.extend_from_slice(&crc.sum().to_be_bytes());
+        with_profile.extend_from_slice(&png[end_of_header..]);
+        with_profile
+    }
+
+    #[test]
+    fn test_convert_to_srgb() {
+        let img = RgbaImage::from_pixel(1, 1, Rgba([255, 0, 0, 200]));
+        let png = png_with_profile(&img, &swapped_profile());
+        let decoded = image::load_from_memory(&png).unwrap();
+
+        let converted = convert_to_srgb(decoded, Cursor::new(&png)).into_rgba8();
+        let [r, g, b, a] = converted.get_pixel(0, 0).0;
+        assert!(r < 8 && g < 8 && b > 247, "{:?}", converted.get_pixel(0, 0));
+        assert_eq!(a, 200);
+
+        // Without a profile, the image is not touched
+        let mut plain = Vec::new();
+        DynamicImage::ImageRgba8(img.clone())
+            .write_to(&mut Cursor::new(&mut plain), ImageOutputFormat::Png)
+            .unwrap();
+        let converted = convert_to_srgb(DynamicImage::ImageRgba8(img.clone()), Cursor::new(plain));
+        assert_eq!(converted.into_rgba8(), img);
+    }
+}
@@ -0,0 +1,132 @@
+// Reading of the IFDs of TIFF files, the directories which describe each image in them, for
+// camera RAW files and the pages of multi-page TIFFs
+use std::collections::HashSet;
+use std::io::{ErrorKind, Read, Seek, SeekFrom};
+
+pub(super) struct Entry {
+    pub tag: u16,
+    kind: u16,
+    count: u32,
+    value: [u8; 4],
+}
+
+pub(super) struct Tiff<R> {
+    pub reader: R,
+    big_endian: bool,
+    // Canon's CR2 marks itself with "CR" after the header
+    pub cr2: bool,
+    pub first_ifd: u32,
+}
+
+impl<R: Read + Seek> Tiff<R> {
+    // Read the header, None if it is not a TIFF file
+    pub fn new(mut reader: R) -> std::io::Result<Option<Self>> {
+        let mut header = [0; 10];
+        match reader.read_exact(&mut header) {
+            Err(e) if e.kind() == ErrorKind::UnexpectedEof => return Ok(None),
+            result => result?,
+        }
+        let big_endian = match &header[..4] {
+            b"II*\0" => false,
+            b"MM\0*" => true,
+            _ => return Ok(None),
+        };
+        let mut tiff = Self {
+            reader,
+            big_endian,
+            cr2: &header[8..] == b"CR",
+            first_ifd: 0,
+        };
+        tiff.first_ifd = tiff.u32([header[4], header[5], header[6], header[7]]);
+        Ok(Some(tiff))
+    }
+
+    pub fn u16(&self, bytes: [u8; 2]) -> u16 {
+        match self.big_endian {
+            true => u16::from_be_bytes(bytes),
+            false => u16::from_le_bytes(bytes),
+        }
+    }
+
+    fn u32(&self, bytes: [u8; 4]) -> u32 {
+        match self.big_endian {
+            true => u32::from_be_bytes(bytes),
+            false => u32::from_le_bytes(bytes),
+        }
+    }
+
+    // The bytes of the value in the byte order of the file
+    pub fn u32_bytes(&self, value: u32) -> [u8; 4] {
+        match self.big_endian {
+            true => value.to_be_bytes(),
+            false => value.to_le_bytes(),
+        }
+    }
+
+    // The entries of the IFD at the offset, and the offset of the next one
+    pub fn ifd(&mut self, offset: u32) -> std::io::Result<(Vec<Entry>, u32)> {
+        self.reader.seek(SeekFrom::Start(offset as u64))?;
+        let mut buf = [0; 12];
+        self.reader.read_exact(&mut buf[..2])?;
+        let len = self.u16([buf[0], buf[1]]);
+
+        let mut entries = Vec::with_capacity(len as usize);
+        for _ in 0..len {
+            self.reader.read_exact(&mut buf)?;
+            entries.push(Entry {
+                tag: self.u16([buf[0], buf[1]]),
+                kind: self.u16([buf[2], buf[3]]),
+                count: self.u32([buf[4], buf[5], buf[6], buf[7]]),
+                value: [buf[8], buf[9], buf[10], buf[11]],
+            });
+        }
+        self.reader.read_exact(&mut buf[..4])?;
+        Ok((entries, self.u32([buf[0], buf[1], buf[2], buf[3]])))
+    }
+
+    // The offsets of the IFDs of the main images, one for each page, following the chain of
+    // IFDs from the first one until it ends or loops
+    pub fn page_ifds(&mut self) -> std::io::Result<Vec<u32>> {
+        let mut offsets = Vec::new();
+        let mut visited = HashSet::new();
+        let mut offset = self.first_ifd;
+        while offset != 0 && visited.insert(offset) {
+            offsets.push(offset);
+            offset = self.ifd(offset)?.1;
+        }
+        Ok(offsets)
+    }
+
+    // The integer values of the entry with the tag, which are stored in the entry itself
+    // when they fit in it. Empty if the tag is missing or does not hold integers.
+    pub fn values(&mut self, entries: &[Entry], tag: u16) -> std::io::Result<Vec<u32>> {
+        let Some(entry) = entries.iter().find(|entry| entry.tag == tag) else {
+            return Ok(Vec::new());
+        };
+        // BYTE, SHORT, then LONG and IFD
+        let size = match entry.kind {
+            1 => 1,
+            3 => 2,
+            4 | 13 => 4,
+            _ => return Ok(Vec::new()),
+        };
+        // More values than fit in the largest IFD
+        let count = entry.count.min(u16::MAX as u32) as usize;
+        let mut bytes = vec![0; count * size];
+        if count * size <= 4 {
+            bytes.copy_from_slice(&entry.value[..count * size]);
+        } else {
+            self.reader
+                .seek(SeekFrom::Start(self.u32(entry.value) as u64))?;
+            self.reader.read_exact(&mut bytes)?;
+        }
+        Ok(bytes
+            .chunks_exact(size)
+            .map(|value| match value {
+                [a] => *a as u32,
+                [a, b] => self.u16([*a, *b]) as u32,
+                _ => self.u32([value[0], value[1], value[2], value[3]]),
+            })
+            .collect())
+    }
+}
@@ -1,7 +1,9 @@
 use crate::error::ViuResult;
-use crate::printer::{adjust_offset, find_best_fit, Printer};
+use crate::printer::{
+    adjust_offset, decode_from_memory, fit_to_native, needs_own_decoder, open_image, Printer,
+};
+use crate::utils::{base64, write_graphics};
 use crate::Config;
-use base64::{engine::general_purpose, Engine};
 use image::{DynamicImage, GenericImageView, ImageEncoder};
 use lazy_static::lazy_static;
 use std::{
@@ -48,6 +50,11 @@ impl Printer for iTermPrinter {
         filename: P,
         config: &Config,
     ) -> ViuResult<(u32, u32)> {
+        // Formats which iTerm may not show, and pages other than the first, are sent as PNG,
+        // like decoded images
+        if config.page.is_some() || needs_own_decoder(&filename)? {
+            return self.print(stdout, &open_image(filename, config)?, config);
+        }
         let file = std::fs::File::open(filename)?;
 
         // load the file content
@@ -55,7 +62,7 @@ impl Printer for iTermPrinter {
         let mut file_content = Vec::new();
         buf_reader.read_to_end(&mut file_content)?;
 
-        let img = image::load_from_memory(&file_content[..])?;
+        let img = decode_from_memory(&file_content, config)?;
         print_buffer(stdout, &img, &file_content[..], config)
     }
 }
@@ -70,16 +77,19 @@ fn print_buffer(
 ) -> ViuResult<(u32, u32)> {
     adjust_offset(stdout, config)?;
 
-    let (w, h) = find_best_fit(img, config.width, config.height);
+    let (w, h) = fit_to_native(img.width(), img.height(), config);
 
-    writeln!(
+    write_graphics(
         stdout,
-        "\x1b]1337;File=inline=1;preserveAspectRatio=1;size={};width={};height={}:{}\x07",
-        img_content.len(),
-        w,
-        h,
-        general_purpose::STANDARD.encode(img_content)
+        format_args!(
+            "\x1b]1337;File=inline=1;preserveAspectRatio=1;size={};width={};height={}:{}\x07",
+            img_content.len(),
+            w,
+            h,
+            base64(img_content)
+        ),
     )?;
+    writeln!(stdout)?;
     stdout.flush()?;
 
     Ok((w, h))
@@ -107,6 +117,7 @@ mod tests {
 
     #[test]
     fn test_print_e2e() {
+        crate::utils::clear_terminal_env();
         let mut img = DynamicImage::ImageRgba8(image::RgbaImage::new(2, 3));
         img.put_pixel(1, 2, image::Rgba([2, 4, 6, 8]));
 
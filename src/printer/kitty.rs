@@ -1,15 +1,33 @@
-use crate::error::{ViuError, ViuResult};
-use crate::printer::{adjust_offset, find_best_fit, Printer};
+use crate::error::{ProtocolKind, ViuError, ViuResult};
+use crate::printer::{
+    adjust_offset, align_cells, fit_to_native, needs_alignment, open_image, placeholder, Printer,
+};
+use crate::terminal::{self, verified_protocols};
+use crate::utils::{base64, in_tmux, tmux_passthrough, write_graphics};
 use crate::Config;
 use base64::{engine::general_purpose, Engine};
-use console::{Key, Term};
+use flate2::{write::ZlibEncoder, Compression};
+use image::codecs::png::PngEncoder;
+use image::{DynamicImage, GenericImageView, ImageEncoder, ImageFormat};
 use lazy_static::lazy_static;
 use std::io::Write;
 use std::io::{Error, ErrorKind};
+use std::path::Path;
+use std::sync::atomic::{AtomicU32, Ordering};
 
 pub struct KittyPrinter;
 
 const TEMP_FILE_PREFIX: &str = ".tty-graphics-protocol.viuer.";
+const CHUNK_SIZE: usize = 4096;
+// Data encoded into one chunk: every 3 bytes are 4 characters in base64, so the chunks can be
+// encoded separately without padding in between
+const RAW_CHUNK_SIZE: usize = CHUNK_SIZE / 4 * 3;
+
+// Size of the frames of an animation which kitty keeps, well below its default quota of
+// 320 MB for the images of a terminal
+const ANIMATION_BUDGET: usize = 128 * 1024 * 1024;
+
+static NEXT_IMAGE_ID: AtomicU32 = AtomicU32::new(1);
 lazy_static! {
     static ref KITTY_SUPPORT: KittySupport = check_kitty_support();
 }
@@ -26,25 +44,67 @@ impl Printer for KittyPrinter {
         img: &image::DynamicImage,
         config: &Config,
     ) -> ViuResult<(u32, u32)> {
-        match get_kitty_support() {
-            KittySupport::None => Err(ViuError::KittyNotSupported),
-            KittySupport::Local => {
-                // print from file
-                print_local(stdout, img, config)
-            }
-            KittySupport::Remote => {
-                // print through escape codes
-                print_remote(stdout, img, config)
-            }
+        print_image(stdout, img, config, medium(config)?)
+    }
+
+    // PNG files are sent as they are, unless another format or a page is set in the config
+    fn print_from_file<P: AsRef<Path>>(
+        &self,
+        stdout: &mut impl Write,
+        filename: P,
+        config: &Config,
+    ) -> ViuResult<(u32, u32)> {
+        let medium = medium(config)?;
+        let reader = image::io::Reader::open(&filename)?.with_guessed_format()?;
+        let passthrough = matches!(config.kitty_format, KittyFormat::Auto | KittyFormat::Png)
+            && reader.format() == Some(ImageFormat::Png)
+            && config.page.is_none();
+        if !passthrough {
+            return print_image(stdout, &open_image(filename, config)?, config, medium);
         }
+
+        let size = reader.into_dimensions()?;
+        config.limits.check_dimensions(size.0, size.1)?;
+        let png = std::fs::read(filename)?;
+        print_data(stdout, "f=100", &png, size, medium, config)
     }
+}
 
-    // TODO: guess_format() here in order to treat PNGs specially (f=100).
-    // Also, maybe get channel count and use f=24 or f=32 accordingly.
-    // fn print_from_file(&self, filename: &str, config: &Config) -> ViuResult<(u32, u32)> {}
+/// Where the Kitty printer puts the image data for the terminal to read it, set through
+/// [Config::kitty_medium]. The data is always sent through escape codes when kitty does not
+/// run locally, e.g. over SSH.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum KittyMedium {
+    /// A temporary file when kitty runs locally.
+    Auto,
+    /// A temporary file, which the terminal deletes after reading it.
+    TempFile,
+    /// A POSIX shared memory object, which the terminal unlinks after reading it. Avoids
+    /// writing large images to the disk. Falls back to a temporary file outside of unix.
+    SharedMemory,
+    /// Base64 encoded in escape codes, like over SSH.
+    Direct,
 }
 
-#[derive(PartialEq, Eq, Copy, Clone)]
+/// How the Kitty printer sends the image data to the terminal, set through
+/// [Config::kitty_format].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum KittyFormat {
+    /// PNG files which are printed without transformations are sent as they are. Other images
+    /// are sent as RGBA pixels, compressed when they go through escape codes instead of a
+    /// temporary file.
+    Auto,
+    /// PNG, encoded first unless the image comes from a PNG file.
+    Png,
+    /// Uncompressed RGBA pixels.
+    Rgba,
+    /// RGBA pixels compressed with zlib.
+    CompressedRgba,
+}
+
+#[derive(Debug, PartialEq, Eq, Copy, Clone)]
 /// The extend to which the Kitty graphics protocol can be used.
 pub enum KittySupport {
     /// The Kitty graphics protocol is not supported.
@@ -57,137 +117,517 @@ pub enum KittySupport {
 
 // Check if Kitty protocol can be used
 fn check_kitty_support() -> KittySupport {
-    if let Ok(term) = std::env::var("TERM") {
-        if term.contains("kitty") {
-            if has_local_support().is_ok() {
-                return KittySupport::Local;
-            } else {
-                return KittySupport::Remote;
-            }
+    // Inside tmux, TERM is tmux's own, but kitty's window id is still inherited
+    let in_kitty = std::env::var("TERM").is_ok_and(|term| term.contains("kitty"))
+        || std::env::var("KITTY_WINDOW_ID").is_ok_and(|id| !id.is_empty());
+    if in_kitty {
+        if has_local_support() {
+            return KittySupport::Local;
+        } else {
+            return KittySupport::Remote;
         }
     }
     KittySupport::None
 }
 
-// Query the terminal whether it can display an image from a file
-fn has_local_support() -> ViuResult {
+// Query the terminal whether it can display an image from a file. The reply may never come,
+// e.g. when tmux does not pass the query through, or when KITTY_WINDOW_ID was inherited from
+// another terminal, so it is only awaited until the query's timeout.
+fn has_local_support() -> bool {
     // create a temp file that will hold a 1x1 image
     let x = image::RgbaImage::new(1, 1);
-    let raw_img = x.as_raw();
-    let path = store_in_tmp_file(raw_img)?;
-
-    // send the query
-    print!(
-        // t=t tells Kitty it's reading from a temp file and will delete if afterwards
+    let Ok(path) = store_in_tmp_file(x.as_raw()) else {
+        return false;
+    };
+    let Some(name) = path.to_str() else {
+        return false;
+    };
+
+    // t=t tells Kitty it's reading from a temp file and will delete if afterwards
+    let query = format!(
         "\x1b_Gi=31,s=1,v=1,a=q,t=t;{}\x1b\\",
-        general_purpose::STANDARD.encode(path.to_str().ok_or_else(|| std::io::Error::new(
-            std::io::ErrorKind::Other,
-            "Could not convert path to &str"
-        ))?)
+        general_purpose::STANDARD.encode(name)
     );
-    std::io::stdout().flush()?;
-
-    // collect Kitty's response after the query
-    let term = Term::stdout();
-    let mut response = Vec::new();
-
-    // TODO: could use a queue of length 3
-    while let Ok(key) = term.read_key() {
-        // The response will end with Esc('x1b'), followed by Backslash('\').
-        // Also, break if the Unknown key is found, which is returned when we're not in a tty
-        let should_break = key == Key::UnknownEscSeq(vec!['\\']) || key == Key::Unknown;
-        response.push(key);
-        if should_break {
-            break;
-        }
+    let query = if in_tmux() {
+        tmux_passthrough(&query)
+    } else {
+        query
+    };
+    let local = terminal::query(&query, terminal::DEFAULT_QUERY_TIMEOUT)
+        .is_some_and(|replies| replies.contains("\x1b_Gi=31;OK"));
+    if !local {
+        // Nothing read the file
+        let _ = std::fs::remove_file(&path);
     }
+    local
+}
 
-    // Kitty response should end with these 3 Keys if it was successful
-    let expected = [
-        Key::Char('O'),
-        Key::Char('K'),
-        Key::UnknownEscSeq(vec!['\\']),
-    ];
+// How the image data is sent: the medium from the config if kitty runs locally, otherwise
+// through escape codes. Never returns KittyMedium::Auto.
+pub(crate) fn medium(config: &Config) -> ViuResult<KittyMedium> {
+    let local = match get_kitty_support() {
+        // Not detected from the environment, but confirmed by the terminal, e.g. over SSH
+        KittySupport::None if verified_protocols().kitty => false,
+        KittySupport::None if verified_protocols().timed_out => return Err(ViuError::QueryTimeout),
+        KittySupport::None => return Err(ViuError::UnsupportedProtocol(ProtocolKind::Kitty)),
+        KittySupport::Local => true,
+        KittySupport::Remote => false,
+    };
+    Ok(match config.kitty_medium {
+        _ if !local => KittyMedium::Direct,
+        KittyMedium::Auto => KittyMedium::TempFile,
+        #[cfg(not(unix))]
+        KittyMedium::SharedMemory => KittyMedium::TempFile,
+        medium => medium,
+    })
+}
 
-    if response.len() >= expected.len() && response[response.len() - 3..] == expected {
-        return Ok(());
+// Print a decoded image in the format from the config
+fn print_image(
+    stdout: &mut impl Write,
+    img: &DynamicImage,
+    config: &Config,
+    medium: KittyMedium,
+) -> ViuResult<(u32, u32)> {
+    let (width, height) = img.dimensions();
+    let format = match config.kitty_format {
+        KittyFormat::Auto if medium == KittyMedium::Direct => KittyFormat::CompressedRgba,
+        KittyFormat::Auto => KittyFormat::Rgba,
+        format => format,
+    };
+
+    match format {
+        KittyFormat::Png => {
+            let mut png = Vec::new();
+            PngEncoder::new(&mut png).write_image(img.as_bytes(), width, height, img.color())?;
+            print_data(stdout, "f=100", &png, (width, height), medium, config)
+        }
+        KittyFormat::CompressedRgba => {
+            let mut encoder = ZlibEncoder::new(Vec::new(), Compression::default());
+            encoder.write_all(img.to_rgba8().as_raw())?;
+            let format = format!("f=32,o=z,s={},v={}", width, height);
+            print_data(
+                stdout,
+                &format,
+                &encoder.finish()?,
+                (width, height),
+                medium,
+                config,
+            )
+        }
+        _ => {
+            let format = format!("f=32,s={},v={}", width, height);
+            let rgba = img.to_rgba8();
+            print_data(
+                stdout,
+                &format,
+                rgba.as_raw(),
+                (width, height),
+                medium,
+                config,
+            )
+        }
     }
-
-    Err(ViuError::KittyResponse(response))
 }
 
-// Print with kitty graphics protocol through a temp file
-// TODO: try with kitty's supported compression
-fn print_local(
+// Print image data described by `format` through the given medium. `size` is the size of
+// the image in pixels.
+fn print_data(
     stdout: &mut impl Write,
-    img: &image::DynamicImage,
+    format: &str,
+    data: &[u8],
+    (width, height): (u32, u32),
+    medium: KittyMedium,
     config: &Config,
 ) -> ViuResult<(u32, u32)> {
-    let rgba = img.to_rgba8();
-    let raw_img = rgba.as_raw();
-    let path = store_in_tmp_file(raw_img)?;
-
-    adjust_offset(stdout, config)?;
-
     // get the desired width and height
-    let (w, h) = find_best_fit(img, config.width, config.height);
-
-    write!(
-        stdout,
-        "\x1b_Gf=32,s={},v={},c={},r={},a=T,t=t;{}\x1b\\",
-        img.width(),
-        img.height(),
-        w,
-        h,
-        general_purpose::STANDARD.encode(path.to_str().ok_or_else(|| ViuError::Io(Error::new(
-            ErrorKind::Other,
-            "Could not convert path to &str"
-        )))?)
-    )?;
+    let (w, h) = fit_to_native(width, height, config);
+
+    // Inside tmux, the image is shown through placeholder cells, which tmux keeps in the pane
+    // like text. The image gets a virtual placement, and no response, which tmux would pass
+    // to the application as input.
+    let placeholders = in_tmux() && w <= placeholder::MAX_CELLS && h <= placeholder::MAX_CELLS;
+    let id = next_image_id();
+    let control = if placeholders {
+        format!("{},c={},r={},a=T,U=1,i={},q=2", format, w, h, id)
+    } else {
+        adjust_offset(stdout, config)?;
+        format!("{},c={},r={},a=T", format, w, h)
+    };
+
+    if medium == KittyMedium::Direct {
+        write_chunked(stdout, &control, data)?;
+    } else {
+        // The terminal reads the data from the file or shared memory object given by its name
+        let (t, name) = store(data, medium)?;
+        write_graphics(
+            stdout,
+            format_args!(
+                "\x1b_G{},t={};{}\x1b\\",
+                control,
+                t,
+                base64(name.as_bytes())
+            ),
+        )?;
+    }
+    if placeholders {
+        placeholder::write_placeholders(stdout, id, (w, h), config)?;
+    }
     writeln!(stdout)?;
     stdout.flush()?;
 
     Ok((w, h))
 }
 
-// Print with escape codes
-// TODO: try compression
-fn print_remote(
+// Get an image id which is unlikely to clash with images from other processes,
+// because transmitting an image with an existing id replaces it
+pub fn next_image_id() -> u32 {
+    let counter = NEXT_IMAGE_ID.fetch_add(1, Ordering::Relaxed) & 0xffff;
+    (std::process::id() & 0xffff) << 16 | counter.max(1)
+}
+
+// An animation played through kitty's animation protocol. The first frame is placed as a new
+// image, and the following ones are added to it with a=f. Kitty keeps the frames, so showing
+// one again, when the animation loops or seeks, only selects it with a=a. Once the frames fill
+// their budget, the remaining ones replace each other in a single frame.
+pub struct KittyAnimation {
+    id: u32,
+    // Kitty's frame number, counted from 1, of each frame of the animation which it keeps
+    frames: Vec<Option<u32>>,
+    // Number of frames of the image in kitty, and their size in bytes
+    count: u32,
+    bytes: usize,
+    budget: usize,
+    // The frame which is replaced by the frames beyond the budget
+    scratch: Option<u32>,
+    // Size of the placement in terminal cells
+    size: (u32, u32),
+}
+
+impl KittyAnimation {
+    pub fn new() -> Self {
+        Self {
+            id: next_image_id(),
+            frames: Vec::new(),
+            count: 0,
+            bytes: 0,
+            budget: ANIMATION_BUDGET,
+            scratch: None,
+            size: (0, 0),
+        }
+    }
+
+    // Show the frame at the index of the animation, sending it through the medium. `render`
+    // gives its image, and is only called when kitty does not have the frame yet. Returns the
+    // size of the placement.
+    pub fn draw(
+        &mut self,
+        stdout: &mut impl Write,
+        index: usize,
+        config: &Config,
+        medium: KittyMedium,
+        render: impl FnOnce() -> DynamicImage,
+    ) -> ViuResult<(u32, u32)> {
+        if let Some(frame) = self.frames.get(index).copied().flatten() {
+            self.show(stdout, frame)?;
+            stdout.flush()?;
+            return Ok(self.size);
+        }
+
+        let rgba = render().to_rgba8();
+        let (width, height) = rgba.dimensions();
+        // q=2 suppresses kitty's responses, which would otherwise end up in the input
+        if self.count == 0 {
+            self.size = fit_to_native(width, height, config);
+            adjust_offset(stdout, config)?;
+            let control = format!(
+                "a=T,i={},q=2,f=32,s={},v={},c={},r={}",
+                self.id, width, height, self.size.0, self.size.1
+            );
+            transmit(stdout, &control, &rgba, medium)?;
+            // The frames are selected as they are due, kitty does not play them by itself
+            write_graphics(
+                stdout,
+                format_args!("\x1b_Ga=a,i={},s=1,q=2\x1b\\", self.id),
+            )?;
+            writeln!(stdout)?;
+            self.count = 1;
+            self.bytes = rgba.len();
+            self.keep(index, 1);
+        } else {
+            let kept = self.bytes + rgba.len() <= self.budget;
+            // Frames beyond the budget replace the scratch frame, once there is one
+            let replaced = self.scratch.filter(|_| !kept);
+            let edit = replaced.map_or(String::new(), |frame| format!(",r={}", frame));
+            // X=1 replaces the pixels of the frame instead of blending over them
+            let control = format!(
+                "a=f,i={}{},q=2,X=1,f=32,s={},v={}",
+                self.id, edit, width, height
+            );
+            transmit(stdout, &control, &rgba, medium)?;
+            let frame = replaced.unwrap_or_else(|| {
+                self.count += 1;
+                self.count
+            });
+            if kept {
+                self.bytes += rgba.len();
+                self.keep(index, frame);
+            } else {
+                self.scratch = Some(frame);
+            }
+            self.show(stdout, frame)?;
+        }
+        stdout.flush()?;
+        Ok(self.size)
+    }
+
+    fn keep(&mut self, index: usize, frame: u32) {
+        if self.frames.len() <= index {
+            self.frames.resize(index + 1, None);
+        }
+        self.frames[index] = Some(frame);
+    }
+
+    // Make the frame the current one of the image
+    fn show(&self, stdout: &mut impl Write, frame: u32) -> ViuResult {
+        write_graphics(
+            stdout,
+            format_args!("\x1b_Ga=a,i={},c={},q=2\x1b\\", self.id, frame),
+        )?;
+        Ok(())
+    }
+}
+
+/// An image stored by the terminal through the Kitty graphics protocol, which can be displayed
+/// any number of times without sending it again. Useful for thumbnails which are shown again
+/// after scrolling, or moved around the screen.
+///
+/// Each handle has a single placement on screen: placing the image again moves it.
+/// ## Example
+/// ```no_run
+/// use viuer::{Config, ImageHandle};
+///
+/// let mut stdout = std::io::stdout();
+/// let img = image::open("img.jpg").expect("Could not decode file.");
+/// let conf = Config::default();
+/// let handle = ImageHandle::transmit(&mut stdout, &img, &conf).expect("Could not send the image.");
+///
+/// for x in [0, 20, 40] {
+///     let conf = Config { x, width: Some(20), ..Default::default() };
+///     handle.place(&mut stdout, &conf).expect("Could not place the image.");
+/// }
+/// handle.delete(&mut stdout).expect("Could not delete the image.");
+/// ```
+#[derive(Debug, PartialEq, Eq)]
+pub struct ImageHandle {
+    id: u32,
+    width: u32,
+    height: u32,
+}
+
+impl ImageHandle {
+    /// Send the image to the terminal without displaying it, through the medium from
+    /// [Config::kitty_medium]. Fails like the Kitty printer with the config when the terminal
+    /// does not support the protocol.
+    pub fn transmit(
+        stdout: &mut impl Write,
+        img: &image::DynamicImage,
+        config: &Config,
+    ) -> ViuResult<Self> {
+        // Kitty is detected like for printing, also when it was only confirmed by a query
+        let medium = medium(config)?;
+
+        let rgba = img.to_rgba8();
+        let handle = Self {
+            id: next_image_id(),
+            width: rgba.width(),
+            height: rgba.height(),
+        };
+        let control = format!(
+            "a=t,i={},q=2,f=32,s={},v={}",
+            handle.id, handle.width, handle.height
+        );
+        transmit(stdout, &control, &rgba, medium)?;
+        stdout.flush()?;
+        Ok(handle)
+    }
+
+    /// The id of the image in the terminal.
+    pub fn id(&self) -> u32 {
+        self.id
+    }
+
+    /// Display the image at the offset and in the size from the config, replacing the previous
+    /// placement. Transformations from the config are not applied, as the image was already
+    /// sent. Returns the dimensions of the image in terminal cells.
+    pub fn place(&self, stdout: &mut impl Write, config: &Config) -> ViuResult<(u32, u32)> {
+        let aligned;
+        let config = if needs_alignment(config) {
+            aligned = align_cells(fit_to_native(self.width, self.height, config), config);
+            &aligned
+        } else {
+            config
+        };
+        adjust_offset(stdout, config)?;
+
+        let (w, h) = fit_to_native(self.width, self.height, config);
+        write_graphics(
+            stdout,
+            format_args!("\x1b_Ga=p,i={},p=1,q=2,c={},r={}\x1b\\", self.id, w, h),
+        )?;
+        writeln!(stdout)?;
+        stdout.flush()?;
+        Ok((w, h))
+    }
+
+    /// Remove the image from the screen, while the terminal keeps it for later placements.
+    pub fn hide(&self, stdout: &mut impl Write) -> ViuResult {
+        write_graphics(
+            stdout,
+            format_args!("\x1b_Ga=d,d=i,i={},q=2\x1b\\", self.id),
+        )?;
+        stdout.flush()?;
+        Ok(())
+    }
+
+    /// Remove the image from the screen and free its data in the terminal.
+    pub fn delete(self, stdout: &mut impl Write) -> ViuResult {
+        write_graphics(
+            stdout,
+            format_args!("\x1b_Ga=d,d=I,i={},q=2\x1b\\", self.id),
+        )?;
+        stdout.flush()?;
+        Ok(())
+    }
+}
+
+// Send the pixels with the given control data through the medium, compressed when they go
+// through escape codes
+fn transmit(
     stdout: &mut impl Write,
-    img: &image::DynamicImage,
-    config: &Config,
-) -> ViuResult<(u32, u32)> {
-    let rgba = img.to_rgba8();
-    let raw = rgba.as_raw();
-    let encoded = general_purpose::STANDARD.encode(raw);
-    let mut iter = encoded.chars().peekable();
-
-    adjust_offset(stdout, config)?;
-
-    let (w, h) = find_best_fit(img, config.width, config.height);
-
-    let first_chunk: String = iter.by_ref().take(4096).collect();
-
-    // write the first chunk, which describes the image
-    write!(
-        stdout,
-        "\x1b_Gf=32,a=T,t=d,s={},v={},c={},r={},m=1;{}\x1b\\",
-        img.width(),
-        img.height(),
-        w,
-        h,
-        first_chunk
-    )?;
-
-    // write all the chunks, each containing 4096 bytes of data
-    while iter.peek().is_some() {
-        let chunk: String = iter.by_ref().take(4096).collect();
-        let m = if iter.peek().is_some() { 1 } else { 0 };
-        write!(stdout, "\x1b_Gm={};{}\x1b\\", m, chunk)?;
+    control: &str,
+    rgba: &image::RgbaImage,
+    medium: KittyMedium,
+) -> ViuResult {
+    if medium == KittyMedium::Direct {
+        let mut encoder = ZlibEncoder::new(Vec::new(), Compression::default());
+        encoder.write_all(rgba.as_raw())?;
+        write_chunked(stdout, &format!("{},o=z", control), &encoder.finish()?)?;
+    } else {
+        let (t, name) = store(rgba.as_raw(), medium)?;
+        write_graphics(
+            stdout,
+            format_args!(
+                "\x1b_G{},t={};{}\x1b\\",
+                control,
+                t,
+                base64(name.as_bytes())
+            ),
+        )?;
     }
-    writeln!(stdout)?;
-    stdout.flush()?;
-    Ok((w, h))
+    Ok(())
+}
+
+// Send data through escape codes, base64 encoded and split in chunks of at most 4096 bytes.
+// Only the first chunk carries the control data. The chunks are encoded while they are
+// written, without holding the whole encoded data in memory.
+fn write_chunked(stdout: &mut impl Write, control: &str, data: &[u8]) -> ViuResult {
+    let mut chunks = data.chunks(RAW_CHUNK_SIZE).enumerate().peekable();
+    while let Some((i, chunk)) = chunks.next() {
+        let m = if chunks.peek().is_some() { 1 } else { 0 };
+        if i == 0 {
+            write_graphics(
+                stdout,
+                format_args!("\x1b_G{},t=d,m={};{}\x1b\\", control, m, base64(chunk)),
+            )?;
+        } else {
+            write_graphics(
+                stdout,
+                format_args!("\x1b_Gm={};{}\x1b\\", m, base64(chunk)),
+            )?;
+        }
+    }
+    Ok(())
+}
+
+// Store the data for the terminal to read it, in shared memory or a temp file. Returns the
+// transmission medium for the control data, and the name of the object or file.
+fn store(data: &[u8], medium: KittyMedium) -> ViuResult<(&'static str, String)> {
+    match medium {
+        #[cfg(unix)]
+        KittyMedium::SharedMemory => Ok(("s", store_in_shm(data)?)),
+        _ => {
+            let path = store_in_tmp_file(data)?;
+            let name = path
+                .to_str()
+                .ok_or_else(|| ViuError::Io(Error::other("Could not convert path to &str")))?;
+            Ok(("t", name.to_owned()))
+        }
+    }
+}
+
+// Create a POSIX shared memory object holding the byte slice. Kitty unlinks it after reading
+// it, like the temp files. Returns the name of the object.
+#[cfg(unix)]
+fn store_in_shm(buf: &[u8]) -> ViuResult<String> {
+    static NEXT_SHM_ID: AtomicU32 = AtomicU32::new(0);
+    let name = format!(
+        "/{}{}.{}",
+        TEMP_FILE_PREFIX.trim_start_matches('.'),
+        std::process::id(),
+        NEXT_SHM_ID.fetch_add(1, Ordering::Relaxed)
+    );
+    let c_name = std::ffi::CString::new(name.as_str())
+        .map_err(|err| ViuError::Io(Error::new(ErrorKind::InvalidInput, err)))?;
+
+    let fd = unsafe {
+        libc::shm_open(
+            c_name.as_ptr(),
+            libc::O_CREAT | libc::O_EXCL | libc::O_RDWR,
+            0o600,
+        )
+    };
+    if fd < 0 {
+        return Err(Error::last_os_error().into());
+    }
+    let result = copy_to_shm(fd, buf);
+    unsafe { libc::close(fd) };
+    if let Err(err) = result {
+        unsafe { libc::shm_unlink(c_name.as_ptr()) };
+        return Err(err.into());
+    }
+    Ok(name)
+}
+
+// Resize the shared memory object to the slice and copy it in through a mapping, as writing
+// to it directly is not supported on every system
+#[cfg(unix)]
+fn copy_to_shm(fd: libc::c_int, buf: &[u8]) -> std::io::Result<()> {
+    if unsafe { libc::ftruncate(fd, buf.len() as libc::off_t) } != 0 {
+        return Err(Error::last_os_error());
+    }
+    if buf.is_empty() {
+        return Ok(());
+    }
+
+    let ptr = unsafe {
+        libc::mmap(
+            std::ptr::null_mut(),
+            buf.len(),
+            libc::PROT_WRITE,
+            libc::MAP_SHARED,
+            fd,
+            0,
+        )
+    };
+    if ptr == libc::MAP_FAILED {
+        return Err(Error::last_os_error());
+    }
+    unsafe {
+        std::ptr::copy_nonoverlapping(buf.as_ptr(), ptr.cast::<u8>(), buf.len());
+        libc::munmap(ptr, buf.len());
+    }
+    Ok(())
 }
 
 // Create a file in temporary dir and write the byte slice to it.
@@ -212,6 +652,7 @@ mod tests {
 
     #[test]
     fn test_print_local() {
+        crate::utils::clear_terminal_env();
         let img = DynamicImage::ImageRgba8(image::RgbaImage::new(40, 25));
         let config = Config {
             x: 4,
@@ -220,31 +661,197 @@ mod tests {
         };
 
         let mut vec = Vec::new();
-        assert_eq!(print_local(&mut vec, &img, &config).unwrap(), (40, 13));
+        assert_eq!(
+            print_image(&mut vec, &img, &config, KittyMedium::TempFile).unwrap(),
+            (40, 13)
+        );
         let result = std::str::from_utf8(&vec).unwrap();
 
         assert!(result.starts_with("\x1b[4;5H\x1b_Gf=32,s=40,v=25,c=40,r=13,a=T,t=t;"));
         assert!(result.ends_with("\x1b\\\n"));
     }
 
+    #[test]
+    fn test_write_chunked() {
+        crate::utils::clear_terminal_env();
+        let mut vec = Vec::new();
+        write_chunked(&mut vec, "a=f,i=3", &[0; 3]).unwrap();
+        assert_eq!(
+            std::str::from_utf8(&vec).unwrap(),
+            "\x1b_Ga=f,i=3,t=d,m=0;AAAA\x1b\\"
+        );
+
+        let mut vec = Vec::new();
+        write_chunked(&mut vec, "a=T", &[0; RAW_CHUNK_SIZE + 3]).unwrap();
+        let result = std::str::from_utf8(&vec).unwrap();
+        assert!(result.starts_with("\x1b_Ga=T,t=d,m=1;AAAA"));
+        assert!(result.ends_with("\x1b\\\x1b_Gm=0;AAAA\x1b\\"));
+    }
+
+    #[test]
+    fn test_image_handle() {
+        crate::utils::clear_terminal_env();
+        let handle = ImageHandle {
+            id: 7,
+            width: 40,
+            height: 25,
+        };
+        let config = Config {
+            x: 4,
+            y: 3,
+            ..Default::default()
+        };
+
+        let mut vec = Vec::new();
+        assert_eq!(handle.place(&mut vec, &config).unwrap(), (40, 13));
+        handle.hide(&mut vec).unwrap();
+        handle.delete(&mut vec).unwrap();
+        assert_eq!(
+            std::str::from_utf8(&vec).unwrap(),
+            "\x1b[4;5H\x1b_Ga=p,i=7,p=1,q=2,c=40,r=13\x1b\\\n\
+             \x1b_Ga=d,d=i,i=7,q=2\x1b\\\x1b_Ga=d,d=I,i=7,q=2\x1b\\"
+        );
+    }
+
+    #[test]
+    fn test_animation() {
+        crate::utils::clear_terminal_env();
+        let frame = |red| {
+            DynamicImage::ImageRgba8(image::RgbaImage::from_pixel(
+                1,
+                1,
+                image::Rgba([red, 0, 0, 255]),
+            ))
+        };
+        let mut animation = KittyAnimation::new();
+        // Room for two frames of 4 bytes
+        animation.budget = 8;
+        let id = animation.id;
+        let config = Config {
+            absolute_offset: false,
+            ..Default::default()
+        };
+        let mut draw = |index, red| {
+            let mut vec = Vec::new();
+            animation
+                .draw(&mut vec, index, &config, KittyMedium::Direct, || frame(red))
+                .unwrap();
+            String::from_utf8(vec).unwrap()
+        };
+
+        let result = draw(0, 0);
+        assert!(result.starts_with(&format!("\x1b_Ga=T,i={},q=2,f=32,s=1,v=1,", id)));
+        assert!(result.contains(",o=z,t=d,m=0;"));
+        assert!(result.ends_with(&format!("\x1b_Ga=a,i={},s=1,q=2\x1b\\\n", id)));
+
+        let result = draw(1, 1);
+        assert!(result.starts_with(&format!("\x1b_Ga=f,i={},q=2,X=1,f=32,s=1,v=1,o=z,", id)));
+        assert!(result.ends_with(&format!("\x1b_Ga=a,i={},c=2,q=2\x1b\\", id)));
+
+        // Kept frames are only selected again
+        assert_eq!(draw(0, 0), format!("\x1b_Ga=a,i={},c=1,q=2\x1b\\", id));
+
+        // Beyond the budget, a third frame is added and then replaced
+        let result = draw(2, 2);
+        assert!(result.starts_with(&format!("\x1b_Ga=f,i={},q=2,", id)));
+        assert!(result.ends_with(&format!("\x1b_Ga=a,i={},c=3,q=2\x1b\\", id)));
+        let result = draw(3, 3);
+        assert!(result.starts_with(&format!("\x1b_Ga=f,i={},r=3,q=2,", id)));
+        assert!(result.ends_with(&format!("\x1b_Ga=a,i={},c=3,q=2\x1b\\", id)));
+        let result = draw(2, 2);
+        assert!(result.starts_with(&format!("\x1b_Ga=f,i={},r=3,q=2,", id)));
+
+        // The frames go through the medium from the config
+        let mut animation = KittyAnimation::new();
+        let mut vec = Vec::new();
+        animation
+            .draw(&mut vec, 0, &config, KittyMedium::TempFile, || frame(0))
+            .unwrap();
+        let result = std::str::from_utf8(&vec).unwrap();
+        let start = result.find(",t=t;").unwrap() + 5;
+        let end = start + result[start..].find('\x1b').unwrap();
+        let path = general_purpose::STANDARD
+            .decode(&result[start..end])
+            .unwrap();
+        let path = String::from_utf8(path).unwrap();
+        assert_eq!(std::fs::read(&path).unwrap(), [0, 0, 0, 255]);
+        std::fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn test_next_image_id() {
+        let (a, b) = (next_image_id(), next_image_id());
+        assert_ne!(a, b);
+        assert_eq!(a >> 16, b >> 16);
+    }
+
     #[test]
     fn test_print_remote() {
+        crate::utils::clear_terminal_env();
         let mut img = DynamicImage::ImageRgba8(image::RgbaImage::new(1, 2));
         img.put_pixel(0, 1, image::Rgba([2, 4, 6, 8]));
 
         let config = Config {
             x: 2,
             y: 5,
+            kitty_format: KittyFormat::Rgba,
             ..Default::default()
         };
 
         let mut vec = Vec::new();
-        assert_eq!(print_remote(&mut vec, &img, &config).unwrap(), (1, 1));
+        assert_eq!(
+            print_image(&mut vec, &img, &config, KittyMedium::Direct).unwrap(),
+            (1, 1)
+        );
         let result = std::str::from_utf8(&vec).unwrap();
 
         assert_eq!(
             result,
-            "\x1b[6;3H\x1b_Gf=32,a=T,t=d,s=1,v=2,c=1,r=1,m=1;AAAAAAIEBgg=\x1b\\\n"
+            "\x1b[6;3H\x1b_Gf=32,s=1,v=2,c=1,r=1,a=T,t=d,m=0;AAAAAAIEBgg=\x1b\\\n"
         );
     }
+
+    #[test]
+    fn test_print_remote_formats() {
+        crate::utils::clear_terminal_env();
+        let img = DynamicImage::ImageRgba8(image::RgbaImage::new(64, 64));
+        let config = Config {
+            absolute_offset: false,
+            width: Some(8),
+            ..Default::default()
+        };
+
+        // Compressed by default
+        let mut vec = Vec::new();
+        print_image(&mut vec, &img, &config, KittyMedium::Direct).unwrap();
+        let result = std::str::from_utf8(&vec).unwrap();
+        assert!(result.starts_with("\x1b_Gf=32,o=z,s=64,v=64,c=8,r=4,a=T,t=d,m=0;"));
+        assert!(result.len() < 64 * 64 * 4);
+
+        let config = Config {
+            kitty_format: KittyFormat::Png,
+            ..config
+        };
+        let mut vec = Vec::new();
+        print_image(&mut vec, &img, &config, KittyMedium::Direct).unwrap();
+        let result = std::str::from_utf8(&vec).unwrap();
+        assert!(result.starts_with("\x1b_Gf=100,c=8,r=4,a=T,t=d,m=0;iVBORw0KGgo"));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_store_in_shm() {
+        let name = store_in_shm(&[1, 2, 3]).unwrap();
+        let c_name = std::ffi::CString::new(name).unwrap();
+        let mut buf = [0u8; 4];
+        let read = unsafe {
+            let fd = libc::shm_open(c_name.as_ptr(), libc::O_RDONLY, 0);
+            assert!(fd >= 0);
+            let read = libc::read(fd, buf.as_mut_ptr().cast(), buf.len());
+            libc::close(fd);
+            libc::shm_unlink(c_name.as_ptr());
+            read
+        };
+        assert_eq!(&buf[..read as usize], [1, 2, 3]);
+    }
 }
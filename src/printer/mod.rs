@@ -1,16 +1,75 @@
 use crate::config::Config;
 use crate::error::{ViuError, ViuResult};
-use crate::utils::terminal_size;
+use crate::utils::{self, terminal_size};
+use crate::{adjust, transform, vision};
 use crossterm::cursor::{MoveRight, MoveTo, MoveToPreviousLine};
 use crossterm::execute;
-use image::{DynamicImage, GenericImageView};
-use std::{io::Write, path::Path};
+use image::codecs::jpeg::JpegDecoder;
+use image::error::{ImageError, ParameterError, ParameterErrorKind};
+use image::{
+    imageops::FilterType, DynamicImage, GenericImageView, ImageBuffer, ImageDecoder, ImageFormat,
+    Luma, Pixel, Rgb, Rgba, RgbaImage,
+};
+use std::borrow::Cow;
+use std::io::{BufRead, Cursor, Seek, Write};
+use std::path::Path;
+use std::sync::Arc;
+
+// Size of a terminal cell in pixels assumed when the actual one is unknown, large enough for
+// most fonts
+const MAX_CELL_PIXEL_SIZE: (f32, f32) = (16.0, 32.0);
+
+// Pixels per cell kept when a view is scaled down for the block printers, twice the 2x4
+// pixels of braille in each direction
+const VIEW_CELL_PIXELS: (u32, u32) = (4, 8);
+
+pub(crate) mod backend;
+
+mod background;
+pub use background::BackgroundPrinter;
 
 mod block;
 pub use block::BlockPrinter;
 
-mod kitty;
-pub use kitty::{get_kitty_support, KittyPrinter, KittySupport};
+mod braille;
+pub use braille::BraillePrinter;
+
+pub(crate) mod cell;
+
+pub(crate) mod dither;
+
+mod edges;
+pub use edges::EdgePrinter;
+
+pub(crate) mod external;
+
+pub(crate) mod gamma;
+
+#[cfg(feature = "color-management")]
+mod icc;
+
+mod ifd;
+
+mod pages;
+pub use pages::image_pages;
+
+mod placeholder;
+
+mod quadrant;
+pub use quadrant::QuadrantPrinter;
+
+mod raw;
+
+mod sextant;
+pub use sextant::SextantPrinter;
+
+mod shade;
+pub use shade::ShadePrinter;
+
+pub(crate) mod kitty;
+pub use kitty::{
+    get_kitty_support, ImageHandle, KittyFormat, KittyMedium, KittyPrinter, KittySupport,
+};
 
 #[cfg(feature = "sixel")]
 mod sixel;
@@ -21,37 +80,326 @@ mod iterm;
 pub use iterm::iTermPrinter;
 pub use iterm::is_iterm_supported;
 
+/// A way of displaying images in the terminal.
+///
+/// Implement this trait to add a custom backend, and print with it through [crate::print_with].
 pub trait Printer {
-    // Print the given image in the terminal while respecting the options in the config struct.
-    // Return the dimensions of the printed image in **terminal cells**.
+    /// Print the given image to `stdout` while respecting the options in the config struct.
+    /// Return the dimensions of the printed image in **terminal cells**.
     fn print(
         &self,
         stdout: &mut impl Write,
         img: &DynamicImage,
         config: &Config,
     ) -> ViuResult<(u32, u32)>;
+
+    /// Read and decode the given file and print it. The default implementation decodes the
+    /// image with the [`image`] crate and passes it to [Printer::print].
     fn print_from_file<P: AsRef<Path>>(
         &self,
         stdout: &mut impl Write,
         filename: P,
         config: &Config,
     ) -> ViuResult<(u32, u32)> {
-        let img = image::io::Reader::open(filename)?
-            .with_guessed_format()?
-            .decode()?;
+        let img = open_image(&filename, config)?;
         self.print(stdout, &img, config)
     }
 }
 
+/// The printers that can be used to display an image.
 #[allow(non_camel_case_types)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum PrinterType {
+    /// Unicode half blocks, available everywhere.
     Block,
+    /// Kitty graphics protocol.
     Kitty,
+    /// iTerm inline images protocol.
     iTerm,
+    /// Sixel graphics.
     #[cfg(feature = "sixel")]
     Sixel,
 }
 
+/// The characters used by the block printer to represent pixels.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum BlockStyle {
+    /// Upper and lower half blocks (▀ and ▄), 1x2 pixels per cell.
+    HalfBlock,
+    /// Quadrant characters (▘, ▚, ▙, ...), 2x2 pixels per cell.
+    Quadrant,
+    /// Braille characters (⠁, ⡇, ⣿, ...), 2x4 pixels per cell. Only the pixels brighter than
+    /// the image's average are drawn, so this works best for line art and plots.
+    Braille,
+    /// Sextant characters (🬀, 🬋, 🬻, ...), 2x3 pixels per cell.
+    /// Requires a font supporting the Symbols for Legacy Computing block.
+    Sextant,
+    /// Only the edges of the image, found with the Sobel operator, drawn as line art with
+    /// braille characters, 2x4 pixels per cell. Keeps large photos readable at small sizes.
+    Edges,
+    /// Shade characters (░, ▒, ▓, █) in the color of the pixel, the denser the brighter it
+    /// is, 1x1 pixel per cell. For fonts which leave gaps between half blocks.
+    Shade,
+    /// Spaces with the color of the pixel as their background, 1x1 pixel per cell. For
+    /// fonts and terminals which draw half blocks with seams or misaligned.
+    Background,
+}
+
+/// When images are printed to stdout, see [Config::when].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum PrintWhen {
+    /// Always print, even when stdout is redirected.
+    Always,
+    /// Print with colors only when stdout is a terminal and the `NO_COLOR` environment
+    /// variable is not set. Otherwise the image is printed with shade characters (░, ▒, ▓
+    /// and █) and no escape sequences for colors.
+    Auto,
+    /// Never print.
+    Never,
+}
+
+/// The colors used when printing with blocks.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum ColorDepth {
+    /// 24-bit RGB colors.
+    TrueColor,
+    /// The xterm 256 color palette.
+    Ansi256,
+    /// The basic 16 color palette.
+    Ansi16,
+}
+
+/// The pattern drawn behind transparent pixels by the block printers, unless
+/// [Config::transparent] is set.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(default))]
+pub struct Checkerboard {
+    /// Color of the squares in the top left corner and every other square from there.
+    /// Defaults to `#666666`.
+    #[cfg_attr(feature = "serde", serde(with = "crate::serde_remote::rgb"))]
+    pub dark: Rgb<u8>,
+    /// Color of the remaining squares. Defaults to `#999999`.
+    #[cfg_attr(feature = "serde", serde(with = "crate::serde_remote::rgb"))]
+    pub light: Rgb<u8>,
+    /// Side length of a square, in pixels of the resized image. Defaults to 1.
+    pub size: u32,
+}
+
+impl Default for Checkerboard {
+    fn default() -> Self {
+        Self {
+            dark: Rgb([102, 102, 102]),
+            light: Rgb([153, 153, 153]),
+            size: 1,
+        }
+    }
+}
+
+/// How the image is sized to the configured width and height.
+/// A missing width or height means that the image is not constrained in that direction,
+/// apart from [SizeMode::Cover] and [SizeMode::Tile] which use the terminal's size instead.
+///
+/// New modes can be added in minor releases, so matches on it need a wildcard arm.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[non_exhaustive]
+pub enum SizeMode {
+    /// Fit within the width and height, preserving the aspect ratio.
+    Contain,
+    /// Fill the width and height, preserving the aspect ratio by cropping what overflows.
+    Cover,
+    /// Match the width and height when both are set, distorting the image if needed.
+    /// Behaves like [SizeMode::Contain] otherwise.
+    Stretch,
+    /// Ignore the width and height and print one image pixel per terminal column and
+    /// two per row.
+    Exact,
+    /// Repeat the image, at the size it has with [SizeMode::Exact], to fill the width and
+    /// height, e.g. for backgrounds and texture previews. Missing ones are taken from the
+    /// terminal's size, like with [SizeMode::Cover].
+    Tile,
+}
+
+/// How the image is sized, set through [Config::resize_policy] in place of [Config::width],
+/// [Config::height] and [Config::size_mode]. Sizes are in terminal cells, whose rows hold two
+/// pixels of the image each.
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum ResizePolicy {
+    /// Fit within the terminal, keeping a row free, while preserving the aspect ratio.
+    FitTerminal,
+    /// Fit within the number of columns, while preserving the aspect ratio.
+    FitWidth(u32),
+    /// Fit within the number of rows, while preserving the aspect ratio.
+    FitHeight(u32),
+    /// Fill exactly the number of columns and rows, distorting the image if needed.
+    Exact(u32, u32),
+    /// Scale the image by the factor, where 1.0 prints one pixel per column and two per row.
+    Scale(f32),
+}
+
+impl ResizePolicy {
+    // The policy which resize used to follow for the optional width and height
+    fn from_bounds(width: Option<u32>, height: Option<u32>) -> Self {
+        match (width, height) {
+            (None, None) => ResizePolicy::FitTerminal,
+            (Some(w), None) => ResizePolicy::FitWidth(w),
+            (None, Some(h)) => ResizePolicy::FitHeight(h),
+            (Some(w), Some(h)) => ResizePolicy::Exact(w, h),
+        }
+    }
+
+    // The width and height bounds of the policy, if it has any
+    pub(crate) fn bounds(self) -> (Option<u32>, Option<u32>) {
+        match self {
+            ResizePolicy::FitTerminal | ResizePolicy::Scale(_) => (None, None),
+            ResizePolicy::FitWidth(w) => (Some(w), None),
+            ResizePolicy::FitHeight(h) => (None, Some(h)),
+            ResizePolicy::Exact(w, h) => (Some(w), Some(h)),
+        }
+    }
+
+    // The size mode which sizes the image to the bounds like the policy
+    pub(crate) fn size_mode(self) -> SizeMode {
+        match self {
+            ResizePolicy::FitTerminal | ResizePolicy::FitWidth(_) | ResizePolicy::FitHeight(_) => {
+                SizeMode::Contain
+            }
+            ResizePolicy::Exact(..) => SizeMode::Stretch,
+            ResizePolicy::Scale(_) => SizeMode::Exact,
+        }
+    }
+
+    // Same policy with the bounds made smaller by the given number of columns and rows,
+    // but not below one cell
+    pub(crate) fn shrink(self, columns: u32, rows: u32) -> Self {
+        let shrink = |size: u32, by: u32| size.saturating_sub(by).max(1);
+        match self {
+            ResizePolicy::FitWidth(w) => ResizePolicy::FitWidth(shrink(w, columns)),
+            ResizePolicy::FitHeight(h) => ResizePolicy::FitHeight(shrink(h, rows)),
+            ResizePolicy::Exact(w, h) => ResizePolicy::Exact(shrink(w, columns), shrink(h, rows)),
+            policy => policy,
+        }
+    }
+}
+
+/// Horizontal position of an image which is narrower than the space available for it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum HAlign {
+    /// Align to the left edge.
+    Left,
+    /// Center horizontally.
+    Center,
+    /// Align to the right edge.
+    Right,
+}
+
+/// Vertical position of an image which is shorter than the space available for it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum VAlign {
+    /// Align to the top edge.
+    Top,
+    /// Center vertically.
+    Middle,
+    /// Align to the bottom edge.
+    Bottom,
+}
+
+/// Dithering applied when the colors of the image have to be reduced to a palette,
+/// i.e. when printing with blocks and a [ColorDepth] other than true color, or with Sixel.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Dither {
+    /// Use the closest color for every pixel.
+    None,
+    /// Ordered dithering with a 4x4 Bayer matrix. Produces a regular pattern, which
+    /// stays the same between the frames of an animation.
+    Ordered,
+    /// Floyd-Steinberg error diffusion.
+    FloydSteinberg,
+    /// Atkinson error diffusion. Keeps more contrast than Floyd-Steinberg.
+    Atkinson,
+}
+
+/// Layout of the pixels in a raw buffer, see [crate::print_raw]. Pixels are stored row by row
+/// from the top left corner, without padding between the rows.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PixelFormat {
+    /// One byte of luminance per pixel.
+    Luma8,
+    /// Red, green and blue bytes.
+    Rgb8,
+    /// Red, green, blue and alpha bytes.
+    Rgba8,
+    /// Blue, green, red and alpha bytes, as read back from many GPUs.
+    Bgra8,
+}
+
+impl PixelFormat {
+    // Number of bytes per pixel
+    fn bytes(self) -> usize {
+        match self {
+            PixelFormat::Luma8 => 1,
+            PixelFormat::Rgb8 => 3,
+            PixelFormat::Rgba8 | PixelFormat::Bgra8 => 4,
+        }
+    }
+}
+
+/// Function run over the image right before it is printed, set through [Config::filter_fn].
+pub type FilterFn = Arc<dyn Fn(&mut RgbaImage) + Send + Sync>;
+
+/// Information about a printed image.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PrintedInfo {
+    /// Width of the printed image in terminal cells.
+    pub width: u32,
+    /// Height of the printed image in terminal cells.
+    pub height: u32,
+    /// The printer that was used.
+    pub printer: PrinterType,
+    /// Number of bytes written to the terminal, escape sequences included.
+    pub bytes_written: usize,
+}
+
+impl PrintedInfo {
+    // Info for an image which was skipped, see Config::when
+    pub(crate) fn nothing() -> Self {
+        Self {
+            width: 0,
+            height: 0,
+            printer: PrinterType::Block,
+            bytes_written: 0,
+        }
+    }
+}
+
+impl PrinterType {
+    // The size in terminal cells of an image of the given size in pixels, printed with this
+    // printer. The graphics protocols do not enlarge images beyond their full resolution.
+    pub(crate) fn fit(&self, img_size: (u32, u32), config: &Config) -> (u32, u32) {
+        match self {
+            PrinterType::Block => fit_to_config(img_size.0, img_size.1, config),
+            _ => fit_to_native(img_size.0, img_size.1, config),
+        }
+    }
+
+    // Same as align, for an image printed with this printer
+    pub(crate) fn align(&self, img_size: (u32, u32), config: &Config) -> Config {
+        if !needs_alignment(config) {
+            return config.clone();
+        }
+        align_cells(self.fit(img_size, config), config)
+    }
+}
+
 impl Printer for PrinterType {
     fn print(
         &self,
@@ -59,8 +407,23 @@ impl Printer for PrinterType {
         img: &DynamicImage,
         config: &Config,
     ) -> ViuResult<(u32, u32)> {
+        let mut img = transform::apply(img, config);
+        // The terminal resizes the images sent with the graphics protocols
+        if matches!(self, PrinterType::Kitty | PrinterType::iTerm) {
+            img = post_process(img, config);
+        }
+        let img = &img;
+        let config = &self.align(img.dimensions(), config);
         match self {
-            PrinterType::Block => BlockPrinter.print(stdout, img, config),
+            PrinterType::Block => match config.block_style {
+                BlockStyle::HalfBlock => BlockPrinter.print(stdout, img, config),
+                BlockStyle::Quadrant => QuadrantPrinter.print(stdout, img, config),
+                BlockStyle::Braille => BraillePrinter.print(stdout, img, config),
+                BlockStyle::Sextant => SextantPrinter.print(stdout, img, config),
+                BlockStyle::Edges => EdgePrinter.print(stdout, img, config),
+                BlockStyle::Shade => ShadePrinter.print(stdout, img, config),
+                BlockStyle::Background => BackgroundPrinter.print(stdout, img, config),
+            },
             PrinterType::Kitty => KittyPrinter.print(stdout, img, config),
             PrinterType::iTerm => iTermPrinter.print(stdout, img, config),
             #[cfg(feature = "sixel")]
@@ -74,8 +437,41 @@ impl Printer for PrinterType {
         filename: P,
         config: &Config,
     ) -> ViuResult<(u32, u32)> {
+        // Photos often store their rotation in the EXIF metadata, which has to be applied
+        // before the transformations from the config
+        #[cfg(feature = "exif")]
+        if let Some(orientation) = transform::exif_orientation(&filename) {
+            let img = open_image(&filename, config)?;
+            return self.print(stdout, &transform::orient(img, orientation), config);
+        }
+
+        // The image has to be decoded here, so that the transformations are not skipped
+        // by printers which send the file's content directly
+        if transform::is_needed(config) {
+            let img = open_image(&filename, config)?;
+            return self.print(stdout, &img, config);
+        }
+
+        let aligned;
+        let config = if needs_alignment(config) {
+            aligned = self.align(image::image_dimensions(&filename)?, config);
+            &aligned
+        } else {
+            config
+        };
+
         match self {
-            PrinterType::Block => BlockPrinter.print_from_file(stdout, filename, config),
+            PrinterType::Block => match config.block_style {
+                BlockStyle::HalfBlock => BlockPrinter.print_from_file(stdout, filename, config),
+                BlockStyle::Quadrant => QuadrantPrinter.print_from_file(stdout, filename, config),
+                BlockStyle::Braille => BraillePrinter.print_from_file(stdout, filename, config),
+                BlockStyle::Sextant => SextantPrinter.print_from_file(stdout, filename, config),
+                BlockStyle::Edges => EdgePrinter.print_from_file(stdout, filename, config),
+                BlockStyle::Shade => ShadePrinter.print_from_file(stdout, filename, config),
+                BlockStyle::Background => {
+                    BackgroundPrinter.print_from_file(stdout, filename, config)
+                }
+            },
             PrinterType::Kitty => KittyPrinter.print_from_file(stdout, filename, config),
             PrinterType::iTerm => iTermPrinter.print_from_file(stdout, filename, config),
             #[cfg(feature = "sixel")]
@@ -84,21 +480,554 @@ impl Printer for PrinterType {
     }
 }
 
+pub(crate) fn needs_alignment(config: &Config) -> bool {
+    config.halign != HAlign::Left || config.valign != VAlign::Top
+}
+
+// Shift the offsets in the config so that an image of the given size in pixels is aligned
+// within the available space: the configured width and height, or the rest of the terminal
+// when they are not set. The alignment in the returned config is reset to the top left,
+// so that applying it again has no effect.
+pub(crate) fn align(img_size: (u32, u32), config: &Config) -> Config {
+    if !needs_alignment(config) {
+        return config.clone();
+    }
+    align_cells(fit_to_config(img_size.0, img_size.1, config), config)
+}
+
+// Same as align, for something of the given size in terminal cells
+pub(crate) fn align_cells((w, h): (u32, u32), config: &Config) -> Config {
+    let (term_w, term_h) = terminal_size();
+    let (width, height) = config.bounds();
+    let bound_w = width.unwrap_or_else(|| (term_w as u32).saturating_sub(config.x as u32));
+    let bound_h = height.unwrap_or_else(|| (term_h as u32).saturating_sub(config.y.max(0) as u32));
+
+    let pad_x = bound_w.saturating_sub(w);
+    let pad_y = bound_h.saturating_sub(h);
+    let pad_x = match config.halign {
+        HAlign::Left => 0,
+        HAlign::Center => pad_x / 2,
+        HAlign::Right => pad_x,
+    };
+    let pad_y = match config.valign {
+        VAlign::Top => 0,
+        VAlign::Middle => pad_y / 2,
+        VAlign::Bottom => pad_y,
+    };
+
+    Config {
+        x: config.x.saturating_add(pad_x as u16),
+        y: config.y.saturating_add(pad_y as i16),
+        halign: HAlign::Left,
+        valign: VAlign::Top,
+        ..config.clone()
+    }
+}
+
+// Decode an image file. Large JPEGs are decoded at a reduced scale when they are printed
+// much smaller than their size, which is a lot faster and needs less memory than decoding
+// them fully and resizing afterwards.
+pub(crate) fn open_image<P: AsRef<Path>>(filename: P, config: &Config) -> ViuResult<DynamicImage> {
+    let img = decode_file(&filename, config)?;
+    #[cfg(feature = "color-management")]
+    let img = icc::convert_to_srgb(
+        img,
+        std::io::BufReader::new(std::fs::File::open(&filename)?),
+    );
+    Ok(img)
+}
+
+// Whether the file is in a format which viuer decodes instead of the image crate. The size
+// of these images is only known once they are decoded.
+pub(crate) fn needs_own_decoder<P: AsRef<Path>>(filename: P) -> ViuResult<bool> {
+    Ok(external::detect(&filename)?.is_some() || raw::is_raw(&filename)?)
+}
+
+// Decode an image file, see open_image
+fn decode_file<P: AsRef<Path>>(filename: P, config: &Config) -> ViuResult<DynamicImage> {
+    if let Some(format) = external::detect(&filename)? {
+        return external::decode(filename, format, config);
+    }
+    // Files without a preview are decoded as ordinary TIFF images
+    if raw::is_raw(&filename)? {
+        if let Some(img) = raw::decode(&filename, config)? {
+            return Ok(img);
+        }
+    }
+    let mut reader = image::io::Reader::open(filename)?.with_guessed_format()?;
+    reader.limits(config.limits.clone());
+    if let Some(page) = config.page {
+        return decode_page(reader, page, config);
+    }
+    if reader.format() != Some(ImageFormat::Jpeg) || full_size_needed(config) {
+        return Ok(reader.decode()?);
+    }
+
+    let mut decoder = JpegDecoder::new(reader.into_inner())?;
+    let size = scaled_decoding_size(config);
+    decoder.scale(size, size)?;
+    // Same checks as the ones done by the reader, for the scaled image
+    let mut limits = config.limits.clone();
+    limits.reserve(decoder.total_bytes())?;
+    decoder.set_limits(limits)?;
+    Ok(DynamicImage::from_decoder(decoder)?)
+}
+
+// Whether the image has to be decoded at its full size rather than scaled down to the size it
+// is printed at. Cropping and overlays work with the original coordinates, and covering the
+// bounds may need more pixels than the bounds in one direction.
+pub(crate) fn full_size_needed(config: &Config) -> bool {
+    config.crop.is_some()
+        || !config.overlays.is_empty()
+        || matches!(config.sizing(), SizeMode::Cover | SizeMode::Tile)
+}
+
+// Decode the page of a multi-page image, see Config::page
+fn decode_page<R: BufRead + Seek>(
+    reader: image::io::Reader<R>,
+    page: u32,
+    config: &Config,
+) -> ViuResult<DynamicImage> {
+    let format = reader.format();
+    let buf = pages::select(reader.into_inner(), format, page)?;
+    let mut reader = image::io::Reader::new(Cursor::new(buf)).with_guessed_format()?;
+    reader.limits(config.limits.clone());
+    Ok(reader.decode()?)
+}
+
+// Decode an image from memory, within the limits from the config
+pub(crate) fn decode_from_memory(buf: &[u8], config: &Config) -> ViuResult<DynamicImage> {
+    decode_from_reader(Cursor::new(buf), config)
+}
+
+// Decode an image from a reader, guessing its format from the content, within the limits
+// from the config
+pub(crate) fn decode_from_reader<R: BufRead + Seek>(
+    mut reader: R,
+    config: &Config,
+) -> ViuResult<DynamicImage> {
+    #[cfg(feature = "color-management")]
+    let start = reader.stream_position()?;
+
+    let mut decoder = image::io::Reader::new(&mut reader).with_guessed_format()?;
+    decoder.limits(config.limits.clone());
+    let img = match config.page {
+        Some(page) => decode_page(decoder, page, config)?,
+        None => decoder.decode()?,
+    };
+
+    // The profile is read from the encoded data again
+    #[cfg(feature = "color-management")]
+    let img = {
+        reader.seek(std::io::SeekFrom::Start(start))?;
+        icc::convert_to_srgb(img, reader)
+    };
+    Ok(img)
+}
+
+// Image from a buffer of pixels in the given format, to be printed with the printer. The
+// buffer is borrowed and scaled down by shrink_view when possible. Otherwise it is copied
+// once, without converting the pixels, apart from swapping the channels of BGRA.
+pub(crate) fn image_from_raw(
+    buf: &[u8],
+    width: u32,
+    height: u32,
+    format: PixelFormat,
+    printer: PrinterType,
+    config: &Config,
+) -> ViuResult<DynamicImage> {
+    let len = (width as usize)
+        .checked_mul(height as usize)
+        .and_then(|pixels| pixels.checked_mul(format.bytes()));
+    if len != Some(buf.len()) {
+        return Err(ImageError::Parameter(ParameterError::from_kind(
+            ParameterErrorKind::DimensionMismatch,
+        ))
+        .into());
+    }
+
+    // The length was checked above
+    fn borrowed<P: Pixel<Subpixel = u8>>(
+        buf: &[u8],
+        width: u32,
+        height: u32,
+    ) -> ImageBuffer<P, &[u8]> {
+        ImageBuffer::from_raw(width, height, buf).expect("buffer matches the image size")
+    }
+    let shrunk = match format {
+        PixelFormat::Luma8 => {
+            shrink_view(&borrowed::<Luma<u8>>(buf, width, height), printer, config)
+        }
+        PixelFormat::Rgb8 => shrink_view(&borrowed::<Rgb<u8>>(buf, width, height), printer, config),
+        PixelFormat::Rgba8 | PixelFormat::Bgra8 => {
+            shrink_view(&borrowed::<Rgba<u8>>(buf, width, height), printer, config)
+        }
+    };
+    if let Some(mut img) = shrunk {
+        // The channels are averaged separately, so they can be swapped afterwards
+        if format == PixelFormat::Bgra8 {
+            img.pixels_mut().for_each(|pixel| pixel.0.swap(0, 2));
+        }
+        return Ok(DynamicImage::ImageRgba8(img));
+    }
+
+    let img = match format {
+        PixelFormat::Luma8 => {
+            ImageBuffer::from_raw(width, height, buf.to_vec()).map(DynamicImage::ImageLuma8)
+        }
+        PixelFormat::Rgb8 => {
+            ImageBuffer::from_raw(width, height, buf.to_vec()).map(DynamicImage::ImageRgb8)
+        }
+        PixelFormat::Rgba8 => {
+            ImageBuffer::from_raw(width, height, buf.to_vec()).map(DynamicImage::ImageRgba8)
+        }
+        PixelFormat::Bgra8 => {
+            let mut rgba = buf.to_vec();
+            for pixel in rgba.chunks_exact_mut(4) {
+                pixel.swap(0, 2);
+            }
+            ImageBuffer::from_raw(width, height, rgba).map(DynamicImage::ImageRgba8)
+        }
+    };
+    Ok(img.expect("buffer matches the image size"))
+}
+
+// The view scaled down by averaging blocks of its pixels in linear light, when it is printed
+// with blocks and has several times more pixels than they draw. The printer then resizes it
+// as usual, without a copy of the whole view being made. None when the view is needed whole,
+// e.g. for the graphics protocols, or when the config transforms or resizes it differently.
+pub(crate) fn shrink_view<V, P>(
+    view: &V,
+    printer: PrinterType,
+    config: &Config,
+) -> Option<RgbaImage>
+where
+    V: GenericImageView<Pixel = P>,
+    P: Pixel<Subpixel = u8>,
+{
+    if printer != PrinterType::Block
+        || !config.linear_resize
+        || config.filter.is_some()
+        || transform::is_needed(config)
+        || !matches!(config.sizing(), SizeMode::Contain | SizeMode::Stretch)
+    {
+        return None;
+    }
+    let (width, height) = view.dimensions();
+    let (w, h) = fit_to_config(width, height, config);
+    let block = (width / w.saturating_mul(VIEW_CELL_PIXELS.0).max(1))
+        .min(height / h.saturating_mul(VIEW_CELL_PIXELS.1).max(1));
+    if block < 2 {
+        return None;
+    }
+    Some(gamma::shrink(view, block))
+}
+
+// Size in pixels which the longer side of a decoded image needs at least, so that it covers
+// the bounds from the config, or the terminal, in either orientation
+pub(crate) fn scaled_decoding_size(config: &Config) -> u16 {
+    let (term_w, term_h) = terminal_size();
+    let (cell_w, cell_h) = utils::cell_pixel_size().unwrap_or(MAX_CELL_PIXEL_SIZE);
+    let (width, height) = config.bounds();
+    let width = width.unwrap_or(term_w as u32) as f32 * cell_w;
+    let height = height.unwrap_or(term_h as u32) as f32 * cell_h;
+    width.max(height).ceil().min(u16::MAX as f32) as u16
+}
+
+// The size in pixels of the image in the file, as it will be printed. The file is only
+// decoded if it has to be transformed first.
+pub(crate) fn file_dimensions<P: AsRef<Path>>(
+    filename: P,
+    config: &Config,
+) -> ViuResult<(u32, u32)> {
+    #[cfg(feature = "exif")]
+    let rotated = transform::exif_orientation(&filename).is_some();
+    #[cfg(not(feature = "exif"))]
+    let rotated = false;
+
+    if rotated
+        || transform::is_needed(config)
+        || config.page.is_some()
+        || needs_own_decoder(&filename)?
+    {
+        let img = open_image(&filename, config)?;
+        #[cfg(feature = "exif")]
+        let img = match transform::exif_orientation(&filename) {
+            Some(orientation) => transform::orient(img, orientation),
+            None => img,
+        };
+        return Ok(transform::apply(&img, config).dimensions());
+    }
+    Ok(image::image_dimensions(filename)?)
+}
+
+// Render the image as rows of cells with the configured block style. Used by printers which
+// need to know the content of every cell, e.g. to only redraw the cells which changed.
+pub(crate) fn render_cells(img: &DynamicImage, config: &Config) -> Vec<Vec<cell::Cell>> {
+    let mut cells = Vec::new();
+    render_cells_into(img, config, &mut cells);
+    cells
+}
+
+// Same as render_cells, reusing the allocations of `cells`, e.g. across animation frames
+pub(crate) fn render_cells_into(
+    img: &DynamicImage,
+    config: &Config,
+    cells: &mut Vec<Vec<cell::Cell>>,
+) {
+    match config.block_style {
+        BlockStyle::HalfBlock => block::render_into(img, config, cells),
+        BlockStyle::Quadrant => quadrant::render_into(img, config, cells),
+        BlockStyle::Braille => braille::render_into(img, config, cells),
+        BlockStyle::Sextant => sextant::render_into(img, config, cells),
+        BlockStyle::Edges => edges::render_into(img, config, cells),
+        BlockStyle::Shade => shade::render_into(img, config, cells),
+        BlockStyle::Background => background::render_into(img, config, cells),
+    }
+}
+
 /// Resize a [image::DynamicImage] so that it fits within optional width and height bounds.
 /// If none are provided, terminal size is used instead.
+///
+/// Same as [resize_with_policy] with [ResizePolicy::FitTerminal], [ResizePolicy::FitWidth],
+/// [ResizePolicy::FitHeight] or [ResizePolicy::Exact], depending on the bounds provided.
 pub fn resize(img: &DynamicImage, width: Option<u32>, height: Option<u32>) -> DynamicImage {
-    let (w, h) = find_best_fit(img, width, height);
-
-    // find_best_fit returns values in terminal cells. Hence, we multiply by two
-    // because a 5x10 image can fit in 5x5 cells. However, a 5x9 image will also
-    // fit in 5x5 and 1 is deducted in such cases.
-    img.resize_exact(
-        w,
-        2 * h - img.height() % 2,
-        image::imageops::FilterType::Triangle,
+    resize_with_policy(img, ResizePolicy::from_bounds(width, height))
+}
+
+/// Resize a [image::DynamicImage] to the size in pixels which is printed for the policy.
+/// The result is twice as high as the number of rows, or one pixel less when the image's
+/// aspect ratio makes its height odd.
+pub fn resize_with_policy(img: &DynamicImage, policy: ResizePolicy) -> DynamicImage {
+    let (w, h) = match policy {
+        ResizePolicy::Scale(scale) => scaled_cells(img.width(), img.height(), scale),
+        policy => {
+            let (width, height) = policy.bounds();
+            find_best_fit(img, width, height)
+        }
+    };
+    let height = pixel_height(img.width(), img.height(), (w, h));
+    gamma::resize(img, w, height, FilterType::Triangle)
+}
+
+// Same as resize, with the sizing options and the filter from the config
+pub(crate) fn resize_for_config(img: &DynamicImage, config: &Config) -> DynamicImage {
+    let (w, h) = fit_to_config(img.width(), img.height(), config);
+
+    // Images which are not stretched to the bounds keep their aspect ratio
+    let stretched = match config.sizing() {
+        SizeMode::Contain | SizeMode::Exact => false,
+        SizeMode::Stretch => matches!(config.bounds(), (Some(_), Some(_))),
+        SizeMode::Cover | SizeMode::Tile => true,
+    };
+    let height = if stretched {
+        2 * h
+    } else {
+        pixel_height(img.width(), half_block_height(img.height(), config), (w, h))
+    };
+    resize_to(img, w, height, config)
+}
+
+// The height in pixels to resize an image to, for `h` rows of cells holding two pixels each.
+// An image whose height at width `w` is odd keeps it, so that the last row is printed with
+// upper half blocks over the terminal's background instead of stretching the image.
+fn pixel_height(img_width: u32, img_height: u32, (w, h): (u32, u32)) -> u32 {
+    let exact = (img_height as f64 * w as f64 / img_width.max(1) as f64)
+        .round()
+        .max(1.0) as u32;
+    if exact + 1 == 2 * h {
+        exact
+    } else {
+        2 * h
+    }
+}
+
+// Resize the image to exactly the given size in pixels. Without a filter set in the config,
+// upscaled images use nearest neighbour to stay sharp and the others a triangle filter.
+pub(crate) fn resize_to(
+    img: &DynamicImage,
+    width: u32,
+    height: u32,
+    config: &Config,
+) -> DynamicImage {
+    let enlarged = width > img.width() || height > img.height();
+    let filter = config.filter.unwrap_or(if config.upscale && enlarged {
+        FilterType::Nearest
+    } else {
+        FilterType::Triangle
+    });
+    // Nearest neighbour does not mix colors, so linear light makes no difference
+    let resized = if config.linear_resize
+        && filter != FilterType::Nearest
+        && (width, height) != img.dimensions()
+    {
+        gamma::resize(img, width, height, filter)
+    } else {
+        img.resize_exact(width, height, filter)
+    };
+    post_process(Cow::Owned(resized), config).into_owned()
+}
+
+// Apply the color adjustments, the vision filter and the threshold, and run Config::filter_fn
+// over the resized image, if the config has any of them
+pub(crate) fn post_process<'a>(
+    img: Cow<'a, DynamicImage>,
+    config: &Config,
+) -> Cow<'a, DynamicImage> {
+    if !adjust::is_needed(config)
+        && config.vision_filter.is_none()
+        && config.threshold.is_none()
+        && config.filter_fn.is_none()
+    {
+        return img;
+    }
+    let mut rgba = img.into_owned().into_rgba8();
+    adjust::apply(&mut rgba, config);
+    if let Some(filter) = config.vision_filter {
+        vision::apply(&mut rgba, filter);
+    }
+    if let Some(threshold) = &config.threshold {
+        adjust::threshold(&mut rgba, threshold);
+    }
+    if let Some(filter_fn) = &config.filter_fn {
+        filter_fn(&mut rgba);
+    }
+    Cow::Owned(DynamicImage::ImageRgba8(rgba))
+}
+
+// The size of the printed image in terminal cells, for an image of the given size in pixels.
+// Unlike find_best_fit, this takes all of the sizing options in the config into account.
+pub(crate) fn fit_to_config(img_width: u32, img_height: u32, config: &Config) -> (u32, u32) {
+    // The fitting below assumes cells twice as high as wide, so stretch the image to
+    // compensate for other ratios
+    let img_height = half_block_height(img_height, config);
+
+    match config.sizing() {
+        SizeMode::Contain => fit_in_bounds(img_width, img_height, config),
+        SizeMode::Stretch => match config.bounds() {
+            (Some(w), Some(h)) => (w, h),
+            _ => fit_in_bounds(img_width, img_height, config),
+        },
+        // The image was already cropped to the aspect ratio of the bounds, or tiled to fill them
+        SizeMode::Cover | SizeMode::Tile => cover_bounds(config),
+        SizeMode::Exact => match config.resize_policy {
+            Some(ResizePolicy::Scale(scale)) => scaled_cells(img_width, img_height, scale),
+            _ => scaled_cells(img_width, img_height, 1.0),
+        },
+    }
+}
+
+// The size in cells of an image of the given size in pixels scaled by the factor, with one
+// pixel per column and two per row
+fn scaled_cells(img_width: u32, img_height: u32, scale: f32) -> (u32, u32) {
+    let width = (img_width as f32 * scale).round() as u32;
+    let height = (img_height as f32 * scale).round() as u32;
+    (width.max(1), (height / 2 + height % 2).max(1))
+}
+
+// The height of the image in pixels half as high as the cells, see fit_to_config
+fn half_block_height(img_height: u32, config: &Config) -> u32 {
+    let ratio = cell_aspect_ratio(config);
+    ((img_height as f32 * 2.0 / ratio).round() as u32).max(1)
+}
+
+// Same as fit_to_config, for printers which display the image in its full resolution. Unless
+// upscaling, the image is not made larger than its size in pixels, when the size of the
+// cells in pixels is known.
+pub(crate) fn fit_to_native(img_width: u32, img_height: u32, config: &Config) -> (u32, u32) {
+    let size = fit_to_config(img_width, img_height, config);
+    match utils::queried_cell_pixel_size() {
+        Some(cell_size) if !config.upscale && config.sizing() == SizeMode::Contain => {
+            limit_to_native(size, (img_width, img_height), cell_size)
+        }
+        _ => size,
+    }
+}
+
+// Shrink the size in cells to the one which shows every pixel of the image once
+fn limit_to_native(
+    (w, h): (u32, u32),
+    (img_width, img_height): (u32, u32),
+    (cell_w, cell_h): (f32, f32),
+) -> (u32, u32) {
+    let native_w = (img_width as f32 / cell_w).ceil() as u32;
+    let native_h = (img_height as f32 / cell_h).ceil() as u32;
+    (w.min(native_w.max(1)), h.min(native_h.max(1)))
+}
+
+// Fit the image within the bounds while preserving its aspect ratio
+fn fit_in_bounds(img_width: u32, img_height: u32, config: &Config) -> (u32, u32) {
+    let (width, height) = config.bounds();
+    if config.upscale {
+        if let Some(size) = upscale_dimensions(img_width, img_height, width, height) {
+            return size;
+        }
+    }
+    match (width, height) {
+        (Some(w), Some(h)) => fit_dimensions(img_width, img_height, w, h),
+        (width, height) => best_fit_dimensions(img_width, img_height, width, height),
+    }
+}
+
+// The ratio between the height and the width of a terminal cell, from the config or detected
+pub(crate) fn cell_aspect_ratio(config: &Config) -> f32 {
+    match config.cell_aspect_ratio {
+        Some(ratio) if ratio.is_finite() && ratio > 0.0 => ratio,
+        _ => utils::cell_aspect_ratio(),
+    }
+}
+
+// The area in terminal cells which SizeMode::Cover and SizeMode::Tile fill: the configured
+// width and height, or the terminal's size for the ones which are not set
+pub(crate) fn cover_bounds(config: &Config) -> (u32, u32) {
+    let (term_w, term_h) = terminal_size();
+    let (width, height) = config.bounds();
+    (
+        width.unwrap_or(term_w as u32),
+        // One less row, like in find_best_fit
+        height.unwrap_or_else(|| (term_h as u32).saturating_sub(1).max(1)),
     )
 }
 
+// Scale an image up so that it fills the bounds, or the terminal if there are none, while
+// preserving its aspect ratio. Returns None if the image does not need to be enlarged.
+fn upscale_dimensions(
+    img_width: u32,
+    img_height: u32,
+    width: Option<u32>,
+    height: Option<u32>,
+) -> Option<(u32, u32)> {
+    let (bound_width, bound_height) = match (width, height) {
+        (None, None) => {
+            // One less row, like in find_best_fit
+            let (term_w, term_h) = terminal_size();
+            (
+                Some(term_w as u32),
+                Some((term_h as u32).saturating_sub(1).max(1)),
+            )
+        }
+        bounds => bounds,
+    };
+
+    let scale_w = bound_width.map(|w| w as f64 / img_width as f64);
+    let scale_h = bound_height.map(|h| 2.0 * h as f64 / img_height as f64);
+    let scale = match (scale_w, scale_h) {
+        (Some(w), Some(h)) => w.min(h),
+        (Some(scale), None) | (None, Some(scale)) => scale,
+        (None, None) => return None,
+    };
+    if scale <= 1.0 {
+        return None;
+    }
+
+    let w = ((img_width as f64 * scale).round() as u32).max(1);
+    let h = ((img_height as f64 * scale / 2.0).round() as u32).max(1);
+    Some((
+        bound_width.map_or(w, |bound| w.min(bound)),
+        bound_height.map_or(h, |bound| h.min(bound)),
+    ))
+}
+
 /// Find the best dimensions for the printed image, based on user's input.
 /// Returns the dimensions of how the image should be printed in **terminal cells**.
 ///
@@ -117,10 +1046,18 @@ pub fn resize(img: &DynamicImage, width: Option<u32>, height: Option<u32>) -> Dy
 /// let (w, h) = find_best_fit(&img, None, None);
 /// assert_eq!(w, 80);
 /// assert_eq!(h, 20);
-//TODO: it might make more sense to change signiture from img to (width, height)
 fn find_best_fit(img: &DynamicImage, width: Option<u32>, height: Option<u32>) -> (u32, u32) {
     let (img_width, img_height) = img.dimensions();
+    best_fit_dimensions(img_width, img_height, width, height)
+}
 
+// Same as find_best_fit, for an image of the given size in pixels.
+fn best_fit_dimensions(
+    img_width: u32,
+    img_height: u32,
+    width: Option<u32>,
+    height: Option<u32>,
+) -> (u32, u32) {
     // Match user's width and height preferences
     match (width, height) {
         (None, None) => {
@@ -172,7 +1109,11 @@ fn fit_dimensions(width: u32, height: u32, bound_width: u32, bound_height: u32)
     };
 
     if use_width {
-        (bound_width, std::cmp::max(1, intermediate / 2))
+        // The last row is half empty when the height is odd
+        (
+            bound_width,
+            std::cmp::max(1, intermediate / 2 + intermediate % 2),
+        )
     } else {
         (intermediate, std::cmp::max(1, bound_height / 2))
     }
@@ -180,7 +1121,7 @@ fn fit_dimensions(width: u32, height: u32, bound_width: u32, bound_height: u32)
 
 // Move the cursor to a location from where it should start printing. Calculations are based on
 // offsets from the config.
-fn adjust_offset(stdout: &mut impl Write, config: &Config) -> ViuResult {
+pub(crate) fn adjust_offset(stdout: &mut impl Write, config: &Config) -> ViuResult {
     if config.absolute_offset {
         if config.y >= 0 {
             // If absolute_offset, move to (x,y).
@@ -248,7 +1189,9 @@ mod tests {
         let img = resize_get_large_test_image();
         let new_img = resize(&img, width, height);
         assert_eq!(new_img.width(), 60);
-        assert_eq!(new_img.height(), 45);
+        // 48 pixels would be exact, but a row is left for the prompt. The height used to take
+        // the parity of the original image, giving 45.
+        assert_eq!(new_img.height(), 46);
 
         let img = resize_get_small_test_image();
         let new_img = resize(&img, width, height);
@@ -264,7 +1207,8 @@ mod tests {
         let img = resize_get_large_test_image();
         let new_img = resize(&img, width, height);
         assert_eq!(new_img.width(), 100);
-        assert_eq!(new_img.height(), 77);
+        // 79.9 pixels high, in 40 rows. Rounding the rows down used to squeeze it to 77.
+        assert_eq!(new_img.height(), 80);
 
         let img = resize_get_small_test_image();
         let new_img = resize(&img, width, height);
@@ -280,7 +1224,8 @@ mod tests {
         let img = resize_get_large_test_image();
         let new_img = resize(&img, width, height);
         assert_eq!(new_img.width(), 225);
-        assert_eq!(new_img.height(), 179);
+        // 179.8 pixels high. The parity of the original image used to give 179.
+        assert_eq!(new_img.height(), 180);
 
         height = Some(4);
         let img = resize_get_small_test_image();
@@ -297,7 +1242,8 @@ mod tests {
         let img = resize_get_large_test_image();
         let new_img = resize(&img, width, height);
         assert_eq!(new_img.width(), 15);
-        assert_eq!(new_img.height(), 17);
+        // Stretched to the bounds. The parity of the original image used to leave out a pixel.
+        assert_eq!(new_img.height(), 18);
 
         let img = resize_get_small_test_image();
         let new_img = resize(&img, width, height);
@@ -305,6 +1251,258 @@ mod tests {
         assert_eq!(new_img.height(), 18);
     }
 
+    #[test]
+    fn test_resize_to_threshold() {
+        let img = DynamicImage::ImageRgba8(image::RgbaImage::from_fn(2, 1, |x, _| {
+            image::Rgba([50 + 200 * x as u8, 0, 0, 255])
+        }));
+        let config = Config {
+            threshold: Some(crate::Threshold {
+                level: crate::ThresholdLevel::Fixed(40),
+                ..Default::default()
+            }),
+            ..Default::default()
+        };
+        let new_img = resize_to(&img, 2, 1, &config);
+        assert_eq!(new_img.get_pixel(0, 0), image::Rgba([0, 0, 0, 255]));
+        assert_eq!(new_img.get_pixel(1, 0), image::Rgba([255, 255, 255, 255]));
+    }
+
+    #[test]
+    fn test_resize_to_filter() {
+        let img = DynamicImage::ImageRgba8(image::RgbaImage::from_fn(2, 2, |x, y| {
+            if x == y {
+                image::Rgba([0, 0, 0, 255])
+            } else {
+                image::Rgba([255, 255, 255, 255])
+            }
+        }));
+        let config = Config {
+            filter: Some(FilterType::Nearest),
+            ..Default::default()
+        };
+        let new_img = resize_to(&img, 4, 4, &config);
+        assert_eq!(new_img.dimensions(), (4, 4));
+        // No blending between the pixels
+        for (_, _, pixel) in new_img.pixels() {
+            assert!(pixel[0] == 0 || pixel[0] == 255);
+        }
+
+        // Nearest neighbour is also the default when upscaling
+        let config = Config {
+            upscale: true,
+            width: Some(4),
+            ..Default::default()
+        };
+        let new_img = resize_for_config(&img, &config);
+        assert_eq!(new_img.dimensions(), (4, 4));
+        for (_, _, pixel) in new_img.pixels() {
+            assert!(pixel[0] == 0 || pixel[0] == 255);
+        }
+    }
+
+    #[test]
+    fn test_filter_fn() {
+        let img = DynamicImage::ImageRgba8(image::RgbaImage::new(4, 4));
+        let config = Config {
+            filter_fn: Some(Arc::new(|img: &mut RgbaImage| {
+                // Runs over the resized image
+                assert_eq!(img.dimensions(), (2, 2));
+                img.put_pixel(0, 0, image::Rgba([255, 0, 0, 255]));
+            })),
+            ..Default::default()
+        };
+        let new_img = resize_to(&img, 2, 2, &config);
+        assert_eq!(new_img.get_pixel(0, 0), image::Rgba([255, 0, 0, 255]));
+        assert!(transform::is_needed(&config));
+    }
+
+    #[test]
+    fn test_resize_odd_height() {
+        // Scaled to 10x9 pixels, the last of the 5 rows is half empty
+        let img = DynamicImage::ImageRgba8(image::RgbaImage::new(20, 18));
+        let mut config = Config {
+            width: Some(10),
+            cell_aspect_ratio: Some(2.0),
+            ..Default::default()
+        };
+        assert_eq!(fit_to_config(20, 18, &config), (10, 5));
+        assert_eq!(resize_for_config(&img, &config).dimensions(), (10, 9));
+        assert_eq!(resize(&img, Some(10), None).dimensions(), (10, 9));
+
+        // Stretched images fill all of their cells
+        config.height = Some(5);
+        config.size_mode = SizeMode::Stretch;
+        assert_eq!(resize_for_config(&img, &config).dimensions(), (10, 10));
+    }
+
+    #[test]
+    fn test_resize_distortion() {
+        // The rows are rounded up, so the resized height is less than a pixel off the height
+        // which keeps the aspect ratio. Rounding them down could take almost two pixels off.
+        for img_width in 1..=40 {
+            for img_height in 1..=40 {
+                for width in 1..=img_width {
+                    let (w, h) = best_fit_dimensions(img_width, img_height, Some(width), None);
+                    let exact = (img_height as f64 * w as f64 / img_width as f64).max(1.0);
+                    let height = pixel_height(img_width, img_height, (w, h));
+                    assert!(height <= 2 * h);
+                    assert!((height as f64 - exact).abs() < 1.0);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_size_modes() {
+        let mut config = Config {
+            width: Some(20),
+            height: Some(20),
+            ..Default::default()
+        };
+        assert_eq!(fit_to_config(100, 50, &config), (20, 5));
+        config.size_mode = SizeMode::Stretch;
+        assert_eq!(fit_to_config(100, 50, &config), (20, 20));
+        config.size_mode = SizeMode::Cover;
+        assert_eq!(fit_to_config(100, 50, &config), (20, 20));
+        config.size_mode = SizeMode::Exact;
+        assert_eq!(fit_to_config(100, 51, &config), (100, 26));
+    }
+
+    #[test]
+    fn test_shrink_view() {
+        let img = RgbaImage::from_pixel(400, 200, image::Rgba([200, 100, 50, 255]));
+        let config = Config {
+            width: Some(10),
+            height: Some(10),
+            ..Default::default()
+        };
+        let shrunk = shrink_view(&*img.view(0, 0, 400, 200), PrinterType::Block, &config).unwrap();
+        assert_eq!(shrunk.dimensions(), (50, 25));
+        assert_eq!(shrunk.get_pixel(49, 24), &image::Rgba([200, 100, 50, 255]));
+
+        assert!(shrink_view(&img, PrinterType::Kitty, &config).is_none());
+        let config = Config {
+            width: Some(100),
+            height: Some(50),
+            ..config
+        };
+        assert!(shrink_view(&img, PrinterType::Block, &config).is_none());
+    }
+
+    #[test]
+    fn test_image_from_raw() {
+        let raw = |buf: &[u8], width, height, format| {
+            image_from_raw(
+                buf,
+                width,
+                height,
+                format,
+                PrinterType::Kitty,
+                &Config::default(),
+            )
+        };
+        let img = raw(&[1, 2, 3, 4, 5, 6, 7, 8], 2, 1, PixelFormat::Bgra8).unwrap();
+        assert_eq!(img.to_rgba8().into_raw(), [3, 2, 1, 4, 7, 6, 5, 8]);
+        let img = raw(&[9; 6], 1, 2, PixelFormat::Rgb8).unwrap();
+        assert_eq!(img.dimensions(), (1, 2));
+
+        assert!(matches!(
+            raw(&[0; 5], 2, 1, PixelFormat::Rgb8),
+            Err(ViuError::Image(_))
+        ));
+        assert!(raw(&[], u32::MAX, u32::MAX, PixelFormat::Rgba8).is_err());
+    }
+
+    #[test]
+    fn test_image_from_raw_shrunk() {
+        // A large frame printed with blocks is scaled down from the borrowed buffer
+        let config = Config {
+            width: Some(10),
+            height: Some(10),
+            ..Default::default()
+        };
+        let buf = [1, 2, 3, 255].repeat(400 * 200);
+        let img = image_from_raw(
+            &buf,
+            400,
+            200,
+            PixelFormat::Bgra8,
+            PrinterType::Block,
+            &config,
+        )
+        .unwrap();
+        assert_eq!(img.dimensions(), (50, 25));
+        assert_eq!(img.get_pixel(0, 0), image::Rgba([3, 2, 1, 255]));
+    }
+
+    #[test]
+    fn test_resize_policy() {
+        let mut config = Config {
+            width: Some(20),
+            height: Some(20),
+            size_mode: SizeMode::Cover,
+            resize_policy: Some(ResizePolicy::FitWidth(40)),
+            ..Default::default()
+        };
+        assert_eq!(fit_to_config(100, 50, &config), (40, 10));
+        config.resize_policy = Some(ResizePolicy::FitHeight(5));
+        assert_eq!(fit_to_config(100, 50, &config), (20, 5));
+        config.resize_policy = Some(ResizePolicy::Exact(30, 3));
+        assert_eq!(fit_to_config(100, 50, &config), (30, 3));
+        config.resize_policy = Some(ResizePolicy::FitTerminal);
+        assert_eq!(fit_to_config(100, 50, &config), (80, 20));
+        config.resize_policy = Some(ResizePolicy::Scale(0.5));
+        assert_eq!(fit_to_config(100, 50, &config), (50, 13));
+
+        let img = DynamicImage::ImageRgba8(image::RgbaImage::new(100, 50));
+        assert_eq!(
+            resize_with_policy(&img, ResizePolicy::Scale(0.5)).dimensions(),
+            (50, 25)
+        );
+        assert_eq!(
+            resize_with_policy(&img, ResizePolicy::FitWidth(40)).dimensions(),
+            resize(&img, Some(40), None).dimensions()
+        );
+    }
+
+    #[test]
+    fn test_cell_aspect_ratio() {
+        let mut config = Config {
+            width: Some(20),
+            ..Default::default()
+        };
+        assert_eq!(fit_to_config(40, 40, &config), (20, 10));
+        // Square cells need as many rows as columns
+        config.cell_aspect_ratio = Some(1.0);
+        assert_eq!(fit_to_config(40, 40, &config), (20, 20));
+        config.cell_aspect_ratio = Some(2.5);
+        assert_eq!(fit_to_config(40, 40, &config), (20, 8));
+    }
+
+    #[test]
+    fn test_upscale() {
+        let mut config = Config {
+            width: Some(40),
+            ..Default::default()
+        };
+        assert_eq!(fit_to_config(16, 16, &config), (16, 8));
+        config.upscale = true;
+        assert_eq!(fit_to_config(16, 16, &config), (40, 20));
+
+        // Fit in the 80x24 test terminal, keeping a row free
+        config.width = None;
+        assert_eq!(fit_to_config(16, 16, &config), (46, 23));
+
+        // Large images are still scaled down
+        assert_eq!(fit_to_config(600, 499, &config), (57, 23));
+
+        // Both bounds are respected
+        config.width = Some(40);
+        config.height = Some(4);
+        assert_eq!(fit_to_config(16, 16, &config), (8, 4));
+    }
+
     // Best fit tests
 
     #[test]
@@ -336,7 +1534,8 @@ mod tests {
         let img = best_fit_large_test_image();
         let (w, h) = find_best_fit(&img, width, height);
         assert_eq!(w, 100);
-        assert_eq!(h, 41);
+        // 83.2 pixels high, the last row is half empty. Rounding down used to give 41 rows.
+        assert_eq!(h, 42);
 
         let img = best_fit_small_test_image();
         let (w, h) = find_best_fit(&img, width, height);
@@ -346,7 +1545,8 @@ mod tests {
         let width = Some(6);
         let (w, h) = find_best_fit(&img, width, height);
         assert_eq!(w, 6);
-        assert_eq!(h, 1);
+        // 3.75 pixels high, which one row used to squeeze to 2
+        assert_eq!(h, 2);
 
         let width = Some(3);
         let (w, h) = find_best_fit(&img, width, height);
@@ -409,6 +1609,50 @@ mod tests {
         assert_eq!((80, 12), fit_dimensions(80, 24, 80, 24));
     }
 
+    #[test]
+    fn test_limit_to_native() {
+        // 100x100 pixels in cells of 10x20 pixels
+        assert_eq!(limit_to_native((46, 23), (100, 100), (10.0, 20.0)), (10, 5));
+        assert_eq!(limit_to_native((8, 4), (100, 100), (10.0, 20.0)), (8, 4));
+        assert_eq!(limit_to_native((46, 23), (5, 5), (10.0, 20.0)), (1, 1));
+    }
+
+    #[test]
+    fn test_align() {
+        let config = Config {
+            width: Some(20),
+            halign: HAlign::Center,
+            ..Default::default()
+        };
+        let aligned = align((10, 10), &config);
+        assert_eq!((aligned.x, aligned.y), (5, 0));
+        assert_eq!(aligned.halign, HAlign::Left);
+
+        let config = Config {
+            x: 2,
+            halign: HAlign::Right,
+            valign: VAlign::Bottom,
+            ..Default::default()
+        };
+        // 10x5 cells in the rest of the 80x24 test terminal
+        let aligned = align((10, 10), &config);
+        assert_eq!((aligned.x, aligned.y), (70, 19));
+    }
+
+    #[test]
+    fn test_align_middle() {
+        let config = Config {
+            y: 4,
+            valign: VAlign::Middle,
+            ..Default::default()
+        };
+        let aligned = align((10, 10), &config);
+        assert_eq!((aligned.x, aligned.y), (0, 11));
+        // Already aligned, nothing changes
+        let again = align((10, 10), &aligned);
+        assert_eq!((again.x, again.y), (0, 11));
+    }
+
     #[test]
     fn test_zero_offset() {
         let config = Config {
@@ -466,4 +1710,43 @@ mod tests {
         let err = adjust_offset(&mut vec, &config).unwrap_err();
         assert!(matches!(err, ViuError::InvalidConfiguration { .. }));
     }
+
+    #[test]
+    fn test_open_image_scaled_jpeg() {
+        let file = tempfile::Builder::new().suffix(".jpg").tempfile().unwrap();
+        DynamicImage::ImageRgb8(image::RgbImage::new(512, 256))
+            .save(file.path())
+            .unwrap();
+
+        // A cell is assumed to be at most 16x32 pixels, so 64 pixels are needed at least
+        let config = Config {
+            width: Some(2),
+            height: Some(2),
+            ..Default::default()
+        };
+        let img = open_image(file.path(), &config).unwrap();
+        assert_eq!(img.dimensions(), (64, 32));
+
+        let cover = Config {
+            size_mode: SizeMode::Cover,
+            ..config
+        };
+        let img = open_image(file.path(), &cover).unwrap();
+        assert_eq!(img.dimensions(), (512, 256));
+    }
+
+    #[test]
+    fn test_decode_from_memory_limits() {
+        let mut png = Vec::new();
+        DynamicImage::ImageRgba8(image::RgbaImage::new(4, 2))
+            .write_to(&mut Cursor::new(&mut png), image::ImageOutputFormat::Png)
+            .unwrap();
+
+        let mut config = Config::default();
+        assert!(decode_from_memory(&png, &config).is_ok());
+
+        config.limits.max_image_width = Some(3);
+        let err = decode_from_memory(&png, &config).unwrap_err();
+        assert!(matches!(err, ViuError::Image(_)));
+    }
 }
@@ -0,0 +1,224 @@
+// Pages of multi-page images, selected through Config::page. TIFF and ICO files are rewritten
+// in memory so that the selected page is their first one, which the image crate decodes.
+use crate::error::{ViuError, ViuResult};
+use crate::printer::ifd::Tiff;
+
+use image::error::{ImageError, ParameterError, ParameterErrorKind};
+use image::ImageFormat;
+use std::io::{BufRead, Cursor, Error, ErrorKind, Read, Seek};
+use std::path::Path;
+
+const IMAGE_WIDTH: u16 = 0x100;
+const IMAGE_LENGTH: u16 = 0x101;
+
+// Size of the header of an ICO file, and of each entry of its directory
+const ICO_HEADER: usize = 6;
+const ICO_ENTRY: usize = 16;
+
+/// Sizes in pixels of the pages of an image file, in order, which [crate::Config::page]
+/// selects from: the pages of a TIFF file, or the icons of an ICO file. Other images have
+/// a single page. Icons larger than 256 pixels are listed as 256 pixels wide and high.
+///
+/// ## Example
+/// ```no_run
+/// use viuer::{image_pages, print_from_file, Config};
+///
+/// // The largest icon, its size is listed as 256 for larger ones too
+/// let pages = image_pages("app.ico").expect("Could not read the file.");
+/// let (page, _) = pages
+///     .iter()
+///     .enumerate()
+///     .max_by_key(|(_, (w, h))| w * h)
+///     .expect("The file has no icons.");
+/// let conf = Config {
+///     page: Some(page as u32),
+///     ..Default::default()
+/// };
+/// print_from_file("app.ico", &conf).expect("Image printing failed.");
+/// ```
+pub fn image_pages<P: AsRef<Path>>(filename: P) -> ViuResult<Vec<(u32, u32)>> {
+    let reader = image::io::Reader::open(&filename)?.with_guessed_format()?;
+    match reader.format() {
+        Some(ImageFormat::Tiff) => tiff_pages(reader.into_inner()),
+        Some(ImageFormat::Ico) => {
+            let mut header = Vec::new();
+            reader
+                .into_inner()
+                .take(ICO_HEADER as u64 + u16::MAX as u64 * ICO_ENTRY as u64)
+                .read_to_end(&mut header)?;
+            Ok(ico_entries(&header)?
+                .map(|entry| {
+                    // 0 stands for 256
+                    let size = |byte: u8| if byte == 0 { 256 } else { byte as u32 };
+                    (size(entry[0]), size(entry[1]))
+                })
+                .collect())
+        }
+        _ => Ok(vec![reader.into_dimensions()?]),
+    }
+}
+
+fn tiff_pages<R: Read + Seek>(reader: R) -> ViuResult<Vec<(u32, u32)>> {
+    let mut tiff = Tiff::new(reader)?.ok_or_else(invalid)?;
+    let mut sizes = Vec::new();
+    for offset in tiff.page_ifds()? {
+        let (entries, _) = tiff.ifd(offset)?;
+        let width = tiff.values(&entries, IMAGE_WIDTH)?;
+        let height = tiff.values(&entries, IMAGE_LENGTH)?;
+        match (&width[..], &height[..]) {
+            ([width], [height]) => sizes.push((*width, *height)),
+            _ => return Err(invalid().into()),
+        }
+    }
+    Ok(sizes)
+}
+
+// Rewrite the encoded image so that the page, counted from 0, is its first one. Formats
+// without pages only have page 0.
+pub fn select<R: BufRead + Seek>(
+    mut reader: R,
+    format: Option<ImageFormat>,
+    page: u32,
+) -> ViuResult<Vec<u8>> {
+    let mut buf = Vec::new();
+    reader.read_to_end(&mut buf)?;
+    match format {
+        Some(ImageFormat::Tiff) => {
+            let mut tiff = Tiff::new(Cursor::new(&buf))?.ok_or_else(invalid)?;
+            let offset = *tiff
+                .page_ifds()?
+                .get(page as usize)
+                .ok_or_else(|| missing_page(page))?;
+            // Every offset in the file is absolute, so only the first one changes
+            let bytes = tiff.u32_bytes(offset);
+            buf[4..8].copy_from_slice(&bytes);
+            Ok(buf)
+        }
+        Some(ImageFormat::Ico) => {
+            let entry: [u8; ICO_ENTRY] = ico_entries(&buf)?
+                .nth(page as usize)
+                .ok_or_else(|| missing_page(page))?
+                .try_into()
+                .map_err(|_| invalid())?;
+            let size = u32::from_le_bytes([entry[8], entry[9], entry[10], entry[11]]) as usize;
+            let offset = u32::from_le_bytes([entry[12], entry[13], entry[14], entry[15]]) as usize;
+            let data = buf
+                .get(offset..offset.saturating_add(size))
+                .ok_or_else(invalid)?;
+
+            // An ICO file with only this icon, right after its directory
+            let mut ico = vec![0, 0, 1, 0, 1, 0];
+            ico.extend_from_slice(&entry[..12]);
+            ico.extend_from_slice(&((ICO_HEADER + ICO_ENTRY) as u32).to_le_bytes());
+            ico.extend_from_slice(data);
+            Ok(ico)
+        }
+        _ if page == 0 => Ok(buf),
+        _ => Err(missing_page(page)),
+    }
+}
+
+// The entries of the directory of an ICO file
+fn ico_entries(buf: &[u8]) -> std::io::Result<std::slice::ChunksExact<'_, u8>> {
+    let count = match buf {
+        [0, 0, 1, 0, low, high, ..] => u16::from_le_bytes([*low, *high]) as usize,
+        _ => return Err(invalid()),
+    };
+    buf.get(ICO_HEADER..ICO_HEADER + count * ICO_ENTRY)
+        .map(|entries| entries.chunks_exact(ICO_ENTRY))
+        .ok_or_else(invalid)
+}
+
+fn invalid() -> Error {
+    Error::new(ErrorKind::InvalidData, "Invalid image container")
+}
+
+fn missing_page(page: u32) -> ViuError {
+    ImageError::Parameter(ParameterError::from_kind(ParameterErrorKind::Generic(
+        format!("the image has no page {}", page),
+    )))
+    .into()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use image::codecs::ico::{IcoEncoder, IcoFrame};
+    use image::{ColorType, DynamicImage, Rgba, RgbaImage};
+
+    fn decode(buf: Vec<u8>) -> DynamicImage {
+        image::load_from_memory(&buf).unwrap()
+    }
+
+    // A little endian TIFF with a page of 8 bit gray pixels for each (width, gray) pair, all
+    // 2 pixels high, each page's IFD followed by its pixels
+    fn tiff(pages: &[(u32, u8)]) -> Vec<u8> {
+        let mut buf = b"II*\0\x08\0\0\0".to_vec();
+        for (i, &(width, gray)) in pages.iter().enumerate() {
+            let pixels_at = buf.len() as u32 + 2 + 8 * 12 + 4;
+            let entries: [(u16, u16, u32); 8] = [
+                (IMAGE_WIDTH, 4, width),
+                (IMAGE_LENGTH, 4, 2),
+                (0x102, 3, 8),
+                (0x103, 3, 1),
+                (0x106, 3, 1),
+                (0x111, 4, pixels_at),
+                (0x116, 4, 2),
+                (0x117, 4, width * 2),
+            ];
+            buf.extend_from_slice(&(entries.len() as u16).to_le_bytes());
+            for (tag, kind, value) in entries {
+                buf.extend_from_slice(&tag.to_le_bytes());
+                buf.extend_from_slice(&kind.to_le_bytes());
+                buf.extend_from_slice(&1u32.to_le_bytes());
+                // SHORT values are at the start of the field
+                buf.extend_from_slice(&value.to_le_bytes());
+            }
+            let next = match i + 1 < pages.len() {
+                true => pixels_at + width * 2,
+                false => 0,
+            };
+            buf.extend_from_slice(&next.to_le_bytes());
+            buf.resize(buf.len() + width as usize * 2, gray);
+        }
+        buf
+    }
+
+    #[test]
+    fn test_tiff_pages() {
+        let buf = tiff(&[(4, 10), (8, 200)]);
+        assert_eq!(decode(buf.clone()).width(), 4);
+        assert_eq!(tiff_pages(Cursor::new(&buf)).unwrap(), [(4, 2), (8, 2)]);
+
+        let page = decode(select(Cursor::new(&buf), Some(ImageFormat::Tiff), 1).unwrap());
+        assert_eq!((page.width(), page.height()), (8, 2));
+        assert_eq!(page.to_luma8().get_pixel(0, 0)[0], 200);
+        assert!(select(Cursor::new(&buf), Some(ImageFormat::Tiff), 2).is_err());
+    }
+
+    #[test]
+    fn test_ico_pages() {
+        let frames: Vec<IcoFrame> = [(16, 255), (32, 0)]
+            .into_iter()
+            .map(|(size, red)| {
+                let img = RgbaImage::from_pixel(size, size, Rgba([red, 0, 0, 255]));
+                IcoFrame::as_png(img.as_raw(), size, size, ColorType::Rgba8).unwrap()
+            })
+            .collect();
+        let mut buf = Vec::new();
+        IcoEncoder::new(&mut buf).encode_images(&frames).unwrap();
+
+        // The largest icon is decoded by default
+        assert_eq!(decode(buf.clone()).width(), 32);
+        let page = decode(select(Cursor::new(&buf), Some(ImageFormat::Ico), 0).unwrap());
+        assert_eq!(page.to_rgba8().get_pixel(0, 0), &Rgba([255, 0, 0, 255]));
+        assert!(select(Cursor::new(&buf), Some(ImageFormat::Ico), 2).is_err());
+    }
+
+    #[test]
+    fn test_single_page() {
+        let buf = vec![1, 2, 3];
+        assert_eq!(select(Cursor::new(&buf), None, 0).unwrap(), buf);
+        assert!(select(Cursor::new(&buf), Some(ImageFormat::Png), 1).is_err());
+    }
+}
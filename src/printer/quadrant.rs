@@ -0,0 +1,106 @@
+use crate::error::ViuResult;
+use crate::printer::backend::{Backend, CrosstermBackend};
+use crate::printer::cell::{block_pixels, fill_cells, split_colors, write_cells, Cell};
+use crate::printer::dither::dither;
+use crate::printer::{fit_to_config, resize_to, Printer};
+use crate::Config;
+
+use image::DynamicImage;
+use std::io::{BufWriter, Write};
+
+// Quadrant characters indexed by a bit mask of the drawn quarters:
+// top left = 1, top right = 2, bottom left = 4, bottom right = 8
+const QUADRANTS: [char; 16] = [
+    ' ', '▘', '▝', '▀', '▖', '▌', '▞', '▛', '▗', '▚', '▐', '▜', '▄', '▙', '▟', '█',
+];
+
+/// Prints images using quadrant characters, with each cell holding 2x2 pixels.
+pub struct QuadrantPrinter;
+
+impl Printer for QuadrantPrinter {
+    fn print(
+        &self,
+        stdout: &mut impl Write,
+        img: &DynamicImage,
+        config: &Config,
+    ) -> ViuResult<(u32, u32)> {
+        let mut stream = CrosstermBackend::new(BufWriter::new(stdout));
+        print_to_backend(&mut stream, img, config)
+    }
+}
+
+fn print_to_backend(
+    stdout: &mut impl Backend,
+    img: &DynamicImage,
+    config: &Config,
+) -> ViuResult<(u32, u32)> {
+    let mut cells = Vec::new();
+    render_into(img, config, &mut cells);
+    write_cells(stdout, &cells, config)
+}
+
+// Resize the image to fit the config and convert it to rows of cells, reusing the
+// allocations of `cells`
+pub fn render_into(img: &DynamicImage, config: &Config, cells: &mut Vec<Vec<Cell>>) {
+    let (w, h) = fit_to_config(img.width(), img.height(), config);
+    let mut img = resize_to(img, 2 * w, 2 * h, config).into_rgba8();
+    dither(&mut img, config);
+
+    fill_cells(cells, w, h, |row, col| {
+        let pixels = block_pixels(&img, 2 * col, 2 * row, 2, 2, config);
+        let (mask, fg, bg) = split_colors(&pixels);
+        Cell {
+            ch: QUADRANTS[mask as usize],
+            fg,
+            bg,
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ColorDepth;
+    use image::GenericImage;
+
+    #[test]
+    fn test_quadrant_printer() {
+        let mut img = DynamicImage::ImageRgba8(image::RgbaImage::new(2, 2));
+        img.put_pixel(0, 0, image::Rgba([255, 0, 0, 255]));
+        img.put_pixel(1, 0, image::Rgba([0, 0, 255, 255]));
+        img.put_pixel(0, 1, image::Rgba([0, 0, 255, 255]));
+        img.put_pixel(1, 1, image::Rgba([255, 0, 0, 255]));
+
+        let config = Config {
+            absolute_offset: false,
+            color_depth: ColorDepth::TrueColor,
+            width: Some(1),
+            height: Some(1),
+            ..Default::default()
+        };
+        let mut buf = CrosstermBackend::new(vec![]);
+
+        assert_eq!(print_to_backend(&mut buf, &img, &config).unwrap(), (1, 1));
+        assert_eq!(
+            std::str::from_utf8(buf.get_ref()).unwrap(),
+            "\x1b[0m\x1b[38;2;255;0;0m\x1b[48;2;0;0;255m▚\x1b[0m\n"
+        );
+    }
+
+    #[test]
+    fn test_quadrant_printer_transparent() {
+        let img = DynamicImage::ImageRgba8(image::RgbaImage::new(2, 2));
+        let config = Config {
+            absolute_offset: false,
+            transparent: true,
+            ..Default::default()
+        };
+        let mut buf = CrosstermBackend::new(vec![]);
+
+        print_to_backend(&mut buf, &img, &config).unwrap();
+        assert_eq!(
+            std::str::from_utf8(buf.get_ref()).unwrap(),
+            "\x1b[1C\x1b[1C\x1b[0m\n"
+        );
+    }
+}
@@ -0,0 +1,452 @@
+// Camera RAW files. CR2, NEF, ARW and DNG files are TIFF containers which hold JPEG previews
+// of the photo next to the sensor data; the largest preview which the image crate can decode
+// is printed, which is a lot faster than demosaicing the sensor data. Files without one are
+// demosaiced when the sensor data is uncompressed, which is common in DNGs. Compressed sensor
+// data, like the lossless JPEG of CR2 files, is not decoded.
+use crate::error::ViuResult;
+use crate::printer::decode_from_memory;
+use crate::printer::ifd::{Entry, Tiff};
+use crate::Config;
+
+use image::{DynamicImage, RgbImage};
+use std::io::{BufReader, Read, Seek, SeekFrom};
+use std::path::Path;
+
+const NEW_SUBFILE_TYPE: u16 = 0xfe;
+const IMAGE_WIDTH: u16 = 0x100;
+const IMAGE_LENGTH: u16 = 0x101;
+const BITS_PER_SAMPLE: u16 = 0x102;
+const COMPRESSION: u16 = 0x103;
+const PHOTOMETRIC_INTERPRETATION: u16 = 0x106;
+const STRIP_OFFSETS: u16 = 0x111;
+const SAMPLES_PER_PIXEL: u16 = 0x115;
+const STRIP_BYTE_COUNTS: u16 = 0x117;
+const SUB_IFDS: u16 = 0x14a;
+const JPEG_OFFSET: u16 = 0x201;
+const JPEG_LENGTH: u16 = 0x202;
+const CFA_REPEAT_PATTERN_DIM: u16 = 0x828d;
+const CFA_PATTERN: u16 = 0x828e;
+const DNG_VERSION: u16 = 0xc612;
+const BLACK_LEVEL: u16 = 0xc61a;
+const WHITE_LEVEL: u16 = 0xc61d;
+
+// The photometric interpretation of sensor data behind a colour filter array
+const CFA: u32 = 32803;
+
+// Most IFDs which are read, so that corrupt files with loops in them end
+const MAX_IFDS: usize = 32;
+
+// Uncompressed sensor data with a 2x2 colour filter array, stored in strips
+struct Cfa {
+    width: u32,
+    height: u32,
+    bits: u32,
+    // The colour of each sample in the 2x2 block, 0 for red, 1 for green and 2 for blue
+    pattern: [u8; 4],
+    black: u32,
+    white: u32,
+    strips: Vec<(u32, u32)>,
+}
+
+impl<R: Read + Seek> Tiff<R> {
+    // Whether the file is a camera RAW rather than an ordinary TIFF image: a CR2, a DNG, or a
+    // file whose first image is only a reduced-resolution one or has sub-images, like NEF
+    // and ARW. A file whose first IFD cannot be read is not.
+    fn is_raw(&mut self) -> bool {
+        if self.cr2 {
+            return true;
+        }
+        let Ok((entries, _)) = self.ifd(self.first_ifd) else {
+            return false;
+        };
+        entries
+            .iter()
+            .any(|entry| entry.tag == DNG_VERSION || entry.tag == SUB_IFDS)
+            || self.values(&entries, NEW_SUBFILE_TYPE).ok().as_deref() == Some(&[1])
+    }
+
+    // The entries of all IFDs, from the chain which starts at the first one and their
+    // sub-IFDs. IFDs which cannot be read are skipped, with the ones they link to.
+    fn ifds(&mut self) -> Vec<Vec<Entry>> {
+        let mut ifds = Vec::new();
+        let mut visited = Vec::new();
+        let mut pending = vec![self.first_ifd];
+        while let Some(offset) = pending.pop() {
+            if offset == 0 || visited.contains(&offset) || visited.len() == MAX_IFDS {
+                continue;
+            }
+            visited.push(offset);
+            let Ok((entries, next)) = self.ifd(offset) else {
+                continue;
+            };
+            pending.push(next);
+            pending.extend(self.values(&entries, SUB_IFDS).unwrap_or_default());
+            ifds.push(entries);
+        }
+        ifds
+    }
+
+    // The single value of the entry with the tag
+    fn value(&mut self, entries: &[Entry], tag: u16) -> Option<u32> {
+        match self.values(entries, tag).ok()?[..] {
+            [value] => Some(value),
+            _ => None,
+        }
+    }
+
+    // Offset and length of the JPEG data in all IFDs, largest first
+    fn jpegs(&mut self) -> Vec<(u32, u32)> {
+        let mut jpegs = Vec::new();
+        for entries in self.ifds() {
+            if let (Some(offset), Some(length)) = (
+                self.value(&entries, JPEG_OFFSET),
+                self.value(&entries, JPEG_LENGTH),
+            ) {
+                jpegs.push((offset, length));
+            }
+            // Old-style and new-style JPEG compression of a single strip
+            if let (Some(6 | 7), Some(offset), Some(length)) = (
+                self.value(&entries, COMPRESSION),
+                self.value(&entries, STRIP_OFFSETS),
+                self.value(&entries, STRIP_BYTE_COUNTS),
+            ) {
+                jpegs.push((offset, length));
+            }
+        }
+        jpegs.sort_by_key(|&(_, length)| std::cmp::Reverse(length));
+        jpegs.dedup();
+        jpegs
+    }
+
+    // The first IFD with uncompressed sensor data of 8 or 16 bits per sample behind a 2x2
+    // colour filter array
+    fn cfa(&mut self) -> Option<Cfa> {
+        self.ifds()
+            .into_iter()
+            .find_map(|entries| self.cfa_of(&entries))
+    }
+
+    fn cfa_of(&mut self, entries: &[Entry]) -> Option<Cfa> {
+        if self.value(entries, PHOTOMETRIC_INTERPRETATION) != Some(CFA)
+            || self.value(entries, COMPRESSION).unwrap_or(1) != 1
+            || self.value(entries, SAMPLES_PER_PIXEL).unwrap_or(1) != 1
+            || self.values(entries, CFA_REPEAT_PATTERN_DIM).ok()?[..] != [2, 2]
+        {
+            return None;
+        }
+        let pattern = match self.values(entries, CFA_PATTERN).ok()?[..] {
+            [a, b, c, d] if [a, b, c, d].iter().all(|&colour| colour <= 2) => {
+                [a as u8, b as u8, c as u8, d as u8]
+            }
+            _ => return None,
+        };
+        let bits = self.value(entries, BITS_PER_SAMPLE)?;
+        if bits != 8 && bits != 16 {
+            return None;
+        }
+        let offsets = self.values(entries, STRIP_OFFSETS).ok()?;
+        let lengths = self.values(entries, STRIP_BYTE_COUNTS).ok()?;
+        if offsets.len() != lengths.len() {
+            return None;
+        }
+        // Black levels for each sample of the pattern are not told apart
+        let black = self.values(entries, BLACK_LEVEL).ok()?;
+        Some(Cfa {
+            width: self.value(entries, IMAGE_WIDTH)?,
+            height: self.value(entries, IMAGE_LENGTH)?,
+            bits,
+            pattern,
+            black: black.first().copied().unwrap_or(0),
+            white: self.value(entries, WHITE_LEVEL).unwrap_or((1 << bits) - 1),
+            strips: offsets.into_iter().zip(lengths).collect(),
+        })
+    }
+
+    // The samples of the sensor data, row by row. None if the strips hold less than the
+    // whole image or lie outside of the file.
+    fn samples(&mut self, cfa: &Cfa) -> ViuResult<Option<Vec<u16>>> {
+        let file_len = self.reader.seek(SeekFrom::End(0))?;
+        let bytes = cfa.bits as u64 / 8;
+        let needed = cfa.width as u64 * cfa.height as u64 * bytes;
+        let available: u64 = cfa.strips.iter().map(|&(_, length)| length as u64).sum();
+        if needed > available.min(file_len) {
+            return Ok(None);
+        }
+        let mut data = Vec::with_capacity(needed as usize);
+        for &(offset, length) in &cfa.strips {
+            let length = (length as u64).min(needed - data.len() as u64);
+            if offset as u64 + length > file_len {
+                return Ok(None);
+            }
+            self.reader.seek(SeekFrom::Start(offset as u64))?;
+            (&mut self.reader).take(length).read_to_end(&mut data)?;
+        }
+        Ok(Some(match bytes {
+            1 => data.into_iter().map(u16::from).collect(),
+            _ => data
+                .chunks_exact(2)
+                .map(|sample| self.u16([sample[0], sample[1]]))
+                .collect(),
+        }))
+    }
+}
+
+// Check whether the file is a camera RAW. Files which cannot be read as TIFF are left to the
+// image crate, which reports what is wrong with them.
+pub fn is_raw<P: AsRef<Path>>(filename: P) -> ViuResult<bool> {
+    if !cfg!(feature = "raw") {
+        return Ok(false);
+    }
+    let file = BufReader::new(std::fs::File::open(filename)?);
+    match Tiff::new(file) {
+        Ok(Some(mut tiff)) => Ok(tiff.is_raw()),
+        _ => Ok(false),
+    }
+}
+
+// Decode the largest preview in the RAW file, or demosaic its sensor data if it has none.
+// Some ordinary TIFF images look like RAW files, e.g. those with sub-images, and have neither.
+pub fn decode<P: AsRef<Path>>(filename: P, config: &Config) -> ViuResult<Option<DynamicImage>> {
+    decode_reader(BufReader::new(std::fs::File::open(filename)?), config)
+}
+
+fn decode_reader<R: Read + Seek>(reader: R, config: &Config) -> ViuResult<Option<DynamicImage>> {
+    let Some(mut tiff) = Tiff::new(reader)? else {
+        return Ok(None);
+    };
+    if let Some(img) = decode_preview(&mut tiff, config)? {
+        return Ok(Some(img));
+    }
+    let Some(cfa) = tiff.cfa() else {
+        return Ok(None);
+    };
+    config.limits.check_dimensions(cfa.width, cfa.height)?;
+    Ok(tiff
+        .samples(&cfa)?
+        .map(|samples| DynamicImage::ImageRgb8(demosaic(&cfa, &samples))))
+}
+
+fn decode_preview<R: Read + Seek>(
+    tiff: &mut Tiff<R>,
+    config: &Config,
+) -> ViuResult<Option<DynamicImage>> {
+    let file_len = tiff.reader.seek(SeekFrom::End(0))?;
+
+    for (offset, length) in tiff.jpegs() {
+        if offset as u64 + length as u64 > file_len {
+            continue;
+        }
+        let mut jpeg = vec![0; length as usize];
+        tiff.reader.seek(SeekFrom::Start(offset as u64))?;
+        tiff.reader.read_exact(&mut jpeg)?;
+        if is_lossy_jpeg(&jpeg) {
+            if let Ok(img) = decode_from_memory(&jpeg, config) {
+                return Ok(Some(img));
+            }
+        }
+    }
+    Ok(None)
+}
+
+// Bilinear demosaicing: each colour of a pixel is the mean of the samples of that colour in
+// the 3x3 block around it, which has at least one of each colour away from the edges. The
+// linear sensor values are gamma encoded, without the colour calibration of the camera.
+fn demosaic(cfa: &Cfa, samples: &[u16]) -> RgbImage {
+    let (width, height) = (cfa.width as usize, cfa.height as usize);
+    let range = cfa.white.saturating_sub(cfa.black).max(1) as f32;
+    RgbImage::from_fn(cfa.width, cfa.height, |x, y| {
+        let (x, y) = (x as usize, y as usize);
+        let mut sums = [0.0; 3];
+        let mut counts = [0; 3];
+        for row in y.saturating_sub(1)..(y + 2).min(height) {
+            for column in x.saturating_sub(1)..(x + 2).min(width) {
+                let colour = cfa.pattern[row % 2 * 2 + column % 2] as usize;
+                let sample = (samples[row * width + column] as u32).saturating_sub(cfa.black);
+                sums[colour] += (sample as f32 / range).min(1.0);
+                counts[colour] += 1;
+            }
+        }
+        image::Rgb(std::array::from_fn(|colour| match counts[colour] {
+            0 => 0,
+            count => ((sums[colour] / count as f32).powf(1.0 / 2.2) * 255.0).round() as u8,
+        }))
+    })
+}
+
+// Whether the data is a baseline or progressive JPEG, unlike the lossless JPEG which some
+// RAW formats store the sensor data in
+fn is_lossy_jpeg(data: &[u8]) -> bool {
+    if !data.starts_with(&[0xff, 0xd8]) {
+        return false;
+    }
+    let mut i = 2;
+    while i + 4 <= data.len() && data[i] == 0xff {
+        match data[i + 1] {
+            0xc0..=0xc2 => return true,
+            // Other frame types, or the image data without a frame header
+            0xc3 | 0xc5..=0xc7 | 0xc9..=0xcb | 0xcd..=0xcf | 0xda | 0xd9 => return false,
+            // Fill bytes before a marker
+            0xff => i += 1,
+            _ => i += 2 + u16::from_be_bytes([data[i + 2], data[i + 3]]) as usize,
+        }
+    }
+    false
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use image::{codecs::jpeg::JpegEncoder, GenericImageView, RgbImage};
+    use std::io::Cursor;
+
+    fn jpeg(width: u32, height: u32) -> Vec<u8> {
+        let mut buf = Vec::new();
+        let img = RgbImage::from_pixel(width, height, image::Rgb([200, 10, 10]));
+        JpegEncoder::new(&mut buf).encode_image(&img).unwrap();
+        buf
+    }
+
+    // A little endian TIFF with the IFDs, given as their entries of a LONG value
+    fn tiff(ifds: &[&[(u16, u32)]]) -> Vec<u8> {
+        let ifds: Vec<Vec<_>> = ifds
+            .iter()
+            .map(|entries| {
+                entries
+                    .iter()
+                    .map(|&(tag, value)| (tag, 4, 1, value.to_le_bytes()))
+                    .collect()
+            })
+            .collect();
+        tiff_of(&ifds.iter().map(Vec::as_slice).collect::<Vec<_>>())
+    }
+
+    // The tag, type, count and value of an IFD entry
+    type RawEntry = (u16, u16, u32, [u8; 4]);
+
+    // A little endian TIFF with the IFDs, given as their entries
+    fn tiff_of(ifds: &[&[RawEntry]]) -> Vec<u8> {
+        let mut buf = b"II*\0\x08\0\0\0".to_vec();
+        for (i, entries) in ifds.iter().enumerate() {
+            buf.extend_from_slice(&(entries.len() as u16).to_le_bytes());
+            for &(tag, kind, count, value) in entries.iter() {
+                buf.extend_from_slice(&tag.to_le_bytes());
+                buf.extend_from_slice(&kind.to_le_bytes());
+                buf.extend_from_slice(&count.to_le_bytes());
+                buf.extend_from_slice(&value);
+            }
+            let next = match i + 1 < ifds.len() {
+                true => buf.len() as u32 + 4,
+                false => 0,
+            };
+            buf.extend_from_slice(&next.to_le_bytes());
+        }
+        buf
+    }
+
+    #[test]
+    fn test_decode_preview() {
+        let (small, large) = (jpeg(4, 2), jpeg(16, 8));
+        // Offsets of the JPEGs after the two IFDs of 3 entries
+        let start = 8 + 2 * (2 + 3 * 12 + 4);
+        let (small_at, large_at) = (start, start + small.len() as u32);
+        let mut buf = tiff(&[
+            &[
+                (NEW_SUBFILE_TYPE, 1),
+                (JPEG_OFFSET, small_at),
+                (JPEG_LENGTH, small.len() as u32),
+            ],
+            &[
+                (COMPRESSION, 6),
+                (STRIP_OFFSETS, large_at),
+                (STRIP_BYTE_COUNTS, large.len() as u32),
+            ],
+        ]);
+        assert_eq!(buf.len() as u32, start);
+        buf.extend_from_slice(&small);
+        buf.extend_from_slice(&large);
+
+        let mut raw = Tiff::new(Cursor::new(&buf)).unwrap().unwrap();
+        assert!(raw.is_raw());
+        assert_eq!(
+            raw.jpegs(),
+            [
+                (large_at, large.len() as u32),
+                (small_at, small.len() as u32)
+            ]
+        );
+        let img = decode_reader(Cursor::new(&buf), &Config::default()).unwrap();
+        assert_eq!(img.map(|img| img.dimensions()), Some((16, 8)));
+
+        // A TIFF with sub-images is taken for a RAW file, but has no preview
+        let buf = tiff(&[&[(SUB_IFDS, 0)]]);
+        assert!(Tiff::new(Cursor::new(&buf)).unwrap().unwrap().is_raw());
+        assert!(decode_reader(Cursor::new(&buf), &Config::default())
+            .unwrap()
+            .is_none());
+    }
+
+    #[test]
+    fn test_not_raw() {
+        let mut buf = Vec::new();
+        DynamicImage::new_rgb8(2, 2)
+            .write_to(&mut Cursor::new(&mut buf), image::ImageFormat::Tiff)
+            .unwrap();
+        let mut tiff = Tiff::new(Cursor::new(&buf)).unwrap().unwrap();
+        assert!(!tiff.is_raw());
+        assert!(Tiff::new(Cursor::new(b"\x89PNG")).unwrap().is_none());
+
+        // Cut off in the first IFD, which is left to the image crate to report
+        let buf = b"II*\0\x08\0\0\0\x05\0";
+        assert!(!Tiff::new(Cursor::new(buf)).unwrap().unwrap().is_raw());
+        assert!(decode_reader(Cursor::new(buf), &Config::default())
+            .unwrap()
+            .is_none());
+    }
+
+    #[test]
+    fn test_demosaic() {
+        let long = |tag, value: u32| (tag, 4, 1, value.to_le_bytes());
+        // 4x4 samples after the IFD of 9 entries, all red in an RGGB pattern
+        let start = 8 + 2 + 9 * 12 + 4;
+        let mut buf = tiff_of(&[&[
+            long(IMAGE_WIDTH, 4),
+            long(IMAGE_LENGTH, 4),
+            long(BITS_PER_SAMPLE, 8),
+            long(PHOTOMETRIC_INTERPRETATION, CFA),
+            long(STRIP_OFFSETS, start),
+            long(STRIP_BYTE_COUNTS, 16),
+            (CFA_REPEAT_PATTERN_DIM, 3, 2, [2, 0, 2, 0]),
+            (CFA_PATTERN, 1, 4, [0, 1, 1, 2]),
+            (DNG_VERSION, 1, 4, [1, 4, 0, 0]),
+        ]]);
+        assert_eq!(buf.len() as u32, start);
+        for y in 0..4 {
+            buf.extend((0..4).map(|x| match (x % 2, y % 2) {
+                (0, 0) => 255,
+                _ => 0,
+            }));
+        }
+
+        let img = decode_reader(Cursor::new(&buf), &Config::default())
+            .unwrap()
+            .unwrap();
+        assert_eq!(img.dimensions(), (4, 4));
+        assert!(img.to_rgb8().pixels().all(|p| p.0 == [255, 0, 0]));
+
+        // The strip is cut off
+        buf.truncate(buf.len() - 1);
+        assert!(decode_reader(Cursor::new(&buf), &Config::default())
+            .unwrap()
+            .is_none());
+    }
+
+    #[test]
+    fn test_is_lossy_jpeg() {
+        assert!(is_lossy_jpeg(&jpeg(2, 2)));
+        // Lossless JPEG, with a frame header of type 3
+        assert!(!is_lossy_jpeg(&[
+            0xff, 0xd8, 0xff, 0xc4, 0x00, 0x02, 0xff, 0xc3, 0x00, 0x02
+        ]));
+        assert!(!is_lossy_jpeg(b"\xff\xd9"));
+    }
+}
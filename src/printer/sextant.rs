@@ -0,0 +1,114 @@
+use crate::error::ViuResult;
+use crate::printer::backend::{Backend, CrosstermBackend};
+use crate::printer::cell::{block_pixels, fill_cells, split_colors, write_cells, Cell};
+use crate::printer::dither::dither;
+use crate::printer::{fit_to_config, resize_to, Printer};
+use crate::Config;
+
+use image::DynamicImage;
+use std::io::{BufWriter, Write};
+
+const SEXTANT_START: u32 = 0x1FB00;
+const LEFT_HALF: u32 = 0b010101;
+const RIGHT_HALF: u32 = 0b101010;
+
+/// Prints images using the sextant characters from the Symbols for Legacy Computing block,
+/// with each cell holding 2x3 pixels. Requires a font which supports Unicode 13.
+pub struct SextantPrinter;
+
+impl Printer for SextantPrinter {
+    fn print(
+        &self,
+        stdout: &mut impl Write,
+        img: &DynamicImage,
+        config: &Config,
+    ) -> ViuResult<(u32, u32)> {
+        let mut stream = CrosstermBackend::new(BufWriter::new(stdout));
+        print_to_backend(&mut stream, img, config)
+    }
+}
+
+fn print_to_backend(
+    stdout: &mut impl Backend,
+    img: &DynamicImage,
+    config: &Config,
+) -> ViuResult<(u32, u32)> {
+    let mut cells = Vec::new();
+    render_into(img, config, &mut cells);
+    write_cells(stdout, &cells, config)
+}
+
+// Resize the image to fit the config and convert it to rows of cells, reusing the
+// allocations of `cells`
+pub fn render_into(img: &DynamicImage, config: &Config, cells: &mut Vec<Vec<Cell>>) {
+    let (w, h) = fit_to_config(img.width(), img.height(), config);
+    let mut img = resize_to(img, 2 * w, 3 * h, config).into_rgba8();
+    dither(&mut img, config);
+
+    fill_cells(cells, w, h, |row, col| {
+        let pixels = block_pixels(&img, 2 * col, 3 * row, 2, 3, config);
+        let (mask, fg, bg) = split_colors(&pixels);
+        Cell {
+            ch: sextant(mask),
+            fg,
+            bg,
+        }
+    });
+}
+
+// Map a bit mask of the drawn sixths (top left = 1, top right = 2, middle left = 4, ...)
+// to a character. The empty, full and half patterns already exist in the Block Elements
+// block, so they are skipped in the sextant range.
+fn sextant(mask: u32) -> char {
+    let code = match mask {
+        0 => return ' ',
+        LEFT_HALF => return '▌',
+        RIGHT_HALF => return '▐',
+        0b111111 => return '█',
+        m if m > RIGHT_HALF => SEXTANT_START + m - 3,
+        m if m > LEFT_HALF => SEXTANT_START + m - 2,
+        m => SEXTANT_START + m - 1,
+    };
+    char::from_u32(code).unwrap_or(' ')
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ColorDepth;
+
+    #[test]
+    fn test_sextant_chars() {
+        assert_eq!(sextant(0), ' ');
+        assert_eq!(sextant(1), '\u{1FB00}');
+        assert_eq!(sextant(20), '\u{1FB13}');
+        assert_eq!(sextant(21), '▌');
+        assert_eq!(sextant(22), '\u{1FB14}');
+        assert_eq!(sextant(42), '▐');
+        assert_eq!(sextant(43), '\u{1FB28}');
+        assert_eq!(sextant(62), '\u{1FB3B}');
+        assert_eq!(sextant(63), '█');
+    }
+
+    #[test]
+    fn test_sextant_printer() {
+        let mut img = image::RgbaImage::from_pixel(2, 3, image::Rgba([0, 0, 0, 255]));
+        img.put_pixel(0, 0, image::Rgba([200, 100, 0, 255]));
+        let img = DynamicImage::ImageRgba8(img);
+
+        let config = Config {
+            absolute_offset: false,
+            color_depth: ColorDepth::TrueColor,
+            width: Some(1),
+            height: Some(1),
+            ..Default::default()
+        };
+        let mut buf = CrosstermBackend::new(vec![]);
+
+        assert_eq!(print_to_backend(&mut buf, &img, &config).unwrap(), (1, 1));
+        assert_eq!(
+            std::str::from_utf8(buf.get_ref()).unwrap(),
+            "\x1b[0m\x1b[38;2;200;100;0m\x1b[48;2;0;0;0m\u{1FB00}\x1b[0m\n"
+        );
+    }
+}
@@ -0,0 +1,95 @@
+use crate::error::ViuResult;
+use crate::printer::backend::{Backend, CrosstermBackend};
+use crate::printer::cell::{fill_cells, luminance, pixel_rgb, write_cells, Cell};
+use crate::printer::dither::dither;
+use crate::printer::{fit_to_config, resize_to, Printer};
+use crate::Config;
+
+use image::DynamicImage;
+use std::io::{BufWriter, Write};
+
+// The shade characters from the lightest to the densest
+const SHADES: [char; 4] = ['░', '▒', '▓', '█'];
+
+/// Prints images using the shade characters (░, ▒, ▓ and █), with each cell holding one
+/// pixel. The pixel's color is the foreground, and the brighter it is, the denser the
+/// character, over the terminal's background.
+pub struct ShadePrinter;
+
+impl Printer for ShadePrinter {
+    fn print(
+        &self,
+        stdout: &mut impl Write,
+        img: &DynamicImage,
+        config: &Config,
+    ) -> ViuResult<(u32, u32)> {
+        let mut stream = CrosstermBackend::new(BufWriter::new(stdout));
+        print_to_backend(&mut stream, img, config)
+    }
+}
+
+fn print_to_backend(
+    stdout: &mut impl Backend,
+    img: &DynamicImage,
+    config: &Config,
+) -> ViuResult<(u32, u32)> {
+    let mut cells = Vec::new();
+    render_into(img, config, &mut cells);
+    write_cells(stdout, &cells, config)
+}
+
+// Resize the image to fit the config and convert it to rows of cells, reusing the
+// allocations of `cells`
+pub fn render_into(img: &DynamicImage, config: &Config, cells: &mut Vec<Vec<Cell>>) {
+    let (w, h) = fit_to_config(img.width(), img.height(), config);
+    let mut img = resize_to(img, w, h, config).into_rgba8();
+    dither(&mut img, config);
+
+    fill_cells(cells, w, h, |row, col| {
+        let fg = pixel_rgb(col, row, img.get_pixel(col, row), config);
+        Cell {
+            ch: fg.map_or(' ', shade),
+            fg,
+            bg: None,
+        }
+    });
+}
+
+fn shade(rgb: (u8, u8, u8)) -> char {
+    SHADES[luminance(rgb) as usize * SHADES.len() / 256]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ColorDepth;
+
+    #[test]
+    fn test_shade_printer() {
+        let img = DynamicImage::ImageRgba8(image::RgbaImage::from_fn(2, 1, |x, _| {
+            if x == 0 {
+                image::Rgba([40, 40, 40, 255])
+            } else {
+                image::Rgba([255, 0, 0, 0])
+            }
+        }));
+        let config = Config {
+            absolute_offset: false,
+            transparent: true,
+            color_depth: ColorDepth::TrueColor,
+            width: Some(2),
+            height: Some(1),
+            size_mode: crate::SizeMode::Stretch,
+            ..Default::default()
+        };
+        let mut buf = CrosstermBackend::new(vec![]);
+
+        assert_eq!(print_to_backend(&mut buf, &img, &config).unwrap(), (2, 1));
+        assert_eq!(
+            std::str::from_utf8(buf.get_ref()).unwrap(),
+            "\x1b[0m\x1b[38;2;40;40;40m░\x1b[1C\x1b[0m\n"
+        );
+        assert_eq!(shade((255, 255, 255)), '█');
+        assert_eq!(shade((128, 128, 128)), '▓');
+    }
+}
@@ -1,11 +1,12 @@
 use crate::error::ViuResult;
-use crate::printer::{adjust_offset, find_best_fit, Printer};
-use crate::Config;
+use crate::printer::{adjust_offset, dither, fit_to_native, resize_to, Printer};
+use crate::utils;
+use crate::{ColorDepth, Config, Dither};
 use console::{Key, Term};
-use image::{imageops::FilterType, DynamicImage, GenericImageView};
+use image::{DynamicImage, GenericImageView};
 use lazy_static::lazy_static;
 use sixel_rs::encoder::{Encoder, QuickFrameBuilder};
-use sixel_rs::optflags::EncodePolicy;
+use sixel_rs::optflags::{DiffusionMethod, EncodePolicy};
 use std::io::Write;
 
 pub struct SixelPrinter;
@@ -26,15 +27,24 @@ impl Printer for SixelPrinter {
         img: &DynamicImage,
         config: &Config,
     ) -> ViuResult<(u32, u32)> {
-        let (w, h) = find_best_fit(img, config.width, config.height);
+        let (w, h) = fit_to_native(img.width(), img.height(), config);
+
+        // Cells are assumed to be 6x12 pixels when their size is not known
+        let (cell_w, cell_h) = utils::queried_cell_pixel_size().unwrap_or((6.0, 12.0));
+        let width = (w as f32 * cell_w).round() as u32;
+        let height = (h as f32 * cell_h).round() as u32;
 
         //TODO: the max 1000 width is an xterm bug workaround, other terminals may not be affected
-        let resized_img =
-            img.resize_exact(std::cmp::min(6 * w, 1000), 12 * h, FilterType::Triangle);
+        let resized_img = resize_to(img, std::cmp::min(width, 1000), height, config);
 
         let (width, height) = resized_img.dimensions();
 
-        let rgba = resized_img.to_rgba8();
+        let mut rgba = resized_img.to_rgba8();
+        // libsixel only diffuses errors, so ordered dithering is done here, to the xterm palette
+        // which fits in the 256 colors of a Sixel image and is then kept as it is
+        if config.dither == Dither::Ordered {
+            dither::ordered(&mut rgba, ColorDepth::Ansi256);
+        }
         let raw = rgba.as_raw();
 
         adjust_offset(stdout, config)?;
@@ -42,6 +52,11 @@ impl Printer for SixelPrinter {
         let encoder = Encoder::new()?;
 
         encoder.set_encode_policy(EncodePolicy::Fast)?;
+        encoder.set_diffusion(match config.dither {
+            Dither::None | Dither::Ordered => DiffusionMethod::None,
+            Dither::FloydSteinberg => DiffusionMethod::FS,
+            Dither::Atkinson => DiffusionMethod::Atkinson,
+        })?;
 
         let frame = QuickFrameBuilder::new()
             .width(width as usize)
@@ -49,7 +64,15 @@ impl Printer for SixelPrinter {
             .format(sixel_rs::sys::PixelFormat::RGBA8888)
             .pixels(raw.to_vec());
 
+        // libsixel writes to stdout or to a file. The output goes through a file, so that it
+        // can be written to the writer like the other printers' output.
+        let output = tempfile::Builder::new().suffix(".six").tempfile()?;
+        encoder.set_output(output.path())?;
         encoder.encode_bytes(frame)?;
+        // The file is closed along with the encoder
+        drop(encoder);
+        stdout.write_all(&std::fs::read(output.path())?)?;
+        stdout.flush()?;
 
         Ok((w, h))
     }
@@ -95,3 +118,24 @@ fn check_sixel_support() -> bool {
     }
     false
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use image::RgbaImage;
+
+    #[test]
+    fn test_print_to_writer() {
+        let img = DynamicImage::ImageRgba8(RgbaImage::new(12, 24));
+        let config = Config {
+            absolute_offset: false,
+            width: Some(2),
+            ..Default::default()
+        };
+        let mut out = Vec::new();
+        SixelPrinter.print(&mut out, &img, &config).unwrap();
+        let out = String::from_utf8_lossy(&out);
+        assert!(out.contains("\x1bP"));
+        assert!(out.ends_with("\x1b\\"));
+    }
+}
@@ -0,0 +1,106 @@
+// Serde support for the types from the image crate which are used in Config.
+// Colors are written as `[r, g, b]` arrays, filters by their variant name and limits as
+// a map of their fields.
+use image::{imageops::FilterType, io::Limits, Rgb};
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+#[derive(Serialize, Deserialize)]
+#[serde(remote = "FilterType")]
+enum FilterTypeDef {
+    Nearest,
+    Triangle,
+    CatmullRom,
+    Gaussian,
+    Lanczos3,
+}
+
+#[derive(Serialize, Deserialize)]
+struct Filter(#[serde(with = "FilterTypeDef")] FilterType);
+
+pub mod rgb {
+    use super::*;
+
+    pub fn serialize<S: Serializer>(color: &Rgb<u8>, serializer: S) -> Result<S::Ok, S::Error> {
+        color.0.serialize(serializer)
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Rgb<u8>, D::Error> {
+        Ok(Rgb(<[u8; 3]>::deserialize(deserializer)?))
+    }
+}
+
+pub mod option_rgb {
+    use super::*;
+
+    pub fn serialize<S: Serializer>(
+        color: &Option<Rgb<u8>>,
+        serializer: S,
+    ) -> Result<S::Ok, S::Error> {
+        color.map(|color| color.0).serialize(serializer)
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<Option<Rgb<u8>>, D::Error> {
+        Ok(Option::<[u8; 3]>::deserialize(deserializer)?.map(Rgb))
+    }
+}
+
+pub mod option_filter {
+    use super::*;
+
+    pub fn serialize<S: Serializer>(
+        filter: &Option<FilterType>,
+        serializer: S,
+    ) -> Result<S::Ok, S::Error> {
+        filter.map(Filter).serialize(serializer)
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<Option<FilterType>, D::Error> {
+        Ok(Option::<Filter>::deserialize(deserializer)?.map(|Filter(filter)| filter))
+    }
+}
+
+pub mod limits {
+    use super::*;
+
+    // Limits cannot be constructed outside of the image crate, so the fields are copied
+    #[derive(Serialize, Deserialize)]
+    #[serde(default)]
+    struct LimitsDef {
+        max_image_width: Option<u32>,
+        max_image_height: Option<u32>,
+        max_alloc: Option<u64>,
+    }
+
+    impl Default for LimitsDef {
+        fn default() -> Self {
+            let limits = Limits::default();
+            Self {
+                max_image_width: limits.max_image_width,
+                max_image_height: limits.max_image_height,
+                max_alloc: limits.max_alloc,
+            }
+        }
+    }
+
+    pub fn serialize<S: Serializer>(limits: &Limits, serializer: S) -> Result<S::Ok, S::Error> {
+        LimitsDef {
+            max_image_width: limits.max_image_width,
+            max_image_height: limits.max_image_height,
+            max_alloc: limits.max_alloc,
+        }
+        .serialize(serializer)
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Limits, D::Error> {
+        let def = LimitsDef::deserialize(deserializer)?;
+        let mut limits = Limits::default();
+        limits.max_image_width = def.max_image_width;
+        limits.max_image_height = def.max_image_height;
+        limits.max_alloc = def.max_alloc;
+        Ok(limits)
+    }
+}
@@ -0,0 +1,166 @@
+// Printing several images one after the other.
+use crate::error::ViuResult;
+use crate::utils::should_print;
+use crate::{clear, print_from_file, Config};
+
+use crossterm::event::{self, Event, KeyCode, KeyEvent, KeyEventKind, KeyModifiers};
+use crossterm::terminal::{disable_raw_mode, enable_raw_mode};
+use std::path::Path;
+use std::thread;
+use std::time::{Duration, Instant};
+
+/// Options for showing several images one after the other with [print_slideshow].
+#[derive(Clone)]
+pub struct SlideshowConfig {
+    /// How long every image stays on screen. Defaults to 5 seconds.
+    pub delay: Duration,
+    /// Start again from the first image after the last one. Defaults to false.
+    pub repeat: bool,
+    /// Move through the images with the keyboard: n, space or the right arrow shows the next
+    /// image, p or the left arrow the previous one, and q, Esc or Ctrl-C ends the slideshow.
+    /// The terminal is put in raw mode while waiting for a key. Defaults to false.
+    pub keyboard: bool,
+    /// Options used to print every image. Defaults to [Config::default].
+    pub config: Config,
+}
+
+impl Default for SlideshowConfig {
+    fn default() -> Self {
+        Self {
+            delay: Duration::from_secs(5),
+            repeat: false,
+            keyboard: false,
+            config: Config::default(),
+        }
+    }
+}
+
+// What to do after an image was shown
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Action {
+    Next,
+    Previous,
+    Quit,
+}
+
+/// Print the image files one after the other, waiting for [SlideshowConfig::delay] before
+/// the next one. Every image is removed with [crate::clear] before the next is printed, so
+/// the slideshow takes the place of a single image. The last image stays on the screen.
+///
+/// Returns when the last image was shown, unless [SlideshowConfig::repeat] is set, or when
+/// the user quits with the keyboard.
+/// ## Example
+/// ```no_run
+/// use std::path::PathBuf;
+/// use viuer::{print_slideshow, SlideshowConfig};
+///
+/// let paths = vec![PathBuf::from("a.png"), PathBuf::from("b.jpg")];
+/// let options = SlideshowConfig {
+///     keyboard: true,
+///     ..Default::default()
+/// };
+/// print_slideshow(&paths, &options).expect("Slideshow failed.");
+/// ```
+pub fn print_slideshow<P: AsRef<Path>>(paths: &[P], options: &SlideshowConfig) -> ViuResult {
+    let config = &options.config;
+    if paths.is_empty() || !should_print(config.when) {
+        return Ok(());
+    }
+
+    let mut index = 0;
+    loop {
+        let info = print_from_file(&paths[index], config)?;
+        let action = wait(options)?;
+        match next_index(index, paths.len(), action, options.repeat) {
+            Some(next) => {
+                clear(&info, config)?;
+                index = next;
+            }
+            None => return Ok(()),
+        }
+    }
+}
+
+// Wait for the delay, or for a key which was pressed before it ran out
+fn wait(options: &SlideshowConfig) -> ViuResult<Action> {
+    if !options.keyboard {
+        thread::sleep(options.delay);
+        return Ok(Action::Next);
+    }
+    // Raw mode is only enabled while waiting, since the printers rely on newlines moving
+    // the cursor to the start of the next line
+    enable_raw_mode()?;
+    let action = read_action(options.delay);
+    disable_raw_mode()?;
+    action
+}
+
+fn read_action(delay: Duration) -> ViuResult<Action> {
+    let deadline = Instant::now() + delay;
+    loop {
+        let left = deadline.saturating_duration_since(Instant::now());
+        if left.is_zero() || !event::poll(left)? {
+            return Ok(Action::Next);
+        }
+        if let Event::Key(key) = event::read()? {
+            if let Some(action) = key_action(key) {
+                return Ok(action);
+            }
+        }
+    }
+}
+
+fn key_action(key: KeyEvent) -> Option<Action> {
+    if key.kind == KeyEventKind::Release {
+        return None;
+    }
+    match key.code {
+        KeyCode::Char('n') | KeyCode::Char(' ') | KeyCode::Right => Some(Action::Next),
+        KeyCode::Char('p') | KeyCode::Left => Some(Action::Previous),
+        KeyCode::Char('q') | KeyCode::Esc => Some(Action::Quit),
+        // Raw mode turns Ctrl-C into a key instead of a signal
+        KeyCode::Char('c') if key.modifiers.contains(KeyModifiers::CONTROL) => Some(Action::Quit),
+        _ => None,
+    }
+}
+
+// Index of the image to show after the action, or None when the slideshow ends
+fn next_index(index: usize, len: usize, action: Action, repeat: bool) -> Option<usize> {
+    match action {
+        Action::Next if index + 1 < len => Some(index + 1),
+        Action::Next if repeat => Some(0),
+        Action::Next | Action::Quit => None,
+        Action::Previous if index > 0 => Some(index - 1),
+        Action::Previous if repeat => Some(len - 1),
+        // Show the first image again
+        Action::Previous => Some(0),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_next_index() {
+        assert_eq!(next_index(0, 3, Action::Next, false), Some(1));
+        assert_eq!(next_index(2, 3, Action::Next, false), None);
+        assert_eq!(next_index(2, 3, Action::Next, true), Some(0));
+        assert_eq!(next_index(0, 3, Action::Previous, false), Some(0));
+        assert_eq!(next_index(0, 3, Action::Previous, true), Some(2));
+        assert_eq!(next_index(1, 3, Action::Quit, true), None);
+    }
+
+    #[test]
+    fn test_key_action() {
+        let key = |code| KeyEvent::new(code, KeyModifiers::NONE);
+        assert_eq!(key_action(key(KeyCode::Char('n'))), Some(Action::Next));
+        assert_eq!(key_action(key(KeyCode::Left)), Some(Action::Previous));
+        assert_eq!(key_action(key(KeyCode::Esc)), Some(Action::Quit));
+        assert_eq!(key_action(key(KeyCode::Char('c'))), None);
+        assert_eq!(
+            key_action(KeyEvent::new(KeyCode::Char('c'), KeyModifiers::CONTROL)),
+            Some(Action::Quit)
+        );
+    }
+}
@@ -0,0 +1,397 @@
+// Detection of the terminal's features, from the environment and from its replies to queries.
+use crate::utils::{in_screen, in_tmux, over_ssh, tmux_passthrough, truecolor_available};
+use crate::{get_kitty_support, is_iterm_supported, KittySupport};
+
+use image::Rgb;
+use lazy_static::lazy_static;
+use std::sync::Mutex;
+use std::time::Duration;
+
+pub(crate) const DEFAULT_QUERY_TIMEOUT: Duration = Duration::from_millis(100);
+
+// Primary device attributes, answered by every terminal. Sent after the other queries,
+// so that its reply marks the end of the replies.
+const DEVICE_ATTRIBUTES: &str = "\x1b[c";
+// XTGETTCAP for the "RGB" and "Tc" capabilities, which report true color support
+const TRUECOLOR_QUERY: &str = "\x1bP+q524742\x1b\\\x1bP+q5463\x1b\\";
+// A kitty graphics query for a 1x1 image, sent directly. Wrapped for tmux when inside it.
+const KITTY_QUERY: &str = "\x1b_Gi=31,s=1,v=1,a=q,t=d,f=24;AAAA\x1b\\";
+// Size of a cell in pixels
+const CELL_SIZE_QUERY: &str = "\x1b[16t";
+// Size of the text area in pixels
+const WINDOW_SIZE_QUERY: &str = "\x1b[14t";
+// OSC 11, the background color
+const BACKGROUND_QUERY: &str = "\x1b]11;?\x1b\\";
+// XTVERSION, the name and version of the terminal
+const VERSION_QUERY: &str = "\x1b[>q";
+
+lazy_static! {
+    static ref BACKGROUND_COLOR: Option<Rgb<u8>> = terminal_background_color();
+    // The window size in pixels replied by the terminal, None until it is queried
+    static ref WINDOW_PIXELS: Mutex<Option<Option<(u16, u16)>>> = Mutex::new(None);
+    static ref VERIFIED_PROTOCOLS: VerifiedProtocols = verify_protocols(DEFAULT_QUERY_TIMEOUT);
+}
+
+/// How the graphics protocols supported by the terminal are detected.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum ProtocolPolicy {
+    /// Query the terminal when the environment variables may describe another terminal than
+    /// the one showing the output: over SSH, detected through `SSH_TTY` or `SSH_CONNECTION`,
+    /// or inside tmux. Trust the environment variables otherwise.
+    Auto,
+    /// Trust the environment variables, such as `TERM`, without querying the terminal.
+    Environment,
+    /// Only use the protocols which the terminal confirms in its replies to queries.
+    Query,
+}
+
+impl ProtocolPolicy {
+    // Whether the protocols have to be confirmed by the terminal
+    pub(crate) fn requires_query(self) -> bool {
+        match self {
+            ProtocolPolicy::Auto => over_ssh() || in_tmux(),
+            ProtocolPolicy::Environment => false,
+            ProtocolPolicy::Query => true,
+        }
+    }
+}
+
+// Graphics protocols which the terminal confirmed in its replies
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub(crate) struct VerifiedProtocols {
+    pub kitty: bool,
+    pub iterm: bool,
+    pub sixel: bool,
+    // The terminal did not finish replying before the timeout
+    pub timed_out: bool,
+}
+
+/// The features of the terminal, as returned by [capabilities].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Capabilities {
+    /// Whether 24-bit colors are supported.
+    pub truecolor: bool,
+    /// Support for the Kitty graphics protocol.
+    pub kitty_graphics: KittySupport,
+    /// Whether the iTerm inline images protocol is supported.
+    pub iterm_graphics: bool,
+    /// Whether Sixel graphics are supported.
+    pub sixel: bool,
+    /// Width and height of a cell in pixels, if the terminal reports them.
+    pub cell_pixel_size: Option<(u16, u16)>,
+    /// Whether the program runs inside tmux.
+    pub tmux: bool,
+    /// Whether the program runs inside GNU screen, in which case only blocks are printed.
+    pub screen: bool,
+}
+
+/// Detect what the terminal supports, waiting at most 100ms for its replies.
+/// See [capabilities_with_timeout].
+pub fn capabilities() -> Capabilities {
+    capabilities_with_timeout(DEFAULT_QUERY_TIMEOUT)
+}
+
+/// Detect what the terminal supports. The environment variables are checked first, and the
+/// terminal is then queried for what they do not tell, waiting at most `timeout` for its
+/// replies. Queries are only sent when both stdin and stdout are terminals, and are answered
+/// through stdin, so they should not be made while another thread reads the input.
+///
+/// ## Example
+/// ```no_run
+/// use std::time::Duration;
+/// use viuer::capabilities_with_timeout;
+///
+/// let caps = capabilities_with_timeout(Duration::from_millis(500));
+/// if caps.sixel || caps.iterm_graphics {
+///     println!("Images will be printed in full resolution");
+/// }
+/// ```
+pub fn capabilities_with_timeout(timeout: Duration) -> Capabilities {
+    let cell_pixel_size = window_cell_size();
+    let mut caps = Capabilities {
+        truecolor: truecolor_available(),
+        kitty_graphics: KittySupport::None,
+        iterm_graphics: is_iterm_supported(),
+        sixel: false,
+        cell_pixel_size,
+        tmux: std::env::var("TMUX").is_ok_and(|tmux| !tmux.is_empty()),
+        screen: in_screen(),
+    };
+
+    let kitty_term = std::env::var("TERM").is_ok_and(|term| term.contains("kitty"))
+        || std::env::var("KITTY_WINDOW_ID").is_ok_and(|id| !id.is_empty());
+    if kitty_term {
+        caps.kitty_graphics = get_kitty_support();
+    }
+
+    let mut queries = String::new();
+    if !caps.truecolor {
+        queries.push_str(TRUECOLOR_QUERY);
+    }
+    if !kitty_term {
+        queries.push_str(&kitty_query());
+    }
+    if cell_pixel_size.is_none() {
+        queries.push_str(CELL_SIZE_QUERY);
+    }
+
+    if let Some(replies) = query(&queries, timeout) {
+        caps.truecolor |= replies.contains("\x1bP1+r");
+        if replies.contains("\x1b_Gi=31;OK") {
+            caps.kitty_graphics = KittySupport::Remote;
+        }
+        caps.sixel = parse_device_attributes(&replies).is_some_and(|attrs| attrs.contains(&4));
+        caps.cell_pixel_size = caps.cell_pixel_size.or_else(|| parse_cell_size(&replies));
+    }
+    caps
+}
+
+/// Query the terminal's background color, waiting at most 100ms for the reply.
+/// Returns None if the terminal does not report it or can not be queried, e.g. when
+/// stdin or stdout is not a terminal.
+pub fn terminal_background_color() -> Option<Rgb<u8>> {
+    query(BACKGROUND_QUERY, DEFAULT_QUERY_TIMEOUT).and_then(|replies| parse_color(&replies))
+}
+
+// The graphics protocols confirmed by the terminal, only queried the first time they are
+// needed
+pub(crate) fn verified_protocols() -> VerifiedProtocols {
+    *VERIFIED_PROTOCOLS
+}
+
+fn verify_protocols(timeout: Duration) -> VerifiedProtocols {
+    let queries = format!("{}{}", kitty_query(), VERSION_QUERY);
+    query(&queries, timeout)
+        .map(|replies| parse_verified_protocols(&replies))
+        .unwrap_or_default()
+}
+
+fn kitty_query() -> String {
+    if in_tmux() {
+        tmux_passthrough(KITTY_QUERY)
+    } else {
+        KITTY_QUERY.to_owned()
+    }
+}
+
+fn parse_verified_protocols(replies: &str) -> VerifiedProtocols {
+    VerifiedProtocols {
+        // The reply to the device attributes comes last
+        timed_out: parse_device_attributes(replies).is_none(),
+        kitty: replies.contains("\x1b_Gi=31;OK"),
+        // Only iTerm2 and WezTerm are known to support the iTerm protocol
+        iterm: parse_version(replies)
+            .is_some_and(|version| version.starts_with("iTerm2") || version.starts_with("WezTerm")),
+        sixel: parse_device_attributes(replies).is_some_and(|attrs| attrs.contains(&4)),
+    }
+}
+
+// Name and version of the terminal from the XTVERSION reply, "\x1bP>|{name} {version}\x1b\\"
+fn parse_version(replies: &str) -> Option<&str> {
+    let start = replies.find("\x1bP>|")? + 4;
+    let end = start + replies[start..].find('\x1b')?;
+    Some(&replies[start..end])
+}
+
+// The background color, only queried the first time it is needed
+pub(crate) fn cached_background_color() -> Option<Rgb<u8>> {
+    *BACKGROUND_COLOR
+}
+
+// Color from the OSC 11 reply, "\x1b]11;rgb:{r}/{g}/{b}" terminated by ST or BEL. Each
+// component has 1 to 4 hex digits.
+fn parse_color(replies: &str) -> Option<Rgb<u8>> {
+    let start = replies.find("\x1b]11;rgb:")? + 9;
+    let end = start + replies[start..].find(['\x1b', '\x07'])?;
+    let mut components = replies[start..end].split('/').map(|hex| {
+        let max = 16u32
+            .checked_pow(hex.len() as u32)
+            .filter(|_| hex.len() <= 4)?
+            - 1;
+        let value = u32::from_str_radix(hex, 16).ok()?;
+        Some(((value * 255 + max / 2) / max) as u8)
+    });
+    let color = Rgb([
+        components.next()??,
+        components.next()??,
+        components.next()??,
+    ]);
+    components.next().is_none().then_some(color)
+}
+
+// The size of the window in pixels from the terminal's reply to a query, for terminals which
+// do not report it through the window size. Only queried the first time it is needed, and
+// again after the window is resized.
+pub(crate) fn cached_window_pixels() -> Option<(u16, u16)> {
+    *WINDOW_PIXELS
+        .lock()
+        .unwrap_or_else(|e| e.into_inner())
+        .get_or_insert_with(|| {
+            query(WINDOW_SIZE_QUERY, DEFAULT_QUERY_TIMEOUT)
+                .and_then(|replies| parse_window_size(&replies))
+        })
+}
+
+// Forget the window size in pixels after a resize, so that it is queried again
+#[cfg(all(unix, feature = "signal-handler"))]
+pub(crate) fn invalidate_window_pixels() {
+    *WINDOW_PIXELS.lock().unwrap_or_else(|e| e.into_inner()) = None;
+}
+
+// Cell size computed from the window size in pixels, if the terminal sets it
+fn window_cell_size() -> Option<(u16, u16)> {
+    let size = crossterm::terminal::window_size().ok()?;
+    if size.width == 0 || size.height == 0 || size.columns == 0 || size.rows == 0 {
+        return None;
+    }
+    Some((size.width / size.columns, size.height / size.rows))
+}
+
+// Parameters of the primary device attributes reply, e.g. "\x1b[?62;4;22c"
+fn parse_device_attributes(replies: &str) -> Option<Vec<u32>> {
+    let start = replies.find("\x1b[?")? + 3;
+    let end = start + replies[start..].find('c')?;
+    replies[start..end]
+        .split(';')
+        .map(|attr| attr.parse().ok())
+        .collect()
+}
+
+// Width and height from the cell size reply, "\x1b[6;{height};{width}t"
+fn parse_cell_size(replies: &str) -> Option<(u16, u16)> {
+    parse_size_reply(replies, "\x1b[6;")
+}
+
+// Width and height from the window size reply, "\x1b[4;{height};{width}t"
+fn parse_window_size(replies: &str) -> Option<(u16, u16)> {
+    parse_size_reply(replies, "\x1b[4;")
+}
+
+fn parse_size_reply(replies: &str, prefix: &str) -> Option<(u16, u16)> {
+    let start = replies.find(prefix)? + prefix.len();
+    let end = start + replies[start..].find('t')?;
+    let (height, width) = replies[start..end].split_once(';')?;
+    Some((width.parse().ok()?, height.parse().ok()?))
+}
+
+// Send the queries and collect the replies, until the device attributes arrive or the
+// timeout expires. Returns None if the terminal can not be queried.
+#[cfg(unix)]
+pub(crate) fn query(queries: &str, timeout: Duration) -> Option<String> {
+    use crossterm::terminal::{disable_raw_mode, enable_raw_mode, is_raw_mode_enabled};
+    use std::io::{IsTerminal, Write};
+    use std::time::Instant;
+
+    if !std::io::stdin().is_terminal() || !std::io::stdout().is_terminal() {
+        return None;
+    }
+
+    // Raw mode keeps the replies from being echoed, and makes them readable before a newline
+    let was_raw = is_raw_mode_enabled().ok()?;
+    if !was_raw {
+        enable_raw_mode().ok()?;
+    }
+
+    let mut stdout = std::io::stdout();
+    let sent = write!(stdout, "{}{}", queries, DEVICE_ATTRIBUTES).and_then(|_| stdout.flush());
+
+    let deadline = Instant::now() + timeout;
+    let mut replies = Vec::new();
+    while sent.is_ok() && parse_device_attributes(&String::from_utf8_lossy(&replies)).is_none() {
+        let remaining = deadline.saturating_duration_since(Instant::now());
+        if remaining.is_zero() || !read_stdin(&mut replies, remaining) {
+            break;
+        }
+    }
+
+    if !was_raw {
+        let _ = disable_raw_mode();
+    }
+    sent.ok()?;
+    Some(String::from_utf8_lossy(&replies).into_owned())
+}
+
+#[cfg(not(unix))]
+pub(crate) fn query(_queries: &str, _timeout: Duration) -> Option<String> {
+    None
+}
+
+// Wait for input on stdin and append what is available to the buffer. Returns false if
+// nothing arrived before the timeout. The file descriptor is read directly, as the buffer
+// of std::io::Stdin would keep the input that poll() waits for.
+#[cfg(unix)]
+fn read_stdin(buf: &mut Vec<u8>, timeout: Duration) -> bool {
+    let mut fd = libc::pollfd {
+        fd: libc::STDIN_FILENO,
+        events: libc::POLLIN,
+        revents: 0,
+    };
+    let timeout = timeout.as_millis().min(i32::MAX as u128) as i32;
+    // SAFETY: a single valid pollfd is passed
+    if unsafe { libc::poll(&mut fd, 1, timeout) } <= 0 {
+        return false;
+    }
+
+    let mut chunk = [0u8; 256];
+    // SAFETY: the pointer and length describe the chunk buffer
+    let read = unsafe { libc::read(libc::STDIN_FILENO, chunk.as_mut_ptr().cast(), chunk.len()) };
+    if read <= 0 {
+        return false;
+    }
+    buf.extend_from_slice(&chunk[..read as usize]);
+    true
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_device_attributes() {
+        let replies = "\x1bP1+r5463\x1b\\\x1b[?62;4;22c";
+        assert_eq!(parse_device_attributes(replies), Some(vec![62, 4, 22]));
+        assert_eq!(parse_device_attributes("\x1b[?1;2c"), Some(vec![1, 2]));
+        assert_eq!(parse_device_attributes("\x1b[?62;4"), None);
+    }
+
+    #[test]
+    fn test_parse_color() {
+        let reply = "\x1b]11;rgb:1e1e/1e1e/2e2e\x1b\\\x1b[?62c";
+        assert_eq!(parse_color(reply), Some(Rgb([30, 30, 46])));
+        assert_eq!(
+            parse_color("\x1b]11;rgb:f/80/0\x07"),
+            Some(Rgb([255, 128, 0]))
+        );
+        assert_eq!(parse_color("\x1b]11;rgb:ff/ff\x07"), None);
+        assert_eq!(parse_color("\x1b[?62c"), None);
+    }
+
+    #[test]
+    fn test_parse_verified_protocols() {
+        let replies = "\x1b_Gi=31;OK\x1b\\\x1bP>|WezTerm 20240203\x1b\\\x1b[?62;22c";
+        let verified = parse_verified_protocols(replies);
+        assert!(verified.kitty && verified.iterm && !verified.sixel);
+        assert!(!verified.timed_out);
+        assert!(parse_verified_protocols("\x1b_Gi=31;OK\x1b\\").timed_out);
+
+        let replies = "\x1bP>|XTerm(388)\x1b\\\x1b[?63;4c";
+        assert_eq!(
+            parse_verified_protocols(replies),
+            VerifiedProtocols {
+                kitty: false,
+                iterm: false,
+                sixel: true,
+                timed_out: false,
+            }
+        );
+    }
+
+    #[test]
+    fn test_parse_cell_size() {
+        assert_eq!(parse_cell_size("\x1b[6;18;9t\x1b[?62c"), Some((9, 18)));
+        assert_eq!(parse_cell_size("\x1b[?62c"), None);
+        assert_eq!(parse_window_size("\x1b[4;480;640t"), Some((640, 480)));
+        assert_eq!(parse_window_size("\x1b[6;18;9t"), None);
+    }
+}
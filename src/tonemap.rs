@@ -0,0 +1,165 @@
+// Fitting high dynamic range images into the 8 bits per channel printed to the terminal.
+// Converting them directly clips their light. 16-bit images already fit the range, but
+// linear astronomy data which only uses the bottom of it comes out almost black, so they
+// can be exposed too.
+use crate::printer::gamma::{linear_to_srgb, srgb_to_linear};
+use crate::Config;
+use image::{ColorType, DynamicImage, Rgba, RgbaImage};
+
+// Average light that the automatic exposure maps the image to, mid gray
+const KEY: f32 = 0.18;
+
+/// Operator used to fit the light of high dynamic range images, like EXR files, into the
+/// range of the terminal, set through [Config::tone_mapping]. 16-bit images are only tone
+/// mapped when [Config::exposure] is set, and images with 8 bits per channel are printed as
+/// they are.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum ToneMapping {
+    /// Clip the light above the range, like a direct conversion to 8 bits does.
+    Clamp,
+    /// Compress the highlights smoothly with the Reinhard operator, keeping the shadows.
+    Reinhard,
+    /// Filmic curve approximating the ACES reference rendering, with more contrast than
+    /// [ToneMapping::Reinhard].
+    Aces,
+}
+
+// Map the image to 8 bits per channel. Returns None for images which are converted by
+// narrowing their values, like 8-bit ones or 16-bit ones without an exposure.
+pub fn apply(img: &DynamicImage, config: &Config) -> Option<DynamicImage> {
+    let is_float = match img.color() {
+        ColorType::Rgb32F | ColorType::Rgba32F => true,
+        ColorType::L16 | ColorType::La16 | ColorType::Rgb16 | ColorType::Rgba16
+            if config.exposure.is_some() =>
+        {
+            false
+        }
+        _ => return None,
+    };
+
+    // Floating point images hold linear light, 16-bit ones are encoded like 8-bit sRGB
+    let mut light = img.to_rgba32f();
+    if !is_float {
+        for p in light.pixels_mut() {
+            for c in &mut p.0[..3] {
+                *c = srgb_to_linear(*c);
+            }
+        }
+    }
+
+    let exposure = match (config.exposure, config.tone_mapping) {
+        (Some(exposure), _) => exposure,
+        (None, ToneMapping::Clamp) => 1.0,
+        (None, _) => KEY / average_luminance(&light),
+    };
+    let operator: fn(f32) -> f32 = match config.tone_mapping {
+        ToneMapping::Clamp => |x: f32| x,
+        ToneMapping::Reinhard => |x: f32| x / (1.0 + x),
+        ToneMapping::Aces => aces,
+    };
+
+    let mapped = RgbaImage::from_fn(light.width(), light.height(), |x, y| {
+        let p = light.get_pixel(x, y);
+        // NaN values, which some EXR files contain, become black
+        let map = |c: f32| linear_to_srgb(operator((c * exposure).max(0.0)));
+        Rgba([
+            map(p[0]),
+            map(p[1]),
+            map(p[2]),
+            (p[3].clamp(0.0, 1.0) * 255.0).round() as u8,
+        ])
+    });
+    Some(DynamicImage::ImageRgba8(mapped))
+}
+
+// Logarithmic average of the luminance, which is not dominated by a few bright pixels
+fn average_luminance(light: &image::Rgba32FImage) -> f32 {
+    if light.is_empty() {
+        return KEY;
+    }
+    let sum: f64 = light
+        .pixels()
+        .map(|p| {
+            let luminance = 0.2126 * p[0] + 0.7152 * p[1] + 0.0722 * p[2];
+            let luminance = if luminance.is_finite() {
+                luminance
+            } else {
+                0.0
+            };
+            (1e-4 + luminance.max(0.0) as f64).ln()
+        })
+        .sum();
+    (sum / light.pixels().len() as f64).exp() as f32
+}
+
+// Fit of the ACES curve by Krzysztof Narkowicz
+fn aces(x: f32) -> f32 {
+    let x = x * 0.6;
+    (x * (2.51 * x + 0.03)) / (x * (2.43 * x + 0.59) + 0.14)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use image::{GenericImageView, ImageBuffer, Rgba32FImage};
+
+    fn config(tone_mapping: ToneMapping, exposure: Option<f32>) -> Config {
+        Config {
+            tone_mapping,
+            exposure,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_8_bit_unchanged() {
+        let img = DynamicImage::ImageRgba8(RgbaImage::new(2, 2));
+        assert!(apply(&img, &Config::default()).is_none());
+    }
+
+    #[test]
+    fn test_16_bit_narrowed() {
+        let img = DynamicImage::ImageRgb16(ImageBuffer::from_fn(16, 16, |x, y| {
+            image::Rgb([(x * 4096) as u16, (y * 4096) as u16, 65535])
+        }));
+        assert!(apply(&img, &Config::default()).is_none());
+
+        let mapped = apply(&img, &config(ToneMapping::Clamp, Some(1.0))).unwrap();
+        assert_eq!(mapped.to_rgba8(), img.to_rgba8());
+        let exposed = apply(&img, &config(ToneMapping::Reinhard, Some(4.0))).unwrap();
+        assert!(exposed.get_pixel(1, 1)[0] > img.to_rgba8().get_pixel(1, 1)[0]);
+    }
+
+    #[test]
+    fn test_dark_image_exposed() {
+        // Linear data using a tiny part of the range, like astrophotography
+        let img = DynamicImage::ImageRgba32F(Rgba32FImage::from_fn(4, 1, |x, _| {
+            let value = 0.001 * (x + 1) as f32;
+            Rgba([value, value, value, 1.0])
+        }));
+        assert_eq!(img.get_pixel(3, 0), Rgba([1, 1, 1, 255]));
+
+        for operator in [ToneMapping::Reinhard, ToneMapping::Aces] {
+            let mapped = apply(&img, &config(operator, None)).unwrap();
+            let values: Vec<_> = mapped.pixels().map(|(_, _, p)| p[0]).collect();
+            assert!(values.windows(2).all(|w| w[0] < w[1]), "{:?}", values);
+            assert!(values[1] > 64, "{:?}", values);
+        }
+    }
+
+    #[test]
+    fn test_highlights_compressed() {
+        let img = DynamicImage::ImageRgb32F(ImageBuffer::from_fn(3, 1, |x, _| {
+            let value = [1.0, 4.0, f32::NAN][x as usize];
+            image::Rgb([value, value, value])
+        }));
+        let clamped = apply(&img, &config(ToneMapping::Clamp, Some(1.0))).unwrap();
+        assert_eq!(clamped.get_pixel(0, 0), clamped.get_pixel(1, 0));
+        assert_eq!(clamped.get_pixel(2, 0), Rgba([0, 0, 0, 255]));
+
+        let reinhard = apply(&img, &config(ToneMapping::Reinhard, Some(1.0))).unwrap();
+        assert!(reinhard.get_pixel(0, 0)[0] < reinhard.get_pixel(1, 0)[0]);
+        assert!(reinhard.get_pixel(1, 0)[0] < 255);
+    }
+}
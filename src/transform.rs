@@ -0,0 +1,415 @@
+use crate::printer::{cell_aspect_ratio, cover_bounds};
+use crate::terminal::cached_background_color;
+use crate::{adjust, overlay, tonemap};
+use crate::{Config, SizeMode};
+use image::{imageops, DynamicImage, GenericImageView, Rgb, RgbaImage};
+use std::borrow::Cow;
+
+/// Clockwise rotation applied to the image before printing, set through [Config::rotate].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Rotation {
+    /// Rotate by 90 degrees.
+    Rotate90,
+    /// Rotate by 180 degrees.
+    Rotate180,
+    /// Rotate by 270 degrees.
+    Rotate270,
+}
+
+// Check whether the config requires the image to be modified before printing. If it does,
+// printers cannot pass the original file data through to the terminal.
+pub fn is_needed(config: &Config) -> bool {
+    config.crop.is_some()
+        || config.rotate.is_some()
+        || config.flip_h
+        || config.flip_v
+        || config.grayscale
+        || background(config).is_some()
+        || matches!(config.sizing(), SizeMode::Cover | SizeMode::Tile)
+        || !config.overlays.is_empty()
+        || config.filter_fn.is_some()
+        || adjust::is_needed(config)
+        || config.vision_filter.is_some()
+        || config.threshold.is_some()
+}
+
+// Apply the transformations from the config to the image. If there is nothing to do,
+// the original image is returned without copying it.
+pub fn apply<'a>(img: &'a DynamicImage, config: &Config) -> Cow<'a, DynamicImage> {
+    let mut img = Cow::Borrowed(img);
+
+    if let Some(mapped) = tonemap::apply(&img, config) {
+        img = Cow::Owned(mapped);
+    }
+
+    if let Some((x, y, width, height)) = crop_region(img.dimensions(), config) {
+        img = Cow::Owned(img.crop_imm(x, y, width, height));
+    }
+
+    img = match config.rotate {
+        Some(Rotation::Rotate90) => Cow::Owned(img.rotate90()),
+        Some(Rotation::Rotate180) => Cow::Owned(img.rotate180()),
+        Some(Rotation::Rotate270) => Cow::Owned(img.rotate270()),
+        None => img,
+    };
+
+    if config.flip_h {
+        img = Cow::Owned(img.fliph());
+    }
+
+    if config.flip_v {
+        img = Cow::Owned(img.flipv());
+    }
+
+    if let Some(background) = background(config) {
+        img = Cow::Owned(blend_background(&img, background));
+    }
+
+    if config.grayscale {
+        img = Cow::Owned(img.grayscale());
+    }
+
+    if config.sizing() == SizeMode::Cover {
+        img = Cow::Owned(crop_to_ratio(&img, cover_ratio(config)));
+    }
+
+    if config.sizing() == SizeMode::Tile {
+        img = Cow::Owned(tile(&img, tile_size(config)));
+    }
+
+    if !config.overlays.is_empty() {
+        img = Cow::Owned(overlay::apply(&img, &config.overlays));
+    }
+
+    img
+}
+
+// The dimensions of the image returned by apply, for an image of the given dimensions
+pub fn dimensions((width, height): (u32, u32), config: &Config) -> (u32, u32) {
+    let (mut width, mut height) = match crop_region((width, height), config) {
+        Some((_, _, width, height)) => (width, height),
+        None => (width, height),
+    };
+    if matches!(
+        config.rotate,
+        Some(Rotation::Rotate90) | Some(Rotation::Rotate270)
+    ) {
+        std::mem::swap(&mut width, &mut height);
+    }
+    if config.sizing() == SizeMode::Cover {
+        let (w, h, _, _) = ratio_crop((width, height), cover_ratio(config));
+        return (w, h);
+    }
+    if config.sizing() == SizeMode::Tile {
+        return tile_size(config);
+    }
+    (width, height)
+}
+
+// The region to crop from an image of the given dimensions, kept within the image so that
+// cropping never fails
+fn crop_region(
+    (img_width, img_height): (u32, u32),
+    config: &Config,
+) -> Option<(u32, u32, u32, u32)> {
+    let (x, y, width, height) = config.crop?;
+    if img_width == 0 || img_height == 0 {
+        return None;
+    }
+    let x = x.min(img_width - 1);
+    let y = y.min(img_height - 1);
+    let width = width.clamp(1, img_width - x);
+    let height = height.clamp(1, img_height - y);
+    Some((x, y, width, height))
+}
+
+// The aspect ratio in pixels of the area filled by SizeMode::Cover
+fn cover_ratio(config: &Config) -> f64 {
+    let (width, height) = cover_bounds(config);
+    width as f64 / (height as f64 * cell_aspect_ratio(config) as f64)
+}
+
+// The size in pixels of the area filled by SizeMode::Tile, with one pixel per column and as
+// many per row as keep the proportions, i.e. two for cells twice as high as wide
+fn tile_size(config: &Config) -> (u32, u32) {
+    let (width, height) = cover_bounds(config);
+    let height = (height as f32 * cell_aspect_ratio(config)).round() as u32;
+    (width, height.max(1))
+}
+
+// Repeat the image from the top left corner to fill the given size in pixels
+fn tile(img: &DynamicImage, (width, height): (u32, u32)) -> DynamicImage {
+    let mut tiled = RgbaImage::new(width, height);
+    imageops::tile(&mut tiled, &img.to_rgba8());
+    DynamicImage::ImageRgba8(tiled)
+}
+
+// Read the orientation from the file's EXIF metadata. Returns None if it is missing,
+// or if the image is already stored the right way up.
+#[cfg(feature = "exif")]
+pub fn exif_orientation<P: AsRef<std::path::Path>>(filename: P) -> Option<u32> {
+    // The decoders of HEIC and AVIF already apply the rotation stored in the container, which
+    // the EXIF orientation only repeats
+    if crate::printer::external::detect(&filename).ok()?.is_some() {
+        return None;
+    }
+    let file = std::fs::File::open(filename).ok()?;
+    let exif = exif::Reader::new()
+        .read_from_container(&mut std::io::BufReader::new(file))
+        .ok()?;
+    let field = exif.get_field(exif::Tag::Orientation, exif::In::PRIMARY)?;
+    field
+        .value
+        .get_uint(0)
+        .filter(|&orientation| orientation != 1)
+}
+
+// Rotate and flip the image according to an EXIF orientation value
+#[cfg(feature = "exif")]
+pub fn orient(img: DynamicImage, orientation: u32) -> DynamicImage {
+    match orientation {
+        2 => img.fliph(),
+        3 => img.rotate180(),
+        4 => img.flipv(),
+        5 => img.rotate90().fliph(),
+        6 => img.rotate90(),
+        7 => img.rotate270().fliph(),
+        8 => img.rotate270(),
+        _ => img,
+    }
+}
+
+// Crop the center of the image to the given aspect ratio, width divided by height
+fn crop_to_ratio(img: &DynamicImage, ratio: f64) -> DynamicImage {
+    let (w, h, x, y) = ratio_crop(img.dimensions(), ratio);
+    img.crop_imm(x, y, w, h)
+}
+
+// Size and position of the centered region with the given aspect ratio
+fn ratio_crop((img_width, img_height): (u32, u32), ratio: f64) -> (u32, u32, u32, u32) {
+    let (width, height) = (img_width as f64, img_height as f64);
+
+    let (w, h) = if width / height > ratio {
+        (height * ratio, height)
+    } else {
+        (width, width / ratio)
+    };
+    let (w, h) = ((w.round() as u32).max(1), (h.round() as u32).max(1));
+    (
+        w,
+        h,
+        img_width.saturating_sub(w) / 2,
+        img_height.saturating_sub(h) / 2,
+    )
+}
+
+// The color to blend the image with: the configured one or, if it should be detected in
+// transparent mode, the terminal's
+fn background(config: &Config) -> Option<Rgb<u8>> {
+    config.background.or_else(|| {
+        if config.transparent && config.detect_background {
+            cached_background_color()
+        } else {
+            None
+        }
+    })
+}
+
+// Alpha-blend every pixel over the background color, making the image opaque.
+fn blend_background(img: &DynamicImage, background: Rgb<u8>) -> DynamicImage {
+    let mut rgba = img.to_rgba8();
+    for pixel in rgba.pixels_mut() {
+        let alpha = pixel[3] as u32;
+        for c in 0..3 {
+            let blended = pixel[c] as u32 * alpha + background[c] as u32 * (255 - alpha);
+            pixel[c] = ((blended + 127) / 255) as u8;
+        }
+        pixel[3] = 255;
+    }
+    DynamicImage::ImageRgba8(rgba)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use image::{GenericImageView, Rgba};
+
+    #[test]
+    fn test_dimensions() {
+        let img = DynamicImage::ImageRgba8(image::RgbaImage::new(40, 25));
+        let configs = [
+            Config::default(),
+            Config {
+                crop: Some((30, 5, 20, 100)),
+                rotate: Some(Rotation::Rotate270),
+                ..Default::default()
+            },
+            Config {
+                width: Some(8),
+                height: Some(3),
+                size_mode: SizeMode::Cover,
+                cell_aspect_ratio: Some(2.0),
+                rotate: Some(Rotation::Rotate90),
+                ..Default::default()
+            },
+        ];
+        for config in configs {
+            assert_eq!(
+                dimensions(img.dimensions(), &config),
+                apply(&img, &config).dimensions()
+            );
+        }
+    }
+
+    #[test]
+    fn test_no_transform() {
+        let img = DynamicImage::ImageRgba8(image::RgbaImage::new(2, 2));
+        let config = Config::default();
+        assert!(!is_needed(&config));
+        assert!(matches!(apply(&img, &config), Cow::Borrowed(_)));
+    }
+
+    #[test]
+    fn test_background() {
+        let mut img = image::RgbaImage::new(3, 1);
+        img.put_pixel(0, 0, Rgba([255, 0, 0, 255]));
+        img.put_pixel(1, 0, Rgba([255, 255, 255, 128]));
+        let config = Config {
+            background: Some(Rgb([30, 30, 46])),
+            ..Default::default()
+        };
+        assert!(is_needed(&config));
+        let img = apply(&DynamicImage::ImageRgba8(img), &config).into_owned();
+        assert_eq!(img.get_pixel(0, 0), Rgba([255, 0, 0, 255]));
+        assert_eq!(img.get_pixel(1, 0), Rgba([143, 143, 151, 255]));
+        assert_eq!(img.get_pixel(2, 0), Rgba([30, 30, 46, 255]));
+    }
+
+    #[test]
+    fn test_crop() {
+        let mut img = image::RgbaImage::new(10, 10);
+        img.put_pixel(3, 4, Rgba([255, 0, 0, 255]));
+        let img = DynamicImage::ImageRgba8(img);
+
+        let config = Config {
+            crop: Some((3, 4, 2, 5)),
+            ..Default::default()
+        };
+        assert!(is_needed(&config));
+        let cropped = apply(&img, &config);
+        assert_eq!(cropped.dimensions(), (2, 5));
+        assert_eq!(cropped.get_pixel(0, 0), Rgba([255, 0, 0, 255]));
+
+        // Regions reaching outside of the image are clamped
+        let config = Config {
+            crop: Some((8, 20, 5, 5)),
+            ..Default::default()
+        };
+        assert_eq!(apply(&img, &config).dimensions(), (2, 1));
+    }
+
+    #[test]
+    fn test_rotate_and_flip() {
+        let mut img = image::RgbaImage::new(3, 2);
+        img.put_pixel(0, 0, Rgba([255, 0, 0, 255]));
+        let img = DynamicImage::ImageRgba8(img);
+
+        let config = Config {
+            rotate: Some(Rotation::Rotate90),
+            ..Default::default()
+        };
+        assert!(is_needed(&config));
+        let rotated = apply(&img, &config);
+        assert_eq!(rotated.dimensions(), (2, 3));
+        assert_eq!(rotated.get_pixel(1, 0), Rgba([255, 0, 0, 255]));
+
+        let config = Config {
+            flip_h: true,
+            flip_v: true,
+            ..Default::default()
+        };
+        let flipped = apply(&img, &config);
+        assert_eq!(flipped.get_pixel(2, 1), Rgba([255, 0, 0, 255]));
+    }
+
+    #[cfg(feature = "exif")]
+    #[test]
+    fn test_orient() {
+        let mut img = image::RgbaImage::new(3, 2);
+        img.put_pixel(1, 0, Rgba([255, 0, 0, 255]));
+        let img = DynamicImage::ImageRgba8(img);
+
+        // Where the pixel at (1, 0) ends up for every orientation
+        let expected = [
+            (1, 0),
+            (1, 0),
+            (1, 1),
+            (1, 1),
+            (0, 1),
+            (1, 1),
+            (1, 1),
+            (0, 1),
+        ];
+        for (orientation, &(x, y)) in (1..=8).zip(expected.iter()) {
+            let oriented = orient(img.clone(), orientation);
+            assert_eq!(
+                oriented.get_pixel(x, y),
+                Rgba([255, 0, 0, 255]),
+                "orientation {}",
+                orientation
+            );
+        }
+    }
+
+    #[test]
+    fn test_cover_crop() {
+        let img = DynamicImage::ImageRgba8(image::RgbaImage::new(100, 50));
+        let config = Config {
+            width: Some(20),
+            height: Some(20),
+            size_mode: SizeMode::Cover,
+            ..Default::default()
+        };
+        assert!(is_needed(&config));
+        // 20x20 cells hold 20x40 pixels
+        assert_eq!(apply(&img, &config).dimensions(), (25, 50));
+    }
+
+    #[test]
+    fn test_grayscale() {
+        let img =
+            DynamicImage::ImageRgba8(image::RgbaImage::from_pixel(1, 1, Rgba([255, 0, 0, 128])));
+        let config = Config {
+            grayscale: true,
+            ..Default::default()
+        };
+        assert!(is_needed(&config));
+        let pixel = apply(&img, &config).get_pixel(0, 0);
+        assert_eq!(pixel[0], pixel[1]);
+        assert_eq!(pixel[1], pixel[2]);
+        assert_eq!(pixel[3], 128);
+    }
+
+    #[test]
+    fn test_tile() {
+        let mut img = image::RgbaImage::new(3, 2);
+        img.put_pixel(0, 0, Rgba([255, 0, 0, 255]));
+        let config = Config {
+            width: Some(7),
+            height: Some(3),
+            size_mode: SizeMode::Tile,
+            ..Default::default()
+        };
+        assert!(is_needed(&config));
+        // 7x3 cells hold 7x6 pixels
+        let img = DynamicImage::ImageRgba8(img);
+        let tiled = apply(&img, &config);
+        assert_eq!(tiled.dimensions(), (7, 6));
+        assert_eq!(dimensions((3, 2), &config), (7, 6));
+        for (x, y) in [(0, 0), (3, 2), (6, 4)] {
+            assert_eq!(tiled.get_pixel(x, y), Rgba([255, 0, 0, 255]));
+        }
+        assert_eq!(tiled.get_pixel(1, 0), Rgba([0, 0, 0, 0]));
+    }
+}
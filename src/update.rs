@@ -0,0 +1,137 @@
+// Redrawing part of an image which was printed before.
+use crate::error::ViuResult;
+use crate::printer::backend::CrosstermBackend;
+use crate::printer::{cell, render_cells};
+use crate::utils::{cells, write_at_once};
+use crate::{clear, decoration, print_to, transform, Config, PrintedInfo, PrinterType, Rect};
+
+use crossterm::cursor::{
+    MoveTo, MoveToNextLine, MoveToPreviousLine, RestorePosition, SavePosition,
+};
+use crossterm::{execute, queue};
+use image::{DynamicImage, GenericImageView};
+use std::io::Write;
+
+/// Redraw the cells within `rect` of an image printed with the given config, which returned
+/// the given info. `img` is the image as it should look now, e.g. with a progress indicator
+/// or an annotation drawn over it, in the same size as the one which was printed. `rect` is
+/// given in cells from the top left corner of the image, decorations excluded.
+///
+/// The rest of the image is not touched, so it does not flash. This needs the image to be
+/// printed with blocks: images printed with a graphics protocol are removed with
+/// [crate::clear] and printed again whole.
+///
+/// The cursor has to be where printing left it, as for [crate::clear], and it is left there.
+/// ## Example
+/// ```no_run
+/// use image::Rgba;
+/// use viuer::{print, reprint_region, Config, PrinterType, Rect};
+///
+/// let mut img = image::open("img.jpg").expect("Could not decode file.").to_rgba8();
+/// let conf = Config {
+///     width: Some(40),
+///     printer: Some(PrinterType::Block),
+///     ..Default::default()
+/// };
+/// let info = print(&img.clone().into(), &conf).expect("Image printing failed.");
+///
+/// // Mark the top left corner
+/// for x in 0..10 {
+///     img.put_pixel(x, 0, Rgba([255, 0, 0, 255]));
+/// }
+/// let corner = Rect { x: 0, y: 0, width: 5, height: 1 };
+/// reprint_region(&img.into(), corner, &info, &conf).expect("Image printing failed.");
+/// ```
+pub fn reprint_region(
+    img: &DynamicImage,
+    rect: Rect,
+    info: &PrintedInfo,
+    config: &Config,
+) -> ViuResult {
+    if info.width == 0 || info.height == 0 || rect.width == 0 || rect.height == 0 {
+        return Ok(());
+    }
+    let mut stdout = std::io::stdout();
+    if info.printer != PrinterType::Block {
+        clear(info, config)?;
+        print_to(img, config, &mut stdout)?;
+        return Ok(());
+    }
+    write_at_once(&mut stdout, |mut stdout| {
+        reprint_cells(&mut stdout, img, rect, info, config)
+    })
+}
+
+fn reprint_cells(
+    stdout: &mut impl Write,
+    img: &DynamicImage,
+    rect: Rect,
+    info: &PrintedInfo,
+    config: &Config,
+) -> ViuResult {
+    // The image was printed within the decorations, aligned by the printer
+    let inner = decoration::inner_config(config);
+    let img = transform::apply(img, &inner);
+    let inner = decoration::align(&inner, PrinterType::Block.fit(img.dimensions(), &inner));
+    let rows = render_cells(&img, &inner);
+
+    execute!(stdout, SavePosition)?;
+    // Go to the first line of the image
+    if inner.absolute_offset {
+        queue!(stdout, MoveTo(0, inner.y.max(0) as u16))?;
+    } else if config.restore_cursor {
+        if inner.y > 0 {
+            queue!(stdout, MoveToNextLine(inner.y as u16))?;
+        } else if inner.y < 0 {
+            queue!(stdout, MoveToPreviousLine(-inner.y as u16))?;
+        }
+    } else {
+        // The lines printed above the image, within what was printed
+        let above = (inner.y - config.y).max(0) as u32;
+        let lines = cells(info.height.saturating_sub(above))?;
+        queue!(stdout, MoveToPreviousLine(lines))?;
+    }
+
+    let region = (rect.x, rect.y, rect.width, rect.height);
+    let mut backend = CrosstermBackend::new(&mut *stdout);
+    cell::write_cells_region(&mut backend, &rows, region, inner.x, inner.depth())?;
+    execute!(stdout, RestorePosition)?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ColorDepth;
+    use image::{Rgba, RgbaImage};
+
+    #[test]
+    fn test_reprint_cells() {
+        let mut img = RgbaImage::from_pixel(4, 4, Rgba([0, 0, 0, 255]));
+        let config = Config {
+            absolute_offset: false,
+            x: 2,
+            color_depth: ColorDepth::TrueColor,
+            printer: Some(PrinterType::Block),
+            ..Default::default()
+        };
+        let mut out = Vec::new();
+        let info = print_to(&img.clone().into(), &config, &mut out).unwrap();
+        assert_eq!((info.width, info.height), (4, 2));
+
+        img.put_pixel(1, 2, Rgba([255, 0, 0, 255]));
+        let rect = Rect {
+            x: 1,
+            y: 1,
+            width: 1,
+            height: 1,
+        };
+        let mut out = Vec::new();
+        reprint_cells(&mut out, &img.into(), rect, &info, &config).unwrap();
+        // Up to the first line of the image, then to its second line and third column
+        assert_eq!(
+            std::str::from_utf8(&out).unwrap(),
+            "\x1b7\x1b[2F\x1b[1E\x1b[3C\x1b[0m\x1b[38;2;0;0;0m\x1b[48;2;255;0;0m▄\x1b[0m\x1b8"
+        );
+    }
+}
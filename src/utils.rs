@@ -1,6 +1,15 @@
+use crate::error::{ViuError, ViuResult};
+use crate::terminal::cached_window_pixels;
+use crate::window;
+use crate::{BlockStyle, ColorDepth, Config, PrintWhen, PrinterType};
+use base64::display::Base64Display;
+use base64::engine::general_purpose::STANDARD;
 use std::env;
+use std::fmt;
+use std::io::{self, IsTerminal, Write};
 
 const DEFAULT_TERM_SIZE: (u16, u16) = (80, 24);
+const DEFAULT_CELL_ASPECT_RATIO: f32 = 2.0;
 
 pub fn truecolor_available() -> bool {
     if let Ok(value) = env::var("COLORTERM") {
@@ -10,9 +19,45 @@ pub fn truecolor_available() -> bool {
     }
 }
 
+// Guess the colors supported by the terminal from its environment. Truecolor is reported
+// through COLORTERM. Terminals known to support only the basic palette get 16 colors,
+// everything else is assumed to handle the 256 color palette.
+pub fn color_depth() -> ColorDepth {
+    // The legacy Windows console only knows its 16 colors, which crossterm sets through
+    // the console API
+    if !virtual_terminal_enabled() {
+        return ColorDepth::Ansi16;
+    }
+    if truecolor_available() {
+        return ColorDepth::TrueColor;
+    }
+    match env::var("TERM") {
+        Ok(term) if term.contains("256") => ColorDepth::Ansi256,
+        Ok(term) if is_16_color_term(&term) => ColorDepth::Ansi16,
+        _ => ColorDepth::Ansi256,
+    }
+}
+
+// Enable the processing of escape sequences by the Windows console with SetConsoleMode, as
+// older builds of Windows 10 leave it disabled. Returns false if it could not be enabled.
+#[cfg(windows)]
+pub fn virtual_terminal_enabled() -> bool {
+    crossterm::ansi_support::supports_ansi()
+}
+
+#[cfg(not(windows))]
+pub fn virtual_terminal_enabled() -> bool {
+    true
+}
+
+fn is_16_color_term(term: &str) -> bool {
+    matches!(term, "linux" | "ansi" | "cons25" | "dumb") || term.starts_with("vt")
+}
+
 /// Try to get the terminal size. If unsuccessful, fallback to a default (80x24).
 ///
-/// Uses [crossterm::terminal::size].
+/// Uses [crossterm::terminal::size]. With the "signal-handler" feature on unix, the size is
+/// cached and updated when the window is resized, see [crate::on_resize].
 /// ## Example
 /// The example below prints "img.jpg" with dimensions 80x40 in the center of the terminal.
 /// ```no_run
@@ -32,24 +77,320 @@ pub fn truecolor_available() -> bool {
 /// };
 /// print_from_file("img.jpg", &config).expect("Image printing failed.");
 /// ```
-#[cfg(not(test))]
 pub fn terminal_size() -> (u16, u16) {
+    // Return a constant when running the tests
+    if cfg!(test) {
+        return DEFAULT_TERM_SIZE;
+    }
+    window::cached_size(query_terminal_size)
+}
+
+// Terminal size as reported now, without the cache
+pub fn query_terminal_size() -> (u16, u16) {
     match crossterm::terminal::size() {
         Ok(s) => s,
         Err(_) => DEFAULT_TERM_SIZE,
     }
 }
 
-// Return a constant when running the tests
+/// Try to get the size of the terminal's text area in pixels, as width and height.
+///
+/// Uses the window size reported through the terminal driver (TIOCGWINSZ). If it is not set,
+/// the terminal is asked with the `CSI 14 t` query, the first time this is called.
+/// Returns None if neither is available.
+pub fn terminal_size_pixels() -> Option<(u16, u16)> {
+    window_pixels().or_else(cached_window_pixels)
+}
+
+// Size of the text area in pixels as reported through the terminal driver, if it is set
+fn window_pixels() -> Option<(u16, u16)> {
+    match crossterm::terminal::window_size() {
+        Ok(size) if size.width > 0 && size.height > 0 => Some((size.width, size.height)),
+        _ => None,
+    }
+}
+
+// Width and height of a terminal cell in pixels, if the terminal driver reports the size of
+// the window. The terminal is not queried, since that reads from stdin while raw mode is on,
+// taking keystrokes from applications which print images.
+pub fn cell_pixel_size() -> Option<(f32, f32)> {
+    cell_size(window_pixels()?)
+}
+
+// Same as cell_pixel_size, also asking the terminal for the size of the window. Only used
+// once a graphics printer is chosen, since they need the size in pixels.
+pub fn queried_cell_pixel_size() -> Option<(f32, f32)> {
+    cell_size(terminal_size_pixels()?)
+}
+
+fn cell_size((width, height): (u16, u16)) -> Option<(f32, f32)> {
+    let (columns, rows) = terminal_size();
+    if width == 0 || height == 0 || columns == 0 || rows == 0 {
+        return None;
+    }
+    Some((width as f32 / columns as f32, height as f32 / rows as f32))
+}
+
+// Height of a terminal cell divided by its width. Fall back to 2 if the size of the cells
+// is not known.
+pub fn cell_aspect_ratio() -> f32 {
+    cell_pixel_size().map_or(DEFAULT_CELL_ASPECT_RATIO, |(width, height)| height / width)
+}
+
+// Printer chosen by the user through the environment, overriding detection.
+// VIUER_NO_GRAPHICS=1 disables the graphics protocols, VIUER_BACKEND names a printer.
+pub fn printer_from_env() -> Option<PrinterType> {
+    if env::var("VIUER_NO_GRAPHICS").is_ok_and(|value| value == "1") {
+        return Some(PrinterType::Block);
+    }
+    backend_from_env().map(Backend::printer)
+}
+
+// A printer named by VIUER_BACKEND
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Backend {
+    Printer(PrinterType),
+    // Blocks without colors, as printed when colors are turned off, see uncolored
+    Ascii,
+}
+
+impl Backend {
+    fn printer(self) -> PrinterType {
+        match self {
+            Backend::Printer(printer) => printer,
+            Backend::Ascii => PrinterType::Block,
+        }
+    }
+}
+
+fn backend_from_env() -> Option<Backend> {
+    parse_backend(&env::var("VIUER_BACKEND").ok()?)
+}
+
+// Backend named by VIUER_BACKEND. Unknown names are ignored.
+fn parse_backend(name: &str) -> Option<Backend> {
+    let printer = match name.trim().to_ascii_lowercase().as_str() {
+        "blocks" | "block" => PrinterType::Block,
+        "kitty" => PrinterType::Kitty,
+        "iterm" => PrinterType::iTerm,
+        #[cfg(feature = "sixel")]
+        "sixel" => PrinterType::Sixel,
+        "ascii" => return Some(Backend::Ascii),
+        _ => return None,
+    };
+    Some(Backend::Printer(printer))
+}
+
+// Whether the program runs inside tmux
+pub fn in_tmux() -> bool {
+    env::var("TMUX").is_ok_and(|tmux| !tmux.is_empty())
+}
+
+// Whether output to stdout is wanted
+pub fn should_print(when: PrintWhen) -> bool {
+    when != PrintWhen::Never
+}
+
+// Whether the output to stdout may use colors. See https://no-color.org for NO_COLOR.
+// VIUER_BACKEND=ascii turns them off in any case.
+pub fn colored(when: PrintWhen) -> bool {
+    if backend_from_env() == Some(Backend::Ascii) {
+        return false;
+    }
+    when != PrintWhen::Auto
+        || io::stdout().is_terminal() && env::var("NO_COLOR").map_or(true, |v| v.is_empty())
+}
+
+// The config for printing without colors: the image is drawn with blocks whose shape shows
+// it on its own, as the colors are removed by Uncolored
+pub fn uncolored(config: &Config) -> Config {
+    let block_style = match config.block_style {
+        style @ (BlockStyle::Braille | BlockStyle::Edges) => style,
+        _ => BlockStyle::Shade,
+    };
+    Config {
+        printer: Some(PrinterType::Block),
+        block_style,
+        ..config.clone()
+    }
+}
+
+// Call `print` with stdout, or without colors when `config.when` asks for it
+pub fn with_stdout<T>(
+    config: &Config,
+    print: impl FnOnce(&mut dyn Write, &Config) -> ViuResult<T>,
+) -> ViuResult<T> {
+    if colored(config.when) {
+        return print(&mut io::stdout(), config);
+    }
+    print(&mut Uncolored::new(io::stdout()), &uncolored(config))
+}
+
+// Whether the program runs on a remote host, where the environment variables describe the
+// terminal the session was started from
+pub fn over_ssh() -> bool {
+    ["SSH_TTY", "SSH_CONNECTION"]
+        .iter()
+        .any(|name| env::var(name).is_ok_and(|value| !value.is_empty()))
+}
+
+// Whether the program runs inside GNU screen. tmux sets TERM to screen* as well, so it is
+// excluded.
+pub fn in_screen() -> bool {
+    env::var("STY").is_ok_and(|sty| !sty.is_empty())
+        || env::var("TERM").is_ok_and(|term| term.starts_with("screen")) && !in_tmux()
+}
+
+// Remove the variables describing a multiplexer or a remote session, so that the tests of
+// escape sequences do not depend on the terminal they run in. They are only ever removed,
+// which keeps the tests running in parallel in agreement.
 #[cfg(test)]
-pub fn terminal_size() -> (u16, u16) {
-    DEFAULT_TERM_SIZE
+pub fn clear_terminal_env() {
+    for name in [
+        "TMUX",
+        "STY",
+        "SSH_TTY",
+        "SSH_CONNECTION",
+        "VIUER_BACKEND",
+        "VIUER_NO_GRAPHICS",
+    ] {
+        env::remove_var(name);
+    }
+}
+
+// Wrap an escape sequence in a tmux DCS passthrough, which forwards it to the outer terminal.
+// The escape characters inside it are doubled. Needs `set -g allow-passthrough on` since tmux 3.3.
+pub fn tmux_passthrough(sequence: &str) -> String {
+    format!("\x1bPtmux;{}\x1b\\", sequence.replace('\x1b', "\x1b\x1b"))
+}
+
+// Write a graphics protocol escape sequence, wrapped for tmux when running inside it
+pub fn write_graphics(stdout: &mut impl Write, sequence: fmt::Arguments) -> io::Result<()> {
+    if in_tmux() {
+        write!(stdout, "{}", tmux_passthrough(&sequence.to_string()))
+    } else {
+        stdout.write_fmt(sequence)
+    }
+}
+
+// Base64 encoding of the data, written while formatting instead of being collected in a
+// string first
+pub fn base64(data: &[u8]) -> impl fmt::Display + '_ {
+    Base64Display::new(data, &STANDARD)
+}
+
+// Convert a number of terminal cells to the type of the cursor movements, which cannot
+// go further than u16::MAX cells
+pub fn cells(count: u32) -> ViuResult<u16> {
+    u16::try_from(count).map_err(|_| ViuError::SizeOverflow)
+}
+
+// Collect the output in a buffer and write it at once, so that the terminal does not show a
+// partly drawn image, e.g. over a slow SSH connection
+pub fn write_at_once<T>(
+    writer: &mut impl Write,
+    write: impl FnOnce(&mut dyn Write) -> ViuResult<T>,
+) -> ViuResult<T> {
+    write_at_once_with(writer, &mut Vec::new(), write)
+}
+
+// Same as write_at_once, collecting the output in `buf`, so that it can be reused for
+// repeated writes, e.g. of animation frames
+pub fn write_at_once_with<T>(
+    writer: &mut impl Write,
+    buf: &mut Vec<u8>,
+    write: impl FnOnce(&mut dyn Write) -> ViuResult<T>,
+) -> ViuResult<T> {
+    buf.clear();
+    let result = write(buf)?;
+    writer.write_all(buf)?;
+    writer.flush()?;
+    Ok(result)
+}
+
+// Writer which keeps track of the number of bytes written through it
+pub struct CountingWriter<'a, W: Write> {
+    inner: &'a mut W,
+    pub count: usize,
+}
+
+impl<'a, W: Write> CountingWriter<'a, W> {
+    pub fn new(inner: &'a mut W) -> Self {
+        Self { inner, count: 0 }
+    }
+}
+
+impl<W: Write> Write for CountingWriter<'_, W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let written = self.inner.write(buf)?;
+        self.count += written;
+        Ok(written)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.inner.flush()
+    }
+}
+
+// Writer which drops the SGR escape sequences, which set the colors, from the output
+pub struct Uncolored<W: Write> {
+    inner: W,
+    // The escape sequence being written, which can be split across writes
+    sequence: Vec<u8>,
+}
+
+impl<W: Write> Uncolored<W> {
+    pub fn new(inner: W) -> Self {
+        Self {
+            inner,
+            sequence: Vec::new(),
+        }
+    }
+}
+
+impl<W: Write> Write for Uncolored<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let mut out = Vec::with_capacity(buf.len());
+        for &byte in buf {
+            if self.sequence.is_empty() {
+                if byte == 0x1b {
+                    self.sequence.push(byte);
+                } else {
+                    out.push(byte);
+                }
+                continue;
+            }
+
+            self.sequence.push(byte);
+            let csi = self.sequence[1] == b'[';
+            // Other sequences than CSI are kept, and so are the CSI sequences which end with
+            // anything else than `m`, like cursor movements
+            if !csi || self.sequence.len() > 2 && (0x40..=0x7e).contains(&byte) {
+                if !csi || byte != b'm' {
+                    out.extend_from_slice(&self.sequence);
+                }
+                self.sequence.clear();
+            }
+        }
+        self.inner.write_all(&out)?;
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.inner.flush()
+    }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
 
+    #[test]
+    fn test_cells() {
+        assert_eq!(cells(80).unwrap(), 80);
+        assert!(matches!(cells(70_000), Err(ViuError::SizeOverflow)));
+    }
+
     #[test]
     fn test_truecolor() {
         env::set_var("COLORTERM", "truecolor");
@@ -57,4 +398,72 @@ mod tests {
         env::set_var("COLORTERM", "");
         assert!(!truecolor_available());
     }
+
+    #[test]
+    fn test_16_color_term() {
+        assert!(is_16_color_term("linux"));
+        assert!(is_16_color_term("vt100"));
+        assert!(!is_16_color_term("xterm-256color"));
+    }
+
+    #[test]
+    fn test_should_print() {
+        clear_terminal_env();
+        assert!(should_print(PrintWhen::Always));
+        assert!(should_print(PrintWhen::Auto));
+        assert!(!should_print(PrintWhen::Never));
+        assert!(colored(PrintWhen::Always));
+    }
+
+    #[test]
+    fn test_uncolored() {
+        let mut out = Uncolored::new(Vec::new());
+        out.write_all(b"\x1b[38;5;1").unwrap();
+        out.write_all(b"m\xe2\x96\x91\x1b[0m\x1b[2C\x1b7x").unwrap();
+        assert_eq!(out.inner, "░\x1b[2C\x1b7x".as_bytes());
+    }
+
+    #[test]
+    fn test_parse_backend() {
+        let printer = |name| parse_backend(name).map(Backend::printer);
+        assert_eq!(printer("blocks"), Some(PrinterType::Block));
+        assert_eq!(printer("iTerm"), Some(PrinterType::iTerm));
+        assert_eq!(printer(" kitty\n"), Some(PrinterType::Kitty));
+        // Blocks without colors
+        assert_eq!(parse_backend("ASCII"), Some(Backend::Ascii));
+        assert_eq!(printer("ascii"), Some(PrinterType::Block));
+        assert_eq!(parse_backend(""), None);
+    }
+
+    #[test]
+    fn test_tmux_passthrough() {
+        assert_eq!(
+            tmux_passthrough("\x1b_Ga=d\x1b\\"),
+            "\x1bPtmux;\x1b\x1b_Ga=d\x1b\x1b\\\x1b\\"
+        );
+    }
+
+    #[test]
+    fn test_write_at_once() {
+        let mut out = Vec::new();
+        let mut writer = CountingWriter::new(&mut out);
+        let result: ViuResult<_> = write_at_once(&mut writer, |buf| {
+            write!(buf, "ab")?;
+            write!(buf, "cd")?;
+            Ok(1)
+        });
+        assert_eq!(result.unwrap(), 1);
+        assert_eq!(writer.count, 4);
+        assert_eq!(out, b"abcd");
+    }
+
+    #[test]
+    fn test_counting_writer() {
+        let mut buf = Vec::new();
+        let mut writer = CountingWriter::new(&mut buf);
+        write!(writer, "▄▀").unwrap();
+        writer.write_all(b"\n").unwrap();
+        assert_eq!(writer.count, 7);
+        assert_eq!(buf.len(), 7);
+    }
 }
@@ -0,0 +1,212 @@
+//! Interactive viewer to pan and zoom over images too large to show at the terminal's
+//! resolution, like maps. Only the visible part of the image is rendered.
+//!
+//! ## Example
+//! ```no_run
+//! use viuer::{viewer, Config};
+//!
+//! let img = image::open("map.png").expect("Could not decode file.");
+//! viewer::run(&img, &Config::default()).expect("Viewer failed.");
+//! ```
+use crate::error::ViuResult;
+use crate::utils::{should_print, with_stdout};
+use crate::{print_to, Config};
+
+use crossterm::cursor::{Hide, Show};
+use crossterm::event::{self, Event, KeyCode, KeyEvent, KeyEventKind, KeyModifiers};
+use crossterm::execute;
+use crossterm::terminal::{
+    disable_raw_mode, enable_raw_mode, Clear, ClearType, EnterAlternateScreen, LeaveAlternateScreen,
+};
+use image::{DynamicImage, GenericImageView};
+use std::io::Write;
+
+// Factor by which a key press zooms in or out
+const ZOOM_STEP: f64 = 1.5;
+// Fraction of the visible part which a key press pans by
+const PAN_STEP: f64 = 0.25;
+
+/// Show the image on the alternate screen, fit to the terminal or to the bounds in the
+/// config, until the user quits. The keys are:
+/// - the arrow keys or h, j, k and l to pan,
+/// - + or = to zoom in, - to zoom out and 0 to show the whole image again,
+/// - q, Esc or Ctrl-C to quit.
+///
+/// The visible part of the image is set as [Config::crop], replacing the one in the config.
+/// The terminal is put in raw mode while waiting for a key, and the screen is restored when
+/// the viewer returns.
+pub fn run(img: &DynamicImage, config: &Config) -> ViuResult {
+    if !should_print(config.when) {
+        return Ok(());
+    }
+    with_stdout(config, |mut stdout, config| {
+        execute!(&mut stdout, EnterAlternateScreen, Hide)?;
+        let result = view(&mut stdout, img, config);
+        let restored = execute!(&mut stdout, Show, LeaveAlternateScreen);
+        result?;
+        Ok(restored?)
+    })
+}
+
+fn view(stdout: &mut impl Write, img: &DynamicImage, config: &Config) -> ViuResult {
+    let mut viewport = Viewport::new(img.dimensions());
+    loop {
+        execute!(stdout, Clear(ClearType::All))?;
+        let config = Config {
+            crop: Some(viewport.region()),
+            absolute_offset: true,
+            y: config.y.max(0),
+            restore_cursor: false,
+            ..config.clone()
+        };
+        print_to(img, &config, stdout)?;
+
+        // Resizes only need the image to be printed again
+        let key = loop {
+            enable_raw_mode()?;
+            let event = event::read();
+            disable_raw_mode()?;
+            match event? {
+                Event::Key(key) => break key,
+                Event::Resize(..) => break KeyEvent::new(KeyCode::Null, KeyModifiers::NONE),
+                _ => {}
+            }
+        };
+        if !viewport.handle(key) {
+            return Ok(());
+        }
+    }
+}
+
+// The part of the image which is shown: its center in pixels, and how much it is magnified
+// compared to the whole image
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct Viewport {
+    img_size: (f64, f64),
+    center: (f64, f64),
+    zoom: f64,
+}
+
+impl Viewport {
+    fn new((width, height): (u32, u32)) -> Self {
+        let img_size = (width.max(1) as f64, height.max(1) as f64);
+        Self {
+            img_size,
+            center: (img_size.0 / 2.0, img_size.1 / 2.0),
+            zoom: 1.0,
+        }
+    }
+
+    // Size of the visible part in pixels
+    fn size(&self) -> (f64, f64) {
+        (self.img_size.0 / self.zoom, self.img_size.1 / self.zoom)
+    }
+
+    // The visible part as (x, y, width, height), as used by Config::crop
+    fn region(&self) -> (u32, u32, u32, u32) {
+        let (width, height) = self.size();
+        let x = self.center.0 - width / 2.0;
+        let y = self.center.1 - height / 2.0;
+        (
+            x.round() as u32,
+            y.round() as u32,
+            (width.round() as u32).max(1),
+            (height.round() as u32).max(1),
+        )
+    }
+
+    fn pan(&mut self, dx: f64, dy: f64) {
+        let (width, height) = self.size();
+        self.center.0 += dx * width;
+        self.center.1 += dy * height;
+        self.clamp();
+    }
+
+    // Zoom by the factor, from showing the whole image up to a single pixel
+    fn zoom_by(&mut self, factor: f64) {
+        let max_zoom = self.img_size.0.min(self.img_size.1);
+        self.zoom = (self.zoom * factor).clamp(1.0, max_zoom.max(1.0));
+        self.clamp();
+    }
+
+    // Keep the visible part within the image
+    fn clamp(&mut self) {
+        let (width, height) = self.size();
+        self.center.0 = self
+            .center
+            .0
+            .clamp(width / 2.0, self.img_size.0 - width / 2.0);
+        self.center.1 = self
+            .center
+            .1
+            .clamp(height / 2.0, self.img_size.1 - height / 2.0);
+    }
+
+    // Apply the key, returning false when the viewer should quit
+    fn handle(&mut self, key: KeyEvent) -> bool {
+        if key.kind == KeyEventKind::Release {
+            return true;
+        }
+        match key.code {
+            KeyCode::Left | KeyCode::Char('h') => self.pan(-PAN_STEP, 0.0),
+            KeyCode::Right | KeyCode::Char('l') => self.pan(PAN_STEP, 0.0),
+            KeyCode::Up | KeyCode::Char('k') => self.pan(0.0, -PAN_STEP),
+            KeyCode::Down | KeyCode::Char('j') => self.pan(0.0, PAN_STEP),
+            KeyCode::Char('+') | KeyCode::Char('=') => self.zoom_by(ZOOM_STEP),
+            KeyCode::Char('-') => self.zoom_by(1.0 / ZOOM_STEP),
+            KeyCode::Char('0') => {
+                *self = Viewport::new((self.img_size.0 as u32, self.img_size.1 as u32))
+            }
+            KeyCode::Char('q') | KeyCode::Esc => return false,
+            // Raw mode turns Ctrl-C into a key instead of a signal
+            KeyCode::Char('c') if key.modifiers.contains(KeyModifiers::CONTROL) => return false,
+            _ => {}
+        }
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn key(code: KeyCode) -> KeyEvent {
+        KeyEvent::new(code, KeyModifiers::NONE)
+    }
+
+    #[test]
+    fn test_viewport_zoom() {
+        let mut viewport = Viewport::new((1000, 500));
+        assert_eq!(viewport.region(), (0, 0, 1000, 500));
+        viewport.zoom_by(2.0);
+        assert_eq!(viewport.region(), (250, 125, 500, 250));
+        // Not further out than the whole image
+        viewport.zoom_by(0.1);
+        assert_eq!(viewport.region(), (0, 0, 1000, 500));
+        // Not further in than a single pixel
+        viewport.zoom_by(10_000.0);
+        assert_eq!(viewport.region().2, 2);
+        assert_eq!(viewport.region().3, 1);
+    }
+
+    #[test]
+    fn test_viewport_pan() {
+        let mut viewport = Viewport::new((1000, 500));
+        // The whole image is visible, so there is nothing to pan to
+        viewport.pan(0.25, 0.0);
+        assert_eq!(viewport.region(), (0, 0, 1000, 500));
+
+        viewport.zoom_by(2.0);
+        assert!(viewport.handle(key(KeyCode::Right)));
+        assert_eq!(viewport.region(), (375, 125, 500, 250));
+        // Stops at the edges
+        for _ in 0..10 {
+            viewport.handle(key(KeyCode::Char('k')));
+        }
+        assert_eq!(viewport.region(), (375, 0, 500, 250));
+
+        assert!(viewport.handle(key(KeyCode::Char('0'))));
+        assert_eq!(viewport.region(), (0, 0, 1000, 500));
+        assert!(!viewport.handle(key(KeyCode::Char('q'))));
+    }
+}
@@ -0,0 +1,127 @@
+// Printing a window into an image laid out at the full resolution of the cells.
+use crate::error::ViuResult;
+use crate::printer::cell_aspect_ratio;
+use crate::utils::should_print;
+use crate::{print_to, Config, PrintedInfo, ResizePolicy};
+
+use image::{DynamicImage, GenericImageView};
+
+// Region of an image in pixels, as (x, y, width, height)
+type Region = (u32, u32, u32, u32);
+
+/// A rectangle in terminal cells.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct Rect {
+    /// Column of the left edge.
+    pub x: u32,
+    /// Row of the top edge.
+    pub y: u32,
+    /// Number of columns.
+    pub width: u32,
+    /// Number of rows.
+    pub height: u32,
+}
+
+/// Print a window into the image, as if the whole image was printed at the full resolution
+/// of the cells: one pixel per column, and as many pixels per row as keep the proportions,
+/// i.e. two for cells twice as high as wide. Callers can move the window to scroll through
+/// images too large for the terminal.
+///
+/// Only the pixels under the window are resized and printed, so moving it around a large
+/// image does not resize the whole image every time. The window is clamped to the image,
+/// and [Config::crop] and the sizing options of the config are replaced.
+///
+/// Returns the dimensions of the printed part in terminal cells.
+/// ## Example
+/// ```no_run
+/// use viuer::{print_viewport, Config, Rect};
+///
+/// let img = image::open("map.png").expect("Could not decode file.");
+/// // Scroll down by 10 rows at a time
+/// for y in (0..100).step_by(10) {
+///     let window = Rect {
+///         x: 0,
+///         y,
+///         width: 80,
+///         height: 20,
+///     };
+///     print_viewport(&img, window, &Config::default()).expect("Image printing failed.");
+/// }
+/// ```
+pub fn print_viewport(
+    img: &DynamicImage,
+    viewport: Rect,
+    config: &Config,
+) -> ViuResult<PrintedInfo> {
+    if !should_print(config.when) {
+        return Ok(PrintedInfo::nothing());
+    }
+    let region = viewport_region(img.dimensions(), viewport, cell_aspect_ratio(config));
+    let (crop, (width, height)) = match region {
+        Some(region) => region,
+        None => return Ok(PrintedInfo::nothing()),
+    };
+    let config = Config {
+        crop: Some(crop),
+        resize_policy: Some(ResizePolicy::Exact(width, height)),
+        ..config.clone()
+    };
+    print_to(img, &config, &mut std::io::stdout())
+}
+
+// The region of the image under the window, and the size in cells it takes. None if the window is outside of the image.
+fn viewport_region(
+    (img_width, img_height): (u32, u32),
+    viewport: Rect,
+    ratio: f32,
+) -> Option<(Region, (u32, u32))> {
+    // Height in pixels of a row
+    let ratio = ratio as f64;
+    let rows = (img_height as f64 / ratio).ceil() as u32;
+    if viewport.x >= img_width || viewport.y >= rows || viewport.width == 0 || viewport.height == 0
+    {
+        return None;
+    }
+    let width = viewport.width.min(img_width - viewport.x);
+    let height = viewport.height.min(rows - viewport.y);
+
+    let y = (viewport.y as f64 * ratio).round() as u32;
+    let bottom = (((viewport.y + height) as f64 * ratio).round() as u32).min(img_height);
+    let region = (viewport.x, y, width, bottom.saturating_sub(y).max(1));
+    Some((region, (width, height)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_viewport_region() {
+        let window = Rect {
+            x: 10,
+            y: 5,
+            width: 20,
+            height: 10,
+        };
+        assert_eq!(
+            viewport_region((100, 100), window, 2.0),
+            Some(((10, 10, 20, 20), (20, 10)))
+        );
+        // Clamped to the image, whose 45 pixels fill 23 rows
+        assert_eq!(
+            viewport_region((25, 45), window, 2.0),
+            Some(((10, 10, 15, 20), (15, 10)))
+        );
+        let window = Rect { y: 20, ..window };
+        assert_eq!(
+            viewport_region((25, 45), window, 2.0),
+            Some(((10, 40, 15, 5), (15, 3)))
+        );
+        // Square cells hold one pixel per row
+        assert_eq!(
+            viewport_region((100, 100), window, 1.0),
+            Some(((10, 20, 20, 10), (20, 10)))
+        );
+        assert_eq!(viewport_region((5, 5), window, 2.0), None);
+    }
+}
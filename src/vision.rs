@@ -0,0 +1,129 @@
+// Simulation of color vision deficiencies, and daltonization to make images easier to tell
+// apart for people who have them. Both work in linear light, on the resized image.
+use crate::printer::gamma::{linear_to_srgb, srgb_to_linear};
+use image::RgbaImage;
+
+type Matrix = [[f32; 3]; 3];
+
+/// A color vision deficiency, where one kind of cone in the eye is missing.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum ColorBlindness {
+    /// No red cones, which makes reds dark and hard to tell from greens.
+    Protanopia,
+    /// No green cones, the most common deficiency, which confuses reds and greens.
+    Deuteranopia,
+    /// No blue cones, which confuses blues with greens and yellows with violets.
+    Tritanopia,
+}
+
+/// Filter for reviewing the accessibility of images, set through [crate::Config::vision_filter].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum VisionFilter {
+    /// Show the image as it looks with the deficiency.
+    Simulate(ColorBlindness),
+    /// Shift the colors which cannot be told apart with the deficiency towards the ones
+    /// which can, known as daltonization.
+    Correct(ColorBlindness),
+}
+
+impl ColorBlindness {
+    // Simulation in linear RGB at full severity, from Machado, Oliveira and Fernandes (2009)
+    fn simulation(self) -> Matrix {
+        match self {
+            ColorBlindness::Protanopia => [
+                [0.152286, 1.052583, -0.204868],
+                [0.114503, 0.786281, 0.099216],
+                [-0.003882, -0.048116, 1.051998],
+            ],
+            ColorBlindness::Deuteranopia => [
+                [0.367322, 0.860646, -0.227968],
+                [0.280085, 0.672501, 0.047413],
+                [-0.011820, 0.042940, 0.968881],
+            ],
+            ColorBlindness::Tritanopia => [
+                [1.255528, -0.076749, -0.178779],
+                [-0.078411, 0.930809, 0.147602],
+                [0.004733, 0.691367, 0.303900],
+            ],
+        }
+    }
+
+    // Where the difference lost by the simulation is moved to: the red lost by protanopes
+    // and deuteranopes into green and blue, the blue lost by tritanopes into red and green
+    fn correction(self) -> Matrix {
+        match self {
+            ColorBlindness::Protanopia | ColorBlindness::Deuteranopia => {
+                [[0.0, 0.0, 0.0], [0.7, 1.0, 0.0], [0.7, 0.0, 1.0]]
+            }
+            ColorBlindness::Tritanopia => [[1.0, 0.0, 0.7], [0.0, 1.0, 0.7], [0.0, 0.0, 0.0]],
+        }
+    }
+}
+
+// Apply the filter to the image, in place
+pub fn apply(img: &mut RgbaImage, filter: VisionFilter) {
+    let mut linear = [0.0; 256];
+    for (i, value) in linear.iter_mut().enumerate() {
+        *value = srgb_to_linear(i as f32 / 255.0);
+    }
+
+    for pixel in img.pixels_mut() {
+        let rgb = [
+            linear[pixel[0] as usize],
+            linear[pixel[1] as usize],
+            linear[pixel[2] as usize],
+        ];
+        let filtered = match filter {
+            VisionFilter::Simulate(deficiency) => multiply(&deficiency.simulation(), rgb),
+            VisionFilter::Correct(deficiency) => {
+                let simulated = multiply(&deficiency.simulation(), rgb);
+                let error = [0, 1, 2].map(|c| rgb[c] - simulated[c]);
+                let shift = multiply(&deficiency.correction(), error);
+                [0, 1, 2].map(|c| rgb[c] + shift[c])
+            }
+        };
+        for c in 0..3 {
+            pixel[c] = linear_to_srgb(filtered[c]);
+        }
+    }
+}
+
+fn multiply(matrix: &Matrix, rgb: [f32; 3]) -> [f32; 3] {
+    matrix.map(|row| row[0] * rgb[0] + row[1] * rgb[1] + row[2] * rgb[2])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use image::Rgba;
+
+    #[test]
+    fn test_simulate() {
+        let mut img = RgbaImage::from_vec(
+            3,
+            1,
+            vec![255, 0, 0, 255, 0, 255, 0, 255, 128, 128, 128, 128],
+        )
+        .unwrap();
+        apply(
+            &mut img,
+            VisionFilter::Simulate(ColorBlindness::Deuteranopia),
+        );
+        // Red and green look alike, gray and the alpha are kept
+        let (red, green) = (img.get_pixel(0, 0), img.get_pixel(1, 0));
+        assert!(red[0].abs_diff(green[0]) < 80 && red[1].abs_diff(green[1]) < 80);
+        assert_eq!(img.get_pixel(2, 0), &Rgba([128, 128, 128, 128]));
+    }
+
+    #[test]
+    fn test_correct() {
+        let mut img = RgbaImage::from_pixel(1, 1, Rgba([200, 50, 50, 255]));
+        apply(&mut img, VisionFilter::Correct(ColorBlindness::Protanopia));
+        // The red a protanope cannot see is moved into the other channels
+        let pixel = img.get_pixel(0, 0);
+        assert_eq!(pixel[0], 200);
+        assert!(pixel[1] > 50 && pixel[2] > 50);
+    }
+}
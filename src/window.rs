@@ -0,0 +1,263 @@
+use crate::error::{ViuError, ViuResult};
+use crate::{Config, PrintedInfo};
+
+use image::DynamicImage;
+use lazy_static::lazy_static;
+use std::sync::atomic::{AtomicU32, Ordering};
+use std::sync::mpsc::{self, Receiver, Sender};
+use std::sync::Mutex;
+use std::thread::{self, JoinHandle};
+
+// Called with the new size, returns false once it should not be called anymore
+type ResizeCallback = Box<dyn Fn(u16, u16) -> bool + Send>;
+
+// The terminal size, with the columns in the upper half and the rows in the lower one.
+// 0 when it is not cached.
+static CACHED_SIZE: AtomicU32 = AtomicU32::new(0);
+
+lazy_static! {
+    static ref CALLBACKS: Mutex<Vec<ResizeCallback>> = Mutex::new(Vec::new());
+    // Whether the resizes of the window are watched, so that the size can be cached
+    static ref WATCHING: bool = watch_resizes();
+}
+
+/// Call `callback` with the new number of columns and rows whenever the terminal window is
+/// resized, e.g. to print the image again at the new size. The callback runs on a
+/// background thread, which is started the first time it is needed.
+///
+/// Resizes are only observed on unix with the "signal-handler" feature, through SIGWINCH.
+/// Returns false otherwise, in which case the callback is never called.
+/// ## Example
+/// ```no_run
+/// viuer::on_resize(|columns, rows| {
+///     eprintln!("The terminal is now {}x{}", columns, rows);
+/// });
+/// ```
+pub fn on_resize(callback: impl Fn(u16, u16) + Send + 'static) -> bool {
+    subscribe(move |columns, rows| {
+        callback(columns, rows);
+        true
+    })
+}
+
+// Same as on_resize, for a callback which can ask to be removed
+fn subscribe(callback: impl Fn(u16, u16) -> bool + Send + 'static) -> bool {
+    if !*WATCHING {
+        return false;
+    }
+    CALLBACKS
+        .lock()
+        .unwrap_or_else(|e| e.into_inner())
+        .push(Box::new(callback));
+    true
+}
+
+/// An image printed by [watch], or `print_from_file_watch` with the `watch` feature, which
+/// is printed again whenever the terminal is resized or the file changes.
+pub struct Watch {
+    // Sends true to print the image again, or false to stop
+    pub(crate) events: Sender<bool>,
+    thread: Option<JoinHandle<ViuResult>>,
+    // Reports the changes of the file, see print_from_file_watch
+    #[cfg(feature = "watch")]
+    pub(crate) file_watcher: Option<notify::RecommendedWatcher>,
+}
+
+impl Watch {
+    // Print with `print` now, and again on every resize or when asked to through the events
+    pub(crate) fn start<F>(config: Config, mut print: F) -> ViuResult<Self>
+    where
+        F: FnMut(&Config) -> ViuResult<PrintedInfo> + Send + 'static,
+    {
+        let info = print(&config)?;
+        let (events, received) = mpsc::channel();
+        let sender = events.clone();
+        subscribe(move |_, _| sender.send(true).is_ok());
+        let thread = thread::Builder::new()
+            .name("viuer-watch".to_owned())
+            .spawn(move || reprint(&config, info, received, print))?;
+        Ok(Watch {
+            events,
+            thread: Some(thread),
+            #[cfg(feature = "watch")]
+            file_watcher: None,
+        })
+    }
+
+    /// Stop printing the image again. The image stays on the screen.
+    ///
+    /// Returns the error which ended the reprinting early, if any.
+    pub fn stop(mut self) -> ViuResult {
+        self.join()
+    }
+
+    fn join(&mut self) -> ViuResult {
+        let _ = self.events.send(false);
+        match self.thread.take().map(JoinHandle::join) {
+            Some(Ok(result)) => result,
+            Some(Err(panic)) => std::panic::resume_unwind(panic),
+            None => Ok(()),
+        }
+    }
+}
+
+impl Drop for Watch {
+    fn drop(&mut self) {
+        if !thread::panicking() {
+            let _ = self.join();
+        }
+    }
+}
+
+/// Print an image, and print it again scaled to the new size whenever the terminal is
+/// resized, until [Watch::stop] is called or the [Watch] is dropped. The image printed
+/// before is removed with [crate::clear] first, so the same rules for the cursor apply: use
+/// [Config::absolute_offset] or [Config::restore_cursor] when other output follows the image.
+///
+/// Resizes are only observed where [on_resize] supports them. Elsewhere, the image is
+/// printed once.
+/// ## Example
+/// ```no_run
+/// use viuer::{watch, Config};
+///
+/// let img = image::open("logo.png").expect("Could not open the image.");
+/// let conf = Config {
+///     restore_cursor: true,
+///     ..Default::default()
+/// };
+/// let watch = watch(img, conf).expect("Image printing failed.");
+/// // ...
+/// watch.stop().expect("Image printing failed.");
+/// ```
+pub fn watch(img: DynamicImage, config: Config) -> ViuResult<Watch> {
+    Watch::start(config, move |config| crate::print(&img, config))
+}
+
+// Print the image again whenever asked to, until asked to stop. An image which cannot be
+// decoded, e.g. a file which is still being written, is left out until the next time.
+fn reprint<F>(
+    config: &Config,
+    mut info: PrintedInfo,
+    events: Receiver<bool>,
+    mut print: F,
+) -> ViuResult
+where
+    F: FnMut(&Config) -> ViuResult<PrintedInfo>,
+{
+    while let Ok(true) = events.recv() {
+        // Only the last of several quick changes is printed
+        if events.try_iter().any(|again| !again) {
+            break;
+        }
+        crate::clear(&info, config)?;
+        info = match print(config) {
+            Err(ViuError::Image(_)) => PrintedInfo::nothing(),
+            result => result?,
+        };
+    }
+    Ok(())
+}
+
+// The cached terminal size, or the one returned by `size` when nothing is cached. It is only
+// cached while resizes are watched, as it could not be invalidated otherwise.
+pub(crate) fn cached_size(size: impl FnOnce() -> (u16, u16)) -> (u16, u16) {
+    if !*WATCHING {
+        return size();
+    }
+    match CACHED_SIZE.load(Ordering::Relaxed) {
+        0 => {
+            let size = size();
+            cache(size);
+            size
+        }
+        cached => ((cached >> 16) as u16, cached as u16),
+    }
+}
+
+fn cache((columns, rows): (u16, u16)) {
+    CACHED_SIZE.store((columns as u32) << 16 | rows as u32, Ordering::Relaxed);
+}
+
+// Cache the new size and let the callbacks know about it
+#[cfg(all(unix, feature = "signal-handler"))]
+fn resized() {
+    let (columns, rows) = crate::utils::query_terminal_size();
+    cache((columns, rows));
+    crate::terminal::invalidate_window_pixels();
+    notify(columns, rows);
+}
+
+// Call the callbacks with the new size. They are taken out of the list while they run, so
+// that they can call on_resize without waiting for the lock held here. The callbacks added
+// meanwhile are kept after them.
+#[cfg(all(unix, feature = "signal-handler"))]
+fn notify(columns: u16, rows: u16) {
+    let mut callbacks = std::mem::take(&mut *CALLBACKS.lock().unwrap_or_else(|e| e.into_inner()));
+    callbacks.retain(|callback| callback(columns, rows));
+    let mut list = CALLBACKS.lock().unwrap_or_else(|e| e.into_inner());
+    callbacks.append(&mut list);
+    *list = callbacks;
+}
+
+// Start a thread which handles SIGWINCH. signal-hook keeps the handlers installed by the
+// application working.
+#[cfg(all(unix, feature = "signal-handler"))]
+fn watch_resizes() -> bool {
+    use signal_hook::consts::SIGWINCH;
+    use signal_hook::iterator::Signals;
+
+    let mut signals = match Signals::new([SIGWINCH]) {
+        Ok(signals) => signals,
+        Err(_) => return false,
+    };
+    std::thread::Builder::new()
+        .name("viuer-resize".to_owned())
+        .spawn(move || signals.forever().for_each(|_| resized()))
+        .is_ok()
+}
+
+// Without SIGWINCH, resizes cannot be noticed. Windows reports them as console input
+// events, which would have to be read from the application's input.
+#[cfg(not(all(unix, feature = "signal-handler")))]
+fn watch_resizes() -> bool {
+    false
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_reprint_stops() {
+        let (events, received) = mpsc::channel();
+        events.send(true).unwrap();
+        events.send(true).unwrap();
+        events.send(false).unwrap();
+        let mut printed = 0;
+        let print = |_: &Config| {
+            printed += 1;
+            Ok(PrintedInfo::nothing())
+        };
+        let info = PrintedInfo::nothing();
+        assert!(reprint(&Config::default(), info, received, print).is_ok());
+        assert_eq!(printed, 0);
+    }
+
+    #[cfg(all(unix, feature = "signal-handler"))]
+    #[test]
+    fn test_callback_subscribes() {
+        // A callback can add another one while it is called
+        let (sender, received) = mpsc::channel();
+        CALLBACKS.lock().unwrap().push(Box::new(move |columns, _| {
+            let sender = sender.clone();
+            CALLBACKS.lock().unwrap().push(Box::new(move |_, rows| {
+                sender.send(rows).unwrap();
+                false
+            }));
+            columns < 100
+        }));
+        notify(100, 30);
+        notify(100, 40);
+        assert_eq!(received.try_iter().collect::<Vec<_>>(), [40]);
+    }
+}